@@ -0,0 +1,88 @@
+//! Replication throughput over a network with latency, with and without pipelined AppendEntries.
+//!
+//! Brings a 3 node cluster online behind `RaftRouter::send_delay`, writes a number of entries
+//! through many concurrent clients, and reports how long it takes for each value of
+//! `Config::max_inflight`.
+//!
+//! cargo bench -p async-raft --bench replication_pipelining
+
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Result;
+use async_raft::Config;
+use async_raft::SnapshotPolicy;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[path = "../tests/fixtures/mod.rs"]
+#[allow(unused_macros)]
+mod fixtures;
+
+/// The maximum network delay of each message, in milliseconds, as drawn at random by the router.
+const SEND_DELAY: u64 = 20;
+/// The number of clients writing concurrently.
+const CLIENTS: u64 = 256;
+/// The number of entries each client writes.
+const WRITES_PER_CLIENT: u64 = 20;
+/// The maximum number of entries per AppendEntries RPC.
+const MAX_PAYLOAD_ENTRIES: u64 = 16;
+
+fn main() -> Result<()> {
+    let rt = tokio::runtime::Builder::new_multi_thread().worker_threads(6).enable_all().build()?;
+
+    println!(
+        "{} entries, {} clients, max {} entries per payload, <= {} ms send delay",
+        CLIENTS * WRITES_PER_CLIENT,
+        CLIENTS,
+        MAX_PAYLOAD_ENTRIES,
+        SEND_DELAY
+    );
+    for max_inflight in [1, 2, 4, 8].iter().copied() {
+        let elapsed = rt.block_on(write_entries(max_inflight))?;
+        let rate = (CLIENTS * WRITES_PER_CLIENT) as f64 / elapsed.as_secs_f64();
+        println!(
+            "max_inflight: {:>2}: {:>8.1} ms, {:>8.1} entries/s",
+            max_inflight,
+            elapsed.as_secs_f64() * 1000.0,
+            rate
+        );
+    }
+
+    Ok(())
+}
+
+/// Write entries to a new cluster with the given in-flight window, and return how long it took.
+async fn write_entries(max_inflight: u64) -> Result<Duration> {
+    let config = Arc::new(
+        Config::build("bench".into())
+            .heartbeat_interval(100)
+            .election_timeout_min(500)
+            .election_timeout_max(1000)
+            .max_payload_entries(MAX_PAYLOAD_ENTRIES)
+            .max_inflight(max_inflight)
+            .snapshot_policy(SnapshotPolicy::LogsSinceLast(CLIENTS * WRITES_PER_CLIENT * 2))
+            .validate()
+            .expect("failed to build Raft config"),
+    );
+    let router = Arc::new(RaftRouter::builder(config).send_delay(SEND_DELAY).build());
+    router.new_nodes_from_single(btreeset! {0, 1, 2}, btreeset! {}).await?;
+
+    let started = Instant::now();
+
+    let mut clients = Vec::new();
+    for client in 0..CLIENTS {
+        let router = router.clone();
+        clients.push(tokio::spawn(async move {
+            for serial in 0..WRITES_PER_CLIENT {
+                router.client_request(0, &client.to_string(), serial).await;
+            }
+        }));
+    }
+    for client in clients {
+        client.await?;
+    }
+
+    Ok(started.elapsed())
+}
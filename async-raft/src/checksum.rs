@@ -0,0 +1,119 @@
+//! CRC-32 checksums, for checking that snapshots arrive intact.
+//!
+//! The leader sends the checksum of every chunk of a snapshot along with it, and that of the whole
+//! snapshot in its `SnapshotMeta`. The receiving node checks both before installing the snapshot.
+
+use std::io::SeekFrom;
+
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncSeek;
+use tokio::io::AsyncSeekExt;
+
+/// The lookup table of the CRC-32 (IEEE 802.3) polynomial, in reversed bit order.
+const TABLE: [u32; 256] = make_table();
+
+const fn make_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// A CRC-32 checksum, computed incrementally.
+#[derive(Clone, Debug)]
+pub struct Crc32 {
+    state: u32,
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Crc32 {
+    /// Create a new instance, for the checksum of no data.
+    pub fn new() -> Self {
+        Self { state: !0 }
+    }
+
+    /// Add the given data to the checksum.
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.state = TABLE[((self.state ^ byte as u32) & 0xff) as usize] ^ (self.state >> 8);
+        }
+    }
+
+    /// The checksum of the data added so far.
+    pub fn finish(&self) -> u32 {
+        !self.state
+    }
+}
+
+/// The CRC-32 checksum of the given data.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.finish()
+}
+
+/// The CRC-32 checksum of the whole of the given snapshot, read from its start.
+pub async fn snapshot_checksum<S>(snapshot: &mut S) -> std::io::Result<u32>
+where S: AsyncRead + AsyncSeek + Unpin + ?Sized {
+    snapshot.seek(SeekFrom::Start(0)).await?;
+
+    let mut crc = Crc32::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n_read = snapshot.read(&mut buf).await?;
+        if n_read == 0 {
+            return Ok(crc.finish());
+        }
+        crc.update(&buf[..n_read]);
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////
+// Unit Tests ////////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(0, crc32(b""));
+        assert_eq!(0xCBF4_3926, crc32(b"123456789"));
+
+        let mut crc = Crc32::new();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(0xCBF4_3926, crc.finish());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_checksum() -> std::io::Result<()> {
+        let data: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+        let mut snapshot = Cursor::new(data.clone());
+        snapshot.seek(SeekFrom::End(0)).await?;
+
+        assert_eq!(crc32(&data), snapshot_checksum(&mut snapshot).await?);
+        Ok(())
+    }
+}
@@ -0,0 +1,129 @@
+//! Compression of snapshots sent to other nodes.
+//!
+//! The leader compresses every chunk of a snapshot on its own, with the codec set in
+//! `Config::snapshot_compression`, and names the codec in the `InstallSnapshotRequest`. The
+//! receiving node decompresses the chunk before it is written to its `SnapshotData`, so storage
+//! implementations only ever see the raw snapshot. Offsets and checksums always refer to the raw
+//! snapshot too.
+//!
+//! Codecs are enabled by cargo features, e.g. `lz4`. Every node names the codecs it supports in its
+//! `InstallSnapshotResponse`, and the leader only compresses chunks once the receiving node has
+//! named the configured codec. The first chunk of a transfer is therefore always sent
+//! uncompressed, and a node which does not know of compression at all, e.g. one built before it
+//! was added, is sent the whole snapshot uncompressed. A node which was built without the codec of
+//! a chunk does not take it, and answers that it did not decompress it, upon which the leader
+//! sends the rest of the snapshot to it uncompressed. So does a node which fails to decompress a
+//! chunk, e.g. because it was corrupted in transit.
+//!
+//! A compressed chunk is at most `MAX_RAW_CHUNK_SIZE` bytes when decompressed, whatever the
+//! configs of the nodes, so that a corrupted or malicious chunk can not make a node allocate more.
+
+use std::io;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// The largest size of a compressed chunk of a snapshot when decompressed: 64 MiB.
+///
+/// The leader sends chunks larger than this uncompressed.
+pub const MAX_RAW_CHUNK_SIZE: u64 = 64 * 1024 * 1024;
+
+/// A codec with which snapshots may be compressed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SnapshotCompression {
+    /// LZ4 block compression, prefixed with the size of the raw data. Requires the `lz4` feature.
+    Lz4,
+}
+
+impl SnapshotCompression {
+    /// Whether this build of the crate supports the codec, i.e. was built with its cargo feature.
+    pub fn is_supported(&self) -> bool {
+        match self {
+            SnapshotCompression::Lz4 => cfg!(feature = "lz4"),
+        }
+    }
+
+    /// The codecs which this build of the crate supports.
+    pub fn supported() -> Vec<SnapshotCompression> {
+        [SnapshotCompression::Lz4].iter().copied().filter(|codec| codec.is_supported()).collect()
+    }
+
+    /// Compress the given chunk of a snapshot.
+    pub fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "lz4")]
+            SnapshotCompression::Lz4 => Ok(lz4_flex::compress_prepend_size(data)),
+            #[cfg(not(feature = "lz4"))]
+            SnapshotCompression::Lz4 => {
+                let _ = data;
+                Err(self.unsupported())
+            }
+        }
+    }
+
+    /// Decompress the given chunk of a snapshot, which is at most `max_size` bytes when raw.
+    ///
+    /// The size of the raw chunk is checked before anything is allocated for it, so that a corrupted or malicious
+    /// chunk can not make the node allocate more than `max_size` bytes.
+    pub fn decompress(&self, data: &[u8], max_size: usize) -> io::Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "lz4")]
+            SnapshotCompression::Lz4 => {
+                let invalid = |reason: String| io::Error::new(io::ErrorKind::InvalidData, reason);
+                if data.len() < 4 {
+                    return Err(invalid("the chunk is too short for its size prefix".into()));
+                }
+                let (size, compressed) = data.split_at(4);
+                let size = u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as usize;
+                if size > max_size {
+                    return Err(invalid(format!(
+                        "the chunk is {} bytes when raw, more than the {} bytes allowed",
+                        size, max_size
+                    )));
+                }
+                lz4_flex::decompress(compressed, size).map_err(|err| invalid(err.to_string()))
+            }
+            #[cfg(not(feature = "lz4"))]
+            SnapshotCompression::Lz4 => {
+                let _ = (data, max_size);
+                Err(self.unsupported())
+            }
+        }
+    }
+
+    #[cfg(not(feature = "lz4"))]
+    fn unsupported(&self) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("snapshot compression {:?} is not supported by this build", self),
+        )
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////
+// Unit Tests ////////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compression_round_trip() {
+        let codec = SnapshotCompression::Lz4;
+        let data = b"snapshot ".repeat(1000);
+
+        if !codec.is_supported() {
+            assert!(codec.compress(&data).is_err());
+            assert!(codec.decompress(&data, data.len()).is_err());
+            return;
+        }
+
+        let compressed = codec.compress(&data).unwrap();
+        assert!(compressed.len() < data.len() / 10);
+        assert_eq!(data, codec.decompress(&compressed, data.len()).unwrap());
+
+        assert!(codec.decompress(&compressed[..compressed.len() / 2], data.len()).is_err());
+        assert!(codec.decompress(&compressed, data.len() - 1).is_err());
+        assert!(codec.decompress(&compressed[..3], data.len()).is_err());
+    }
+}
@@ -1,11 +1,20 @@
 //! Raft runtime configuration.
 
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
 use rand::thread_rng;
 use rand::Rng;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::compression::SnapshotCompression;
 use crate::error::ConfigError;
+use crate::LogId;
+use crate::NodeId;
+use crate::RaftMetrics;
+use crate::State;
 
 /// Default election timeout minimum, in milliseconds.
 pub const DEFAULT_ELECTION_TIMEOUT_MIN: u64 = 150;
@@ -19,6 +28,16 @@ pub const DEFAULT_LOGS_SINCE_LAST: u64 = 5000;
 pub const DEFAULT_MAX_PAYLOAD_ENTRIES: u64 = 300;
 /// Default replication lag threshold.
 pub const DEFAULT_REPLICATION_LAG_THRESHOLD: u64 = 1000;
+/// Default maximum number of AppendEntries RPCs in flight to a single target.
+pub const DEFAULT_MAX_INFLIGHT: u64 = 1;
+/// Default capacity of the queue of requests to a Raft node.
+pub const DEFAULT_API_QUEUE_CAPACITY: u64 = 1024;
+/// Default capacity of the queues between a Raft leader and its replication streams.
+pub const DEFAULT_REPLICATION_QUEUE_CAPACITY: u64 = 1024;
+/// Default maximum number of nodes a request may be forwarded through on its way to the leader.
+pub const DEFAULT_MAX_FORWARD_HOPS: u64 = 3;
+/// Default time in milliseconds after which an idle client session expires: 1 hour.
+pub const DEFAULT_CLIENT_SESSION_TTL: u64 = 60 * 60 * 1000;
 /// Default snapshot chunksize.
 pub const DEFAULT_SNAPSHOT_CHUNKSIZE: u64 = 1024 * 1024 * 3;
 
@@ -27,12 +46,30 @@ pub const DEFAULT_SNAPSHOT_CHUNKSIZE: u64 = 1024 * 1024 * 3;
 /// This governs when periodic snapshots will be taken, and also governs the conditions which
 /// would cause a leader to send an `InstallSnapshot` RPC to a follower based on replication lag.
 ///
-/// Additional policies may become available in the future.
+/// A snapshot is only taken if entries have been applied since the last one. Policies which
+/// depend on time, i.e. `TimeSinceLast` and `Custom`, are checked every `heartbeat_interval` as
+/// well as when entries are applied.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum SnapshotPolicy {
     /// A snapshot will be generated once the log has grown the specified number of logs since
     /// the last snapshot.
     LogsSinceLast(u64),
+    /// A snapshot will be generated once the entries appended to the log since the last snapshot
+    /// add up to the specified number of bytes, as sized by `Entry::payload_size`.
+    LogBytesSinceLast(u64),
+    /// A snapshot will be generated once the specified number of milliseconds has passed since the
+    /// last snapshot, or since the node started.
+    TimeSinceLast(u64),
+    /// A snapshot will be generated once any of the given policies would generate one.
+    Any(Vec<SnapshotPolicy>),
+    /// A snapshot will be generated once all of the given policies would generate one.
+    All(Vec<SnapshotPolicy>),
+    /// A snapshot will be generated once the given policy, which looks at the node's `SnapshotMetrics`,
+    /// decides so.
+    ///
+    /// This variant can not be serialized.
+    #[serde(skip)]
+    Custom(SnapshotPolicyFn),
 }
 
 impl Default for SnapshotPolicy {
@@ -41,6 +78,248 @@ impl Default for SnapshotPolicy {
     }
 }
 
+impl SnapshotPolicy {
+    /// Whether a snapshot is to be generated, given the node's metrics and its progress since the
+    /// last snapshot.
+    pub(crate) fn should_snapshot(&self, metrics: &SnapshotMetrics, progress: &SnapshotProgress) -> bool {
+        match self {
+            SnapshotPolicy::LogsSinceLast(threshold) => progress.logs_since_last >= *threshold,
+            SnapshotPolicy::LogBytesSinceLast(threshold) => progress.log_bytes_since_last >= *threshold,
+            SnapshotPolicy::TimeSinceLast(ms) => progress.since_last >= Duration::from_millis(*ms),
+            SnapshotPolicy::Any(policies) => policies.iter().any(|policy| policy.should_snapshot(metrics, progress)),
+            SnapshotPolicy::All(policies) => policies.iter().all(|policy| policy.should_snapshot(metrics, progress)),
+            SnapshotPolicy::Custom(f) => f.policy.should_snapshot(metrics, progress),
+        }
+    }
+
+    /// The number of entries a replication target may lag behind before the leader sends it a
+    /// snapshot instead, i.e. the smallest `LogsSinceLast` threshold which may trigger a snapshot.
+    ///
+    /// `None` if the policy does not count entries, in which case a target is only sent a snapshot
+    /// once the entries it lacks have been purged from the leader's log.
+    pub(crate) fn max_lag_entries(&self) -> Option<u64> {
+        match self {
+            SnapshotPolicy::LogsSinceLast(threshold) => Some(*threshold),
+            SnapshotPolicy::Any(policies) => policies.iter().filter_map(|policy| policy.max_lag_entries()).min(),
+            SnapshotPolicy::All(policies) => policies.iter().filter_map(|policy| policy.max_lag_entries()).max(),
+            _ => None,
+        }
+    }
+
+    /// Whether the policy depends on the bytes of the log, which the node then keeps track of.
+    pub(crate) fn uses_log_bytes(&self) -> bool {
+        match self {
+            SnapshotPolicy::LogBytesSinceLast(_) => true,
+            SnapshotPolicy::Any(policies) | SnapshotPolicy::All(policies) => policies.iter().any(Self::uses_log_bytes),
+            _ => false,
+        }
+    }
+
+    /// Whether the policy depends on time, so that it is to be checked periodically.
+    pub(crate) fn uses_time(&self) -> bool {
+        match self {
+            SnapshotPolicy::TimeSinceLast(_) | SnapshotPolicy::Custom(_) => true,
+            SnapshotPolicy::Any(policies) | SnapshotPolicy::All(policies) => policies.iter().any(Self::uses_time),
+            _ => false,
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        match self {
+            SnapshotPolicy::Any(policies) | SnapshotPolicy::All(policies) => {
+                if policies.is_empty() {
+                    return Err(ConfigError::EmptySnapshotPolicy);
+                }
+                policies.iter().try_for_each(Self::validate)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A user supplied policy deciding whether to generate a snapshot, see `SnapshotPolicy::Custom`.
+///
+/// This is implemented for functions taking the same arguments as `should_snapshot`.
+pub trait CustomSnapshotPolicy: Send + Sync + 'static {
+    /// Whether a snapshot is to be generated, given the metrics last reported by the node, and its
+    /// progress since the last snapshot.
+    fn should_snapshot(&self, metrics: &SnapshotMetrics, progress: &SnapshotProgress) -> bool;
+}
+
+impl<F> CustomSnapshotPolicy for F
+where F: Fn(&SnapshotMetrics, &SnapshotProgress) -> bool + Send + Sync + 'static
+{
+    fn should_snapshot(&self, metrics: &SnapshotMetrics, progress: &SnapshotProgress) -> bool {
+        self(metrics, progress)
+    }
+}
+
+/// A `CustomSnapshotPolicy` for `SnapshotPolicy::Custom`.
+#[derive(Clone)]
+pub struct SnapshotPolicyFn {
+    policy: Arc<dyn CustomSnapshotPolicy>,
+}
+
+impl SnapshotPolicyFn {
+    /// Create a new instance from the given policy.
+    pub fn new<P: CustomSnapshotPolicy>(policy: P) -> Self {
+        Self {
+            policy: Arc::new(policy),
+        }
+    }
+}
+
+impl fmt::Debug for SnapshotPolicyFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SnapshotPolicyFn")
+    }
+}
+
+impl PartialEq for SnapshotPolicyFn {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.policy, &other.policy)
+    }
+}
+
+/// The metrics of a node given to `SnapshotPolicy::Custom`: those of its `RaftMetrics` which do not
+/// depend on the type of its node IDs, so that `Config` does not either.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotMetrics {
+    /// The state of the Raft node.
+    pub state: State,
+    /// The current term of the Raft node.
+    pub current_term: u64,
+    /// The last log index to be appended to this Raft node's log.
+    pub last_log_index: u64,
+    /// The last log index to be applied to this Raft node's state machine.
+    pub last_applied: u64,
+    /// The id of the last log included in snapshot.
+    pub snapshot: LogId,
+    /// The number of requests waiting in the queue of requests to the Raft node, see `RaftMetrics::api_queue_len`.
+    pub api_queue_len: u64,
+}
+
+impl<NID: NodeId> From<&RaftMetrics<NID>> for SnapshotMetrics {
+    fn from(metrics: &RaftMetrics<NID>) -> Self {
+        Self {
+            state: metrics.state,
+            current_term: metrics.current_term,
+            last_log_index: metrics.last_log_index,
+            last_applied: metrics.last_applied,
+            snapshot: metrics.snapshot,
+            api_queue_len: metrics.api_queue_len,
+        }
+    }
+}
+
+/// The growth of a node's log since its last snapshot, given to `SnapshotPolicy::Custom` along
+/// with the node's `SnapshotMetrics`.
+#[derive(Clone, Debug)]
+pub struct SnapshotProgress {
+    /// The number of entries applied to the state machine since the last snapshot.
+    pub logs_since_last: u64,
+    /// The bytes of the entries appended to the log since the last snapshot, as sized by
+    /// `Entry::payload_size`.
+    ///
+    /// These are only counted if the config uses the bytes of the log elsewhere, i.e. the snapshot
+    /// policy includes a `LogBytesSinceLast`, e.g. within an `Any`, or `ready_to_join` is
+    /// `MaxLagBytes`. Otherwise this is 0.
+    pub log_bytes_since_last: u64,
+    /// The time since the last snapshot, or since the node started if it has not taken one since.
+    pub since_last: Duration,
+}
+
+/// The policy used by the leader to confirm that it is still the leader before serving a `client_read`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum ReadPolicy {
+    /// The leader exchanges a round of heartbeats with a majority of the cluster for every read (§8).
+    #[default]
+    Heartbeat,
+    /// The leader serves reads locally while it holds a lease (§6.4.1 of the Raft dissertation).
+    ///
+    /// The lease starts when a majority of the cluster has acknowledged the leader, and lasts for
+    /// `election_timeout_min - max_clock_drift` milliseconds, as followers will not vote for another
+    /// candidate within `election_timeout_min` of hearing from the leader. When the lease has expired,
+    /// the leader falls back to a round of heartbeats.
+    ///
+    /// This relies on bounded clock drift between nodes. It is only safe if clocks on different nodes
+    /// do not drift apart by more than `max_clock_drift` milliseconds over an election timeout.
+    Lease {
+        /// The maximum clock drift between nodes, in milliseconds. Must be less than `election_timeout_min`.
+        max_clock_drift: u64,
+    },
+}
+
+/// The policy used by the leader to decide when a non-voter being synced is ready to join the
+/// cluster as a voting member.
+///
+/// A membership change does not proceed until every new member is ready, see `Raft::change_membership`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum ReadyToJoinPolicy {
+    /// Ready once the replication stream to the non-voter runs at line rate, i.e. once it is no more
+    /// than `replication_lag_threshold` entries behind.
+    #[default]
+    LineRate,
+    /// Ready once the non-voter has replicated all but the given number of entries of the leader's log.
+    MaxLagEntries(u64),
+    /// Ready once the entries the non-voter has yet to replicate add up to at most the given number
+    /// of bytes, as sized by `Entry::payload_size`.
+    MaxLagBytes(u64),
+    /// Ready once the non-voter has caught up within the given number of milliseconds.
+    ///
+    /// The leader syncs the non-voter in rounds (§4.2.1 of the Raft dissertation). Each round
+    /// replicates the entries the leader had when it started, and the non-voter is ready once a
+    /// round took no longer than the given time.
+    CaughtUpWithin(u64),
+    /// Ready once the given function returns true.
+    ///
+    /// This variant can not be serialized.
+    #[serde(skip)]
+    Custom(ReadyToJoinFn),
+}
+
+/// A function deciding whether a non-voter is ready to join the cluster, see `ReadyToJoinPolicy::Custom`.
+#[derive(Clone)]
+pub struct ReadyToJoinFn(Arc<dyn Fn(&SyncProgress) -> bool + Send + Sync>);
+
+impl ReadyToJoinFn {
+    /// Create a new instance from the given function.
+    pub fn new<F: Fn(&SyncProgress) -> bool + Send + Sync + 'static>(f: F) -> Self {
+        Self(Arc::new(f))
+    }
+
+    pub(crate) fn call(&self, progress: &SyncProgress) -> bool {
+        (self.0)(progress)
+    }
+}
+
+impl fmt::Debug for ReadyToJoinFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ReadyToJoinFn")
+    }
+}
+
+impl PartialEq for ReadyToJoinFn {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// The progress of a non-voter being synced by the leader, given to `ReadyToJoinPolicy::Custom`.
+#[derive(Clone, Debug)]
+pub struct SyncProgress {
+    /// The last log the non-voter is known to have replicated.
+    pub matched: LogId,
+    /// The index of the last entry in the leader's log.
+    pub last_log_index: u64,
+    /// Whether the replication stream to the non-voter runs at line rate.
+    pub is_line_rate: bool,
+    /// How long the last finished round of catching up took, if one has finished.
+    pub last_round: Option<Duration>,
+    /// How long ago the leader started to sync the non-voter.
+    pub elapsed: Duration,
+}
+
 /// The runtime configuration for a Raft node.
 ///
 /// The default values used by this type should generally work well for Raft clusters which will
@@ -97,6 +376,16 @@ pub struct Config {
     /// up-to-speed. If this is too low, it will take longer for the nodes to be brought up to
     /// consistency with the rest of the cluster.
     pub max_payload_entries: u64,
+    /// The maximum number of bytes of entries per payload allowed to be transmitted during replication.
+    ///
+    /// Entries are sized by `Entry::payload_size`. A payload is cut short before the entry which
+    /// would take it over this limit, but always carries at least one entry, so that an entry
+    /// larger than the limit is still replicated, alone.
+    ///
+    /// This also limits the chunks of snapshots sent to other nodes, see `snapshot_chunk_size`.
+    ///
+    /// Defaults to `None`, i.e. payloads are only limited by `max_payload_entries`.
+    pub max_payload_bytes: Option<u64>,
     /// The distance behind in log replication a follower must fall before it is considered "lagging".
     ///
     /// This configuration parameter controls replication streams from the leader to followers in
@@ -104,12 +393,112 @@ pub struct Config {
     /// entries being replicated, and instead will fetch entries directly from the log until it is
     /// up-to-speed, at which time it will transition out of "lagging" state back into "line-rate" state.
     pub replication_lag_threshold: u64,
+    /// The maximum number of AppendEntries RPCs which may be in flight to a single target at once.
+    ///
+    /// While a replication stream runs at line rate, it sends the next payload of entries without
+    /// waiting for the responses to the previous ones, as long as fewer than this many are
+    /// outstanding. Over a network with high latency, this allows replicating more than one
+    /// payload per round trip. If requests overtake each other on their way to the target, the
+    /// target rejects the ones it receives too early, and the stream resends from the last entry
+    /// the target has acknowledged.
+    ///
+    /// Defaults to 1, i.e. every request waits for the previous one to be answered.
+    pub max_inflight: u64,
     /// The snapshot policy to use for a Raft node.
     pub snapshot_policy: SnapshotPolicy,
     /// The maximum snapshot chunk size allowed when transmitting snapshots (in bytes).
     ///
+    /// Chunks are also limited by `max_payload_bytes`, if that is less, see `snapshot_chunk_size`.
+    ///
     /// Defaults to 3Mib.
     pub snapshot_max_chunk_size: u64,
+    /// The codec with which to compress the chunks of snapshots sent to other nodes.
+    ///
+    /// Chunks are compressed one by one, each being at most `snapshot_chunk_size` bytes before
+    /// compression. Chunks are sent uncompressed if that is larger than
+    /// `compression::MAX_RAW_CHUNK_SIZE`, which the receiving node decompresses chunks up to.
+    ///
+    /// Chunks are only compressed once the receiving node has named the codec as supported in its
+    /// response to an earlier chunk. A node which does not support the codec, or does not know of
+    /// compression at all, is sent the snapshot uncompressed. The codec must be enabled with its
+    /// cargo feature, e.g. `lz4`. Defaults to `None`, i.e. snapshots are sent uncompressed.
+    pub snapshot_compression: Option<SnapshotCompression>,
+    /// Whether candidates should run a PreVote round before starting an election (§9.6 of the
+    /// Raft dissertation).
+    ///
+    /// When enabled, a node whose election timeout fires first asks its peers whether they would
+    /// grant it a vote for the next term, and only increments its term once a quorum agrees. This
+    /// prevents a node which has been partitioned away from disrupting the cluster with a higher
+    /// term once it rejoins.
+    ///
+    /// Defaults to `false`.
+    pub pre_vote: bool,
+    /// Whether the leader should step down when it has lost contact with the cluster (CheckQuorum).
+    ///
+    /// If enabled, the leader reverts to follower state when it has not received a response from
+    /// a majority of the cluster (of both config groups, during joint consensus) within
+    /// `election_timeout_max`. This keeps a leader which has been partitioned away from accepting
+    /// client writes which can never be committed.
+    ///
+    /// Defaults to `false`.
+    pub check_quorum: bool,
+    /// The policy used by the leader to confirm its leadership when serving client reads.
+    ///
+    /// Defaults to `ReadPolicy::Heartbeat`.
+    pub read_policy: ReadPolicy,
+    /// The policy used by the leader to decide when a non-voter being synced is ready to join the cluster.
+    ///
+    /// Defaults to `ReadyToJoinPolicy::LineRate`.
+    pub ready_to_join: ReadyToJoinPolicy,
+    /// The time in milliseconds a membership change may spend syncing new nodes.
+    ///
+    /// If the new nodes are not ready to join the cluster by then, the change is aborted with
+    /// `ChangeConfigError::SyncTimeout`. Defaults to `None`, i.e. syncing takes as long as it needs.
+    pub non_voter_sync_timeout: Option<u64>,
+    /// The maximum number of requests which may be queued up for a Raft node.
+    ///
+    /// Every call of the `Raft` API, including the RPCs from other nodes, is queued up for the
+    /// Raft node to process in order. Once the queue is full, callers wait for room in it, which
+    /// applies backpressure to clients when the node can not keep up, e.g. due to a slow disk.
+    ///
+    /// Defaults to 1024.
+    pub api_queue_capacity: u64,
+    /// The maximum number of events which may be queued up between a Raft leader and its
+    /// replication streams.
+    ///
+    /// This bounds the queue from the leader to each replication stream, as well as the queue
+    /// from all replication streams back to the leader. A replication stream whose queue is full
+    /// catches up from storage once there is room in it again, rather than being sent every event.
+    ///
+    /// Defaults to 1024.
+    pub replication_queue_capacity: u64,
+    /// The time in milliseconds `Raft::client_write` may wait for room in a full request queue.
+    ///
+    /// Once this has passed, the write fails with `ClientWriteError::Overloaded`, without having
+    /// been submitted. `Some(0)` fails at once. Defaults to `None`, i.e. writes wait as long as it
+    /// takes.
+    pub client_write_queue_timeout: Option<u64>,
+    /// Whether a node which is not the leader should forward client writes, linearizable reads
+    /// and membership changes to the leader itself, instead of failing with `ForwardToLeader`.
+    ///
+    /// Writes and membership changes are forwarded with `RaftNetwork::forward_client_write` and
+    /// `RaftNetwork::forward_change_membership`, which must then be implemented, and reads with
+    /// `RaftNetwork::send_read_index`. Writes with a deadline, submitted with
+    /// `Raft::client_write_with_deadline`, are not forwarded. Defaults to `false`.
+    pub forward_to_leader: bool,
+    /// The maximum number of nodes a forwarded request may pass through on its way to the leader.
+    ///
+    /// A node which does not know the leader any better than the node which forwarded the request
+    /// to it forwards it again, e.g. after an election. Once a request has been forwarded this many
+    /// times, or would be forwarded back to a node it passed through, it fails with `ForwardToLeader`
+    /// instead. Defaults to 3.
+    pub max_forward_hops: u64,
+    /// The time in milliseconds after which an idle client session expires, see the `session` module.
+    ///
+    /// Sessions expire by the time at which the leader appended their writes, so that every node
+    /// expires them alike, which requires this to be the same on every node. A client must not
+    /// retry a write for longer than this. Defaults to 1 hour.
+    pub client_session_ttl: u64,
 }
 
 impl Config {
@@ -125,9 +514,23 @@ impl Config {
             heartbeat_interval: None,
             install_snapshot_timeout: None,
             max_payload_entries: None,
+            max_payload_bytes: None,
             replication_lag_threshold: None,
+            max_inflight: None,
             snapshot_policy: None,
             snapshot_max_chunk_size: None,
+            snapshot_compression: None,
+            pre_vote: None,
+            check_quorum: None,
+            read_policy: None,
+            ready_to_join: None,
+            non_voter_sync_timeout: None,
+            api_queue_capacity: None,
+            replication_queue_capacity: None,
+            client_write_queue_timeout: None,
+            forward_to_leader: None,
+            max_forward_hops: None,
+            client_session_ttl: None,
         }
     }
 
@@ -135,6 +538,16 @@ impl Config {
     pub fn new_rand_election_timeout(&self) -> u64 {
         thread_rng().gen_range(self.election_timeout_min..self.election_timeout_max)
     }
+
+    /// The number of bytes of a snapshot sent per InstallSnapshot RPC, before compression.
+    ///
+    /// This is `snapshot_max_chunk_size`, or `max_payload_bytes` if that is less.
+    pub fn snapshot_chunk_size(&self) -> u64 {
+        match self.max_payload_bytes {
+            Some(max_bytes) => std::cmp::min(max_bytes, self.snapshot_max_chunk_size),
+            None => self.snapshot_max_chunk_size,
+        }
+    }
 }
 
 /// A configuration builder to ensure that runtime config is valid.
@@ -157,12 +570,40 @@ pub struct ConfigBuilder {
 
     /// The maximum number of entries per payload allowed to be transmitted during replication.
     pub max_payload_entries: Option<u64>,
+    /// The maximum number of bytes of entries per payload allowed to be transmitted during replication.
+    pub max_payload_bytes: Option<u64>,
     /// The distance behind in log replication a follower must fall before it is considered "lagging".
     pub replication_lag_threshold: Option<u64>,
+    /// The maximum number of AppendEntries RPCs which may be in flight to a single target at once.
+    pub max_inflight: Option<u64>,
     /// The snapshot policy.
     pub snapshot_policy: Option<SnapshotPolicy>,
     /// The maximum snapshot chunk size.
     pub snapshot_max_chunk_size: Option<u64>,
+    /// The codec with which to compress the chunks of snapshots sent to other nodes.
+    pub snapshot_compression: Option<SnapshotCompression>,
+    /// Whether candidates should run a PreVote round before starting an election.
+    pub pre_vote: Option<bool>,
+    /// Whether the leader should step down when it has lost contact with a quorum.
+    pub check_quorum: Option<bool>,
+    /// The policy used by the leader to confirm its leadership when serving client reads.
+    pub read_policy: Option<ReadPolicy>,
+    /// The policy used by the leader to decide when a non-voter is ready to join the cluster.
+    pub ready_to_join: Option<ReadyToJoinPolicy>,
+    /// The time in milliseconds a membership change may spend syncing new nodes.
+    pub non_voter_sync_timeout: Option<u64>,
+    /// The maximum number of requests which may be queued up for a Raft node.
+    pub api_queue_capacity: Option<u64>,
+    /// The maximum number of events which may be queued up between a leader and its replication streams.
+    pub replication_queue_capacity: Option<u64>,
+    /// The time in milliseconds a client write may wait for room in a full request queue.
+    pub client_write_queue_timeout: Option<u64>,
+    /// Whether a node which is not the leader should forward requests to the leader itself.
+    pub forward_to_leader: Option<bool>,
+    /// The maximum number of nodes a forwarded request may pass through on its way to the leader.
+    pub max_forward_hops: Option<u64>,
+    /// The time in milliseconds after which an idle client session expires.
+    pub client_session_ttl: Option<u64>,
 }
 
 impl ConfigBuilder {
@@ -195,12 +636,24 @@ impl ConfigBuilder {
         self
     }
 
+    /// Set the desired value for `max_payload_bytes`.
+    pub fn max_payload_bytes(mut self, val: u64) -> Self {
+        self.max_payload_bytes = Some(val);
+        self
+    }
+
     /// Set the desired value for `replication_lag_threshold`.
     pub fn replication_lag_threshold(mut self, val: u64) -> Self {
         self.replication_lag_threshold = Some(val);
         self
     }
 
+    /// Set the desired value for `max_inflight`.
+    pub fn max_inflight(mut self, val: u64) -> Self {
+        self.max_inflight = Some(val);
+        self
+    }
+
     /// Set the desired value for `snapshot_policy`.
     pub fn snapshot_policy(mut self, val: SnapshotPolicy) -> Self {
         self.snapshot_policy = Some(val);
@@ -213,6 +666,78 @@ impl ConfigBuilder {
         self
     }
 
+    /// Set the desired value for `snapshot_compression`.
+    pub fn snapshot_compression(mut self, val: SnapshotCompression) -> Self {
+        self.snapshot_compression = Some(val);
+        self
+    }
+
+    /// Set the desired value for `pre_vote`.
+    pub fn pre_vote(mut self, val: bool) -> Self {
+        self.pre_vote = Some(val);
+        self
+    }
+
+    /// Set the desired value for `check_quorum`.
+    pub fn check_quorum(mut self, val: bool) -> Self {
+        self.check_quorum = Some(val);
+        self
+    }
+
+    /// Set the desired value for `read_policy`.
+    pub fn read_policy(mut self, val: ReadPolicy) -> Self {
+        self.read_policy = Some(val);
+        self
+    }
+
+    /// Set the desired value for `ready_to_join`.
+    pub fn ready_to_join(mut self, val: ReadyToJoinPolicy) -> Self {
+        self.ready_to_join = Some(val);
+        self
+    }
+
+    /// Set the desired value for `non_voter_sync_timeout`.
+    pub fn non_voter_sync_timeout(mut self, val: u64) -> Self {
+        self.non_voter_sync_timeout = Some(val);
+        self
+    }
+
+    /// Set the desired value for `api_queue_capacity`.
+    pub fn api_queue_capacity(mut self, val: u64) -> Self {
+        self.api_queue_capacity = Some(val);
+        self
+    }
+
+    /// Set the desired value for `replication_queue_capacity`.
+    pub fn replication_queue_capacity(mut self, val: u64) -> Self {
+        self.replication_queue_capacity = Some(val);
+        self
+    }
+
+    /// Set the desired value for `client_write_queue_timeout`.
+    pub fn client_write_queue_timeout(mut self, val: u64) -> Self {
+        self.client_write_queue_timeout = Some(val);
+        self
+    }
+
+    /// Set the desired value for `forward_to_leader`.
+    pub fn forward_to_leader(mut self, val: bool) -> Self {
+        self.forward_to_leader = Some(val);
+        self
+    }
+
+    /// Set the desired value for `max_forward_hops`.
+    pub fn max_forward_hops(mut self, val: u64) -> Self {
+        self.max_forward_hops = Some(val);
+        self
+    }
+
+    /// Set the desired value for `client_session_ttl`.
+    pub fn client_session_ttl(mut self, val: u64) -> Self {
+        self.client_session_ttl = Some(val);
+        self
+    }
+
     /// Validate the state of this builder and produce a new `Config` instance if valid.
     pub fn validate(self) -> Result<Config, ConfigError> {
         // Roll a random election time out based on the configured min & max or their respective defaults.
@@ -233,9 +758,44 @@ impl ConfigBuilder {
         if max_payload_entries == 0 {
             return Err(ConfigError::MaxPayloadEntriesTooSmall);
         }
+        if self.max_payload_bytes == Some(0) {
+            return Err(ConfigError::MaxPayloadBytesTooSmall);
+        }
         let replication_lag_threshold = self.replication_lag_threshold.unwrap_or(DEFAULT_REPLICATION_LAG_THRESHOLD);
+        let max_inflight = self.max_inflight.unwrap_or(DEFAULT_MAX_INFLIGHT);
+        if max_inflight == 0 {
+            return Err(ConfigError::MaxInflightTooSmall);
+        }
         let snapshot_policy = self.snapshot_policy.unwrap_or_else(SnapshotPolicy::default);
+        snapshot_policy.validate()?;
         let snapshot_max_chunk_size = self.snapshot_max_chunk_size.unwrap_or(DEFAULT_SNAPSHOT_CHUNKSIZE);
+        if let Some(codec) = self.snapshot_compression {
+            if !codec.is_supported() {
+                return Err(ConfigError::SnapshotCompressionUnsupported(codec));
+            }
+        }
+        let pre_vote = self.pre_vote.unwrap_or(false);
+        let check_quorum = self.check_quorum.unwrap_or(false);
+        let read_policy = self.read_policy.unwrap_or_default();
+        if let ReadPolicy::Lease { max_clock_drift } = &read_policy {
+            if *max_clock_drift >= election_timeout_min {
+                return Err(ConfigError::InvalidMaxClockDrift);
+            }
+        }
+        let ready_to_join = self.ready_to_join.unwrap_or_default();
+        let api_queue_capacity = self.api_queue_capacity.unwrap_or(DEFAULT_API_QUEUE_CAPACITY);
+        if api_queue_capacity == 0 {
+            return Err(ConfigError::ApiQueueCapacityTooSmall);
+        }
+        let replication_queue_capacity = self.replication_queue_capacity.unwrap_or(DEFAULT_REPLICATION_QUEUE_CAPACITY);
+        if replication_queue_capacity == 0 {
+            return Err(ConfigError::ReplicationQueueCapacityTooSmall);
+        }
+        let forward_to_leader = self.forward_to_leader.unwrap_or(false);
+        let max_forward_hops = self.max_forward_hops.unwrap_or(DEFAULT_MAX_FORWARD_HOPS);
+        if max_forward_hops == 0 {
+            return Err(ConfigError::MaxForwardHopsTooSmall);
+        }
         Ok(Config {
             cluster_name: self.cluster_name,
             election_timeout_min,
@@ -243,9 +803,23 @@ impl ConfigBuilder {
             heartbeat_interval,
             install_snapshot_timeout,
             max_payload_entries,
+            max_payload_bytes: self.max_payload_bytes,
             replication_lag_threshold,
+            max_inflight,
             snapshot_policy,
             snapshot_max_chunk_size,
+            snapshot_compression: self.snapshot_compression,
+            pre_vote,
+            check_quorum,
+            read_policy,
+            ready_to_join,
+            non_voter_sync_timeout: self.non_voter_sync_timeout,
+            api_queue_capacity,
+            replication_queue_capacity,
+            client_write_queue_timeout: self.client_write_queue_timeout,
+            forward_to_leader,
+            max_forward_hops,
+            client_session_ttl: self.client_session_ttl.unwrap_or(DEFAULT_CLIENT_SESSION_TTL),
         })
     }
 }
@@ -265,9 +839,24 @@ mod tests {
         assert!(cfg.election_timeout_max <= DEFAULT_ELECTION_TIMEOUT_MAX as u64);
         assert!(cfg.heartbeat_interval == DEFAULT_HEARTBEAT_INTERVAL as u64);
         assert!(cfg.max_payload_entries == DEFAULT_MAX_PAYLOAD_ENTRIES);
+        assert!(cfg.max_payload_bytes.is_none());
         assert!(cfg.replication_lag_threshold == DEFAULT_REPLICATION_LAG_THRESHOLD);
+        assert!(cfg.max_inflight == DEFAULT_MAX_INFLIGHT);
         assert!(cfg.snapshot_max_chunk_size == DEFAULT_SNAPSHOT_CHUNKSIZE);
+        assert!(cfg.snapshot_chunk_size() == DEFAULT_SNAPSHOT_CHUNKSIZE);
         assert!(cfg.snapshot_policy == SnapshotPolicy::LogsSinceLast(DEFAULT_LOGS_SINCE_LAST));
+        assert!(cfg.snapshot_compression.is_none());
+        assert!(!cfg.pre_vote);
+        assert!(!cfg.check_quorum);
+        assert!(cfg.read_policy == ReadPolicy::Heartbeat);
+        assert!(cfg.ready_to_join == ReadyToJoinPolicy::LineRate);
+        assert!(cfg.non_voter_sync_timeout.is_none());
+        assert!(cfg.api_queue_capacity == DEFAULT_API_QUEUE_CAPACITY);
+        assert!(cfg.replication_queue_capacity == DEFAULT_REPLICATION_QUEUE_CAPACITY);
+        assert!(cfg.client_write_queue_timeout.is_none());
+        assert!(!cfg.forward_to_leader);
+        assert!(cfg.max_forward_hops == DEFAULT_MAX_FORWARD_HOPS);
+        assert!(cfg.client_session_ttl == DEFAULT_CLIENT_SESSION_TTL);
     }
 
     #[test]
@@ -277,9 +866,22 @@ mod tests {
             .election_timeout_min(100)
             .heartbeat_interval(10)
             .max_payload_entries(100)
+            .max_payload_bytes(4096)
             .replication_lag_threshold(100)
+            .max_inflight(8)
             .snapshot_max_chunk_size(200)
             .snapshot_policy(SnapshotPolicy::LogsSinceLast(10000))
+            .pre_vote(true)
+            .check_quorum(true)
+            .read_policy(ReadPolicy::Lease { max_clock_drift: 10 })
+            .ready_to_join(ReadyToJoinPolicy::CaughtUpWithin(100))
+            .non_voter_sync_timeout(5000)
+            .api_queue_capacity(64)
+            .replication_queue_capacity(32)
+            .client_write_queue_timeout(100)
+            .forward_to_leader(true)
+            .max_forward_hops(5)
+            .client_session_ttl(60_000)
             .validate()
             .unwrap();
 
@@ -287,9 +889,23 @@ mod tests {
         assert!(cfg.election_timeout_max <= 200);
         assert!(cfg.heartbeat_interval == 10);
         assert!(cfg.max_payload_entries == 100);
+        assert!(cfg.max_payload_bytes == Some(4096));
         assert!(cfg.replication_lag_threshold == 100);
+        assert!(cfg.max_inflight == 8);
         assert!(cfg.snapshot_max_chunk_size == 200);
+        assert!(cfg.snapshot_chunk_size() == 200);
         assert!(cfg.snapshot_policy == SnapshotPolicy::LogsSinceLast(10000));
+        assert!(cfg.pre_vote);
+        assert!(cfg.check_quorum);
+        assert!(cfg.read_policy == ReadPolicy::Lease { max_clock_drift: 10 });
+        assert!(cfg.ready_to_join == ReadyToJoinPolicy::CaughtUpWithin(100));
+        assert!(cfg.non_voter_sync_timeout == Some(5000));
+        assert!(cfg.api_queue_capacity == 64);
+        assert!(cfg.replication_queue_capacity == 32);
+        assert!(cfg.client_write_queue_timeout == Some(100));
+        assert!(cfg.forward_to_leader);
+        assert!(cfg.max_forward_hops == 5);
+        assert!(cfg.client_session_ttl == 60_000);
     }
 
     #[test]
@@ -299,4 +915,123 @@ mod tests {
         let err = res.unwrap_err();
         assert_eq!(err, ConfigError::InvalidElectionTimeoutMinMax);
     }
+
+    #[test]
+    fn test_invalid_max_clock_drift_produces_expected_error() {
+        let res = Config::build("cluster0".into())
+            .election_timeout_min(100)
+            .election_timeout_max(200)
+            .read_policy(ReadPolicy::Lease { max_clock_drift: 100 })
+            .validate();
+        assert!(res.is_err());
+        let err = res.unwrap_err();
+        assert_eq!(err, ConfigError::InvalidMaxClockDrift);
+    }
+
+    #[test]
+    fn test_invalid_max_payload_bytes_produces_expected_error() {
+        let res = Config::build("cluster0".into()).max_payload_bytes(0).validate();
+        assert!(res.is_err());
+        let err = res.unwrap_err();
+        assert_eq!(err, ConfigError::MaxPayloadBytesTooSmall);
+    }
+
+    #[test]
+    fn test_invalid_api_queue_capacity_produces_expected_error() {
+        let res = Config::build("cluster0".into()).api_queue_capacity(0).validate();
+        assert!(res.is_err());
+        let err = res.unwrap_err();
+        assert_eq!(err, ConfigError::ApiQueueCapacityTooSmall);
+    }
+
+    #[test]
+    fn test_invalid_replication_queue_capacity_produces_expected_error() {
+        let res = Config::build("cluster0".into()).replication_queue_capacity(0).validate();
+        assert!(res.is_err());
+        let err = res.unwrap_err();
+        assert_eq!(err, ConfigError::ReplicationQueueCapacityTooSmall);
+    }
+
+    #[test]
+    fn test_invalid_max_forward_hops_produces_expected_error() {
+        let res = Config::build("cluster0".into()).max_forward_hops(0).validate();
+        assert!(res.is_err());
+        let err = res.unwrap_err();
+        assert_eq!(err, ConfigError::MaxForwardHopsTooSmall);
+    }
+
+    #[test]
+    fn test_snapshot_compression_requires_its_feature() {
+        let res = Config::build("cluster0".into()).snapshot_compression(SnapshotCompression::Lz4).validate();
+        if cfg!(feature = "lz4") {
+            assert!(res.unwrap().snapshot_compression == Some(SnapshotCompression::Lz4));
+        } else {
+            assert_eq!(
+                res.unwrap_err(),
+                ConfigError::SnapshotCompressionUnsupported(SnapshotCompression::Lz4)
+            );
+        }
+    }
+
+    #[test]
+    fn test_snapshot_policy() {
+        let mut metrics = SnapshotMetrics::from(&RaftMetrics::new_initial(0u64));
+        metrics.state = State::Leader;
+        metrics.snapshot = LogId { term: 1, index: 10 };
+        let progress = SnapshotProgress {
+            logs_since_last: 100,
+            log_bytes_since_last: 4096,
+            since_last: Duration::from_millis(500),
+        };
+
+        assert!(SnapshotPolicy::LogsSinceLast(100).should_snapshot(&metrics, &progress));
+        assert!(!SnapshotPolicy::LogsSinceLast(101).should_snapshot(&metrics, &progress));
+        assert!(SnapshotPolicy::LogBytesSinceLast(4096).should_snapshot(&metrics, &progress));
+        assert!(!SnapshotPolicy::LogBytesSinceLast(4097).should_snapshot(&metrics, &progress));
+        assert!(SnapshotPolicy::TimeSinceLast(500).should_snapshot(&metrics, &progress));
+        assert!(!SnapshotPolicy::TimeSinceLast(501).should_snapshot(&metrics, &progress));
+
+        let any = SnapshotPolicy::Any(vec![
+            SnapshotPolicy::LogsSinceLast(1000),
+            SnapshotPolicy::TimeSinceLast(100),
+        ]);
+        assert!(any.should_snapshot(&metrics, &progress));
+        let all = SnapshotPolicy::All(vec![
+            SnapshotPolicy::LogsSinceLast(50),
+            SnapshotPolicy::TimeSinceLast(1000),
+        ]);
+        assert!(!all.should_snapshot(&metrics, &progress));
+
+        let custom = SnapshotPolicy::Custom(SnapshotPolicyFn::new(
+            |metrics: &SnapshotMetrics, _: &SnapshotProgress| metrics.state == State::Follower,
+        ));
+        assert!(!custom.should_snapshot(&metrics, &progress));
+        metrics.state = State::Follower;
+        assert!(custom.should_snapshot(&metrics, &progress));
+
+        assert_eq!(Some(50), all.max_lag_entries());
+        assert_eq!(Some(1000), any.max_lag_entries());
+        assert_eq!(None, SnapshotPolicy::TimeSinceLast(100).max_lag_entries());
+        assert!(!any.uses_log_bytes());
+        assert!(any.uses_time());
+        assert!(!custom.uses_log_bytes());
+        assert!(custom.uses_time());
+    }
+
+    #[test]
+    fn test_invalid_snapshot_policy_produces_expected_error() {
+        let policy = SnapshotPolicy::Any(vec![SnapshotPolicy::All(vec![])]);
+        let res = Config::build("cluster0".into()).snapshot_policy(policy).validate();
+        assert!(res.is_err());
+        let err = res.unwrap_err();
+        assert_eq!(err, ConfigError::EmptySnapshotPolicy);
+    }
+
+    #[test]
+    fn test_invalid_max_inflight_produces_expected_error() {
+        let res = Config::build("cluster0".into()).max_inflight(0).validate();
+        assert!(res.is_err());
+        let err = res.unwrap_err();
+        assert_eq!(err, ConfigError::MaxInflightTooSmall);
+    }
 }
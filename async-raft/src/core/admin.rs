@@ -1,18 +1,30 @@
+use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::collections::HashSet;
 
+use tokio::sync::oneshot;
+use tokio::time::Duration;
+use tokio::time::Instant;
+use tracing_futures::Instrument;
+
 use crate::core::client::ClientRequestEntry;
 use crate::core::ConsensusState;
 use crate::core::LeaderState;
+use crate::core::LeaderTransfer;
 use crate::core::NonVoterReplicationState;
 use crate::core::NonVoterState;
 use crate::core::State;
 use crate::core::UpdateCurrentLeader;
 use crate::error::ChangeConfigError;
 use crate::error::InitializeError;
+use crate::error::TransferLeaderError;
+use crate::raft::ChangeMembershipPhase;
+use crate::raft::ChangeMembershipProgress;
 use crate::raft::ClientWriteRequest;
 use crate::raft::MembershipConfig;
+use crate::raft::Node;
 use crate::raft::ResponseTx;
+use crate::raft::TimeoutNowRequest;
 use crate::replication::RaftEvent;
 use crate::AppData;
 use crate::AppDataResponse;
@@ -21,13 +33,12 @@ use crate::RaftError;
 use crate::RaftNetwork;
 use crate::RaftStorage;
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> NonVoterState<'a, D, R, N, S> {
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D, R, NID>, S: RaftStorage<D, R, NID>, NID: NodeId>
+    NonVoterState<'a, D, R, N, S, NID>
+{
     /// Handle the admin `init_with_config` command.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub(super) async fn handle_init_with_config(
-        &mut self,
-        mut members: BTreeSet<NodeId>,
-    ) -> Result<(), InitializeError> {
+    pub(super) async fn handle_init_with_config(&mut self, mut members: BTreeSet<NID>) -> Result<(), InitializeError> {
         if self.core.last_log_id.index != 0 || self.core.current_term != 0 {
             tracing::error!({self.core.last_log_id.index, self.core.current_term}, "rejecting init_with_config request as last_log_index or current_term is 0");
             return Err(InitializeError::NotAllowed);
@@ -35,7 +46,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
         // Ensure given config contains this nodes ID as well.
         if !members.contains(&self.core.id) {
-            members.insert(self.core.id);
+            members.insert(self.core.id.clone());
         }
 
         // Build a new membership config from given init data & assign it as the new cluster
@@ -43,6 +54,8 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         self.core.membership = MembershipConfig {
             members,
             members_after_consensus: None,
+            learners: BTreeSet::new(),
+            nodes: BTreeMap::new(),
         };
 
         // Become a candidate and start campaigning for leadership. If this node is the only node
@@ -50,7 +63,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         // know it is our ID due to the above code where we ensure our own ID is present.
         if self.core.membership.members.len() == 1 {
             self.core.current_term += 1;
-            self.core.voted_for = Some(self.core.id);
+            self.core.voted_for = Some(self.core.id.clone());
             self.core.set_target_state(State::Leader);
             self.core.save_hard_state().await?;
         } else {
@@ -61,11 +74,13 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     }
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> LeaderState<'a, D, R, N, S> {
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D, R, NID>, S: RaftStorage<D, R, NID>, NID: NodeId>
+    LeaderState<'a, D, R, N, S, NID>
+{
     /// Add a new node to the cluster as a non-voter, bringing it up-to-speed, and then responding
     /// on the given channel.
     #[tracing::instrument(level = "trace", skip(self, tx))]
-    pub(super) fn add_member(&mut self, target: NodeId, tx: ResponseTx) {
+    pub(super) async fn add_member(&mut self, target: NID, node: Option<Node>, tx: ResponseTx<NID>) {
         // Ensure the node doesn't already exist in the current config, in the set of new nodes
         // alreading being synced, or in the nodes being removed.
         if self.core.membership.members.contains(&target)
@@ -83,18 +98,97 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             return;
         }
 
+        // Learners are recorded in the membership config, which may only change in a uniform consensus state.
+        if !matches!(self.consensus_state, ConsensusState::Uniform) {
+            let _ = tx.send(Err(ChangeConfigError::ConfigChangeInProgress.into()));
+            return;
+        }
+
+        // Record the new learner in the log, so that future leaders replicate to it as well.
+        let mut membership = self.core.membership.clone();
+        membership.learners.insert(target.clone());
+        if let Some(node) = node {
+            membership.nodes.insert(target.clone(), node);
+        }
+        if let Err(err) = self.append_membership_log(membership.clone(), None).await {
+            tracing::error!("append learner log error: {:?}", err);
+            let _ = tx.send(Err(err.into()));
+            return;
+        }
+        self.core.membership = membership;
+
         // Spawn a replication stream for the new member. Track state as a non-voter so that it
         // can be updated to be added to the cluster config once it has been brought up-to-date.
-        let state = self.spawn_replication_stream(target);
-        self.non_voters.insert(target, NonVoterReplicationState {
-            state,
-            is_ready_to_join: false,
-            tx: Some(tx),
-        });
+        let state = self.spawn_replication_stream(target.clone());
+        let state = NonVoterReplicationState::new(state, self.core.last_log_id.index, Some(tx));
+        self.non_voters.insert(target, state);
+        self.leader_report_metrics();
     }
 
+    /// Remove a non-voter, terminating its replication stream.
     #[tracing::instrument(level = "trace", skip(self, tx))]
-    pub(super) async fn change_membership(&mut self, members: BTreeSet<NodeId>, tx: ResponseTx) {
+    pub(super) async fn remove_non_voter(&mut self, target: NID, tx: ResponseTx<NID>) {
+        if !self.non_voters.contains_key(&target) {
+            let _ = tx.send(Err(ChangeConfigError::NotNonVoter(target).into()));
+            return;
+        }
+
+        // Non-voters being synced for a membership change are needed to finish it.
+        if let ConsensusState::NonVoterSync { awaiting, .. } = &self.consensus_state {
+            if awaiting.contains(&target) {
+                let _ = tx.send(Err(ChangeConfigError::ConfigChangeInProgress.into()));
+                return;
+            }
+        }
+
+        let is_learner = self.core.membership.learners.contains(&target);
+        if is_learner && !matches!(self.consensus_state, ConsensusState::Uniform) {
+            let _ = tx.send(Err(ChangeConfigError::ConfigChangeInProgress.into()));
+            return;
+        }
+
+        // Take the node out of replication first, so that the removed node does not receive the config change
+        // removing it. It is only terminated once the change is in the log, as appending may fail.
+        let node = self.non_voters.remove(&target);
+        if is_learner {
+            let mut membership = self.core.membership.clone();
+            membership.learners.remove(&target);
+            membership.nodes.remove(&target);
+            if let Err(err) = self.append_membership_log(membership.clone(), None).await {
+                tracing::error!("append learner log error: {:?}", err);
+                if let Some(node) = node {
+                    self.non_voters.insert(target, node);
+                }
+                let _ = tx.send(Err(err.into()));
+                return;
+            }
+            self.core.membership = membership;
+        }
+
+        if let Some(mut node) = node {
+            node.state.replstream.send(RaftEvent::Terminate);
+            if let Some(add_tx) = node.tx {
+                let _ = add_tx.send(Err(ChangeConfigError::NonVoterRemoved(target.clone()).into()));
+            }
+        }
+        self.leader_metrics.replication.remove(&target);
+        self.leader_report_metrics();
+
+        let _ = tx.send(Ok(0));
+    }
+
+    /// Start a joint consensus membership change.
+    ///
+    /// If `progress` is given, the change was started with `Raft::start_change_membership`, and
+    /// each phase it reaches is reported through it.
+    #[tracing::instrument(level = "trace", skip(self, progress, tx))]
+    pub(super) async fn change_membership(
+        &mut self,
+        members: BTreeSet<NID>,
+        nodes: BTreeMap<NID, Node>,
+        progress: Option<ChangeMembershipProgress>,
+        tx: ResponseTx<NID>,
+    ) {
         // Ensure cluster will have at least one node.
         if members.is_empty() {
             let _ = tx.send(Err(ChangeConfigError::InoperableConfig.into()));
@@ -104,12 +198,18 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         // Only allow config updates when currently in a uniform consensus state.
         match &self.consensus_state {
             ConsensusState::Uniform => (),
-            ConsensusState::NonVoterSync { .. } | ConsensusState::Joint { .. } => {
+            ConsensusState::NonVoterSync { .. } | ConsensusState::Joint { .. } | ConsensusState::Single { .. } => {
                 let _ = tx.send(Err(ChangeConfigError::ConfigChangeInProgress.into()));
                 return;
             }
         }
 
+        // The change is accepted. When this routine is called again once new nodes have been
+        // synced, the progress reporter is kept from the first call.
+        if progress.is_some() {
+            self.change_progress = progress;
+        }
+
         // Check the proposed config for any new nodes. If ALL new nodes already have replication
         // streams AND are ready to join, then we can immediately proceed with entering joint
         // consensus. Else, new nodes need to first be brought up-to-speed.
@@ -127,20 +227,25 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                 None => {
                     // Spawn a replication stream for the new member. Track state as a non-voter so that it
                     // can be updated to be added to the cluster config once it has been brought up-to-date.
-                    let state = self.spawn_replication_stream(*new_node);
-                    self.non_voters.insert(*new_node, NonVoterReplicationState {
-                        state,
-                        is_ready_to_join: false,
-                        tx: None,
-                    });
+                    let state = self.spawn_replication_stream(new_node.clone());
+                    let state = NonVoterReplicationState::new(state, self.core.last_log_id.index, None);
+                    self.non_voters.insert(new_node.clone(), state);
                 }
             }
-            awaiting.insert(*new_node);
+            awaiting.insert(new_node.clone());
         }
         // If there are new nodes which need to sync, then we need to wait until they are synced.
         // Once they've finished, this routine will be called again to progress further.
         if !awaiting.is_empty() {
-            self.consensus_state = ConsensusState::NonVoterSync { awaiting, members, tx };
+            self.consensus_state = ConsensusState::NonVoterSync {
+                awaiting,
+                members,
+                nodes,
+                single: false,
+                deadline: self.sync_deadline(),
+                tx,
+            };
+            self.report_change_phase(ChangeMembershipPhase::Syncing);
             return;
         }
 
@@ -150,16 +255,25 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         }
         self.consensus_state = ConsensusState::Joint { is_committed: false };
         self.core.membership.members_after_consensus = Some(members.clone());
+        // Learners which become voting members are no longer learners.
+        for id in members.iter() {
+            self.core.membership.learners.remove(id);
+        }
+        // The joint config carries the metadata of both the old and the new members.
+        for (id, node) in nodes {
+            if members.contains(&id) {
+                self.core.membership.nodes.insert(id, node);
+            }
+        }
 
         // Create final_config first, the joint config may be committed at once if the cluster has only 1 node
         // and changes core.membership.
-        let final_config = MembershipConfig {
-            members: members.clone(),
-            members_after_consensus: None,
-        };
+        let final_config = self.core.membership.to_final_config();
 
         let joint_config = self.core.membership.clone();
 
+        self.report_change_phase(ChangeMembershipPhase::Joint);
+
         let res = self.append_membership_log(joint_config, None).await;
         if let Err(e) = res {
             tracing::error!("append joint log error: {:?}", e);
@@ -171,13 +285,190 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         }
     }
 
-    #[tracing::instrument(level = "trace", skip(self, resp_tx), fields(id=self.core.id))]
+    /// Cancel the membership change with the given ID, if it is still syncing new nodes.
+    #[tracing::instrument(level = "trace", skip(self, tx))]
+    pub(super) fn cancel_change_membership(&mut self, id: u64, tx: ResponseTx<NID>) {
+        let is_syncing = matches!(self.consensus_state, ConsensusState::NonVoterSync { .. });
+        let is_target = self.change_progress.as_ref().map(|progress| progress.id == id).unwrap_or(false);
+        if !is_syncing || !is_target {
+            let _ = tx.send(Err(ChangeConfigError::NotSyncing.into()));
+            return;
+        }
+
+        self.abort_non_voter_sync(ChangeConfigError::Cancelled);
+        let _ = tx.send(Ok(0));
+    }
+
+    /// Abort the membership change which is syncing new nodes, as they were not ready to join in time.
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub(super) fn abort_non_voter_sync_timeout(&mut self) {
+        if let ConsensusState::NonVoterSync { awaiting, .. } = &self.consensus_state {
+            let mut not_ready = awaiting.iter().cloned().collect::<Vec<_>>();
+            not_ready.sort();
+            self.abort_non_voter_sync(ChangeConfigError::SyncTimeout(not_ready));
+        }
+    }
+
+    /// Abort the membership change which is syncing new nodes, responding with the given error.
+    ///
+    /// Nodes which were being synced only for this change stop being replicated to. Non-voters
+    /// added with `Raft::add_non_voter` are recorded as learners, and keep being replicated to.
+    fn abort_non_voter_sync(&mut self, err: ChangeConfigError<NID>) {
+        if let ConsensusState::NonVoterSync {
+            awaiting,
+            tx: change_tx,
+            ..
+        } = std::mem::replace(&mut self.consensus_state, ConsensusState::Uniform)
+        {
+            for target in awaiting {
+                if self.core.membership.learners.contains(&target) {
+                    continue;
+                }
+                if let Some(mut node) = self.non_voters.remove(&target) {
+                    node.state.replstream.send(RaftEvent::Terminate);
+                }
+                self.leader_metrics.replication.remove(&target);
+            }
+            let _ = change_tx.send(Err(err.into()));
+        }
+        self.change_progress = None;
+        self.leader_report_metrics();
+    }
+
+    /// The time at which a membership change starting to sync new nodes now is aborted.
+    fn sync_deadline(&self) -> Option<Instant> {
+        self.core.config.non_voter_sync_timeout.map(|ms| Instant::now() + Duration::from_millis(ms))
+    }
+
+    /// Report the phase the membership change in progress has reached, if it was started with
+    /// `Raft::start_change_membership`.
+    pub(super) fn report_change_phase(&mut self, phase: ChangeMembershipPhase) {
+        if let Some(progress) = &self.change_progress {
+            let _ = progress.tx.send(phase);
+        }
+        if phase == ChangeMembershipPhase::FinalCommitted {
+            self.change_progress = None;
+        }
+    }
+
+    /// Add a voter with a single-server membership change.
+    #[tracing::instrument(level = "trace", skip(self, tx))]
+    pub(super) async fn add_voter(&mut self, target: NID, node: Option<Node>, tx: ResponseTx<NID>) {
+        let mut members = self.core.membership.members.clone();
+        members.insert(target.clone());
+        let nodes = node.into_iter().map(|node| (target.clone(), node)).collect();
+        self.change_membership_single(members, nodes, tx).await;
+    }
+
+    /// Remove a voter with a single-server membership change.
+    #[tracing::instrument(level = "trace", skip(self, tx))]
+    pub(super) async fn remove_voter(&mut self, target: NID, tx: ResponseTx<NID>) {
+        let mut members = self.core.membership.members.clone();
+        members.remove(&target);
+        self.change_membership_single(members, BTreeMap::new(), tx).await;
+    }
+
+    /// Change the membership by a single voter, without going through joint consensus (§4.1 of the
+    /// Raft dissertation).
+    ///
+    /// The new config takes effect as soon as it is appended. No other change may start until it
+    /// has been committed, which also holds for any config change left over from an earlier term.
+    #[tracing::instrument(level = "trace", skip(self, tx))]
+    pub(super) async fn change_membership_single(
+        &mut self,
+        members: BTreeSet<NID>,
+        nodes: BTreeMap<NID, Node>,
+        tx: ResponseTx<NID>,
+    ) {
+        // Ensure cluster will have at least one node.
+        if members.is_empty() {
+            let _ = tx.send(Err(ChangeConfigError::InoperableConfig.into()));
+            return;
+        }
+
+        // The initial entry of this term commits every entry before it, including the config
+        // entries of earlier leaders.
+        if !matches!(self.consensus_state, ConsensusState::Uniform) || self.core.commit_index < self.initial_entry_index
+        {
+            let _ = tx.send(Err(ChangeConfigError::ConfigChangeInProgress.into()));
+            return;
+        }
+
+        let mut changed = members.symmetric_difference(&self.core.membership.members);
+        let target = match (changed.next(), changed.next()) {
+            (Some(target), None) => target.clone(),
+            (None, _) => {
+                let _ = tx.send(Err(ChangeConfigError::Noop.into()));
+                return;
+            }
+            (Some(_), Some(_)) => {
+                let _ = tx.send(Err(ChangeConfigError::NotSingleChange.into()));
+                return;
+            }
+        };
+
+        if members.contains(&target) {
+            // A new voter needs to be brought up-to-speed first, as it counts towards the commit index
+            // right away. Once it is, this routine will be called again to progress further.
+            match self.non_voters.get(&target) {
+                Some(node) if node.is_ready_to_join => (),
+                non_voter => {
+                    if non_voter.is_none() {
+                        let state = self.spawn_replication_stream(target.clone());
+                        let state = NonVoterReplicationState::new(state, self.core.last_log_id.index, None);
+                        self.non_voters.insert(target.clone(), state);
+                    }
+                    let mut awaiting = HashSet::new();
+                    awaiting.insert(target);
+                    self.consensus_state = ConsensusState::NonVoterSync {
+                        awaiting,
+                        members,
+                        nodes,
+                        single: true,
+                        deadline: self.sync_deadline(),
+                        tx,
+                    };
+                    self.leader_report_metrics();
+                    return;
+                }
+            }
+
+            if let Some(non_voter) = self.non_voters.remove(&target) {
+                self.nodes.insert(target.clone(), non_voter.state);
+            }
+            self.core.membership.learners.remove(&target);
+        } else if target == self.core.id {
+            self.is_stepping_down = true;
+        }
+
+        self.core.membership.members = members;
+        for (id, node) in nodes {
+            if self.core.membership.members.contains(&id) {
+                self.core.membership.nodes.insert(id, node);
+            }
+        }
+        self.core.membership.retain_known_nodes();
+
+        // The replication stream of a removed voter is dropped once it has replicated this entry,
+        // see `handle_uniform_consensus_committed`.
+        self.consensus_state = ConsensusState::Single {
+            index: self.core.last_log_id.index + 1,
+        };
+        self.leader_report_metrics();
+
+        let res = self.append_membership_log(self.core.membership.clone(), Some(tx)).await;
+        if let Err(e) = res {
+            tracing::error!("append single-server change log error: {:?}", e);
+        }
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, resp_tx), fields(id=%self.core.id))]
     pub async fn append_membership_log(
         &mut self,
-        mem: MembershipConfig,
-        resp_tx: Option<ResponseTx>,
+        mem: MembershipConfig<NID>,
+        resp_tx: Option<ResponseTx<NID>>,
     ) -> Result<(), RaftError> {
-        let payload = ClientWriteRequest::<D>::new_config(mem);
+        let payload = ClientWriteRequest::<D, NID>::new_config(mem);
         let res = self.append_payload_to_log(payload.entry).await;
         let entry = match res {
             Ok(entry) => entry,
@@ -205,6 +496,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         if let ConsensusState::Joint { is_committed, .. } = &mut self.consensus_state {
             *is_committed = true; // Mark as committed.
         }
+        self.report_change_phase(ChangeMembershipPhase::JointCommitted);
         // Only proceed to finalize this joint consensus if there are no remaining nodes being synced.
         if self.consensus_state.is_joint_consensus_safe_to_finalize() {
             self.update_replication_state();
@@ -255,7 +547,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             }
 
             let non_voter_state = self.non_voters.remove(node_id).unwrap();
-            self.nodes.insert(*node_id, non_voter_state.state);
+            self.nodes.insert(node_id.clone(), non_voter_state.state);
         }
     }
 
@@ -271,6 +563,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         // Cut the cluster config over to the new membership config.
         if let Some(new_members) = self.core.membership.members_after_consensus.take() {
             self.core.membership.members = new_members;
+            self.core.membership.retain_known_nodes();
         }
         self.consensus_state = ConsensusState::Uniform;
 
@@ -288,6 +581,21 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     /// Handle the commitment of a uniform consensus cluster configuration.
     #[tracing::instrument(level = "trace", skip(self))]
     pub(super) fn handle_uniform_consensus_committed(&mut self, index: u64) {
+        // The first uniform config committed after the joint config is the final config.
+        let is_final = self
+            .change_progress
+            .as_ref()
+            .map(|progress| *progress.tx.borrow() == ChangeMembershipPhase::JointCommitted);
+        if is_final.unwrap_or(false) {
+            self.report_change_phase(ChangeMembershipPhase::FinalCommitted);
+        }
+
+        if let ConsensusState::Single { index: change_index } = self.consensus_state {
+            if index >= change_index {
+                self.consensus_state = ConsensusState::Uniform;
+            }
+        }
+
         // Step down if needed.
         if self.is_stepping_down {
             tracing::debug!("raft node is stepping down");
@@ -306,7 +614,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             .filter(|(id, _)| !membership.contains(id))
             .filter_map(|(idx, replstate)| {
                 if replstate.matched.index >= index {
-                    Some(*idx)
+                    Some(idx.clone())
                 } else {
                     replstate.remove_after_commit = Some(index);
                     None
@@ -314,17 +622,17 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             })
             .collect();
 
-        let follower_ids: Vec<u64> = self.nodes.keys().cloned().collect();
-        let non_voter_ids: Vec<u64> = self.non_voters.keys().cloned().collect();
+        let follower_ids: Vec<NID> = self.nodes.keys().cloned().collect();
+        let non_voter_ids: Vec<NID> = self.non_voters.keys().cloned().collect();
         tracing::debug!("nodes: {:?}", follower_ids);
         tracing::debug!("non_voters: {:?}", non_voter_ids);
         tracing::debug!("membership: {:?}", self.core.membership);
         tracing::debug!("nodes_to_remove: {:?}", nodes_to_remove);
 
         for target in nodes_to_remove {
-            tracing::debug!(target, "removing target node from replication pool");
-            if let Some(node) = self.nodes.remove(&target) {
-                let _ = node.replstream.repl_tx.send((RaftEvent::Terminate, tracing::debug_span!("CH")));
+            tracing::debug!(target=%target, "removing target node from replication pool");
+            if let Some(mut node) = self.nodes.remove(&target) {
+                node.replstream.send(RaftEvent::Terminate);
 
                 // remove metrics entry
                 self.leader_metrics.replication.remove(&target);
@@ -332,4 +640,118 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         }
         self.leader_report_metrics();
     }
+
+    /// Start a leadership transfer to the given target (§3.10 of the Raft dissertation).
+    ///
+    /// Client writes are rejected until the transfer has finished. The TimeoutNow RPC is sent once
+    /// the target's log matches the leader's, which may be at once.
+    #[tracing::instrument(level = "trace", skip(self, tx))]
+    pub(super) fn transfer_leadership(
+        &mut self,
+        target: NID,
+        tx: oneshot::Sender<Result<(), TransferLeaderError<NID>>>,
+    ) {
+        if self.leader_transfer.is_some() {
+            let _ = tx.send(Err(TransferLeaderError::TransferInProgress));
+            return;
+        }
+
+        // Nothing to do if this node is the target.
+        if target == self.core.id {
+            let _ = tx.send(Ok(()));
+            return;
+        }
+
+        if !self.core.membership.contains(&target) || !self.nodes.contains_key(&target) {
+            let _ = tx.send(Err(TransferLeaderError::InvalidTarget(target)));
+            return;
+        }
+
+        // The dissertation suggests aborting the transfer if it has not completed within an election timeout.
+        let deadline = Instant::now() + Duration::from_millis(self.core.config.election_timeout_max);
+        self.leader_transfer = Some(LeaderTransfer {
+            target,
+            deadline,
+            timeout_now_sent: false,
+            tx,
+        });
+        self.send_timeout_now_if_ready();
+    }
+
+    /// Send the TimeoutNow RPC to the target of the current leadership transfer, if the target's
+    /// log is up-to-date and the RPC has not yet been sent.
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub(super) fn send_timeout_now_if_ready(&mut self) {
+        let transfer = match &mut self.leader_transfer {
+            Some(transfer) if !transfer.timeout_now_sent => transfer,
+            _ => return,
+        };
+
+        let matched = match self.nodes.get(&transfer.target) {
+            Some(state) => state.matched,
+            None => return,
+        };
+        if matched.index < self.core.last_log_id.index {
+            return;
+        }
+
+        transfer.timeout_now_sent = true;
+
+        let target = transfer.target.clone();
+        let rpc = TimeoutNowRequest {
+            term: self.core.current_term,
+            leader_id: self.core.id.clone(),
+        };
+        let network = self.core.network.clone();
+        let span = tracing::debug_span!("sending TimeoutNow", target=%target);
+        tokio::spawn(
+            async move {
+                if let Err(err) = network.send_timeout_now(target.clone(), rpc).await {
+                    tracing::error!({error=%err, target=%target}, "error while sending TimeoutNow to target");
+                }
+            }
+            .instrument(span),
+        );
+    }
+
+    /// Abort the current leadership transfer, as the target did not take over in time.
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub(super) fn abort_leader_transfer(&mut self) {
+        if let Some(transfer) = self.leader_transfer.take() {
+            // The target may still act on a delayed TimeoutNow, and peers vote for it regardless of
+            // this node's heartbeats. Acknowledgements of earlier requests must not back a read lease.
+            if transfer.timeout_now_sent {
+                self.lease_not_before = Some(Instant::now());
+            }
+            let _ = transfer.tx.send(Err(TransferLeaderError::Timeout(transfer.target)));
+        }
+    }
+
+    /// Respond to the current leadership transfer, if any, as this node is no longer the leader.
+    ///
+    /// If the target was asked to take over, the response waits until this node learns who the new
+    /// leader is, giving the election another election timeout to finish.
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub(super) fn finish_leader_transfer(&mut self) {
+        let mut transfer = match self.leader_transfer.take() {
+            Some(transfer) => transfer,
+            None => return,
+        };
+
+        if self.core.target_state == State::Shutdown {
+            let _ = transfer.tx.send(Err(RaftError::ShuttingDown.into()));
+        } else if transfer.timeout_now_sent {
+            transfer.deadline = Instant::now() + Duration::from_millis(self.core.config.election_timeout_max);
+            self.core.stepped_down_transfer = Some(transfer);
+            // The new leader may be known already, if it is what made this node step down.
+            if self.core.current_leader.as_ref() != Some(&self.core.id) {
+                self.core.resolve_stepped_down_transfer();
+            }
+        } else {
+            // This node stepped down for some other reason before the target was asked to take over.
+            let _ = transfer.tx.send(Err(TransferLeaderError::NodeNotLeader(
+                self.core.current_leader.clone(),
+            )));
+        }
+    }
 }
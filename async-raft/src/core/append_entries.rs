@@ -1,5 +1,6 @@
 use tracing::Instrument;
 
+use crate::core::apply_to_state_machine;
 use crate::core::RaftCore;
 use crate::core::State;
 use crate::core::UpdateCurrentLeader;
@@ -13,19 +14,22 @@ use crate::AppData;
 use crate::AppDataResponse;
 use crate::LogId;
 use crate::MessageSummary;
+use crate::NodeId;
 use crate::RaftError;
 use crate::RaftNetwork;
 use crate::RaftStorage;
 use crate::Update;
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> RaftCore<D, R, N, S> {
+impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D, R, NID>, S: RaftStorage<D, R, NID>, NID: NodeId>
+    RaftCore<D, R, N, S, NID>
+{
     /// An RPC invoked by the leader to replicate log entries (§5.3); also used as heartbeat (§5.2).
     ///
     /// See `receiver implementation: AppendEntries RPC` in raft-essentials.md in this repo.
     #[tracing::instrument(level="trace", skip(self, msg), fields(msg=%msg.summary()))]
     pub(super) async fn handle_append_entries_request(
         &mut self,
-        msg: AppendEntriesRequest<D>,
+        msg: AppendEntriesRequest<D, NID>,
     ) -> RaftResult<AppendEntriesResponse> {
         tracing::debug!(%self.last_log_id);
 
@@ -43,6 +47,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         }
 
         self.update_next_election_timeout(true);
+        self.leader_commit_index = msg.leader_commit;
 
         // Caveat: Because we can not just delete `log[prev_log_id.index..]`, (which results in loss of committed
         // entry), the commit index must be update only after append-entries
@@ -89,7 +94,10 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         }
 
         if prev_log_id.index == u64::MIN || prev_log_id == self.last_log_id {
-            // Matches! Great!
+            // Matches! Great! Skip the entries this node already has, in case they were sent again.
+            if self.last_log_id.index > prev_log_id.index {
+                msg_entries = self.delete_inconsistent_log(prev_log_id, msg_entries).await?;
+            }
             return self.append_apply_log_entries(msg_entries, valid_commit_index).await;
         }
 
@@ -130,10 +138,13 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         // The target entry was found. Compare its term with target term to ensure everything is consistent.
         if local_prev_log_id == prev_log_id {
             // We've found a point of agreement with the leader. If we have any logs present
-            // with an index greater than this, then we must delete them per §5.3.
-
+            // with an index greater than this, then we must delete the inconsistent ones per §5.3,
+            // and skip the entries we already have.
+            //
+            // A heartbeat carries no entries, so nothing is compared with it or deleted, even if it lags behind the
+            // entries this node has already received.
             if self.last_log_id.index > prev_log_id.index {
-                self.delete_inconsistent_log(prev_log_id, msg_entries).await?;
+                msg_entries = self.delete_inconsistent_log(prev_log_id, msg_entries).await?;
             }
             tracing::debug!("end log consistency check");
 
@@ -166,8 +177,17 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         Ok(entry.log_id)
     }
 
+    /// Delete the log entries after `prev_log_id` which conflict with the given ones, and return the given entries
+    /// which are not in the log already, to be appended to it.
+    ///
+    /// The leader may send entries this node already has, e.g. when a replication stream is restarted, or resends
+    /// a payload whose response was lost, so appending them must be idempotent.
     #[tracing::instrument(level="debug", skip(self, msg_entries), fields(msg_entries=%msg_entries.summary()))]
-    async fn delete_inconsistent_log(&mut self, prev_log_id: LogId, msg_entries: &[Entry<D>]) -> RaftResult<()> {
+    async fn delete_inconsistent_log<'e>(
+        &mut self,
+        prev_log_id: LogId,
+        msg_entries: &'e [Entry<D, NID>],
+    ) -> RaftResult<&'e [Entry<D, NID>]> {
         // Caveat: Deleting then appending entries are not atomic, thus deleting consistent entries may cause loss of
         // committed logs.
         //
@@ -188,21 +208,34 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         // **The safe way is to skip every entry that present in append_entries message then delete only the
         // inconsistent entries**.
 
-        let end = std::cmp::min(prev_log_id.index + msg_entries.len() as u64, self.last_log_id.index + 1);
+        // Entries up to the last applied one are committed, thus consistent with the leader, and may have been
+        // purged from the log.
+        let applied = std::cmp::min(
+            self.last_applied.index.saturating_sub(prev_log_id.index),
+            msg_entries.len() as u64,
+        );
+        let (prev_log_id, msg_entries) = match applied as usize {
+            0 => (prev_log_id, msg_entries),
+            n => (msg_entries[n - 1].log_id, &msg_entries[n..]),
+        };
+
+        // The given entries follow `prev_log_id`. Compare those this node has a log entry at.
+        let start = prev_log_id.index + 1;
+        let end = std::cmp::min(start + msg_entries.len() as u64, self.last_log_id.index + 1);
+        if start >= end {
+            return Ok(msg_entries);
+        }
 
         tracing::debug!(
             "find and delete inconsistent log entries [{}, {}), last_log_id: {}, entries: {}",
-            prev_log_id,
+            start,
             end,
             self.last_log_id,
             msg_entries.summary()
         );
 
-        let entries = self
-            .storage
-            .get_log_entries(prev_log_id.index..end)
-            .await
-            .map_err(|err| self.map_fatal_storage_error(err))?;
+        let entries =
+            self.storage.get_log_entries(start..end).await.map_err(|err| self.map_fatal_storage_error(err))?;
 
         for (i, ent) in entries.iter().enumerate() {
             if ent.log_id.term != msg_entries[i].log_id.term {
@@ -212,16 +245,19 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                     .delete_logs_from(ent.log_id.index..)
                     .await
                     .map_err(|err| self.map_fatal_storage_error(err))?;
+                self.last_log_id = if i == 0 { prev_log_id } else { entries[i - 1].log_id };
 
                 let membership =
                     self.storage.get_membership_config().await.map_err(|err| self.map_fatal_storage_error(err))?;
 
                 self.update_membership(membership)?;
 
-                break;
+                return Ok(&msg_entries[i..]);
             }
         }
-        Ok(())
+
+        // Every entry compared is already in the log.
+        Ok(&msg_entries[entries.len()..])
     }
 
     /// Walks backward 50 entries to find the last log entry that has the same `term` as `prev_log_id`, which is
@@ -257,7 +293,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     #[tracing::instrument(level="debug", skip(self, entries), fields(entries=%entries.summary()))]
     async fn append_apply_log_entries(
         &mut self,
-        entries: &[Entry<D>],
+        entries: &[Entry<D, NID>],
         commit_index: u64,
     ) -> RaftResult<AppendEntriesResponse> {
         if !entries.is_empty() {
@@ -282,7 +318,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// Configuration changes are also detected and applied here. See `configuration changes`
     /// in the raft-essentials.md in this repo.
     #[tracing::instrument(level = "trace", skip(self, entries))]
-    async fn append_log_entries(&mut self, entries: &[Entry<D>]) -> RaftResult<()> {
+    async fn append_log_entries(&mut self, entries: &[Entry<D, NID>]) -> RaftResult<()> {
         // Check the given entries for any config changes and take the most recent.
         let last_conf_change = entries
             .iter()
@@ -299,6 +335,9 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         // Replicate entries to log (same as append, but in follower mode).
         let entry_refs = entries.iter().collect::<Vec<_>>();
         self.storage.append_to_log(&entry_refs).await.map_err(|err| self.map_fatal_storage_error(err))?;
+        for entry in entries.iter() {
+            self.log_sizes.append(entry);
+        }
         if let Some(entry) = entries.last() {
             self.last_log_id = entry.log_id;
         }
@@ -366,12 +405,13 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         // to ensure that only a single task can replicate data to the state machine, and that is
         // owned by a single task, not shared between multiple threads/tasks.
         let storage = self.storage.clone();
+        let sessions = self.client_sessions.clone();
         let handle = tokio::spawn(
             async move {
                 // Create a new vector of references to the entries data ... might have to change this
                 // interface a bit before 1.0.
                 let entries_refs: Vec<_> = entries.iter().collect();
-                storage.apply_to_state_machine(&entries_refs).await?;
+                apply_to_state_machine(&*storage, &sessions, &entries_refs).await?;
                 Ok(last_log_id)
             }
             .instrument(tracing::debug_span!("spawn")),
@@ -390,6 +430,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         let stop = std::cmp::min(self.commit_index, self.last_log_id.index) + 1;
         let start = self.last_applied.index + 1;
         let storage = self.storage.clone();
+        let sessions = self.client_sessions.clone();
 
         // If we already have an active replication task, then do nothing.
         if !self.replicate_to_sm_handle.is_empty() {
@@ -416,7 +457,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 if data_entries.is_empty() {
                     return Ok(new_last_applied);
                 }
-                storage.apply_to_state_machine(&data_entries).await?;
+                apply_to_state_machine(&*storage, &sessions, &data_entries).await?;
                 Ok(new_last_applied)
             }
             .instrument(tracing::debug_span!("spawn-init-replicate-to-sm")),
@@ -6,60 +6,87 @@ use futures::stream::FuturesUnordered;
 use futures::stream::StreamExt;
 use tokio::time::timeout;
 use tokio::time::Duration;
+use tokio::time::Instant;
 use tracing::Instrument;
+use tracing::Span;
 
+use crate::config::ReadPolicy;
+use crate::core::apply_to_state_machine;
 use crate::core::LeaderState;
+use crate::core::RaftCore;
 use crate::core::State;
 use crate::error::ClientReadError;
 use crate::error::ClientWriteError;
 use crate::error::RaftError;
 use crate::error::RaftResult;
 use crate::error::ResponseError;
+use crate::error::StaleReadError;
 use crate::quorum;
 use crate::raft::AppendEntriesRequest;
 use crate::raft::ClientReadResponseTx;
+use crate::raft::ClientWriteBatch;
 use crate::raft::ClientWriteRequest;
 use crate::raft::ClientWriteResponse;
 use crate::raft::ClientWriteResponseTx;
 use crate::raft::Entry;
+use crate::raft::EntryNormal;
 use crate::raft::EntryPayload;
+use crate::raft::MaxLag;
+use crate::raft::RaftMsg;
+use crate::raft::ReadIndexRequest;
+use crate::raft::ReadIndexResponseTx;
 use crate::raft::ResponseTx;
+use crate::raft::StaleReadResponseTx;
 use crate::replication::RaftEvent;
 use crate::AppData;
 use crate::AppDataResponse;
 use crate::LogId;
+use crate::NodeId;
 use crate::RaftNetwork;
 use crate::RaftStorage;
 
+/// The least length of `LeaderState::awaiting_committed` at which abandoned requests are pruned from it.
+pub(super) const AWAITING_COMMITTED_PRUNE_MIN: usize = 64;
+
 /// A wrapper around a ClientRequest which has been transformed into an Entry, along with its response channel.
-pub(super) struct ClientRequestEntry<D: AppData, R: AppDataResponse> {
+pub(super) struct ClientRequestEntry<D: AppData, R: AppDataResponse, NID: NodeId> {
     /// The Arc'd entry of the ClientRequest.
     ///
     /// This value is Arc'd so that it may be sent across thread boundaries for replication
     /// without having to clone the data payload itself.
-    pub entry: Arc<Entry<D>>,
+    pub entry: Arc<Entry<D, NID>>,
     /// The response channel for the request.
-    pub tx: ClientOrInternalResponseTx<D, R>,
+    pub tx: ClientOrInternalResponseTx<D, R, NID>,
 }
 
-impl<D: AppData, R: AppDataResponse> ClientRequestEntry<D, R> {
+impl<D: AppData, R: AppDataResponse, NID: NodeId> ClientRequestEntry<D, R, NID> {
     /// Create a new instance from the raw components of a client request.
-    pub(crate) fn from_entry<T: Into<ClientOrInternalResponseTx<D, R>>>(entry: Entry<D>, tx: T) -> Self {
+    pub(crate) fn from_entry<T: Into<ClientOrInternalResponseTx<D, R, NID>>>(entry: Entry<D, NID>, tx: T) -> Self {
         Self {
             entry: Arc::new(entry),
             tx: tx.into(),
         }
     }
+
+    /// Whether the client which sent this request has stopped waiting for its response.
+    pub(crate) fn is_abandoned(&self) -> bool {
+        match &self.tx {
+            ClientOrInternalResponseTx::Client(tx) => tx.is_closed(),
+            ClientOrInternalResponseTx::Internal(_) => false,
+        }
+    }
 }
 
 /// An enum type wrapping either a client response channel or an internal Raft response channel.
 #[derive(derive_more::From)]
-pub enum ClientOrInternalResponseTx<D: AppData, R: AppDataResponse> {
-    Client(ClientWriteResponseTx<D, R>),
-    Internal(Option<ResponseTx>),
+pub enum ClientOrInternalResponseTx<D: AppData, R: AppDataResponse, NID: NodeId> {
+    Client(ClientWriteResponseTx<D, R, NID>),
+    Internal(Option<ResponseTx<NID>>),
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> LeaderState<'a, D, R, N, S> {
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D, R, NID>, S: RaftStorage<D, R, NID>, NID: NodeId>
+    LeaderState<'a, D, R, N, S, NID>
+{
     /// Commit the initial entry which new leaders are obligated to create when first coming to power, per §8.
     #[tracing::instrument(level = "trace", skip(self))]
     pub(super) async fn commit_initial_leader_entry(&mut self) -> RaftResult<()> {
@@ -67,7 +94,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         // config, else a blank payload.
         let last_index = self.core.last_log_id.index;
 
-        let req: ClientWriteRequest<D> = if last_index == 0 {
+        let req: ClientWriteRequest<D, NID> = if last_index == 0 {
             ClientWriteRequest::new_config(self.core.membership.clone())
         } else {
             // Complete a partial member-change:
@@ -101,6 +128,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         // Commit the initial payload to the cluster.
         let entry = self.append_payload_to_log(req.entry).await?;
         self.core.last_log_id.term = self.core.current_term; // This only ever needs to be updated once per term.
+        self.initial_entry_index = entry.log_id.index;
 
         let cr_entry = ClientRequestEntry::from_entry(entry, None);
         self.replicate_client_request(cr_entry).await;
@@ -109,6 +137,68 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     }
 
     /// Handle client read requests.
+    #[tracing::instrument(level = "trace", skip(self, tx))]
+    pub(super) async fn handle_client_read_request(&mut self, tx: ClientReadResponseTx<NID>) {
+        let _ = tx.send(self.confirm_leadership().await);
+    }
+
+    /// Handle read index requests (§6.4 of the Raft dissertation).
+    ///
+    /// Once leadership is confirmed, respond with the commit index. A new leader does not know
+    /// the latest commit index until it has committed an entry in its term, so requests are held
+    /// back until then.
+    ///
+    /// An RPC which was sent to another node as the leader, or whose sender has seen a later term
+    /// than this leader's, is refused: this node may have been deposed.
+    #[tracing::instrument(level = "trace", skip(self, tx))]
+    pub(super) async fn handle_read_index_request(
+        &mut self,
+        rpc: Option<ReadIndexRequest<NID>>,
+        tx: ReadIndexResponseTx<NID>,
+    ) {
+        if let Some(rpc) = &rpc {
+            if rpc.leader_id != self.core.id || rpc.term > self.core.current_term {
+                tracing::debug!(
+                    { self.core.current_term, rpc.term, %rpc.leader_id },
+                    "refusing ReadIndex RPC sent to another leader or in a later term"
+                );
+                let _ = tx.send(Err(ClientReadError::ForwardToLeader(None)));
+                return;
+            }
+        }
+
+        if let Err(err) = self.confirm_leadership().await {
+            let _ = tx.send(Err(err));
+            return;
+        }
+        self.awaiting_read_index.push(tx);
+        self.respond_read_index_requests();
+    }
+
+    /// Respond to the read index requests awaiting a commit in this leader's term, if there is one.
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub(super) fn respond_read_index_requests(&mut self) {
+        if self.awaiting_read_index.is_empty() || self.core.commit_index < self.initial_entry_index {
+            return;
+        }
+
+        // All entries from the initial entry on are from this leader's term.
+        let read_index = LogId {
+            term: self.core.current_term,
+            index: self.core.commit_index,
+        };
+        for tx in self.awaiting_read_index.drain(..) {
+            let _ = tx.send(Ok(read_index));
+        }
+    }
+
+    /// Handle stale read requests, measuring the time since a quorum of the cluster last acknowledged this leader.
+    #[tracing::instrument(level = "trace", skip(self, tx))]
+    pub(super) fn handle_stale_read_request(&self, max_lag: MaxLag, tx: StaleReadResponseTx) {
+        let _ = tx.send(self.core.check_staleness(max_lag, self.calc_quorum_acked_at(), self.core.commit_index));
+    }
+
+    /// Confirm that this node is still the cluster leader, in order to serve a read request.
     ///
     /// Spawn requests to all members of the cluster, include members being added in joint
     /// consensus. Each request will have a timeout, and we respond once we have a majority
@@ -120,8 +210,16 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     /// request (its information may be stale if a more recent leader has been elected). Raft
     /// handles this by having the leader exchange heartbeat messages with a majority of the
     /// cluster before responding to read-only requests.
-    #[tracing::instrument(level = "trace", skip(self, tx))]
-    pub(super) async fn handle_client_read_request(&mut self, tx: ClientReadResponseTx) {
+    ///
+    /// With `ReadPolicy::Lease`, leadership is confirmed at once while the leader's lease is valid.
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn confirm_leadership(&mut self) -> Result<(), ClientReadError<NID>> {
+        if let ReadPolicy::Lease { max_clock_drift } = self.core.config.read_policy {
+            if self.is_lease_valid(max_clock_drift) {
+                return Ok(());
+            }
+        }
+
         // Setup sentinel values to track when we've received majority confirmation of leadership.
         let mut c0_confirmed = 0usize;
         // Will never be zero, as we don't allow it when proposing config changes.
@@ -152,8 +250,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         // If we already have all needed confirmations — which would be the case for single node
         // clusters — then respond.
         if c0_confirmed >= c0_needed && c1_confirmed >= c1_needed {
-            let _ = tx.send(Ok(()));
-            return;
+            return Ok(());
         }
 
         // Spawn parallel requests, all with the standard timeout for heartbeats.
@@ -161,17 +258,17 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         for (id, node) in self.nodes.iter() {
             let rpc = AppendEntriesRequest {
                 term: self.core.current_term,
-                leader_id: self.core.id,
+                leader_id: self.core.id.clone(),
                 prev_log_id: node.matched,
                 entries: vec![],
                 leader_commit: self.core.commit_index,
             };
-            let target = *id;
+            let target = id.clone();
             let network = self.core.network.clone();
             let ttl = Duration::from_millis(self.core.config.heartbeat_interval);
             let task = tokio::spawn(
                 async move {
-                    match timeout(ttl, network.send_append_entries(target, rpc)).await {
+                    match timeout(ttl, network.send_append_entries(target.clone(), rpc)).await {
                         Ok(Ok(data)) => Ok((target, data)),
                         Ok(Err(err)) => Err((target, err)),
                         Err(_timeout) => Err((target, anyhow!("timeout waiting for leadership confirmation"))),
@@ -179,7 +276,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                 }
                 .instrument(tracing::debug_span!("spawn")),
             )
-            .map_err(move |err| (*id, err));
+            .map_err(move |err| (id.clone(), err));
             pending.push(task);
         }
 
@@ -189,11 +286,11 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             let (target, data) = match res {
                 Ok(Ok(res)) => res,
                 Ok(Err((target, err))) => {
-                    tracing::error!(target, error=%err, "timeout while confirming leadership for read request");
+                    tracing::error!(target=%target, error=%err, "timeout while confirming leadership for read request");
                     continue;
                 }
                 Err((target, err)) => {
-                    tracing::error!(target, "{}", err);
+                    tracing::error!(target=%target, "{}", err);
                     continue;
                 }
             };
@@ -219,55 +316,154 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                 c1_confirmed += 1;
             }
             if c0_confirmed >= c0_needed && c1_confirmed >= c1_needed {
-                let _ = tx.send(Ok(()));
-                return;
+                return Ok(());
             }
         }
 
         // If we've hit this location, then we've failed to gather needed confirmations due to
         // request failures.
-        let _ = tx.send(Err(ClientReadError::RaftError(RaftError::RaftNetwork(anyhow!(
+        Err(ClientReadError::RaftError(RaftError::RaftNetwork(anyhow!(
             "too many requests failed, could not confirm leadership"
-        )))));
+        ))))
     }
 
-    /// Handle client write requests.
-    #[tracing::instrument(level = "trace", skip(self, rpc, tx))]
-    pub(super) async fn handle_client_write_request(
+    /// Check if this leader holds a valid read lease (§6.4.1 of the Raft dissertation).
+    ///
+    /// Followers do not vote for another candidate within `election_timeout_min` of hearing from
+    /// the leader, so no other leader can be elected until that long after a majority has last
+    /// acknowledged this one, minus the allowed clock drift.
+    fn is_lease_valid(&self, max_clock_drift: u64) -> bool {
+        // A leadership transfer lets the target bypass the above rule.
+        if self.leader_transfer.is_some() {
+            return false;
+        }
+
+        let acked_at = match self.calc_quorum_acked_at() {
+            Some(acked_at) if self.lease_not_before.map(|not_before| acked_at >= not_before).unwrap_or(true) => {
+                acked_at
+            }
+            _ => return false,
+        };
+        let lease = Duration::from_millis(self.core.config.election_timeout_min.saturating_sub(max_clock_drift));
+        Instant::now() < acked_at + lease
+    }
+
+    /// Move the client write requests queued behind the first of a batch into the batch.
+    ///
+    /// Requests are drained until a message of another kind is found, which is returned to be
+    /// handled after the batch, or until the batch holds `max_payload_entries` requests.
+    #[tracing::instrument(level = "trace", skip(self, writes))]
+    pub(super) fn drain_client_write_requests(
         &mut self,
-        rpc: ClientWriteRequest<D>,
-        tx: ClientWriteResponseTx<D, R>,
-    ) {
-        let entry = match self.append_payload_to_log(rpc.entry).await {
-            Ok(entry) => ClientRequestEntry::from_entry(entry, tx),
+        writes: &mut ClientWriteBatch<D, R, NID>,
+    ) -> Option<(RaftMsg<D, R, NID>, Span)> {
+        while (writes.len() as u64) < self.core.config.max_payload_entries {
+            match self.core.rx_api.try_recv() {
+                Ok((RaftMsg::ClientWriteRequest { rpc, tx }, _)) => writes.push((rpc, tx)),
+                Ok((RaftMsg::ClientWriteBatch { rpcs }, _)) => writes.extend(rpcs),
+                Ok(msg) => return Some(msg),
+                Err(_) => break,
+            }
+        }
+        None
+    }
+
+    /// Handle a batch of client write requests.
+    ///
+    /// The entries of the batch are appended to the log at once, and replicated together, but
+    /// each request gets its own response.
+    #[tracing::instrument(level = "trace", skip(self, writes), fields(writes = writes.len()))]
+    pub(super) async fn handle_client_write_requests(&mut self, writes: ClientWriteBatch<D, R, NID>) {
+        // Writes are not accepted while leadership is being handed over, so that the target can catch up.
+        // The new leader is not known yet, hence no leader hint.
+        if self.leader_transfer.is_some() {
+            for (rpc, tx) in writes {
+                let _ = tx.send(Err(rpc.into_error(|data| ClientWriteError::ForwardToLeader(data, None))));
+            }
+            return;
+        }
+
+        // Requests whose deadline passed before they could be appended, or whose caller has gone away since, are
+        // turned away.
+        let mut appendable = Vec::with_capacity(writes.len());
+        for (rpc, tx) in writes {
+            let missed = match &rpc.deadline {
+                Some(deadline) => (tx.is_closed() && deadline.try_abandon()) || !deadline.try_append(),
+                None => false,
+            };
+            if missed {
+                let _ = tx.send(Err(rpc.into_error(ClientWriteError::NotAppended)));
+            } else {
+                appendable.push((rpc, tx));
+            }
+        }
+        if appendable.is_empty() {
+            return;
+        }
+
+        let (payloads, txs): (Vec<_>, Vec<_>) = appendable.into_iter().map(|(rpc, tx)| (rpc.entry, tx)).unzip();
+        let entries = match self.append_payloads_to_log(payloads).await {
+            Ok(entries) => entries,
             Err(err) => {
-                let _ = tx.send(Err(ClientWriteError::RaftError(err)));
+                // A storage error is fatal, so the node is shutting down.
+                let mut err = Some(err);
+                for tx in txs {
+                    let err = err.take().unwrap_or(RaftError::ShuttingDown);
+                    let _ = tx.send(Err(ClientWriteError::RaftError(err)));
+                }
                 return;
             }
         };
-        self.replicate_client_request(entry).await;
+        let reqs = entries.into_iter().zip(txs).map(|(entry, tx)| ClientRequestEntry::from_entry(entry, tx)).collect();
+        self.replicate_client_requests(reqs).await;
     }
 
     /// Transform the given payload into an entry, assign an index and term, and append the entry to the log.
     #[tracing::instrument(level = "trace", skip(self, payload))]
-    pub(super) async fn append_payload_to_log(&mut self, payload: EntryPayload<D>) -> RaftResult<Entry<D>> {
-        let entry = Entry {
-            log_id: LogId {
-                index: self.core.last_log_id.index + 1,
-                term: self.core.current_term,
-            },
-            payload,
-        };
-        self.core
-            .storage
-            .append_to_log(&[&entry])
-            .await
-            .map_err(|err| self.core.map_fatal_storage_error(err))?;
-        self.core.last_log_id.index = entry.log_id.index;
+    pub(super) async fn append_payload_to_log(&mut self, payload: EntryPayload<D, NID>) -> RaftResult<Entry<D, NID>> {
+        let mut entries = self.append_payloads_to_log(vec![payload]).await?;
+        Ok(entries.remove(0))
+    }
+
+    /// Transform the given payloads into entries with consecutive indexes, and append them to the log at once.
+    #[tracing::instrument(level = "trace", skip(self, payloads))]
+    pub(super) async fn append_payloads_to_log(
+        &mut self,
+        payloads: Vec<EntryPayload<D, NID>>,
+    ) -> RaftResult<Vec<Entry<D, NID>>> {
+        let first_index = self.core.last_log_id.index + 1;
+        let entries: Vec<_> = payloads
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut payload)| {
+                // Client sessions expire by the time at which the leader appended their writes.
+                if let EntryPayload::Normal(EntryNormal {
+                    session: Some(session), ..
+                }) = &mut payload
+                {
+                    session.stamp();
+                }
+                Entry {
+                    log_id: LogId {
+                        index: first_index + i as u64,
+                        term: self.core.current_term,
+                    },
+                    payload,
+                }
+            })
+            .collect();
+        let refs: Vec<_> = entries.iter().collect();
+        self.core.storage.append_to_log(&refs).await.map_err(|err| self.core.map_fatal_storage_error(err))?;
+        for entry in entries.iter() {
+            self.core.log_sizes.append(entry);
+        }
+        if let Some(last) = entries.last() {
+            self.core.last_log_id.index = last.log_id.index;
+        }
 
         self.leader_report_metrics();
 
-        Ok(entry)
+        Ok(entries)
     }
 
     /// Begin the process of replicating the given client request.
@@ -276,62 +472,98 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     /// merely beings the process. Once the request is committed to the cluster, its response will
     /// be generated asynchronously.
     #[tracing::instrument(level = "trace", skip(self, req))]
-    pub(super) async fn replicate_client_request(&mut self, req: ClientRequestEntry<D, R>) {
-        // Replicate the request if there are other cluster members. The client response will be
-        // returned elsewhere after the entry has been committed to the cluster.
-        let entry_arc = req.entry.clone();
+    pub(super) async fn replicate_client_request(&mut self, req: ClientRequestEntry<D, R, NID>) {
+        self.replicate_client_requests(vec![req]).await;
+    }
+
+    /// Begin the process of replicating the given client requests, whose entries are consecutive
+    /// and the last ones in the log.
+    ///
+    /// The entries are sent to each replication stream in a single event.
+    #[tracing::instrument(level = "trace", skip(self, reqs))]
+    pub(super) async fn replicate_client_requests(&mut self, reqs: Vec<ClientRequestEntry<D, R, NID>>) {
+        // Replicate the requests if there are other cluster members. The client responses will be
+        // returned elsewhere after the entries have been committed to the cluster.
+        let entries: Vec<_> = reqs.iter().map(|req| req.entry.clone()).collect();
+        let last_index = match entries.last() {
+            Some(last) => last.log_id.index,
+            None => return,
+        };
 
         if self.nodes.is_empty() && self.non_voters.is_empty() {
-            // Else, there are no voting nodes for replication, so the payload is now committed.
-            self.core.commit_index = entry_arc.log_id.index;
+            // Else, there are no voting nodes for replication, so the payloads are now committed.
+            self.core.commit_index = last_index;
             self.leader_report_metrics();
-            self.client_request_post_commit(req).await;
+            for req in reqs {
+                self.client_request_post_commit(req).await;
+            }
+            self.respond_read_index_requests();
             return;
         }
 
-        self.awaiting_committed.push(req);
+        if self.awaiting_committed.len() >= self.awaiting_committed_prune_at {
+            self.prune_abandoned_client_requests();
+        }
+        self.awaiting_committed.extend(reqs);
 
         if !self.nodes.is_empty() {
-            for node in self.nodes.values() {
-                let _ = node.replstream.repl_tx.send((
-                    RaftEvent::Replicate {
-                        entry: entry_arc.clone(),
-                        commit_index: self.core.commit_index,
-                    },
-                    tracing::debug_span!("CH"),
-                ));
+            for node in self.nodes.values_mut() {
+                node.replstream.send(RaftEvent::Replicate {
+                    entries: entries.clone(),
+                    commit_index: self.core.commit_index,
+                });
             }
         }
 
         if !self.non_voters.is_empty() {
             // Replicate to non-voters.
-            for node in self.non_voters.values() {
-                let _ = node.state.replstream.repl_tx.send((
-                    RaftEvent::Replicate {
-                        entry: entry_arc.clone(),
-                        commit_index: self.core.commit_index,
-                    },
-                    tracing::debug_span!("CH"),
-                ));
+            for node in self.non_voters.values_mut() {
+                node.state.replstream.send(RaftEvent::Replicate {
+                    entries: entries.clone(),
+                    commit_index: self.core.commit_index,
+                });
             }
         }
     }
 
+    /// Drop the requests awaiting commitment which their clients have abandoned, e.g. after giving up
+    /// on them while the cluster has no quorum, so that their response channels are not kept around.
+    ///
+    /// Their entries are still applied to the state machine once committed, as applying the entry
+    /// of a later request first applies all entries before it. So the last request is always kept.
+    ///
+    /// This is called every heartbeat interval, and when adding further requests once their number has
+    /// doubled since the last scan, which keeps it cheap under load.
+    pub(super) fn prune_abandoned_client_requests(&mut self) {
+        let last = self.awaiting_committed.len().saturating_sub(1);
+        let mut idx = 0;
+        self.awaiting_committed.retain(|req| {
+            let keep = idx == last || !req.is_abandoned();
+            idx += 1;
+            keep
+        });
+        self.awaiting_committed_prune_at =
+            std::cmp::max(self.awaiting_committed.len() * 2, AWAITING_COMMITTED_PRUNE_MIN);
+    }
+
     /// Handle the post-commit logic for a client request.
     #[tracing::instrument(level = "trace", skip(self, req))]
-    pub(super) async fn client_request_post_commit(&mut self, req: ClientRequestEntry<D, R>) {
+    pub(super) async fn client_request_post_commit(&mut self, req: ClientRequestEntry<D, R, NID>) {
         let entry = &req.entry;
 
         match req.tx {
             ClientOrInternalResponseTx::Client(tx) => {
                 match &entry.payload {
                     EntryPayload::Normal(_) => match self.apply_entry_to_state_machine(&entry).await {
-                        Ok(data) => {
+                        Ok(Some(data)) => {
                             let _ = tx.send(Ok(ClientWriteResponse {
                                 index: req.entry.log_id.index,
                                 data,
                             }));
                         }
+                        Ok(None) => {
+                            let _ = tx.send(Err(ClientWriteError::AlreadyApplied));
+                        }
                         Err(err) => {
                             let _ = tx.send(Err(ClientWriteError::RaftError(err)));
                         }
@@ -381,7 +613,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         self.core.trigger_log_compaction_if_needed(false);
     }
 
-    pub fn handle_special_log(&mut self, entry: &Arc<Entry<D>>) {
+    pub fn handle_special_log(&mut self, entry: &Arc<Entry<D, NID>>) {
         match &entry.payload {
             EntryPayload::ConfigChange(ref mem) => {
                 let m = &mem.membership;
@@ -398,8 +630,11 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     }
 
     /// Apply the given log entry to the state machine.
+    ///
+    /// Returns its data response, or `None` if it is a client write which was applied already and whose response
+    /// is no longer cached.
     #[tracing::instrument(level = "trace", skip(self, entry))]
-    pub(super) async fn apply_entry_to_state_machine(&mut self, entry: &Entry<D>) -> RaftResult<R> {
+    pub(super) async fn apply_entry_to_state_machine(&mut self, entry: &Entry<D, NID>) -> RaftResult<Option<R>> {
         // First, we just ensure that we apply any outstanding up to, but not including, the index
         // of the given entry. We need to be able to return the data response from applying this
         // entry to the state machine.
@@ -424,11 +659,12 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
             let data_entries: Vec<_> = entries.iter().collect();
             if !data_entries.is_empty() {
-                self.core
-                    .storage
-                    .apply_to_state_machine(&data_entries)
+                let responses = apply_to_state_machine(&*self.core.storage, &self.core.client_sessions, &data_entries)
                     .await
                     .map_err(|err| self.core.map_fatal_storage_error(err))?;
+                for (entry, response) in entries.iter().zip(responses) {
+                    self.core.client_responses.answer(entry, response, &self.core.client_sessions);
+                }
             }
         }
 
@@ -441,7 +677,8 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             }
         }
         // Apply this entry to the state machine and return its data response.
-        let res = self.core.storage.apply_to_state_machine(&[entry]).await.map_err(|err| {
+        let res = apply_to_state_machine(&*self.core.storage, &self.core.client_sessions, &[entry]).await;
+        let res = res.map_err(|err| {
             if err.downcast_ref::<S::ShutdownError>().is_some() {
                 // If this is an instance of the storage impl's shutdown error, then trigger shutdown.
                 self.core.map_fatal_storage_error(err)
@@ -457,6 +694,55 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
         // TODO(xp) merge this function to replication_to_state_machine?
 
-        Ok(res.into_iter().next().unwrap())
+        let response = res.into_iter().next().unwrap();
+        Ok(self.core.client_responses.answer(entry, response, &self.core.client_sessions))
+    }
+}
+
+impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D, R, NID>, S: RaftStorage<D, R, NID>, NID: NodeId>
+    RaftCore<D, R, N, S, NID>
+{
+    /// Handle stale read requests on a non-leader, based on the last AppendEntries RPC received from the leader.
+    #[tracing::instrument(level = "trace", skip(self, tx))]
+    pub(super) fn handle_stale_read_request(&self, max_lag: MaxLag, tx: StaleReadResponseTx) {
+        let _ = tx.send(self.check_staleness(max_lag, self.last_heartbeat, self.leader_commit_index));
+    }
+
+    /// Check that the state machine is within `max_lag` of the leader, given the last time the leader was heard
+    /// from and its commit index as of then.
+    ///
+    /// The leader's commit index grows stale once it is no longer heard from, so an entry-based lag also requires
+    /// it to have been heard from within `election_timeout_max`.
+    fn check_staleness(
+        &self,
+        max_lag: MaxLag,
+        last_contact: Option<Instant>,
+        leader_commit_index: u64,
+    ) -> Result<LogId, StaleReadError> {
+        let last_contact = match last_contact {
+            Some(last_contact) => last_contact,
+            None => return Err(StaleReadError::LeaderUnreachable(None)),
+        };
+
+        match max_lag {
+            MaxLag::Time(max_time) => {
+                let elapsed = last_contact.elapsed();
+                if elapsed > max_time {
+                    return Err(StaleReadError::LeaderUnreachable(Some(elapsed)));
+                }
+            }
+            MaxLag::Entries(max_entries) => {
+                let elapsed = last_contact.elapsed();
+                if elapsed > Duration::from_millis(self.config.election_timeout_max) {
+                    return Err(StaleReadError::LeaderUnreachable(Some(elapsed)));
+                }
+                let behind = leader_commit_index.saturating_sub(self.last_applied.index);
+                if behind > max_entries {
+                    return Err(StaleReadError::TooFarBehind(behind));
+                }
+            }
+        }
+
+        Ok(self.last_applied)
     }
 }
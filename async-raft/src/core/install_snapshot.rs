@@ -2,7 +2,12 @@ use std::io::SeekFrom;
 
 use tokio::io::AsyncSeekExt;
 use tokio::io::AsyncWriteExt;
+use tokio::time::Instant;
 
+use crate::checksum::crc32;
+use crate::checksum::snapshot_checksum;
+use crate::compression::SnapshotCompression;
+use crate::compression::MAX_RAW_CHUNK_SIZE;
 use crate::core::RaftCore;
 use crate::core::SnapshotState;
 use crate::core::State;
@@ -13,13 +18,16 @@ use crate::raft::InstallSnapshotResponse;
 use crate::AppData;
 use crate::AppDataResponse;
 use crate::MessageSummary;
+use crate::NodeId;
 use crate::RaftError;
 use crate::RaftNetwork;
 use crate::RaftStorage;
 use crate::SnapshotSegmentId;
 use crate::Update;
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> RaftCore<D, R, N, S> {
+impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D, R, NID>, S: RaftStorage<D, R, NID>, NID: NodeId>
+    RaftCore<D, R, N, S, NID>
+{
     /// Invoked by leader to send chunks of a snapshot to a follower (§7).
     ///
     /// Leaders always send chunks in order. It is important to note that, according to the Raft spec,
@@ -28,12 +36,15 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     #[tracing::instrument(level = "debug", skip(self, req), fields(req=%req.summary()))]
     pub(super) async fn handle_install_snapshot_request(
         &mut self,
-        req: InstallSnapshotRequest,
+        mut req: InstallSnapshotRequest<NID>,
     ) -> RaftResult<InstallSnapshotResponse> {
         // If message's term is less than most recent term, then we do not honor the request.
         if req.term < self.current_term {
             return Ok(InstallSnapshotResponse {
                 term: self.current_term,
+                offset: None,
+                compression: None,
+                supported_compression: Vec::new(),
             });
         }
 
@@ -50,7 +61,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
         // Update current leader if needed.
         if self.current_leader.as_ref() != Some(&req.leader_id) {
-            self.update_current_leader(UpdateCurrentLeader::OtherNode(req.leader_id));
+            self.update_current_leader(UpdateCurrentLeader::OtherNode(req.leader_id.clone()));
             report_metrics = true;
         }
 
@@ -63,18 +74,49 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             self.report_metrics(Update::Ignore);
         }
 
+        // Decompress the chunk before it reaches the snapshot. If this node does not support its codec, or fails to
+        // decompress it, it does not take the chunk, and the leader sends it again uncompressed.
+        let compression = req.compression.take();
+        if let Some(codec) = compression {
+            match codec.decompress(&req.data, MAX_RAW_CHUNK_SIZE as usize) {
+                Ok(data) => req.data = data,
+                Err(err) => {
+                    tracing::warn!(
+                        ?codec,
+                        error=%err,
+                        offset=req.offset,
+                        "failed to decompress snapshot chunk, asking for it uncompressed"
+                    );
+                    return Ok(InstallSnapshotResponse {
+                        term: self.current_term,
+                        offset: Some(req.offset),
+                        compression: None,
+                        supported_compression: SnapshotCompression::supported(),
+                    });
+                }
+            }
+        }
+
+        let mut res = self.install_snapshot_chunk(req).await?;
+        res.compression = compression;
+        res.supported_compression = SnapshotCompression::supported();
+        Ok(res)
+    }
+
+    async fn install_snapshot_chunk(
+        &mut self,
+        req: InstallSnapshotRequest<NID>,
+    ) -> RaftResult<InstallSnapshotResponse> {
         // Compare current snapshot state with received RPC and handle as needed.
         // - Init a new state if it is empty or building a snapshot locally.
         // - Mismatched id with offset=0 indicates a new stream has been sent, the old one should be dropped and start
         //   to receive the new snapshot,
         // - Mismatched id with offset greater than 0 is an out of order message that should be rejected.
         match self.snapshot_state.take() {
-            None => {
-                return self.begin_installing_snapshot(req).await;
-            }
+            None => self.begin_installing_snapshot(req).await,
             Some(SnapshotState::Snapshotting { handle, .. }) => {
                 handle.abort(); // Abort the current compaction in favor of installation from leader.
-                return self.begin_installing_snapshot(req).await;
+                self.begin_installing_snapshot(req).await
             }
             Some(SnapshotState::Streaming { snapshot, id, offset }) => {
                 if req.meta.snapshot_id == id {
@@ -85,75 +127,94 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                     return self.begin_installing_snapshot(req).await;
                 }
 
-                Err(RaftError::SnapshotMismatch {
+                let err = RaftError::SnapshotMismatch {
                     expect: SnapshotSegmentId { id: id.clone(), offset },
                     got: SnapshotSegmentId {
                         id: req.meta.snapshot_id.clone(),
                         offset: req.offset,
                     },
-                })
+                };
+                self.snapshot_state = Some(SnapshotState::Streaming { snapshot, id, offset });
+                Err(err)
             }
         }
     }
 
     #[tracing::instrument(level = "debug", skip(self, req), fields(req=%req.summary()))]
-    async fn begin_installing_snapshot(&mut self, req: InstallSnapshotRequest) -> RaftResult<InstallSnapshotResponse> {
-        let id = req.meta.snapshot_id.clone();
-
-        if req.offset > 0 {
-            return Err(RaftError::SnapshotMismatch {
-                expect: SnapshotSegmentId {
-                    id: id.clone(),
-                    offset: 0,
-                },
-                got: SnapshotSegmentId { id, offset: req.offset },
-            });
+    async fn begin_installing_snapshot(
+        &mut self,
+        req: InstallSnapshotRequest<NID>,
+    ) -> RaftResult<InstallSnapshotResponse> {
+        // Resume receiving the snapshot if part of it was saved, e.g. before a restart.
+        let partial = match self.storage.get_partial_snapshot(&req.meta).await {
+            Ok(partial) => partial,
+            Err(err) => {
+                tracing::warn!(error=%err, "error getting partial snapshot, receiving it anew");
+                None
+            }
+        };
+        if let Some((offset, mut snapshot)) = partial {
+            tracing::debug!(offset, "resuming partial snapshot");
+            snapshot.as_mut().seek(SeekFrom::Start(offset)).await?;
+            return self.continue_installing_snapshot(req, offset, snapshot).await;
         }
 
-        // Create a new snapshot and begin writing its contents.
-        let mut snapshot =
-            self.storage.begin_receiving_snapshot().await.map_err(|err| self.map_fatal_storage_error(err))?;
-        snapshot.as_mut().write_all(&req.data).await?;
-
-        // If this was a small snapshot, and it is already done, then finish up.
-        if req.done {
-            self.finalize_snapshot_installation(req, snapshot).await?;
+        // Ask the leader to send the snapshot from the start.
+        if req.offset > 0 {
             return Ok(InstallSnapshotResponse {
                 term: self.current_term,
+                offset: Some(0),
+                compression: None,
+                supported_compression: Vec::new(),
             });
         }
 
-        // Else, retain snapshot components for later segments & respond.
-        self.snapshot_state = Some(SnapshotState::Streaming {
-            offset: req.data.len() as u64,
-            id,
-            snapshot,
-        });
-        Ok(InstallSnapshotResponse {
-            term: self.current_term,
-        })
+        // Create a new snapshot and begin writing its contents.
+        let snapshot =
+            self.storage.begin_receiving_snapshot().await.map_err(|err| self.map_fatal_storage_error(err))?;
+        self.continue_installing_snapshot(req, 0, snapshot).await
     }
 
     #[tracing::instrument(level = "debug", skip(self, req, snapshot), fields(req=%req.summary()))]
     async fn continue_installing_snapshot(
         &mut self,
-        req: InstallSnapshotRequest,
+        req: InstallSnapshotRequest<NID>,
         mut offset: u64,
         mut snapshot: Box<S::SnapshotData>,
     ) -> RaftResult<InstallSnapshotResponse> {
         let id = req.meta.snapshot_id.clone();
 
-        // Always seek to the target offset if not an exact match.
+        // Only the chunk at the end of what was received can be written. For any other, e.g. when the leader
+        // restarted a transfer which was interrupted, tell the leader where to resume.
         if req.offset != offset {
-            if let Err(err) = snapshot.as_mut().seek(SeekFrom::Start(req.offset)).await {
+            tracing::debug!(req.offset, offset, "snapshot chunk is not at the received offset");
+            self.snapshot_state = Some(SnapshotState::Streaming { offset, id, snapshot });
+            return Ok(InstallSnapshotResponse {
+                term: self.current_term,
+                offset: Some(offset),
+                compression: None,
+                supported_compression: Vec::new(),
+            });
+        }
+
+        // Check the chunk against its checksum before writing it, so that the leader sends it again.
+        if let Some(expect) = req.checksum {
+            let got = crc32(&req.data);
+            if got != expect {
                 self.snapshot_state = Some(SnapshotState::Streaming { offset, id, snapshot });
-                return Err(err.into());
+                return Err(RaftError::SnapshotChecksumMismatch {
+                    id: req.meta.snapshot_id,
+                    offset: Some(req.offset),
+                    expect,
+                    got,
+                });
             }
-            offset = req.offset;
         }
 
         // Write the next segment & update offset.
         if let Err(err) = snapshot.as_mut().write_all(&req.data).await {
+            // The chunk may have been written in part, so it is to be sent again from the last known offset.
+            snapshot.as_mut().seek(SeekFrom::Start(offset)).await?;
             self.snapshot_state = Some(SnapshotState::Streaming { offset, id, snapshot });
             return Err(err.into());
         }
@@ -163,22 +224,59 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         if req.done {
             self.finalize_snapshot_installation(req, snapshot).await?;
         } else {
+            self.save_partial_snapshot(&req, offset, snapshot.as_mut()).await;
             self.snapshot_state = Some(SnapshotState::Streaming { offset, id, snapshot });
         }
         Ok(InstallSnapshotResponse {
             term: self.current_term,
+            offset: Some(offset),
+            compression: None,
+            supported_compression: Vec::new(),
         })
     }
 
+    /// Save the snapshot received so far to storage, so that receiving it can be resumed after a restart.
+    async fn save_partial_snapshot(
+        &mut self,
+        req: &InstallSnapshotRequest<NID>,
+        offset: u64,
+        snapshot: &mut S::SnapshotData,
+    ) {
+        let res = match snapshot.flush().await {
+            Ok(()) => self.storage.save_partial_snapshot(&req.meta, offset, snapshot).await,
+            Err(err) => Err(err.into()),
+        };
+        if let Err(err) = res {
+            tracing::warn!(error=%err, "error saving partial snapshot");
+        }
+    }
+
     /// Finalize the installation of a new snapshot.
     ///
-    /// Any errors which come up from this routine will cause the Raft node to go into shutdown.
+    /// A snapshot which does not match its checksum is discarded. Any storage errors which come up
+    /// from this routine will cause the Raft node to go into shutdown.
     #[tracing::instrument(level = "debug", skip(self, req, snapshot), fields(req=%req.summary()))]
     async fn finalize_snapshot_installation(
         &mut self,
-        req: InstallSnapshotRequest,
+        req: InstallSnapshotRequest<NID>,
         mut snapshot: Box<S::SnapshotData>,
     ) -> RaftResult<()> {
+        // Check the whole snapshot, as it was written, against its checksum before installing it.
+        if let Some(expect) = req.meta.checksum {
+            snapshot.as_mut().flush().await?;
+            let got = snapshot_checksum(snapshot.as_mut()).await?;
+            if got != expect {
+                // Discard what was received, so that the snapshot is received anew.
+                self.save_partial_snapshot(&req, 0, snapshot.as_mut()).await;
+                return Err(RaftError::SnapshotChecksumMismatch {
+                    id: req.meta.snapshot_id,
+                    offset: None,
+                    expect,
+                    got,
+                });
+            }
+        }
+
         snapshot.as_mut().shutdown().await.map_err(|err| self.map_fatal_storage_error(err.into()))?;
 
         self.storage
@@ -191,6 +289,11 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         self.last_log_id = req.meta.last_log_id;
         self.last_applied = req.meta.last_log_id;
         self.snapshot_last_log_id = req.meta.last_log_id;
+        self.client_sessions.reset(req.meta.sessions.clone());
+        self.client_responses.clear();
+        self.snapshot_at = Instant::now();
+        self.log_sizes.purge_upto(req.meta.last_log_id.index);
+        self.log_purged_upto = std::cmp::max(self.log_purged_upto, req.meta.last_log_id.index);
         self.report_metrics(Update::Ignore);
         Ok(())
     }
@@ -10,6 +10,7 @@ mod vote;
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::sync::Arc;
 
 use futures::future::AbortHandle;
@@ -23,6 +24,8 @@ use tokio::sync::mpsc;
 use tokio::sync::oneshot;
 use tokio::sync::watch;
 use tokio::task::JoinHandle;
+use tokio::time::interval;
+use tokio::time::interval_at;
 use tokio::time::sleep_until;
 use tokio::time::Duration;
 use tokio::time::Instant;
@@ -30,27 +33,43 @@ use tracing::Span;
 use tracing_futures::Instrument;
 
 use crate::config::Config;
-use crate::config::SnapshotPolicy;
+use crate::config::ReadyToJoinPolicy;
+use crate::config::SnapshotMetrics;
+use crate::config::SnapshotProgress;
 use crate::core::client::ClientRequestEntry;
+use crate::core::client::AWAITING_COMMITTED_PRUNE_MIN;
 use crate::error::ChangeConfigError;
 use crate::error::ClientReadError;
 use crate::error::ClientWriteError;
 use crate::error::InitializeError;
+use crate::error::PurgeLogError;
 use crate::error::RaftError;
 use crate::error::RaftResult;
+use crate::error::SnapshotError;
+use crate::error::TransferLeaderError;
 use crate::metrics::LeaderMetrics;
 use crate::metrics::RaftMetrics;
+use crate::raft::ChangeMembershipProgress;
 use crate::raft::ClientReadResponseTx;
+use crate::raft::ClientWriteBatch;
 use crate::raft::ClientWriteRequest;
 use crate::raft::ClientWriteResponseTx;
+use crate::raft::Entry;
+use crate::raft::EntryNormal;
 use crate::raft::EntryPayload;
 use crate::raft::MembershipConfig;
+use crate::raft::Node;
 use crate::raft::RaftMsg;
+use crate::raft::ReadIndexResponseTx;
 use crate::raft::ResponseTx;
+use crate::raft::SnapshotResponseTx;
 use crate::replication::RaftEvent;
 use crate::replication::ReplicaEvent;
 use crate::replication::ReplicationStream;
+use crate::session::ClientSessions;
+use crate::session::SessionHistory;
 use crate::storage::HardState;
+use crate::storage::SnapshotMeta;
 use crate::AppData;
 use crate::AppDataResponse;
 use crate::LogId;
@@ -61,13 +80,13 @@ use crate::RaftStorage;
 use crate::Update;
 
 /// The core type implementing the Raft protocol.
-pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
+pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D, R, NID>, S: RaftStorage<D, R, NID>, NID: NodeId> {
     /// This node's ID.
-    id: NodeId,
+    id: NID,
     /// This node's runtime config.
     config: Arc<Config>,
     /// The cluster's current membership configuration.
-    membership: MembershipConfig,
+    membership: MembershipConfig<NID>,
     /// The `RaftNetwork` implementation.
     network: Arc<N>,
     /// The `RaftStorage` implementation.
@@ -102,12 +121,12 @@ pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftSt
     /// but this may also be incremented when a follower becomes a candidate.
     current_term: u64,
     /// The ID of the current leader of the Raft cluster.
-    current_leader: Option<NodeId>,
+    current_leader: Option<NID>,
     /// The ID of the candidate which received this node's vote for the current term.
     ///
     /// Each server will vote for at most one candidate in a given term, on a
     /// first-come-first-served basis. See §5.4.1 for additional restriction on votes.
-    voted_for: Option<NodeId>,
+    voted_for: Option<NID>,
 
     /// The last entry to be appended to the log.
     last_log_id: LogId,
@@ -119,6 +138,22 @@ pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftSt
     ///
     /// This is primarily used in making a determination on when a compaction job needs to be triggered.
     snapshot_last_log_id: LogId,
+    /// The time of the last snapshot, or of when the node started if it has not taken one since.
+    snapshot_at: Instant,
+    /// The sizes of the entries in the log past the last snapshot, if the snapshot policy or the policy deciding when
+    /// non-voters are ready to join depends on them.
+    log_sizes: LogSizes,
+    /// The callers of `Raft::trigger_snapshot` waiting for the snapshot being built.
+    snapshot_waiters: Vec<SnapshotResponseTx<NID>>,
+    /// The index up to which the log may have been purged, with `Raft::purge_log_upto` or by log compaction, which
+    /// may purge every entry the current snapshot covers.
+    ///
+    /// Replication streams send a snapshot to targets which lag behind it, rather than entries.
+    log_purged_upto: u64,
+    /// The client sessions, as of each entry applied since the last snapshot.
+    client_sessions: SessionHistory,
+    /// The responses to the last client write of each session, for answering retries of it.
+    client_responses: ClientResponses<R>,
 
     /// The stream of join handles from state machine replication tasks. There will only ever be
     /// a maximum of 1 element at a time.
@@ -133,29 +168,50 @@ pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftSt
 
     /// The last time a heartbeat was received.
     last_heartbeat: Option<Instant>,
+    /// The leader's commit index, as of the last AppendEntries RPC received from it.
+    leader_commit_index: u64,
     /// The duration until the next election timeout.
     next_election_timeout: Option<Instant>,
+    /// A bool indicating if the leader asked this node to take over leadership via a TimeoutNow RPC.
+    ///
+    /// It is consumed by the next candidate state, which then skips PreVote and marks its vote
+    /// requests as part of a leadership transfer.
+    leadership_transfer: bool,
+    /// A leadership transfer started by this node while it was leader, which asked the target to take over.
+    ///
+    /// It is answered once this node learns who the new leader is, or once its deadline has passed.
+    stepped_down_transfer: Option<LeaderTransfer<NID>>,
 
-    tx_compaction: mpsc::Sender<SnapshotUpdate>,
-    rx_compaction: mpsc::Receiver<SnapshotUpdate>,
+    tx_compaction: mpsc::Sender<SnapshotUpdate<NID>>,
+    rx_compaction: mpsc::Receiver<SnapshotUpdate<NID>>,
 
-    rx_api: mpsc::UnboundedReceiver<(RaftMsg<D, R>, Span)>,
-    tx_metrics: watch::Sender<RaftMetrics>,
+    rx_api: mpsc::Receiver<(RaftMsg<D, R, NID>, Span)>,
+    /// A sender of the API queue, which is only used to measure its length.
+    tx_api: mpsc::Sender<(RaftMsg<D, R, NID>, Span)>,
+    tx_metrics: watch::Sender<RaftMetrics<NID>>,
     rx_shutdown: oneshot::Receiver<()>,
 }
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> RaftCore<D, R, N, S> {
+impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D, R, NID>, S: RaftStorage<D, R, NID>, NID: NodeId>
+    RaftCore<D, R, N, S, NID>
+{
     pub(crate) fn spawn(
-        id: NodeId,
+        id: NID,
         config: Arc<Config>,
         network: Arc<N>,
         storage: Arc<S>,
-        rx_api: mpsc::UnboundedReceiver<(RaftMsg<D, R>, Span)>,
-        tx_metrics: watch::Sender<RaftMetrics>,
+        rx_api: mpsc::Receiver<(RaftMsg<D, R, NID>, Span)>,
+        tx_api: mpsc::Sender<(RaftMsg<D, R, NID>, Span)>,
+        tx_metrics: watch::Sender<RaftMetrics<NID>>,
         rx_shutdown: oneshot::Receiver<()>,
     ) -> JoinHandle<RaftResult<()>> {
-        let membership = MembershipConfig::new_initial(id); // This is updated from storage in the main loop.
+        let membership = MembershipConfig::new_initial(id.clone()); // This is updated from storage in the main loop.
         let (tx_compaction, rx_compaction) = mpsc::channel(1);
+        let log_sizes = LogSizes::new(
+            config.snapshot_policy.uses_log_bytes()
+                || matches!(config.ready_to_join, ReadyToJoinPolicy::MaxLagBytes(_)),
+        );
+        let client_sessions = SessionHistory::new(config.client_session_ttl);
         let this = Self {
             id,
             config,
@@ -171,13 +227,23 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             last_log_id: LogId { term: 0, index: 0 },
             snapshot_state: None,
             snapshot_last_log_id: LogId { term: 0, index: 0 },
+            snapshot_at: Instant::now(),
+            log_sizes,
+            snapshot_waiters: vec![],
+            log_purged_upto: 0,
+            client_sessions,
+            client_responses: ClientResponses::new(),
             replicate_to_sm_handle: FuturesOrdered::new(),
             has_completed_initial_replication_to_sm: false,
             last_heartbeat: None,
+            leader_commit_index: 0,
             next_election_timeout: None,
+            leadership_transfer: false,
+            stepped_down_transfer: None,
             tx_compaction,
             rx_compaction,
             rx_api,
+            tx_api,
             tx_metrics,
             rx_shutdown,
         };
@@ -185,7 +251,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     }
 
     /// The main loop of the Raft protocol.
-    #[tracing::instrument(level="debug", skip(self), fields(id=self.id, cluster=%self.config.cluster_name))]
+    #[tracing::instrument(level="debug", skip(self), fields(id=%self.id, cluster=%self.config.cluster_name))]
     async fn main(mut self) -> RaftResult<()> {
         tracing::debug!("raft node is initializing");
 
@@ -201,12 +267,17 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         self.commit_index = 0;
 
         // Fetch the most recent snapshot in the system.
+        let mut sessions = ClientSessions::default();
         if let Some(snapshot) =
             self.storage.get_current_snapshot().await.map_err(|err| self.map_fatal_storage_error(err))?
         {
             self.snapshot_last_log_id = snapshot.meta.last_log_id;
+            sessions = snapshot.meta.sessions;
             self.report_metrics(Update::Ignore);
         }
+        self.load_log_purged_upto().await?;
+        self.load_log_sizes().await?;
+        self.load_client_sessions(sessions).await?;
 
         let has_log = self.last_log_id.index != u64::MIN;
         let single = self.membership.members.len() == 1;
@@ -266,26 +337,27 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
     /// Report a metrics payload on the current state of the Raft node.
     #[tracing::instrument(level = "trace", skip(self))]
-    fn report_metrics(&mut self, leader_metrics: Update<Option<&LeaderMetrics>>) {
+    fn report_metrics(&mut self, leader_metrics: Update<Option<&LeaderMetrics<NID>>>) {
         let leader_metrics = match leader_metrics {
             Update::Update(v) => v.cloned(),
             Update::Ignore => self.tx_metrics.borrow().leader_metrics.clone(),
         };
 
         let res = self.tx_metrics.send(RaftMetrics {
-            id: self.id,
+            id: self.id.clone(),
             state: self.target_state,
             current_term: self.current_term,
             last_log_index: self.last_log_id.index,
             last_applied: self.last_applied.index,
-            current_leader: self.current_leader,
+            current_leader: self.current_leader.clone(),
             membership_config: self.membership.clone(),
             snapshot: self.snapshot_last_log_id,
+            api_queue_len: self.config.api_queue_capacity - self.tx_api.capacity() as u64,
             leader_metrics,
         });
 
         if let Err(err) = res {
-            tracing::error!(error=%err, id=self.id, "error reporting metrics");
+            tracing::error!(error=%err, id=%self.id, "error reporting metrics");
         }
     }
 
@@ -294,7 +366,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     async fn save_hard_state(&mut self) -> RaftResult<()> {
         let hs = HardState {
             current_term: self.current_term,
-            voted_for: self.voted_for,
+            voted_for: self.voted_for.clone(),
         };
         self.storage.save_hard_state(&hs).await.map_err(|err| self.map_fatal_storage_error(err))
     }
@@ -342,10 +414,10 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
     /// Update the value of the `current_leader` property.
     #[tracing::instrument(level = "trace", skip(self))]
-    fn update_current_leader(&mut self, update: UpdateCurrentLeader) {
+    fn update_current_leader(&mut self, update: UpdateCurrentLeader<NID>) {
         match update {
             UpdateCurrentLeader::ThisNode => {
-                self.current_leader = Some(self.id);
+                self.current_leader = Some(self.id.clone());
             }
             UpdateCurrentLeader::OtherNode(target) => {
                 self.current_leader = Some(target);
@@ -354,11 +426,37 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 self.current_leader = None;
             }
         }
+        self.resolve_stepped_down_transfer();
+    }
+
+    /// Answer the leadership transfer this node started before stepping down, if its outcome is known.
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn resolve_stepped_down_transfer(&mut self) {
+        let transfer = match self.stepped_down_transfer.take() {
+            Some(transfer) => transfer,
+            None => return,
+        };
+
+        // This node may still name itself as leader after stepping down, until it learns of another one.
+        let leader = self.current_leader.as_ref().filter(|leader| **leader != self.id || self.target_state.is_leader());
+        let res = match leader {
+            Some(leader) if *leader == transfer.target => Ok(()),
+            Some(leader) => Err(TransferLeaderError::TargetNotElected {
+                target: transfer.target.clone(),
+                leader: leader.clone(),
+            }),
+            None if Instant::now() >= transfer.deadline => Err(TransferLeaderError::Timeout(transfer.target.clone())),
+            None => {
+                self.stepped_down_transfer = Some(transfer);
+                return;
+            }
+        };
+        let _ = transfer.tx.send(res);
     }
 
     /// Encapsulate the process of updating the current term, as updating the `voted_for` state must also be updated.
     #[tracing::instrument(level = "trace", skip(self))]
-    fn update_current_term(&mut self, new_term: u64, voted_for: Option<NodeId>) {
+    fn update_current_term(&mut self, new_term: u64, voted_for: Option<NID>) {
         if new_term > self.current_term {
             self.current_term = new_term;
             self.voted_for = voted_for;
@@ -372,14 +470,14 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// interface.
     #[tracing::instrument(level = "trace", skip(self))]
     fn map_fatal_storage_error(&mut self, err: anyhow::Error) -> RaftError {
-        tracing::error!({error=?err, id=self.id}, "fatal storage error, shutting down");
+        tracing::error!({error=?err, id=%self.id}, "fatal storage error, shutting down");
         self.set_target_state(State::Shutdown);
         RaftError::RaftStorage(err)
     }
 
     /// Update the node's current membership config & save hard state.
     #[tracing::instrument(level = "trace", skip(self))]
-    fn update_membership(&mut self, cfg: MembershipConfig) -> RaftResult<()> {
+    fn update_membership(&mut self, cfg: MembershipConfig<NID>) -> RaftResult<()> {
         // If the given config does not contain this node's ID, it means one of the following:
         //
         // - the node is currently a non-voter and is replicating an old config to which it has
@@ -400,10 +498,24 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
     /// Update the system's snapshot state based on the given data.
     #[tracing::instrument(level = "trace", skip(self))]
-    fn update_snapshot_state(&mut self, update: SnapshotUpdate) {
-        if let SnapshotUpdate::SnapshotComplete(log_id) = update {
-            self.snapshot_last_log_id = log_id;
-            self.report_metrics(Update::Ignore);
+    fn update_snapshot_state(&mut self, update: SnapshotUpdate<NID>) {
+        match update {
+            SnapshotUpdate::SnapshotComplete(meta) => {
+                self.snapshot_last_log_id = meta.last_log_id;
+                self.client_sessions.settle(meta.last_log_id.index);
+                self.snapshot_at = Instant::now();
+                self.log_sizes.purge_upto(meta.last_log_id.index);
+                self.log_purged_upto = std::cmp::max(self.log_purged_upto, meta.last_log_id.index);
+                self.report_metrics(Update::Ignore);
+                for tx in self.snapshot_waiters.drain(..) {
+                    let _ = tx.send(Ok(meta.clone()));
+                }
+            }
+            SnapshotUpdate::SnapshotFailed => {
+                for tx in self.snapshot_waiters.drain(..) {
+                    let _ = tx.send(Err(SnapshotError::Failed));
+                }
+            }
         }
         // If snapshot state is anything other than streaming, then drop it.
         if let Some(state @ SnapshotState::Streaming { .. }) = self.snapshot_state.take() {
@@ -418,21 +530,28 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         if self.snapshot_state.is_some() {
             return;
         }
-        let SnapshotPolicy::LogsSinceLast(threshold) = &self.config.snapshot_policy;
+        // No snapshot is being built, so the next one includes at least the entries applied by now.
+        self.client_sessions.settle(self.last_applied.index);
+
         // Check to ensure we have actual entries for compaction.
         if self.last_applied.index == 0 || self.last_applied.index < self.snapshot_last_log_id.index {
             return;
         }
 
         if !force {
-            // If we are below the threshold, then there is nothing to do.
-            if self.last_applied.index < self.snapshot_last_log_id.index + *threshold {
+            // If nothing was applied since the last snapshot, or the policy does not call for a snapshot yet, then
+            // there is nothing to do.
+            let progress = self.snapshot_progress();
+            if progress.logs_since_last == 0
+                || !self.config.snapshot_policy.should_snapshot(&SnapshotMetrics::from(&*self.tx_metrics.borrow()), &progress)
+            {
                 return;
             }
         }
 
         // At this point, we are clear to begin a new compaction process.
         let storage = self.storage.clone();
+        let sessions = self.client_sessions.clone();
         let (handle, reg) = AbortHandle::new_pair();
         let (chan_tx, _) = broadcast::channel(1);
         let tx_compaction = self.tx_compaction.clone();
@@ -442,13 +561,14 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         });
         tokio::spawn(
             async move {
-                let f = storage.do_log_compaction();
+                let f = storage.do_log_compaction(&sessions);
                 let res = Abortable::new(f, reg).await;
                 match res {
                     Ok(res) => match res {
                         Ok(snapshot) => {
-                            let _ = tx_compaction.try_send(SnapshotUpdate::SnapshotComplete(snapshot.meta.last_log_id));
-                            let _ = chan_tx.send(snapshot.meta.last_log_id.index); // This will always succeed.
+                            let index = snapshot.meta.last_log_id.index;
+                            let _ = tx_compaction.try_send(SnapshotUpdate::SnapshotComplete(snapshot.meta));
+                            let _ = chan_tx.send(index); // This will always succeed.
                         }
                         Err(err) => {
                             tracing::error!({error=%err}, "error while generating snapshot");
@@ -464,6 +584,111 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         );
     }
 
+    /// Build a snapshot on demand for `Raft::trigger_snapshot`, or wait for the one being built.
+    #[tracing::instrument(level = "trace", skip(self, tx))]
+    pub(self) fn handle_trigger_snapshot(&mut self, tx: SnapshotResponseTx<NID>) {
+        self.trigger_log_compaction_if_needed(true);
+        match &self.snapshot_state {
+            Some(SnapshotState::Snapshotting { .. }) => self.snapshot_waiters.push(tx),
+            Some(SnapshotState::Streaming { .. }) => {
+                let _ = tx.send(Err(SnapshotError::InstallInProgress));
+            }
+            None => {
+                let _ = tx.send(Err(SnapshotError::NothingToSnapshot));
+            }
+        }
+    }
+
+    /// Purge the log up to and including the given index for `Raft::purge_log_upto`.
+    ///
+    /// Whether replication streams still need the entries is checked by the leader beforehand.
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub(self) async fn purge_log_upto(&mut self, upto: u64) -> Result<(), PurgeLogError<NID>> {
+        // The last applied entry is kept, so that a follower can still find where its log matches the leader's.
+        if upto >= self.last_applied.index {
+            return Err(PurgeLogError::NotApplied {
+                upto,
+                last_applied: self.last_applied.index,
+            });
+        }
+
+        self.storage.delete_logs_from(..=upto).await.map_err(|err| self.map_fatal_storage_error(err))?;
+        self.log_sizes.purge_upto(upto);
+        self.log_purged_upto = std::cmp::max(self.log_purged_upto, upto);
+        Ok(())
+    }
+
+    /// Find out whether the log was purged past the current snapshot before the node started.
+    ///
+    /// The entries the current snapshot covers may always have been purged. Which entries past it were purged is not
+    /// known then, so every entry before the last applied one is taken to be.
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn load_log_purged_upto(&mut self) -> RaftResult<()> {
+        self.log_purged_upto = std::cmp::max(self.log_purged_upto, self.snapshot_last_log_id.index);
+        let first = self.snapshot_last_log_id.index + 1;
+        if first >= self.last_applied.index {
+            return Ok(());
+        }
+        let entry = self.storage.try_get_log_entry(first).await.map_err(|err| self.map_fatal_storage_error(err))?;
+        if entry.is_none() {
+            self.log_purged_upto = self.last_applied.index - 1;
+        }
+        Ok(())
+    }
+
+    /// The progress of this node since its last snapshot, which the snapshot policy decides on.
+    fn snapshot_progress(&self) -> SnapshotProgress {
+        SnapshotProgress {
+            logs_since_last: self.last_applied.index.saturating_sub(self.snapshot_last_log_id.index),
+            log_bytes_since_last: self.log_sizes.total(),
+            since_last: self.snapshot_at.elapsed(),
+        }
+    }
+
+    /// Load the sizes of the entries in the log past the last snapshot, if they are kept track of.
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn load_log_sizes(&mut self) -> RaftResult<()> {
+        if !self.log_sizes.enabled {
+            return Ok(());
+        }
+        let end = self.last_log_id.index + 1;
+        let mut start = std::cmp::max(self.snapshot_last_log_id.index, self.log_purged_upto) + 1;
+        while start < end {
+            let stop = std::cmp::min(start + self.config.max_payload_entries, end);
+            let entries =
+                self.storage.get_log_entries(start..stop).await.map_err(|err| self.map_fatal_storage_error(err))?;
+            for entry in entries.iter() {
+                self.log_sizes.append(entry);
+            }
+            start = stop;
+        }
+        Ok(())
+    }
+
+    /// Load the client sessions as of the last applied entry, from those of the current snapshot and the entries
+    /// applied past it.
+    #[tracing::instrument(level = "trace", skip(self, sessions))]
+    async fn load_client_sessions(&mut self, sessions: ClientSessions) -> RaftResult<()> {
+        self.client_sessions.reset(sessions);
+        let end = self.last_applied.index + 1;
+        let mut start = std::cmp::max(self.snapshot_last_log_id.index, self.log_purged_upto) + 1;
+        while start < end {
+            let stop = std::cmp::min(start + self.config.max_payload_entries, end);
+            let entries =
+                self.storage.get_log_entries(start..stop).await.map_err(|err| self.map_fatal_storage_error(err))?;
+            for entry in entries.iter() {
+                if let EntryPayload::Normal(EntryNormal {
+                    session: Some(session), ..
+                }) = &entry.payload
+                {
+                    self.client_sessions.record(entry.log_id.index, session);
+                }
+            }
+            start = stop;
+        }
+        Ok(())
+    }
+
     /// Handle the output of an async task replicating entries to the state machine.
     #[tracing::instrument(level = "trace", skip(self, res))]
     pub(self) fn handle_replicate_to_sm_result(&mut self, res: anyhow::Result<Option<LogId>>) -> RaftResult<()> {
@@ -488,16 +713,25 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
     /// Reject a proposed config change request due to the Raft node being in a state which prohibits the request.
     #[tracing::instrument(level = "trace", skip(self, tx))]
-    fn reject_config_change_not_leader(&self, tx: ResponseTx) {
-        let _ = tx.send(Err(ChangeConfigError::NodeNotLeader(self.current_leader).into()));
+    fn reject_config_change_not_leader(&self, tx: ResponseTx<NID>) {
+        let _ = tx.send(Err(ChangeConfigError::NodeNotLeader(self.current_leader.clone()).into()));
+    }
+
+    /// Reject a leadership transfer request due to the Raft node not being the leader.
+    #[tracing::instrument(level = "trace", skip(self, tx))]
+    fn reject_transfer_leadership_not_leader(&self, tx: oneshot::Sender<Result<(), TransferLeaderError<NID>>>) {
+        let _ = tx.send(Err(TransferLeaderError::NodeNotLeader(self.current_leader.clone())));
     }
 
     /// Forward the given client write request to the leader.
     #[tracing::instrument(level = "trace", skip(self, req, tx))]
-    fn forward_client_write_request(&self, req: ClientWriteRequest<D>, tx: ClientWriteResponseTx<D, R>) {
+    fn forward_client_write_request(&self, req: ClientWriteRequest<D, NID>, tx: ClientWriteResponseTx<D, R, NID>) {
         match req.entry {
             EntryPayload::Normal(entry) => {
-                let _ = tx.send(Err(ClientWriteError::ForwardToLeader(entry.data, self.current_leader)));
+                let _ = tx.send(Err(ClientWriteError::ForwardToLeader(
+                    entry.data,
+                    self.current_leader.clone(),
+                )));
             }
             _ => {
                 // This is unreachable, and well controlled by the type system, but let's log an
@@ -511,16 +745,118 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
     /// Forward the given client read request to the leader.
     #[tracing::instrument(level = "trace", skip(self, tx))]
-    fn forward_client_read_request(&self, tx: ClientReadResponseTx) {
-        let _ = tx.send(Err(ClientReadError::ForwardToLeader(self.current_leader)));
+    fn forward_client_read_request(&self, tx: ClientReadResponseTx<NID>) {
+        let _ = tx.send(Err(ClientReadError::ForwardToLeader(self.current_leader.clone())));
+    }
+
+    /// Forward the given read index request to the leader.
+    #[tracing::instrument(level = "trace", skip(self, tx))]
+    fn forward_read_index_request(&self, tx: ReadIndexResponseTx<NID>) {
+        let _ = tx.send(Err(ClientReadError::ForwardToLeader(self.current_leader.clone())));
+    }
+}
+
+/// Apply the given entries to the state machine, recording the client writes among them in `sessions`.
+///
+/// A client write which was applied already is handed to storage as a blank entry instead, so that storage still
+/// sees every entry. Returns the response to each entry, or `None` for such a duplicate.
+pub(self) async fn apply_to_state_machine<D, R, S, NID>(
+    storage: &S,
+    sessions: &SessionHistory,
+    entries: &[&Entry<D, NID>],
+) -> anyhow::Result<Vec<Option<R>>>
+where
+    D: AppData,
+    R: AppDataResponse,
+    S: RaftStorage<D, R, NID>,
+    NID: NodeId,
+{
+    let blanks: Vec<_> = entries
+        .iter()
+        .map(|entry| match &entry.payload {
+            EntryPayload::Normal(EntryNormal {
+                session: Some(session), ..
+            }) if !sessions.record(entry.log_id.index, session) => Some(Entry {
+                log_id: entry.log_id,
+                payload: EntryPayload::Blank,
+            }),
+            _ => None,
+        })
+        .collect();
+    let refs: Vec<_> =
+        entries.iter().zip(blanks.iter()).map(|(entry, blank)| blank.as_ref().unwrap_or(entry)).collect();
+
+    let responses = storage.apply_to_state_machine(&refs).await?;
+    Ok(responses
+        .into_iter()
+        .zip(blanks.iter())
+        .map(|(res, blank)| if blank.is_some() { None } else { Some(res) })
+        .collect())
+}
+
+/// The responses to the last client write of each session, for answering retries of it.
+///
+/// They are only kept in memory: a retry of a write applied before the node started, or before it installed a
+/// snapshot, can not be answered.
+pub(self) struct ClientResponses<R> {
+    responses: BTreeMap<String, (u64, R)>,
+    /// The number of responses at which to next drop those of expired sessions.
+    prune_at: usize,
+}
+
+impl<R: AppDataResponse> ClientResponses<R> {
+    const PRUNE_MIN: usize = 64;
+
+    fn new() -> Self {
+        Self {
+            responses: BTreeMap::new(),
+            prune_at: Self::PRUNE_MIN,
+        }
+    }
+
+    /// Answer the given entry: cache its response if it is a client write which was applied, or take its response
+    /// from the cache if it is a duplicate.
+    fn answer<D: AppData, NID: NodeId>(
+        &mut self,
+        entry: &Entry<D, NID>,
+        response: Option<R>,
+        sessions: &SessionHistory,
+    ) -> Option<R> {
+        let session = match &entry.payload {
+            EntryPayload::Normal(EntryNormal {
+                session: Some(session), ..
+            }) => session,
+            _ => return response,
+        };
+
+        let response = match response {
+            Some(response) => {
+                self.responses.insert(session.client_id.clone(), (session.seq, response.clone()));
+                response
+            }
+            None => {
+                let cached = self.responses.get(&session.client_id).filter(|(seq, _)| *seq == session.seq);
+                return cached.map(|(_, response)| response.clone());
+            }
+        };
+
+        if self.responses.len() >= self.prune_at {
+            self.responses.retain(|client_id, _| sessions.contains(client_id));
+            self.prune_at = std::cmp::max(self.responses.len() * 2, Self::PRUNE_MIN);
+        }
+        Some(response)
+    }
+
+    fn clear(&mut self) {
+        self.responses.clear();
     }
 }
 
 /// An enum describing the way the current leader property is to be updated.
 #[derive(Debug)]
-pub(self) enum UpdateCurrentLeader {
+pub(self) enum UpdateCurrentLeader<NID: NodeId> {
     Unknown,
-    OtherNode(NodeId),
+    OtherNode(NID),
     ThisNode,
 }
 
@@ -544,11 +880,71 @@ pub(self) enum SnapshotState<S> {
     },
 }
 
+/// The sizes of the entries in the log past the last snapshot, for snapshot policies which depend
+/// on the bytes of the log. Nothing is kept unless enabled.
+pub(self) struct LogSizes {
+    enabled: bool,
+    /// The index and `Entry::payload_size` of every entry, in log order.
+    sizes: VecDeque<(u64, u64)>,
+    total: u64,
+}
+
+impl LogSizes {
+    fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            sizes: VecDeque::new(),
+            total: 0,
+        }
+    }
+
+    /// Add the given entry, which replaces any entry at or after its index.
+    fn append<D: AppData, NID: NodeId>(&mut self, entry: &Entry<D, NID>) {
+        if !self.enabled {
+            return;
+        }
+        while let Some(&(index, size)) = self.sizes.back() {
+            if index < entry.log_id.index {
+                break;
+            }
+            self.sizes.pop_back();
+            self.total -= size;
+        }
+        if let EntryPayload::PurgedMarker = entry.payload {
+            return;
+        }
+        let size = entry.payload_size();
+        self.sizes.push_back((entry.log_id.index, size));
+        self.total += size;
+    }
+
+    /// Drop the entries up to the given index, inclusive, e.g. once they are included in a snapshot.
+    fn purge_upto(&mut self, index: u64) {
+        while let Some(&(i, size)) = self.sizes.front() {
+            if i > index {
+                break;
+            }
+            self.sizes.pop_front();
+            self.total -= size;
+        }
+    }
+
+    /// The sum of the sizes of the entries.
+    fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// The sum of the sizes of the entries after the given index.
+    fn total_after(&self, index: u64) -> u64 {
+        self.sizes.iter().rev().take_while(|(i, _)| *i > index).map(|(_, size)| size).sum()
+    }
+}
+
 /// An update on a snapshot creation process.
 #[derive(Debug)]
-pub(self) enum SnapshotUpdate {
-    /// Snapshot creation has finished successfully and covers the given index.
-    SnapshotComplete(LogId),
+pub(self) enum SnapshotUpdate<NID: NodeId> {
+    /// Snapshot creation has finished successfully, with the given snapshot.
+    SnapshotComplete(SnapshotMeta<NID>),
     /// Snapshot creation failed.
     SnapshotFailed,
 }
@@ -595,40 +991,66 @@ impl State {
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// Volatile state specific to the Raft leader.
-struct LeaderState<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
-    pub(super) core: &'a mut RaftCore<D, R, N, S>,
+struct LeaderState<
+    'a,
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, R, NID>,
+    S: RaftStorage<D, R, NID>,
+    NID: NodeId,
+> {
+    pub(super) core: &'a mut RaftCore<D, R, N, S, NID>,
     /// A mapping of node IDs the replication state of the target node.
-    pub(super) nodes: BTreeMap<NodeId, ReplicationState<D>>,
+    pub(super) nodes: BTreeMap<NID, ReplicationState<D, NID>>,
     /// A mapping of new nodes (non-voters) which are being synced in order to join the cluster.
-    pub(super) non_voters: BTreeMap<NodeId, NonVoterReplicationState<D>>,
+    pub(super) non_voters: BTreeMap<NID, NonVoterReplicationState<D, NID>>,
     /// A bool indicating if this node will be stepping down after committing the current config change.
     pub(super) is_stepping_down: bool,
 
     /// The metrics about a leader
-    pub leader_metrics: LeaderMetrics,
+    pub leader_metrics: LeaderMetrics<NID>,
 
     /// The stream of events coming from replication streams.
-    pub(super) replication_rx: mpsc::UnboundedReceiver<(ReplicaEvent<S::SnapshotData>, Span)>,
+    pub(super) replication_rx: mpsc::Receiver<(ReplicaEvent<S::SnapshotData, NID>, Span)>,
 
     /// The cloneable sender channel for replication stream events.
-    pub(super) replication_tx: mpsc::UnboundedSender<(ReplicaEvent<S::SnapshotData>, Span)>,
+    pub(super) replication_tx: mpsc::Sender<(ReplicaEvent<S::SnapshotData, NID>, Span)>,
 
     /// A buffer of client requests which have been appended locally and are awaiting to be committed to the cluster.
-    pub(super) awaiting_committed: Vec<ClientRequestEntry<D, R>>,
+    pub(super) awaiting_committed: Vec<ClientRequestEntry<D, R, NID>>,
+    /// The length of `awaiting_committed` at which to prune the requests abandoned by their callers.
+    pub(super) awaiting_committed_prune_at: usize,
 
     /// A field tracking the cluster's current consensus state, which is used for dynamic membership.
-    pub(super) consensus_state: ConsensusState,
+    pub(super) consensus_state: ConsensusState<NID>,
+
+    /// Reports the phase of the membership change in progress, if it was started with `Raft::start_change_membership`.
+    pub(super) change_progress: Option<ChangeMembershipProgress>,
+
+    /// The leadership transfer in progress, if any. Client writes are rejected while it is set.
+    pub(super) leader_transfer: Option<LeaderTransfer<NID>>,
+
+    /// Acknowledgements of requests sent before this time do not count towards a read lease.
+    pub(super) lease_not_before: Option<Instant>,
+
+    /// The index of the initial entry of this leader's term, see `commit_initial_leader_entry`.
+    pub(super) initial_entry_index: u64,
+
+    /// Read index requests which are awaiting the commit of the initial entry of this leader's term.
+    pub(super) awaiting_read_index: Vec<ReadIndexResponseTx<NID>>,
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> LeaderState<'a, D, R, N, S> {
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D, R, NID>, S: RaftStorage<D, R, NID>, NID: NodeId>
+    LeaderState<'a, D, R, N, S, NID>
+{
     /// Create a new instance.
-    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S>) -> Self {
+    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S, NID>) -> Self {
         let consensus_state = if core.membership.is_in_joint_consensus() {
             ConsensusState::Joint { is_committed: false }
         } else {
             ConsensusState::Uniform
         };
-        let (replication_tx, replication_rx) = mpsc::unbounded_channel();
+        let (replication_tx, replication_rx) = mpsc::channel(core.config.replication_queue_capacity as usize);
         Self {
             core,
             nodes: BTreeMap::new(),
@@ -638,12 +1060,18 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             replication_tx,
             replication_rx,
             consensus_state,
+            change_progress: None,
             awaiting_committed: Vec::new(),
+            awaiting_committed_prune_at: AWAITING_COMMITTED_PRUNE_MIN,
+            leader_transfer: None,
+            lease_not_before: None,
+            initial_entry_index: 0,
+            awaiting_read_index: Vec::new(),
         }
     }
 
     /// Transition to the Raft leader state.
-    #[tracing::instrument(level="trace", skip(self), fields(id=self.core.id, raft_state="leader"))]
+    #[tracing::instrument(level="trace", skip(self), fields(id=%self.core.id, raft_state="leader"))]
     pub(self) async fn run(mut self) -> RaftResult<()> {
         // Spawn replication streams.
         let targets = self
@@ -655,10 +1083,26 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             .collect::<Vec<_>>();
 
         for target in targets {
-            let state = self.spawn_replication_stream(target);
+            let state = self.spawn_replication_stream(target.clone());
             self.nodes.insert(target, state);
         }
 
+        // Resume replication to the learners recorded in the membership config.
+        let learners = self
+            .core
+            .membership
+            .learners
+            .iter()
+            .filter(|elem| *elem != &self.core.id && !self.core.membership.contains(elem))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for target in learners {
+            let state = self.spawn_replication_stream(target.clone());
+            let state = NonVoterReplicationState::new(state, self.core.last_log_id.index, None);
+            self.non_voters.insert(target, state);
+        }
+
         // Setup state as leader.
         self.core.last_heartbeat = None;
         self.core.next_election_timeout = None;
@@ -668,63 +1112,78 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         // Per §8, commit an initial entry as part of becoming the cluster leader.
         self.commit_initial_leader_entry().await?;
 
+        // Give followers a full election timeout to respond before checking for a quorum.
+        let mut quorum_check = interval_at(
+            Instant::now() + Duration::from_millis(self.core.config.election_timeout_max),
+            Duration::from_millis(self.core.config.heartbeat_interval),
+        );
+        let mut snapshot_check = interval(Duration::from_millis(self.core.config.heartbeat_interval));
+        let mut housekeeping = interval(Duration::from_millis(self.core.config.heartbeat_interval));
+
         loop {
             if !self.core.target_state.is_leader() {
                 tracing::info!("id={} state becomes: {:?}", self.core.id, self.core.target_state);
 
-                for node in self.nodes.values() {
-                    let _ = node.replstream.repl_tx.send((RaftEvent::Terminate, tracing::debug_span!("CH")));
+                for node in self.nodes.values_mut() {
+                    node.replstream.send(RaftEvent::Terminate);
                 }
-                for node in self.non_voters.values() {
-                    let _ = node.state.replstream.repl_tx.send((RaftEvent::Terminate, tracing::debug_span!("CH")));
+                for node in self.non_voters.values_mut() {
+                    node.state.replstream.send(RaftEvent::Terminate);
+                }
+                self.finish_leader_transfer();
+                for tx in self.awaiting_read_index.drain(..) {
+                    let _ = tx.send(Err(ClientReadError::ForwardToLeader(self.core.current_leader.clone())));
                 }
                 return Ok(());
             }
 
+            let transfer_deadline = self.leader_transfer.as_ref().map(|transfer| transfer.deadline);
+            let transfer_timeout = sleep_until(transfer_deadline.unwrap_or_else(Instant::now));
+
+            let sync_deadline = match &self.consensus_state {
+                ConsensusState::NonVoterSync { deadline, .. } => *deadline,
+                _ => None,
+            };
+            let sync_timeout = sleep_until(sync_deadline.unwrap_or_else(Instant::now));
+
             let span = tracing::debug_span!("CHrx:LeaderState");
             let _ent = span.enter();
 
             tokio::select! {
+                _ = quorum_check.tick(), if self.core.config.check_quorum => self.check_quorum(),
+                _ = snapshot_check.tick(), if self.core.config.snapshot_policy.uses_time() => {
+                    self.core.trigger_log_compaction_if_needed(false);
+                }
+                _ = housekeeping.tick() => {
+                    self.resync_replication_streams();
+                    self.prune_abandoned_client_requests();
+                }
+                _ = transfer_timeout, if transfer_deadline.is_some() => {
+                    tracing::info!("leader transfer timed out");
+                    self.abort_leader_transfer();
+                }
+                _ = sync_timeout, if sync_deadline.is_some() => {
+                    tracing::info!("syncing non-voters timed out");
+                    self.abort_non_voter_sync_timeout();
+                }
                 Some((msg,span)) = self.core.rx_api.recv() => {
                     let _ent = span.enter();
                     match msg {
-                        RaftMsg::AppendEntries{rpc, tx} => {
-                            tracing::info!("leader recv from rx_api: AppendEntries, {}", rpc.summary());
-                            let _ = tx.send(self.core.handle_append_entries_request(rpc).await);
-                        }
-                        RaftMsg::RequestVote{rpc, tx} => {
-                            tracing::info!("leader recv from rx_api: RequestVote, {}", rpc.summary());
-                            let _ = tx.send(self.core.handle_vote_request(rpc).await);
-                        }
-                        RaftMsg::InstallSnapshot{rpc, tx} => {
-                            tracing::info!("leader recv from rx_api: InstallSnapshot, {}", rpc.summary());
-                            let _ = tx.send(self.core.handle_install_snapshot_request(rpc).await);
-                        }
-                        RaftMsg::ClientReadRequest{tx} => {
-                            tracing::info!("leader recv from rx_api: ClientReadRequest");
-                            self.handle_client_read_request(tx).await;
-                        }
                         RaftMsg::ClientWriteRequest{rpc, tx} => {
                             tracing::info!("leader recv from rx_api: ClientWriteRequest, {}", rpc.summary());
-                            self.handle_client_write_request(rpc, tx).await;
-                        }
-                        RaftMsg::Initialize{tx, ..} => {
-                            tracing::info!("leader recv from rx_api: Initialize");
-                            self.core.reject_init_with_config(tx);
+                            self.handle_client_write_batch(vec![(rpc, tx)]).await;
                         }
-                        RaftMsg::AddNonVoter{id, tx} => {
-                            tracing::info!("leader recv from rx_api: AddNonVoter, {}", id);
-                            self.add_member(id, tx);
-                        }
-                        RaftMsg::ChangeMembership{members, tx} => {
-                            tracing::info!("leader recv from rx_api: ChangeMembership, {:?}", members);
-                            self.change_membership(members, tx).await;
+                        RaftMsg::ClientWriteBatch{rpcs} => {
+                            tracing::info!("leader recv from rx_api: ClientWriteBatch, {}", rpcs.len());
+                            self.handle_client_write_batch(rpcs).await;
                         }
+                        msg => self.handle_api_msg(msg).await,
                     }
                 },
                 Some(update) = self.core.rx_compaction.recv() => {
                     tracing::info!("leader recv from rx_compaction: {:?}", update);
                     self.core.update_snapshot_state(update);
+                    self.notify_log_purged();
                 }
                 Some((event, span)) = self.replication_rx.recv() => {
                     tracing::info!("leader recv from replication_rx: {:?}", event.summary());
@@ -745,43 +1204,207 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         }
     }
 
+    /// Handle client write requests from the Raft API, together with the ones queued behind them.
+    #[tracing::instrument(level = "trace", skip(self, writes))]
+    async fn handle_client_write_batch(&mut self, mut writes: ClientWriteBatch<D, R, NID>) {
+        let next = self.drain_client_write_requests(&mut writes);
+        self.handle_client_write_requests(writes).await;
+        if let Some((msg, span)) = next {
+            let _ent = span.enter();
+            self.handle_api_msg(msg).await;
+        }
+    }
+
+    /// Handle a message from the Raft API.
+    #[tracing::instrument(level = "trace", skip(self, msg))]
+    async fn handle_api_msg(&mut self, msg: RaftMsg<D, R, NID>) {
+        match msg {
+            RaftMsg::AppendEntries { rpc, tx } => {
+                tracing::info!("leader recv from rx_api: AppendEntries, {}", rpc.summary());
+                let _ = tx.send(self.core.handle_append_entries_request(rpc).await);
+            }
+            RaftMsg::RequestVote { rpc, tx } => {
+                tracing::info!("leader recv from rx_api: RequestVote, {}", rpc.summary());
+                let _ = tx.send(self.core.handle_vote_request(rpc).await);
+            }
+            RaftMsg::InstallSnapshot { rpc, tx } => {
+                tracing::info!("leader recv from rx_api: InstallSnapshot, {}", rpc.summary());
+                let _ = tx.send(self.core.handle_install_snapshot_request(rpc).await);
+            }
+            RaftMsg::ClientReadRequest { tx } => {
+                tracing::info!("leader recv from rx_api: ClientReadRequest");
+                self.handle_client_read_request(tx).await;
+            }
+            RaftMsg::ReadIndex { rpc, tx } => {
+                self.handle_read_index_request(rpc, tx).await;
+            }
+            RaftMsg::StaleRead { max_lag, tx } => {
+                self.handle_stale_read_request(max_lag, tx);
+            }
+            RaftMsg::ClientWriteRequest { rpc, tx } => {
+                tracing::info!("leader recv from rx_api: ClientWriteRequest, {}", rpc.summary());
+                self.handle_client_write_requests(vec![(rpc, tx)]).await;
+            }
+            RaftMsg::ClientWriteBatch { rpcs } => {
+                tracing::info!("leader recv from rx_api: ClientWriteBatch, {}", rpcs.len());
+                self.handle_client_write_requests(rpcs).await;
+            }
+            RaftMsg::Initialize { tx, .. } => {
+                tracing::info!("leader recv from rx_api: Initialize");
+                self.core.reject_init_with_config(tx);
+            }
+            RaftMsg::AddNonVoter { id, node, tx } => {
+                tracing::info!("leader recv from rx_api: AddNonVoter, {}", id);
+                self.add_member(id, node, tx).await;
+            }
+            RaftMsg::RemoveNonVoter { id, tx } => {
+                tracing::info!("leader recv from rx_api: RemoveNonVoter, {}", id);
+                self.remove_non_voter(id, tx).await;
+            }
+            RaftMsg::ChangeMembership {
+                members,
+                nodes,
+                progress,
+                tx,
+            } => {
+                tracing::info!("leader recv from rx_api: ChangeMembership, {:?}", members);
+                self.change_membership(members, nodes, progress, tx).await;
+            }
+            RaftMsg::CancelChangeMembership { id, tx } => {
+                tracing::info!("leader recv from rx_api: CancelChangeMembership, {}", id);
+                self.cancel_change_membership(id, tx);
+            }
+            RaftMsg::AddVoter { id, node, tx } => {
+                tracing::info!("leader recv from rx_api: AddVoter, {}", id);
+                self.add_voter(id, node, tx).await;
+            }
+            RaftMsg::RemoveVoter { id, tx } => {
+                tracing::info!("leader recv from rx_api: RemoveVoter, {}", id);
+                self.remove_voter(id, tx).await;
+            }
+            RaftMsg::TimeoutNow { rpc, tx } => {
+                tracing::info!("leader recv from rx_api: TimeoutNow, {}", rpc.summary());
+                let _ = tx.send(self.core.handle_timeout_now_request(rpc).await);
+            }
+            RaftMsg::TransferLeadership { target, tx } => {
+                tracing::info!("leader recv from rx_api: TransferLeadership, {}", target);
+                self.transfer_leadership(target, tx);
+            }
+            RaftMsg::TriggerSnapshot { tx } => {
+                tracing::info!("leader recv from rx_api: TriggerSnapshot");
+                self.core.handle_trigger_snapshot(tx);
+            }
+            RaftMsg::PurgeLog { upto, tx } => {
+                tracing::info!("leader recv from rx_api: PurgeLog, {}", upto);
+                let _ = tx.send(self.purge_log_upto(upto).await);
+            }
+        }
+    }
+
+    /// Resync the replication streams which missed events as their channel was full.
+    fn resync_replication_streams(&mut self) {
+        for node in self.nodes.values_mut() {
+            node.replstream.resync();
+        }
+        for node in self.non_voters.values_mut() {
+            node.state.replstream.resync();
+        }
+    }
+
     /// Report metrics with leader specific states.
     #[tracing::instrument(level = "trace", skip(self))]
     pub fn leader_report_metrics(&mut self) {
+        self.leader_metrics.non_voters = self.non_voters.keys().cloned().collect();
+        let streams = self.nodes.iter().chain(self.non_voters.iter().map(|(id, node)| (id, &node.state)));
+        self.leader_metrics.replication_queue_lens =
+            streams.map(|(id, node)| (id.clone(), node.replstream.queue_len())).collect();
+        self.leader_metrics.replica_event_queue_len =
+            self.core.config.replication_queue_capacity - self.replication_tx.capacity() as u64;
         self.core.report_metrics(Update::Update(Some(&self.leader_metrics)));
     }
 }
 
 /// A struct tracking the state of a replication stream from the perspective of the Raft actor.
-struct ReplicationState<D: AppData> {
+struct ReplicationState<D: AppData, NID: NodeId> {
     pub matched: LogId,
+    /// The send time of the latest request to which the target responded in this term.
+    pub last_acked: Option<Instant>,
     pub remove_after_commit: Option<u64>,
-    pub replstream: ReplicationStream<D>,
+    pub replstream: ReplicationStream<D, NID>,
 }
 
 /// The same as `ReplicationState`, except for non-voters.
-struct NonVoterReplicationState<D: AppData> {
+struct NonVoterReplicationState<D: AppData, NID: NodeId> {
     /// The replication stream state.
-    pub state: ReplicationState<D>,
+    pub state: ReplicationState<D, NID>,
+    /// A bool indicating if the replication stream to this non-voter runs at line rate.
+    pub is_line_rate: bool,
     /// A bool indicating if this non-voters is ready to join the cluster.
     pub is_ready_to_join: bool,
+    /// The time at which this node started to sync the non-voter.
+    pub sync_started: Instant,
+    /// The leader's last log index when the current round of catching up started.
+    pub round_index: u64,
+    /// The time at which the current round of catching up started.
+    pub round_started: Instant,
+    /// How long the last finished round of catching up took.
+    pub last_round: Option<Duration>,
 
     /// The response channel to use for when this node has successfully synced with the cluster.
-    pub tx: Option<ResponseTx>,
+    pub tx: Option<ResponseTx<NID>>,
+}
+
+impl<D: AppData, NID: NodeId> NonVoterReplicationState<D, NID> {
+    /// Start syncing a non-voter, the first round catching up to the given index.
+    pub(self) fn new(mut state: ReplicationState<D, NID>, last_log_index: u64, tx: Option<ResponseTx<NID>>) -> Self {
+        // A new replication stream optimistically assumes the target has every log. Whether a non-voter is ready to
+        // join is decided by what it has actually replicated, so track it from scratch.
+        state.matched = LogId::default();
+        let now = Instant::now();
+        Self {
+            state,
+            is_line_rate: false,
+            is_ready_to_join: false,
+            sync_started: now,
+            round_index: last_log_index,
+            round_started: now,
+            last_round: None,
+            tx,
+        }
+    }
+}
+
+/// The state of a leadership transfer, from the perspective of the current leader.
+struct LeaderTransfer<NID: NodeId> {
+    /// The node which is to take over leadership.
+    pub target: NID,
+    /// The time at which the transfer is aborted if this node is still the leader, or, once it has
+    /// stepped down, the time by which it must have learned that the target took over.
+    pub deadline: Instant,
+    /// A bool indicating if the TimeoutNow RPC has been sent to the target.
+    pub timeout_now_sent: bool,
+    /// The response channel of the transfer request.
+    pub tx: oneshot::Sender<Result<(), TransferLeaderError<NID>>>,
 }
 
 /// A state enum used by Raft leaders to navigate the joint consensus protocol.
-pub enum ConsensusState {
+pub enum ConsensusState<NID: NodeId> {
     /// The cluster is preparring to go into joint consensus, but the leader is still syncing
     /// some non-voters to prepare them for cluster membership.
     NonVoterSync {
         /// The set of non-voters nodes which are still being synced.
-        awaiting: HashSet<NodeId>,
+        awaiting: HashSet<NID>,
         /// The full membership change which has been proposed.
-        members: BTreeSet<NodeId>,
+        members: BTreeSet<NID>,
+        /// The metadata of members given along with the proposed change.
+        nodes: BTreeMap<NID, Node>,
+        /// Whether the proposed change is a single-server change rather than a joint consensus.
+        single: bool,
+        /// The time at which the change is aborted if the non-voters are not ready by then.
+        deadline: Option<Instant>,
 
         /// The response channel to use once the consensus state is back into uniform state.
-        tx: ResponseTx,
+        tx: ResponseTx<NID>,
     },
     /// The cluster is in a joint consensus state and is syncing new nodes.
     Joint {
@@ -791,11 +1414,19 @@ pub enum ConsensusState {
         /// update this value to true once the new leader's blank payload has been committed.
         is_committed: bool,
     },
+    /// The cluster is changing by a single server, with the config entry at `index`.
+    ///
+    /// The config takes effect as soon as it is appended, and no other change may start until it
+    /// has been committed (§4.1 of the Raft dissertation).
+    Single {
+        /// The log index of the config entry.
+        index: u64,
+    },
     /// The cluster consensus is uniform; not in a joint consensus state.
     Uniform,
 }
 
-impl ConsensusState {
+impl<NID: NodeId> ConsensusState<NID> {
     /// Check the current state to determine if it is in joint consensus, and if it is safe to finalize the joint
     /// consensus.
     ///
@@ -813,8 +1444,15 @@ impl ConsensusState {
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// Volatile state specific to a Raft node in candidate state.
-struct CandidateState<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
-    core: &'a mut RaftCore<D, R, N, S>,
+struct CandidateState<
+    'a,
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, R, NID>,
+    S: RaftStorage<D, R, NID>,
+    NID: NodeId,
+> {
+    core: &'a mut RaftCore<D, R, N, S, NID>,
     /// The number of votes which have been granted by peer nodes of the old (current) config group.
     votes_granted_old: u64,
     /// The number of votes needed from the old (current) config group in order to become the Raft leader.
@@ -823,23 +1461,34 @@ struct CandidateState<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S:
     votes_granted_new: u64,
     /// The number of votes needed from the new config group in order to become the Raft leader (if applicable).
     votes_needed_new: u64,
+    /// A bool indicating if the current round is a PreVote round, which does not increment the term.
+    is_pre_vote: bool,
+    /// A bool indicating if this election was started on the request of the leader, as part of a
+    /// leadership transfer. Only the first election after such a request is marked as such.
+    is_leadership_transfer: bool,
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> CandidateState<'a, D, R, N, S> {
-    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S>) -> Self {
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D, R, NID>, S: RaftStorage<D, R, NID>, NID: NodeId>
+    CandidateState<'a, D, R, N, S, NID>
+{
+    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S, NID>) -> Self {
+        let is_leadership_transfer = std::mem::replace(&mut core.leadership_transfer, false);
         Self {
-            core,
             votes_granted_old: 0,
             votes_needed_old: 0,
             votes_granted_new: 0,
             votes_needed_new: 0,
+            // The leader has already established that this node may take over, so skip PreVote.
+            is_pre_vote: core.config.pre_vote && !is_leadership_transfer,
+            is_leadership_transfer,
+            core,
         }
     }
 
     /// Run the candidate loop.
-    #[tracing::instrument(level="trace", skip(self), fields(id=self.core.id, raft_state="candidate"))]
+    #[tracing::instrument(level="trace", skip(self), fields(id=%self.core.id, raft_state="candidate"))]
     pub(self) async fn run(mut self) -> RaftResult<()> {
-        // Each iteration of the outer loop represents a new term.
+        // Each iteration of the outer loop represents a new term, or a PreVote round for the next term.
         loop {
             if !self.core.target_state.is_candidate() {
                 return Ok(());
@@ -853,12 +1502,15 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                 self.votes_needed_new = ((nodes.len() / 2) + 1) as u64; // Just need a majority.
             }
 
-            // Setup new term.
             self.core.update_next_election_timeout(false); // Generates a new rand value within range.
-            self.core.current_term += 1;
-            self.core.voted_for = Some(self.core.id);
             self.core.update_current_leader(UpdateCurrentLeader::Unknown);
-            self.core.save_hard_state().await?;
+
+            // Setup new term, unless this is a PreVote round, which leaves the term untouched.
+            if !self.is_pre_vote {
+                self.core.current_term += 1;
+                self.core.voted_for = Some(self.core.id.clone());
+                self.core.save_hard_state().await?;
+            }
             self.core.report_metrics(Update::Update(None));
 
             // Send RPCs to all members in parallel.
@@ -870,13 +1522,28 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                     return Ok(());
                 }
                 let timeout_fut = sleep_until(self.core.get_next_election_timeout());
+                let transfer_deadline = self.core.stepped_down_transfer.as_ref().map(|transfer| transfer.deadline);
+                let transfer_timeout = sleep_until(transfer_deadline.unwrap_or_else(Instant::now));
 
                 let span = tracing::debug_span!("CHrx:CandidateState");
                 let _ent = span.enter();
 
                 tokio::select! {
-                    _ = timeout_fut => break, // This election has timed-out. Break to outer loop, which starts a new term.
-                    Some((res, peer)) = pending_votes.recv() => self.handle_vote_response(res, peer).await?,
+                    _ = timeout_fut => {
+                        // This election has timed-out. Break to outer loop, which starts a new term.
+                        self.is_pre_vote = self.core.config.pre_vote;
+                        self.is_leadership_transfer = false;
+                        break;
+                    }
+                    _ = transfer_timeout, if transfer_deadline.is_some() => self.core.resolve_stepped_down_transfer(),
+                    Some((res, peer)) = pending_votes.recv() => {
+                        let was_pre_vote = self.is_pre_vote;
+                        self.handle_vote_response(res, peer).await?;
+                        if was_pre_vote && !self.is_pre_vote {
+                            // The PreVote round succeeded. Break to outer loop, which starts a new term.
+                            break;
+                        }
+                    }
                     Some((msg,span)) = self.core.rx_api.recv() => {
 
                         let _ent = span.enter();
@@ -894,18 +1561,53 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                             RaftMsg::ClientReadRequest{tx} => {
                                 self.core.forward_client_read_request(tx);
                             }
+                            RaftMsg::ReadIndex{tx, ..} => {
+                                self.core.forward_read_index_request(tx);
+                            }
+                            RaftMsg::StaleRead{max_lag, tx} => {
+                                self.core.handle_stale_read_request(max_lag, tx);
+                            }
                             RaftMsg::ClientWriteRequest{rpc, tx} => {
                                 self.core.forward_client_write_request(rpc, tx);
                             }
+                            RaftMsg::ClientWriteBatch{rpcs} => {
+                                for (rpc, tx) in rpcs {
+                                    self.core.forward_client_write_request(rpc, tx);
+                                }
+                            }
                             RaftMsg::Initialize{tx, ..} => {
                                 self.core.reject_init_with_config(tx);
                             }
                             RaftMsg::AddNonVoter{tx, ..} => {
                                 self.core.reject_config_change_not_leader(tx);
                             }
+                            RaftMsg::RemoveNonVoter{tx, ..} => {
+                                self.core.reject_config_change_not_leader(tx);
+                            }
                             RaftMsg::ChangeMembership{tx, ..} => {
                                 self.core.reject_config_change_not_leader(tx);
                             }
+                            RaftMsg::CancelChangeMembership{tx, ..} => {
+                                self.core.reject_config_change_not_leader(tx);
+                            }
+                            RaftMsg::AddVoter{tx, ..} => {
+                                self.core.reject_config_change_not_leader(tx);
+                            }
+                            RaftMsg::RemoveVoter{tx, ..} => {
+                                self.core.reject_config_change_not_leader(tx);
+                            }
+                            RaftMsg::TimeoutNow{rpc, tx} => {
+                                let _ = tx.send(self.core.handle_timeout_now_request(rpc).await);
+                            }
+                            RaftMsg::TransferLeadership{tx, ..} => {
+                                self.core.reject_transfer_leadership_not_leader(tx);
+                            }
+                            RaftMsg::TriggerSnapshot{tx} => {
+                                self.core.handle_trigger_snapshot(tx);
+                            }
+                            RaftMsg::PurgeLog{upto, tx} => {
+                                let _ = tx.send(self.core.purge_log_upto(upto).await);
+                            }
                         }
                     },
                     Some(update) = self.core.rx_compaction.recv() => self.core.update_snapshot_state(update),
@@ -923,24 +1625,36 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// Volatile state specific to a Raft node in follower state.
-pub struct FollowerState<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
-    core: &'a mut RaftCore<D, R, N, S>,
+pub struct FollowerState<
+    'a,
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, R, NID>,
+    S: RaftStorage<D, R, NID>,
+    NID: NodeId,
+> {
+    core: &'a mut RaftCore<D, R, N, S, NID>,
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> FollowerState<'a, D, R, N, S> {
-    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S>) -> Self {
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D, R, NID>, S: RaftStorage<D, R, NID>, NID: NodeId>
+    FollowerState<'a, D, R, N, S, NID>
+{
+    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S, NID>) -> Self {
         Self { core }
     }
 
     /// Run the follower loop.
-    #[tracing::instrument(level="trace", skip(self), fields(id=self.core.id, raft_state="follower"))]
+    #[tracing::instrument(level="trace", skip(self), fields(id=%self.core.id, raft_state="follower"))]
     pub(self) async fn run(self) -> RaftResult<()> {
         self.core.report_metrics(Update::Update(None));
+        let mut snapshot_check = interval(Duration::from_millis(self.core.config.heartbeat_interval));
         loop {
             if !self.core.target_state.is_follower() {
                 return Ok(());
             }
             let election_timeout = sleep_until(self.core.get_next_election_timeout()); // Value is updated as heartbeats are received.
+            let transfer_deadline = self.core.stepped_down_transfer.as_ref().map(|transfer| transfer.deadline);
+            let transfer_timeout = sleep_until(transfer_deadline.unwrap_or_else(Instant::now));
 
             let span = tracing::debug_span!("CHrx:FollowerState");
             let _ent = span.enter();
@@ -948,6 +1662,10 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             tokio::select! {
                 // If an election timeout is hit, then we need to transition to candidate.
                 _ = election_timeout => self.core.set_target_state(State::Candidate),
+                _ = transfer_timeout, if transfer_deadline.is_some() => self.core.resolve_stepped_down_transfer(),
+                _ = snapshot_check.tick(), if self.core.config.snapshot_policy.uses_time() => {
+                    self.core.trigger_log_compaction_if_needed(false);
+                }
                 Some((msg,span)) = self.core.rx_api.recv() => {
 
                     let _ent = span.enter();
@@ -965,18 +1683,53 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                         RaftMsg::ClientReadRequest{tx} => {
                             self.core.forward_client_read_request(tx);
                         }
+                        RaftMsg::ReadIndex{tx, ..} => {
+                            self.core.forward_read_index_request(tx);
+                        }
+                        RaftMsg::StaleRead{max_lag, tx} => {
+                            self.core.handle_stale_read_request(max_lag, tx);
+                        }
                         RaftMsg::ClientWriteRequest{rpc, tx} => {
                             self.core.forward_client_write_request(rpc, tx);
                         }
+                        RaftMsg::ClientWriteBatch{rpcs} => {
+                            for (rpc, tx) in rpcs {
+                                self.core.forward_client_write_request(rpc, tx);
+                            }
+                        }
                         RaftMsg::Initialize{tx, ..} => {
                             self.core.reject_init_with_config(tx);
                         }
                         RaftMsg::AddNonVoter{tx, ..} => {
                             self.core.reject_config_change_not_leader(tx);
                         }
+                        RaftMsg::RemoveNonVoter{tx, ..} => {
+                            self.core.reject_config_change_not_leader(tx);
+                        }
                         RaftMsg::ChangeMembership{tx, ..} => {
                             self.core.reject_config_change_not_leader(tx);
                         }
+                        RaftMsg::CancelChangeMembership{tx, ..} => {
+                            self.core.reject_config_change_not_leader(tx);
+                        }
+                        RaftMsg::AddVoter{tx, ..} => {
+                            self.core.reject_config_change_not_leader(tx);
+                        }
+                        RaftMsg::RemoveVoter{tx, ..} => {
+                            self.core.reject_config_change_not_leader(tx);
+                        }
+                        RaftMsg::TimeoutNow{rpc, tx} => {
+                            let _ = tx.send(self.core.handle_timeout_now_request(rpc).await);
+                        }
+                        RaftMsg::TransferLeadership{tx, ..} => {
+                            self.core.reject_transfer_leadership_not_leader(tx);
+                        }
+                        RaftMsg::TriggerSnapshot{tx} => {
+                            self.core.handle_trigger_snapshot(tx);
+                        }
+                        RaftMsg::PurgeLog{upto, tx} => {
+                            let _ = tx.send(self.core.purge_log_upto(upto).await);
+                        }
                     }
                 },
                 Some(update) = self.core.rx_compaction.recv() => self.core.update_snapshot_state(update),
@@ -993,28 +1746,44 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// Volatile state specific to a Raft node in non-voter state.
-pub struct NonVoterState<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
-    core: &'a mut RaftCore<D, R, N, S>,
+pub struct NonVoterState<
+    'a,
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, R, NID>,
+    S: RaftStorage<D, R, NID>,
+    NID: NodeId,
+> {
+    core: &'a mut RaftCore<D, R, N, S, NID>,
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> NonVoterState<'a, D, R, N, S> {
-    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S>) -> Self {
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D, R, NID>, S: RaftStorage<D, R, NID>, NID: NodeId>
+    NonVoterState<'a, D, R, N, S, NID>
+{
+    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S, NID>) -> Self {
         Self { core }
     }
 
     /// Run the non-voter loop.
-    #[tracing::instrument(level="trace", skip(self), fields(id=self.core.id, raft_state="non-voter"))]
+    #[tracing::instrument(level="trace", skip(self), fields(id=%self.core.id, raft_state="non-voter"))]
     pub(self) async fn run(mut self) -> RaftResult<()> {
         self.core.report_metrics(Update::Update(None));
+        let mut snapshot_check = interval(Duration::from_millis(self.core.config.heartbeat_interval));
         loop {
             if !self.core.target_state.is_non_voter() {
                 return Ok(());
             }
+            let transfer_deadline = self.core.stepped_down_transfer.as_ref().map(|transfer| transfer.deadline);
+            let transfer_timeout = sleep_until(transfer_deadline.unwrap_or_else(Instant::now));
 
             let span = tracing::debug_span!("CHrx:NonVoterState");
             let _ent = span.enter();
 
             tokio::select! {
+                _ = transfer_timeout, if transfer_deadline.is_some() => self.core.resolve_stepped_down_transfer(),
+                _ = snapshot_check.tick(), if self.core.config.snapshot_policy.uses_time() => {
+                    self.core.trigger_log_compaction_if_needed(false);
+                }
                 Some((msg,span)) = self.core.rx_api.recv() => {
 
                     let _ent = span.enter();
@@ -1032,18 +1801,53 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                         RaftMsg::ClientReadRequest{tx} => {
                             self.core.forward_client_read_request(tx);
                         }
+                        RaftMsg::ReadIndex{tx, ..} => {
+                            self.core.forward_read_index_request(tx);
+                        }
+                        RaftMsg::StaleRead{max_lag, tx} => {
+                            self.core.handle_stale_read_request(max_lag, tx);
+                        }
                         RaftMsg::ClientWriteRequest{rpc, tx} => {
                             self.core.forward_client_write_request(rpc, tx);
                         }
+                        RaftMsg::ClientWriteBatch{rpcs} => {
+                            for (rpc, tx) in rpcs {
+                                self.core.forward_client_write_request(rpc, tx);
+                            }
+                        }
                         RaftMsg::Initialize{members, tx} => {
                             let _ = tx.send(self.handle_init_with_config(members).await);
                         }
                         RaftMsg::AddNonVoter{tx, ..} => {
                             self.core.reject_config_change_not_leader(tx);
                         }
+                        RaftMsg::RemoveNonVoter{tx, ..} => {
+                            self.core.reject_config_change_not_leader(tx);
+                        }
                         RaftMsg::ChangeMembership{tx, ..} => {
                             self.core.reject_config_change_not_leader(tx);
                         }
+                        RaftMsg::CancelChangeMembership{tx, ..} => {
+                            self.core.reject_config_change_not_leader(tx);
+                        }
+                        RaftMsg::AddVoter{tx, ..} => {
+                            self.core.reject_config_change_not_leader(tx);
+                        }
+                        RaftMsg::RemoveVoter{tx, ..} => {
+                            self.core.reject_config_change_not_leader(tx);
+                        }
+                        RaftMsg::TimeoutNow{rpc, tx} => {
+                            let _ = tx.send(self.core.handle_timeout_now_request(rpc).await);
+                        }
+                        RaftMsg::TransferLeadership{tx, ..} => {
+                            self.core.reject_transfer_leadership_not_leader(tx);
+                        }
+                        RaftMsg::TriggerSnapshot{tx} => {
+                            self.core.handle_trigger_snapshot(tx);
+                        }
+                        RaftMsg::PurgeLog{upto, tx} => {
+                            let _ = tx.send(self.core.purge_log_upto(upto).await);
+                        }
                     }
                 },
                 Some(update) = self.core.rx_compaction.recv() => self.core.update_snapshot_state(update),
@@ -1,15 +1,19 @@
 use std::collections::BTreeSet;
 
 use tokio::sync::oneshot;
+use tokio::time::Duration;
+use tokio::time::Instant;
 use tracing_futures::Instrument;
 
-use crate::config::SnapshotPolicy;
+use crate::config::ReadyToJoinPolicy;
+use crate::config::SyncProgress;
 use crate::core::ConsensusState;
 use crate::core::LeaderState;
 use crate::core::ReplicationState;
 use crate::core::SnapshotState;
 use crate::core::State;
 use crate::core::UpdateCurrentLeader;
+use crate::error::PurgeLogError;
 use crate::error::RaftResult;
 use crate::quorum;
 use crate::replication::RaftEvent;
@@ -24,35 +28,80 @@ use crate::RaftNetwork;
 use crate::RaftStorage;
 use crate::ReplicationMetrics;
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> LeaderState<'a, D, R, N, S> {
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D, R, NID>, S: RaftStorage<D, R, NID>, NID: NodeId>
+    LeaderState<'a, D, R, N, S, NID>
+{
     /// Spawn a new replication stream returning its replication state handle.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub(super) fn spawn_replication_stream(&self, target: NodeId) -> ReplicationState<D> {
+    pub(super) fn spawn_replication_stream(&self, target: NID) -> ReplicationState<D, NID> {
         let replstream = ReplicationStream::new(
-            self.core.id,
+            self.core.id.clone(),
             target,
             self.core.current_term,
             self.core.config.clone(),
             self.core.last_log_id,
             self.core.commit_index,
+            self.core.log_purged_upto,
             self.core.network.clone(),
             self.core.storage.clone(),
             self.replication_tx.clone(),
         );
         ReplicationState {
             matched: (self.core.current_term, self.core.last_log_id.index).into(),
+            last_acked: None,
             replstream,
             remove_after_commit: None,
         }
     }
 
+    /// Purge the log for `Raft::purge_log_upto`, unless a replication target still needs entries which are not
+    /// covered by the current snapshot.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(super) async fn purge_log_upto(&mut self, upto: u64) -> Result<(), PurgeLogError<NID>> {
+        if upto > self.core.snapshot_last_log_id.index {
+            let targets = self.nodes.iter().chain(self.non_voters.iter().map(|(id, node)| (id, &node.state)));
+            for (target, node) in targets {
+                if node.matched.index < upto {
+                    return Err(PurgeLogError::NeededByReplication {
+                        target: target.clone(),
+                        matched: node.matched,
+                    });
+                }
+            }
+        }
+
+        self.core.purge_log_upto(upto).await?;
+        self.notify_log_purged();
+        Ok(())
+    }
+
+    /// Tell every replication stream the index up to which the log may have been purged, so that targets lagging
+    /// behind it are sent a snapshot instead.
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub(super) fn notify_log_purged(&mut self) {
+        let upto = self.core.log_purged_upto;
+        let streams = self.nodes.values_mut().chain(self.non_voters.values_mut().map(|node| &mut node.state));
+        for node in streams {
+            node.replstream.send(RaftEvent::PurgeLog { upto });
+        }
+    }
+
     /// Handle a replication event coming from one of the replication streams.
     #[tracing::instrument(level = "trace", skip(self, event))]
-    pub(super) async fn handle_replica_event(&mut self, event: ReplicaEvent<S::SnapshotData>) {
+    pub(super) async fn handle_replica_event(&mut self, event: ReplicaEvent<S::SnapshotData, NID>) {
         let res = match event {
             ReplicaEvent::RateUpdate { target, is_line_rate } => self.handle_rate_update(target, is_line_rate).await,
             ReplicaEvent::RevertToFollower { target, term } => self.handle_revert_to_follower(target, term).await,
             ReplicaEvent::UpdateMatchIndex { target, matched } => self.handle_update_matched(target, matched).await,
+            ReplicaEvent::UpdateAcked { target, acked_at } => {
+                self.handle_update_acked(target.clone(), acked_at);
+                // Readiness may depend on more than the replicated logs, check it again while a change awaits it.
+                if self.is_awaited_by_sync(&target) {
+                    self.update_ready_to_join(target).await
+                } else {
+                    Ok(())
+                }
+            }
             ReplicaEvent::NeedsSnapshot { target, tx } => self.handle_needs_snapshot(target, tx).await,
             ReplicaEvent::Shutdown => {
                 self.core.set_target_state(State::Shutdown);
@@ -66,48 +115,123 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
     /// Handle events from replication streams updating their replication rate tracker.
     #[tracing::instrument(level = "trace", skip(self, target, is_line_rate))]
-    async fn handle_rate_update(&mut self, target: NodeId, is_line_rate: bool) -> RaftResult<()> {
+    async fn handle_rate_update(&mut self, target: NID, is_line_rate: bool) -> RaftResult<()> {
         // Get a handle the target's replication stat & update it as needed.
         if let Some(_state) = self.nodes.get_mut(&target) {
             return Ok(());
         }
         // Else, if this is a non-voter, then update as needed.
         if let Some(state) = self.non_voters.get_mut(&target) {
-            // TODO(xp): use Vec<_> to replace the two membership configs.
-            state.is_ready_to_join = is_line_rate;
-            // Issue a response on the non-voters response channel if needed.
-            if state.is_ready_to_join {
-                if let Some(tx) = state.tx.take() {
-                    // TODO(xp): no log index to send
-                    let _ = tx.send(Ok(0));
-                }
-                // If we are in NonVoterSync state, and this is one of the nodes being awaiting, then update.
-                match std::mem::replace(&mut self.consensus_state, ConsensusState::Uniform) {
-                    ConsensusState::NonVoterSync {
-                        mut awaiting,
-                        members,
-                        tx,
-                    } => {
-                        awaiting.remove(&target);
-                        if awaiting.is_empty() {
-                            // We are ready to move forward with entering joint consensus.
-                            self.consensus_state = ConsensusState::Uniform;
-                            self.change_membership(members, tx).await;
+            state.is_line_rate = is_line_rate;
+            self.update_ready_to_join(target).await?;
+        }
+        Ok(())
+    }
+
+    /// Check whether the given non-voter is ready to join the cluster, according to `Config::ready_to_join`,
+    /// and move forward with the membership change waiting for it, if any.
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn update_ready_to_join(&mut self, target: NID) -> RaftResult<()> {
+        let is_ready = self.is_ready_to_join(&target);
+        let state = match self.non_voters.get_mut(&target) {
+            Some(state) => state,
+            None => return Ok(()),
+        };
+        // TODO(xp): use Vec<_> to replace the two membership configs.
+        state.is_ready_to_join = is_ready;
+        // Issue a response on the non-voters response channel if needed.
+        if state.is_ready_to_join {
+            if let Some(tx) = state.tx.take() {
+                // TODO(xp): no log index to send
+                let _ = tx.send(Ok(0));
+            }
+            // If we are in NonVoterSync state, and this is one of the nodes being awaiting, then update.
+            match std::mem::replace(&mut self.consensus_state, ConsensusState::Uniform) {
+                ConsensusState::NonVoterSync {
+                    mut awaiting,
+                    members,
+                    nodes,
+                    single,
+                    deadline,
+                    tx,
+                } => {
+                    awaiting.remove(&target);
+                    if awaiting.is_empty() {
+                        // We are ready to move forward with the membership change.
+                        self.consensus_state = ConsensusState::Uniform;
+                        if single {
+                            self.change_membership_single(members, nodes, tx).await;
                         } else {
-                            // We are still awaiting additional nodes, so replace our original state.
-                            self.consensus_state = ConsensusState::NonVoterSync { awaiting, members, tx };
+                            self.change_membership(members, nodes, None, tx).await;
                         }
+                    } else {
+                        // We are still awaiting additional nodes, so replace our original state.
+                        self.consensus_state = ConsensusState::NonVoterSync {
+                            awaiting,
+                            members,
+                            nodes,
+                            single,
+                            deadline,
+                            tx,
+                        };
                     }
-                    other => self.consensus_state = other, // Set the original value back to what it was.
                 }
+                other => self.consensus_state = other, // Set the original value back to what it was.
             }
         }
         Ok(())
     }
 
+    /// Check whether a membership change is waiting for the given non-voter to be ready to join.
+    fn is_awaited_by_sync(&self, target: &NID) -> bool {
+        match &self.consensus_state {
+            ConsensusState::NonVoterSync { awaiting, .. } => awaiting.contains(target),
+            _ => false,
+        }
+    }
+
+    /// Decide whether a non-voter is ready to join the cluster, according to `Config::ready_to_join`.
+    fn is_ready_to_join(&self, target: &NID) -> bool {
+        let config = &self.core.config;
+        let state = match self.non_voters.get(target) {
+            Some(state) => state,
+            None => return false,
+        };
+        let matched = state.state.matched;
+        let last_log_index = self.core.last_log_id.index;
+
+        match &config.ready_to_join {
+            ReadyToJoinPolicy::LineRate => state.is_line_rate,
+            ReadyToJoinPolicy::MaxLagEntries(max) => last_log_index.saturating_sub(matched.index) <= *max,
+            ReadyToJoinPolicy::MaxLagBytes(max) => self.is_lag_within_bytes(matched.index, *max),
+            ReadyToJoinPolicy::CaughtUpWithin(ms) => {
+                state.last_round.map(|round| round <= Duration::from_millis(*ms)).unwrap_or(false)
+            }
+            ReadyToJoinPolicy::Custom(f) => f.call(&SyncProgress {
+                matched,
+                last_log_index,
+                is_line_rate: state.is_line_rate,
+                last_round: state.last_round,
+                elapsed: state.sync_started.elapsed(),
+            }),
+        }
+    }
+
+    /// Check whether the entries after the given index add up to at most `max` bytes.
+    ///
+    /// The sizes are taken from the running totals of the log the core keeps, so that this does not read storage on
+    /// every update from a replication stream.
+    fn is_lag_within_bytes(&self, matched_index: u64, max: u64) -> bool {
+        if matched_index < self.core.log_purged_upto {
+            // The entries may have been compacted into a snapshot, which the non-voter has yet to install.
+            return false;
+        }
+        self.core.log_sizes.total_after(matched_index) <= max
+    }
+
     /// Handle events from replication streams for when this node needs to revert to follower state.
     #[tracing::instrument(level = "trace", skip(self, term))]
-    async fn handle_revert_to_follower(&mut self, _: NodeId, term: u64) -> RaftResult<()> {
+    async fn handle_revert_to_follower(&mut self, _: NID, term: u64) -> RaftResult<()> {
         if term > self.core.current_term {
             self.core.update_current_term(term, None);
             self.core.save_hard_state().await?;
@@ -119,12 +243,23 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
     /// Handle events from a replication stream which updates the target node's match index.
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn handle_update_matched(&mut self, target: NodeId, matched: LogId) -> RaftResult<()> {
+    async fn handle_update_matched(&mut self, target: NID, matched: LogId) -> RaftResult<()> {
         let mut found = false;
 
+        let mut is_non_voter = false;
+
         if let Some(state) = self.non_voters.get_mut(&target) {
             state.state.matched = matched;
             found = true;
+            is_non_voter = true;
+
+            // Finish the current round of catching up, and start the next one from the leader's last log.
+            if matched.index >= state.round_index {
+                let now = Instant::now();
+                state.last_round = Some(now.duration_since(state.round_started));
+                state.round_index = self.core.last_log_id.index;
+                state.round_started = now;
+            }
         }
 
         // Update target's match index & check if it is awaiting removal.
@@ -146,14 +281,15 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             return Ok(());
         }
 
-        self.update_leader_metrics(target, matched);
+        self.update_leader_metrics(target.clone(), matched);
+        self.send_timeout_now_if_ready();
 
         // Drop replication stream if needed.
         // TODO(xp): is it possible to merge the two node remove routines?
         //           here and that in handle_uniform_consensus_committed()
         if needs_removal {
-            if let Some(node) = self.nodes.remove(&target) {
-                let _ = node.replstream.repl_tx.send((RaftEvent::Terminate, tracing::debug_span!("CH")));
+            if let Some(mut node) = self.nodes.remove(&target) {
+                node.replstream.send(RaftEvent::Terminate);
 
                 // remove metrics entry
                 self.leader_metrics.replication.remove(&target);
@@ -169,21 +305,15 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             self.core.commit_index = commit_index;
 
             // Update all replication streams based on new commit index.
-            for node in self.nodes.values() {
-                let _ = node.replstream.repl_tx.send((
-                    RaftEvent::UpdateCommitIndex {
-                        commit_index: self.core.commit_index,
-                    },
-                    tracing::debug_span!("CH"),
-                ));
+            for node in self.nodes.values_mut() {
+                node.replstream.send(RaftEvent::UpdateCommitIndex {
+                    commit_index: self.core.commit_index,
+                });
             }
-            for node in self.non_voters.values() {
-                let _ = node.state.replstream.repl_tx.send((
-                    RaftEvent::UpdateCommitIndex {
-                        commit_index: self.core.commit_index,
-                    },
-                    tracing::debug_span!("CH"),
-                ));
+            for node in self.non_voters.values_mut() {
+                node.state.replstream.send(RaftEvent::UpdateCommitIndex {
+                    commit_index: self.core.commit_index,
+                });
             }
 
             // Check if there are any pending requests which need to be processed.
@@ -202,15 +332,85 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                     self.client_request_post_commit(request).await;
                 }
             }
+
+            self.respond_read_index_requests();
         }
 
         // TODO(xp): does this update too frequently?
         self.leader_report_metrics();
+
+        if is_non_voter {
+            self.update_ready_to_join(target).await?;
+        }
         Ok(())
     }
 
+    /// Handle events from a replication stream reporting that the target has responded in this term.
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn handle_update_acked(&mut self, target: NID, acked_at: Instant) {
+        let state = match self.nodes.get_mut(&target) {
+            Some(state) => state,
+            None => match self.non_voters.get_mut(&target) {
+                Some(state) => &mut state.state,
+                None => return,
+            },
+        };
+
+        // Responses may arrive out of order, keep the latest.
+        if state.last_acked.map(|last| last < acked_at).unwrap_or(true) {
+            state.last_acked = Some(acked_at);
+        }
+    }
+
+    /// Step down if a quorum of the cluster has not responded within an election timeout (CheckQuorum).
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub(super) fn check_quorum(&mut self) {
+        let timeout = Duration::from_millis(self.core.config.election_timeout_max);
+        let now = Instant::now();
+
+        if let Some(acked_at) = self.calc_quorum_acked_at() {
+            if now.duration_since(acked_at) <= timeout {
+                return;
+            }
+        }
+
+        tracing::info!("stepping down as a quorum of the cluster has not responded within an election timeout");
+        self.core.update_current_leader(UpdateCurrentLeader::Unknown);
+        self.core.set_target_state(State::Follower);
+    }
+
+    /// Get the latest time by which a majority of each config group is known to have accepted this
+    /// node as leader, or `None` if no such majority has responded yet in this term.
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub(super) fn calc_quorum_acked_at(&self) -> Option<Instant> {
+        let now = Instant::now();
+        let c0_acked_at = calculate_quorum_acked_at(self.get_acked_at(&self.core.membership.members, now));
+
+        // If we are in joint consensus, then the new config group must agree as well.
+        match &self.core.membership.members_after_consensus {
+            Some(members) => std::cmp::min(c0_acked_at, calculate_quorum_acked_at(self.get_acked_at(members, now))),
+            None => c0_acked_at,
+        }
+    }
+
+    /// Extract the last time each of the specified nodes acknowledged this leader, counting the leader itself as `now`.
+    fn get_acked_at(&self, node_ids: &BTreeSet<NID>, now: Instant) -> Vec<Option<Instant>> {
+        node_ids
+            .iter()
+            .map(|id| {
+                if *id == self.core.id {
+                    return Some(now);
+                }
+                match self.nodes.get(id) {
+                    Some(state) => state.last_acked,
+                    None => self.non_voters.get(id).and_then(|state| state.state.last_acked),
+                }
+            })
+            .collect()
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
-    fn update_leader_metrics(&mut self, target: NodeId, matched: LogId) {
+    fn update_leader_metrics(&mut self, target: NID, matched: LogId) {
         self.leader_metrics.replication.insert(target, ReplicationMetrics { matched });
     }
 
@@ -228,7 +428,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         std::cmp::min(c0_index, c1_index)
     }
 
-    fn calc_members_commit_index(&self, mem: &BTreeSet<NodeId>, msg: &str) -> u64 {
+    fn calc_members_commit_index(&self, mem: &BTreeSet<NID>, msg: &str) -> u64 {
         let log_ids = self.get_match_log_ids(mem);
         tracing::debug!("{} matched log_ids: {:?}", msg, log_ids);
 
@@ -239,7 +439,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     }
 
     /// Extract the matching index/term of the replication state of specified nodes.
-    fn get_match_log_ids(&self, node_ids: &BTreeSet<NodeId>) -> Vec<LogId> {
+    fn get_match_log_ids(&self, node_ids: &BTreeSet<NID>) -> Vec<LogId> {
         tracing::debug!("to get match log ids of nodes: {:?}", node_ids);
 
         let mut rst = Vec::with_capacity(node_ids.len());
@@ -275,13 +475,10 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     #[tracing::instrument(level = "trace", skip(self, tx))]
     async fn handle_needs_snapshot(
         &mut self,
-        _: NodeId,
-        tx: oneshot::Sender<Snapshot<S::SnapshotData>>,
+        _: NID,
+        tx: oneshot::Sender<Snapshot<S::SnapshotData, NID>>,
     ) -> RaftResult<()> {
-        // Ensure snapshotting is configured, else do nothing.
-        let threshold = match &self.core.config.snapshot_policy {
-            SnapshotPolicy::LogsSinceLast(threshold) => *threshold,
-        };
+        let threshold = self.core.config.snapshot_policy.max_lag_entries();
 
         // Check for existence of current snapshot.
         let current_snapshot_opt = self
@@ -293,12 +490,19 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
         if let Some(snapshot) = current_snapshot_opt {
             // If snapshot exists, ensure its distance from the leader's last log index is <= half
-            // of the configured snapshot threshold, else create a new snapshot.
-            if snapshot_is_within_half_of_threshold(
-                &snapshot.meta.last_log_id.index,
-                &self.core.last_log_id.index,
-                &threshold,
-            ) {
+            // of the configured snapshot threshold, else create a new snapshot. If the policy does
+            // not count entries, the current snapshot is sent as it is. Either way it must cover the
+            // entries purged from the log, which the target could not be sent after it.
+            let covers_purged = snapshot.meta.last_log_id.index >= self.core.log_purged_upto;
+            let is_within_threshold = match threshold {
+                Some(threshold) => snapshot_is_within_half_of_threshold(
+                    &snapshot.meta.last_log_id.index,
+                    &self.core.last_log_id.index,
+                    &threshold,
+                ),
+                None => true,
+            };
+            if is_within_threshold && covers_purged {
                 let _ = tx.send(snapshot);
                 return Ok(());
             }
@@ -366,6 +570,21 @@ fn calculate_new_commit_index(mut log_ids: Vec<LogId>, current_commit: u64, lead
     }
 }
 
+/// Determine the latest time by which a majority of the given nodes had acknowledged the leader.
+///
+/// - `acked_at`: the last acknowledgement time of each node of a config group, `None` for nodes
+///   which have not responded in the current term.
+fn calculate_quorum_acked_at(mut acked_at: Vec<Option<Instant>>) -> Option<Instant> {
+    if acked_at.is_empty() {
+        return None;
+    }
+
+    // `None` sorts first, so the majority-th most recent value is found counting from the end.
+    acked_at.sort_unstable();
+    let offset = acked_at.len() - quorum::majority_of(acked_at.len());
+    acked_at[offset]
+}
+
 /// Check if the given snapshot data is within half of the configured threshold.
 fn snapshot_is_within_half_of_threshold(snapshot_last_index: &u64, last_log_index: &u64, threshold: &u64) -> bool {
     // Calculate distance from actor's last log index.
@@ -412,6 +631,35 @@ mod tests {
         });
     }
 
+    //////////////////////////////////////////////////////////////////////////
+    // calculate_quorum_acked_at /////////////////////////////////////////////
+
+    mod calculate_quorum_acked_at {
+        use super::*;
+
+        #[test]
+        fn empty_is_none() {
+            assert_eq!(calculate_quorum_acked_at(vec![]), None);
+        }
+
+        #[test]
+        fn majority_of_odd_number_of_nodes() {
+            let now = Instant::now();
+            let t = |ms: u64| Some(now - Duration::from_millis(ms));
+            assert_eq!(calculate_quorum_acked_at(vec![t(0), t(300), t(100)]), t(100));
+            assert_eq!(calculate_quorum_acked_at(vec![t(0), None, t(100)]), t(100));
+            assert_eq!(calculate_quorum_acked_at(vec![t(0), None, None]), None);
+        }
+
+        #[test]
+        fn majority_of_even_number_of_nodes() {
+            let now = Instant::now();
+            let t = |ms: u64| Some(now - Duration::from_millis(ms));
+            assert_eq!(calculate_quorum_acked_at(vec![t(0), t(300), t(100), t(200)]), t(200));
+            assert_eq!(calculate_quorum_acked_at(vec![t(0), None, t(100), None]), None);
+        }
+    }
+
     //////////////////////////////////////////////////////////////////////////
     // calculate_new_commit_index ////////////////////////////////////////////
 
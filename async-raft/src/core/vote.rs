@@ -7,6 +7,8 @@ use crate::core::RaftCore;
 use crate::core::State;
 use crate::core::UpdateCurrentLeader;
 use crate::error::RaftResult;
+use crate::raft::TimeoutNowRequest;
+use crate::raft::TimeoutNowResponse;
 use crate::raft::VoteRequest;
 use crate::raft::VoteResponse;
 use crate::AppData;
@@ -15,30 +17,33 @@ use crate::NodeId;
 use crate::RaftNetwork;
 use crate::RaftStorage;
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> RaftCore<D, R, N, S> {
+impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D, R, NID>, S: RaftStorage<D, R, NID>, NID: NodeId>
+    RaftCore<D, R, N, S, NID>
+{
     /// An RPC invoked by candidates to gather votes (§5.2).
     ///
     /// See `receiver implementation: RequestVote RPC` in raft-essentials.md in this repo.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub(super) async fn handle_vote_request(&mut self, msg: VoteRequest) -> RaftResult<VoteResponse> {
-        tracing::debug!({candidate=msg.candidate_id, self.current_term, rpc_term=msg.term}, "start handle_vote_request");
+    pub(super) async fn handle_vote_request(&mut self, msg: VoteRequest<NID>) -> RaftResult<VoteResponse> {
+        tracing::debug!({candidate=%msg.candidate_id, self.current_term, rpc_term=msg.term}, "start handle_vote_request");
 
         // If candidate's current term is less than this nodes current term, reject.
         if msg.term < self.current_term {
-            tracing::debug!({candidate=msg.candidate_id, self.current_term, rpc_term=msg.term}, "RequestVote RPC term is less than current term");
+            tracing::debug!({candidate=%msg.candidate_id, self.current_term, rpc_term=msg.term}, "RequestVote RPC term is less than current term");
             return Ok(VoteResponse {
                 term: self.current_term,
                 vote_granted: false,
             });
         }
 
-        // Do not respond to the request if we've received a heartbeat within the election timeout minimum.
-        if let Some(inst) = &self.last_heartbeat {
+        // Do not respond to the request if we've received a heartbeat within the election timeout minimum,
+        // unless the leader itself asked the candidate to take over.
+        if let Some(inst) = self.last_heartbeat.as_ref().filter(|_| !msg.leadership_transfer) {
             let now = Instant::now();
             let delta = now.duration_since(*inst);
             if self.config.election_timeout_min >= (delta.as_millis() as u64) {
                 tracing::debug!(
-                    { candidate = msg.candidate_id },
+                    { candidate = %msg.candidate_id },
                     "rejecting vote request received within election timeout minimum"
                 );
                 return Ok(VoteResponse {
@@ -48,6 +53,11 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             }
         }
 
+        // A PreVote must not change the state of this node, so it is answered before the term update below.
+        if msg.pre_vote {
+            return Ok(self.handle_pre_vote_request(&msg));
+        }
+
         // Per spec, if we observe a term greater than our own outside of the election timeout
         // minimum, then we must update term & immediately become follower. We still need to
         // do vote checking after this.
@@ -60,11 +70,9 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
         // Check if candidate's log is at least as up-to-date as this node's.
         // If candidate's log is not at least as up-to-date as this node, then reject.
-        let client_is_uptodate =
-            (msg.last_log_term >= self.last_log_id.term) && (msg.last_log_index >= self.last_log_id.index);
-        if !client_is_uptodate {
+        if !self.is_candidate_log_uptodate(&msg) {
             tracing::debug!(
-                { candidate = msg.candidate_id },
+                { candidate = %msg.candidate_id },
                 "rejecting vote request as candidate's log is not up-to-date"
             );
             return Ok(VoteResponse {
@@ -73,9 +81,6 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             });
         }
 
-        // TODO: add hook for PreVote optimization here. If the RPC is a PreVote, then at this
-        // point we can respond to the candidate telling them that we would vote for them.
-
         // Candidate's log is up-to-date so handle voting conditions.
         match &self.voted_for {
             // This node has already voted for the candidate.
@@ -90,11 +95,11 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             }),
             // This node has not yet voted for the current term, so vote for the candidate.
             None => {
-                self.voted_for = Some(msg.candidate_id);
+                self.voted_for = Some(msg.candidate_id.clone());
                 self.set_target_state(State::Follower);
                 self.update_next_election_timeout(false);
                 self.save_hard_state().await?;
-                tracing::debug!({candidate=msg.candidate_id, msg.term}, "voted for candidate");
+                tracing::debug!({candidate=%msg.candidate_id, msg.term}, "voted for candidate");
                 Ok(VoteResponse {
                     term: self.current_term,
                     vote_granted: true,
@@ -102,12 +107,89 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             }
         }
     }
+
+    /// An RPC invoked by the leader to have this node start an election at once, as part of a
+    /// leadership transfer (§3.10 of the Raft dissertation).
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(super) async fn handle_timeout_now_request(
+        &mut self,
+        msg: TimeoutNowRequest<NID>,
+    ) -> RaftResult<TimeoutNowResponse> {
+        // Ignore requests from stale leaders.
+        if msg.term < self.current_term {
+            tracing::debug!({leader=%msg.leader_id, self.current_term, rpc_term=msg.term}, "TimeoutNow RPC term is less than current term");
+            return Ok(TimeoutNowResponse {
+                term: self.current_term,
+            });
+        }
+
+        if msg.term > self.current_term {
+            self.update_current_term(msg.term, None);
+            self.set_target_state(State::Follower);
+            self.save_hard_state().await?;
+        }
+
+        // Only a follower of the requesting leader may take over; non-voters never campaign.
+        if self.target_state.is_follower() {
+            tracing::debug!({leader=%msg.leader_id, msg.term}, "starting election on request of the leader");
+            self.leadership_transfer = true;
+            self.set_target_state(State::Candidate);
+        }
+
+        Ok(TimeoutNowResponse {
+            term: self.current_term,
+        })
+    }
+
+    /// Answer a PreVote request (§9.6 of the Raft dissertation).
+    ///
+    /// The caller must already have rejected requests with a stale term, and requests received
+    /// within the election timeout minimum of a heartbeat. Neither the term nor the vote of this
+    /// node is updated here.
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn handle_pre_vote_request(&self, msg: &VoteRequest<NID>) -> VoteResponse {
+        let vote_granted = if self.target_state.is_leader() {
+            // A leader is in contact with the cluster by definition, so there is no need for an election.
+            tracing::debug!(
+                { candidate = %msg.candidate_id },
+                "rejecting PreVote as this node is the leader"
+            );
+            false
+        } else if !self.is_candidate_log_uptodate(msg) {
+            tracing::debug!(
+                { candidate = %msg.candidate_id },
+                "rejecting PreVote as candidate's log is not up-to-date"
+            );
+            false
+        } else if msg.term > self.current_term {
+            // A vote cast in our current term does not matter for the term the candidate is asking about.
+            true
+        } else {
+            match &self.voted_for {
+                Some(candidate_id) => candidate_id == &msg.candidate_id,
+                None => true,
+            }
+        };
+
+        tracing::debug!({candidate=%msg.candidate_id, msg.term, vote_granted}, "answered PreVote");
+        VoteResponse {
+            term: self.current_term,
+            vote_granted,
+        }
+    }
+
+    /// Check if the log of the candidate of the given vote request is at least as up-to-date as this node's log.
+    fn is_candidate_log_uptodate(&self, msg: &VoteRequest<NID>) -> bool {
+        (msg.last_log_term >= self.last_log_id.term) && (msg.last_log_index >= self.last_log_id.index)
+    }
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> CandidateState<'a, D, R, N, S> {
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D, R, NID>, S: RaftStorage<D, R, NID>, NID: NodeId>
+    CandidateState<'a, D, R, N, S, NID>
+{
     /// Handle response from a vote request sent to a peer.
     #[tracing::instrument(level = "trace", skip(self, res, target))]
-    pub(super) async fn handle_vote_response(&mut self, res: VoteResponse, target: NodeId) -> RaftResult<()> {
+    pub(super) async fn handle_vote_response(&mut self, res: VoteResponse, target: NID) -> RaftResult<()> {
         // If peer's term is greater than current term, revert to follower state.
         if res.term > self.core.current_term {
             self.core.update_current_term(res.term, None);
@@ -137,6 +219,12 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             }
             // If we've received enough votes from both config groups, then transition to leader state`.
             if self.votes_granted_old >= self.votes_needed_old && self.votes_granted_new >= self.votes_needed_new {
+                // A successful PreVote round only allows this node to start a real election.
+                if self.is_pre_vote {
+                    tracing::debug!("starting a new election as minimum number of PreVotes have been received");
+                    self.is_pre_vote = false;
+                    return Ok(());
+                }
                 tracing::debug!("transitioning to leader state as minimum number of votes have been received");
                 self.core.set_target_state(State::Leader);
                 return Ok(());
@@ -148,28 +236,45 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     }
 
     /// Spawn parallel vote requests to all cluster members.
+    ///
+    /// During a PreVote round, the requests ask for votes for the term which this node would
+    /// use for its next election.
     #[tracing::instrument(level = "trace", skip(self))]
-    pub(super) fn spawn_parallel_vote_requests(&self) -> mpsc::Receiver<(VoteResponse, NodeId)> {
+    pub(super) fn spawn_parallel_vote_requests(&self) -> mpsc::Receiver<(VoteResponse, NID)> {
         let all_members = self.core.membership.all_nodes();
         let (tx, rx) = mpsc::channel(all_members.len());
         for member in all_members.into_iter().filter(|member| member != &self.core.id) {
-            let rpc = VoteRequest::new(
-                self.core.current_term,
-                self.core.id,
-                self.core.last_log_id.index,
-                self.core.last_log_id.term,
-            );
+            let rpc = if self.is_pre_vote {
+                VoteRequest::new_pre_vote(
+                    self.core.current_term + 1,
+                    self.core.id.clone(),
+                    self.core.last_log_id.index,
+                    self.core.last_log_id.term,
+                )
+            } else {
+                let mut rpc = VoteRequest::new(
+                    self.core.current_term,
+                    self.core.id.clone(),
+                    self.core.last_log_id.index,
+                    self.core.last_log_id.term,
+                );
+                rpc.leadership_transfer = self.is_leadership_transfer;
+                rpc
+            };
             let (network, tx_inner) = (self.core.network.clone(), tx.clone());
+            let span = tracing::debug_span!("requesting vote from peer", target = %member);
             let _ = tokio::spawn(
                 async move {
-                    match network.send_vote(member, rpc).await {
+                    match network.send_vote(member.clone(), rpc).await {
                         Ok(res) => {
                             let _ = tx_inner.send((res, member)).await;
                         }
-                        Err(err) => tracing::error!({error=%err, peer=member}, "error while requesting vote from peer"),
+                        Err(err) => {
+                            tracing::error!({error=%err, peer=%member}, "error while requesting vote from peer")
+                        }
                     }
                 }
-                .instrument(tracing::debug_span!("requesting vote from peer", target = member)),
+                .instrument(span),
             );
         }
         rx
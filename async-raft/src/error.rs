@@ -1,11 +1,15 @@
 //! Error types exposed by this crate.
 
 use std::fmt;
+use std::time::Duration;
 
 use thiserror::Error;
 
+use crate::compression::SnapshotCompression;
+use crate::raft_types::SnapshotId;
 use crate::raft_types::SnapshotSegmentId;
 use crate::AppData;
+use crate::LogId;
 use crate::NodeId;
 
 /// A result type where the error variant is always a `RaftError`.
@@ -21,6 +25,16 @@ pub enum RaftError {
         expect: SnapshotSegmentId,
         got: SnapshotSegmentId,
     },
+    /// A snapshot being installed, or a chunk of it, does not match its checksum, e.g. because it
+    /// was corrupted in transit. The leader sends the chunk again, or the snapshot from its start.
+    #[error("snapshot {id} does not match its checksum at offset {offset:?}, expect: {expect:08x}, got: {got:08x}")]
+    SnapshotChecksumMismatch {
+        id: SnapshotId,
+        /// The offset of the chunk which does not match, or `None` for the whole snapshot.
+        offset: Option<u64>,
+        expect: u32,
+        got: u32,
+    },
     /// An error which has come from the `RaftStorage` layer.
     #[error("{0}")]
     RaftStorage(anyhow::Error),
@@ -40,33 +54,76 @@ impl From<tokio::io::Error> for RaftError {
 
 /// An error related to a client read request.
 #[derive(Debug, Error)]
-pub enum ClientReadError {
+pub enum ClientReadError<NID: NodeId = u64> {
     /// A Raft error.
     #[error("{0}")]
     RaftError(#[from] RaftError),
     /// The client read request must be forwarded to the cluster leader.
     #[error("the client read request must be forwarded to the cluster leader")]
-    ForwardToLeader(Option<NodeId>),
+    ForwardToLeader(Option<NID>),
+}
+
+/// An error related to a stale read request.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum StaleReadError {
+    /// A Raft error.
+    #[error("{0}")]
+    RaftError(#[from] RaftError),
+    /// The cluster leader has not been heard from within the allowed lag.
+    ///
+    /// Holds the time since the leader was last heard from, or `None` if it never was.
+    #[error("the cluster leader has not been heard from within the allowed lag")]
+    LeaderUnreachable(Option<Duration>),
+    /// The state machine is more entries behind the leader's commit index than allowed.
+    #[error("the state machine is {0} entries behind the leader's commit index")]
+    TooFarBehind(u64),
 }
 
 /// An error related to a client write request.
 #[derive(Error)]
-pub enum ClientWriteError<D: AppData> {
+pub enum ClientWriteError<D: AppData, NID: NodeId = u64> {
     /// A Raft error.
     #[error("{0}")]
     RaftError(#[from] RaftError),
     /// The client write request must be forwarded to the cluster leader.
     #[error("the client write request must be forwarded to the cluster leader")]
-    ForwardToLeader(D, Option<NodeId>),
+    ForwardToLeader(D, Option<NID>),
+    /// The queue of requests to the Raft node is full, and the client write request was not submitted.
+    ///
+    /// It is safe to retry the request later, or on another node.
+    #[error("the Raft node is overloaded, the client write request was not submitted")]
+    Overloaded(D),
+    /// The client write request was not appended to the log by its deadline, and never will be.
+    ///
+    /// It is safe to retry the request.
+    #[error("the client write request was not appended to the log by its deadline")]
+    NotAppended(D),
+    /// The client write request was appended to the log, but was not known to be committed by its deadline.
+    ///
+    /// It may still be committed and applied to the state machine later.
+    #[error("the outcome of the client write request is unknown as of its deadline")]
+    OutcomeUnknown,
+    /// The client write request is a retry of a write of its client session which was applied to
+    /// the state machine already, but whose response is no longer known, e.g. as it was applied
+    /// before this node restarted or became leader.
+    ///
+    /// It must not be retried.
+    #[error("the client write request was applied already")]
+    AlreadyApplied,
 }
 
-impl<D: AppData> fmt::Debug for ClientWriteError<D> {
+impl<D: AppData, NID: NodeId> fmt::Debug for ClientWriteError<D, NID> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ClientWriteError::RaftError(err) => f.debug_tuple("RaftError").field(err).finish(),
             ClientWriteError::ForwardToLeader(_req, node_id) => {
                 f.debug_tuple("ForwardToLeader").field(node_id).finish()
             }
+            ClientWriteError::Overloaded(_req) => f.write_str("Overloaded"),
+            ClientWriteError::NotAppended(_req) => f.write_str("NotAppended"),
+            ClientWriteError::OutcomeUnknown => f.write_str("OutcomeUnknown"),
+            ClientWriteError::AlreadyApplied => f.write_str("AlreadyApplied"),
         }
     }
 }
@@ -82,11 +139,40 @@ pub enum ConfigError {
     /// The given value for max_payload_entries is too small, must be > 0.
     #[error("the given value for max_payload_entries is too small, must be > 0")]
     MaxPayloadEntriesTooSmall,
+    /// The given value for max_payload_bytes is too small, must be > 0.
+    #[error("the given value for max_payload_bytes is too small, must be > 0")]
+    MaxPayloadBytesTooSmall,
+    /// The given value for max_inflight is too small, must be > 0.
+    #[error("the given value for max_inflight is too small, must be > 0")]
+    MaxInflightTooSmall,
+    /// The given value for api_queue_capacity is too small, must be > 0.
+    #[error("the given value for api_queue_capacity is too small, must be > 0")]
+    ApiQueueCapacityTooSmall,
+    /// The given value for replication_queue_capacity is too small, must be > 0.
+    #[error("the given value for replication_queue_capacity is too small, must be > 0")]
+    ReplicationQueueCapacityTooSmall,
+
+    /// The given value for max_forward_hops is too small, must be > 0.
+    #[error("the given value for max_forward_hops is too small, must be > 0")]
+    MaxForwardHopsTooSmall,
+
+    /// A `SnapshotPolicy::Any` or `SnapshotPolicy::All` was given no policies to combine.
+    #[error("a combined snapshot policy must be given at least one policy")]
+    EmptySnapshotPolicy,
+
+    /// The given snapshot compression codec is not supported, i.e. its cargo feature is not enabled.
+    #[error("the given snapshot compression codec {0:?} is not supported, its cargo feature must be enabled")]
+    SnapshotCompressionUnsupported(SnapshotCompression),
 
     /// election_timeout_min smaller than heartbeat_interval would cause endless election.
     /// A recommended election_timeout_min value is about 3 times heartbeat_interval.
     #[error("election_timeout_min value must be > heartbeat_interval")]
     ElectionTimeoutLessThanHeartBeatInterval,
+
+    /// The lease read policy's max_clock_drift must be smaller than election_timeout_min, else the lease is always
+    /// expired.
+    #[error("the lease read policy's max_clock_drift must be < election_timeout_min")]
+    InvalidMaxClockDrift,
 }
 
 /// The set of errors which may take place when initializing a pristine Raft node.
@@ -104,7 +190,7 @@ pub enum InitializeError {
 /// The set of errors which may take place when requesting to propose a config change.
 #[derive(Debug, Error)]
 #[non_exhaustive]
-pub enum ChangeConfigError {
+pub enum ChangeConfigError<NID: NodeId = u64> {
     /// An error related to the processing of the config change request.
     ///
     /// Errors of this type will only come about from the internals of applying the config change
@@ -123,29 +209,128 @@ pub enum ChangeConfigError {
     /// The node the config change proposal was sent to was not the leader of the cluster. The ID
     /// of the current leader is returned if known.
     #[error("this node is not the Raft leader")]
-    NodeNotLeader(Option<NodeId>),
+    NodeNotLeader(Option<NID>),
     /// The proposed config changes would make no difference to the current config.
     ///
     /// This takes into account a current joint consensus and the end result of the config.
     #[error("the proposed config change would have no effect, this is a no-op")]
     Noop,
+    /// The given node is not a non-voter of the cluster.
+    #[error("node {0} is not a non-voter")]
+    NotNonVoter(NID),
+    /// The non-voter was removed with `Raft::remove_non_voter` before it finished syncing.
+    #[error("non-voter {0} was removed before it finished syncing")]
+    NonVoterRemoved(NID),
+    /// A single-server change must add or remove exactly one voter.
+    #[error("the proposed config change does not add or remove exactly one voter")]
+    NotSingleChange,
+    /// The membership change was cancelled with `ChangeMembershipHandle::cancel`.
+    #[error("the membership change was cancelled")]
+    Cancelled,
+    /// The membership change is no longer syncing new nodes, and can not be cancelled.
+    #[error("the membership change is no longer syncing and can not be cancelled")]
+    NotSyncing,
+    /// The new nodes were not ready to join the cluster within `Config::non_voter_sync_timeout`,
+    /// and the membership change was aborted. The nodes which were not ready are returned.
+    #[error("non-voters {0:?} were not ready to join the cluster in time")]
+    SyncTimeout(Vec<NID>),
+    /// The queue of requests to the Raft node is full, and the config change was not submitted.
+    ///
+    /// It is safe to retry the config change later.
+    #[error("the Raft node is overloaded, the config change was not submitted")]
+    Overloaded,
+    /// The config change was not appended to the log by its deadline, and never will be.
+    ///
+    /// It is safe to retry the config change.
+    #[error("the config change was not appended to the log by its deadline")]
+    NotAppended,
+    /// The config change was appended to the log, but was not known to be committed by its deadline.
+    ///
+    /// It may still be committed later.
+    #[error("the outcome of the config change is unknown as of its deadline")]
+    OutcomeUnknown,
 }
 
-impl<D: AppData> From<ClientWriteError<D>> for ChangeConfigError {
-    fn from(src: ClientWriteError<D>) -> Self {
+impl<D: AppData, NID: NodeId> From<ClientWriteError<D, NID>> for ChangeConfigError<NID> {
+    fn from(src: ClientWriteError<D, NID>) -> Self {
         match src {
             ClientWriteError::RaftError(err) => Self::RaftError(err),
             ClientWriteError::ForwardToLeader(_, id) => Self::NodeNotLeader(id),
+            ClientWriteError::Overloaded(_) => Self::Overloaded,
+            ClientWriteError::NotAppended(_) => Self::NotAppended,
+            ClientWriteError::OutcomeUnknown => Self::OutcomeUnknown,
+            // Config changes are not submitted with a client session, so they are never taken for retries.
+            ClientWriteError::AlreadyApplied => Self::Noop,
         }
     }
 }
 
+/// The set of errors which may take place when requesting a leadership transfer.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum TransferLeaderError<NID: NodeId = u64> {
+    /// An internal error has taken place.
+    #[error("{0}")]
+    RaftError(#[from] RaftError),
+    /// The node the transfer request was sent to was not the leader of the cluster. The ID of
+    /// the current leader is returned if known.
+    #[error("this node is not the Raft leader")]
+    NodeNotLeader(Option<NID>),
+    /// The given target is not a voting member of the cluster.
+    #[error("node {0} is not a voting member of the cluster")]
+    InvalidTarget(NID),
+    /// A leadership transfer is already in progress.
+    #[error("a leadership transfer is already in progress")]
+    TransferInProgress,
+    /// The target did not take over leadership within an election timeout.
+    ///
+    /// The transfer has been aborted and the leader has resumed accepting client writes.
+    #[error("leadership transfer to node {0} timed out")]
+    Timeout(NID),
+    /// The leader stepped down after asking the target to take over, but another node became leader.
+    #[error("node {leader} became leader rather than the transfer target node {target}")]
+    TargetNotElected { target: NID, leader: NID },
+}
+
+/// The set of errors which may take place when requesting a snapshot with `Raft::trigger_snapshot`.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum SnapshotError {
+    /// An internal error has taken place.
+    #[error("{0}")]
+    RaftError(#[from] RaftError),
+    /// No log entries have been applied to the state machine which a snapshot could cover.
+    #[error("no log entries have been applied which a snapshot could cover")]
+    NothingToSnapshot,
+    /// A snapshot is being installed from the leader, which replaces the state machine.
+    #[error("a snapshot is being installed from the leader")]
+    InstallInProgress,
+    /// Building the snapshot failed, or it was aborted, e.g. by a snapshot installed from the leader.
+    #[error("building the snapshot failed")]
+    Failed,
+}
+
+/// The set of errors which may take place when requesting to purge the log with `Raft::purge_log_upto`.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum PurgeLogError<NID: NodeId = u64> {
+    /// An internal error has taken place.
+    #[error("{0}")]
+    RaftError(#[from] RaftError),
+    /// Only entries before the last one applied to the state machine may be purged.
+    #[error("log entries up to {upto} can not be purged, the last applied entry is {last_applied}")]
+    NotApplied { upto: u64, last_applied: u64 },
+    /// A replication target still needs entries which would be purged, and no snapshot covers them.
+    #[error("node {target} still needs log entries after {matched}, which are not covered by a snapshot")]
+    NeededByReplication { target: NID, matched: LogId },
+}
+
 // A error wrapper of every type of error that will be sent to the caller.
 #[derive(Debug, Error)]
 #[non_exhaustive]
-pub enum ResponseError {
+pub enum ResponseError<NID: NodeId = u64> {
     #[error(transparent)]
-    ChangeConfig(#[from] ChangeConfigError),
+    ChangeConfig(#[from] ChangeConfigError<NID>),
 
     #[error(transparent)]
     Raft(#[from] RaftError),
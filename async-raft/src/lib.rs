@@ -1,5 +1,7 @@
 #![doc = include_str!("../README.md")]
 
+pub mod checksum;
+pub mod compression;
 pub mod config;
 mod core;
 pub mod error;
@@ -11,22 +13,33 @@ mod quorum;
 pub mod raft;
 mod raft_types;
 mod replication;
+pub mod session;
 pub mod storage;
 mod summary;
 
+use std::fmt;
+use std::hash::Hash;
+
 pub use async_trait;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+pub use crate::compression::SnapshotCompression;
 pub use crate::config::Config;
 pub use crate::config::ConfigBuilder;
+pub use crate::config::ReadPolicy;
+pub use crate::config::ReadyToJoinPolicy;
 pub use crate::config::SnapshotPolicy;
 pub use crate::core::State;
 pub use crate::error::ChangeConfigError;
 pub use crate::error::ClientWriteError;
 pub use crate::error::ConfigError;
 pub use crate::error::InitializeError;
+pub use crate::error::PurgeLogError;
 pub use crate::error::RaftError;
+pub use crate::error::SnapshotError;
+pub use crate::error::StaleReadError;
+pub use crate::error::TransferLeaderError;
 pub use crate::metrics::RaftMetrics;
 pub use crate::network::RaftNetwork;
 pub use crate::raft::Raft;
@@ -35,13 +48,26 @@ pub use crate::raft_types::SnapshotId;
 pub use crate::raft_types::SnapshotSegmentId;
 pub use crate::raft_types::Update;
 pub use crate::replication::ReplicationMetrics;
+pub use crate::session::ClientSession;
+pub use crate::session::ClientSessions;
+pub use crate::session::SessionHistory;
 pub use crate::storage::RaftStorage;
 pub use crate::storage::RaftStorageDebug;
 pub use crate::storage::SnapshotMeta;
 pub use crate::summary::MessageSummary;
 
-/// A Raft node's ID.
-pub type NodeId = u64;
+/// A trait defining the type of a Raft node's ID.
+///
+/// It is implemented for every type satisfying its bounds, such as `u64`, `String` or a UUID type, so
+/// that applications may identify nodes the way they already do, instead of translating their IDs
+/// to integers. Every Raft type which refers to nodes is generic over it, defaulting to `u64`.
+pub trait NodeId:
+    Clone + fmt::Debug + fmt::Display + Ord + Hash + Send + Sync + Serialize + DeserializeOwned + 'static
+{
+}
+
+impl<T> NodeId for T where T: Clone + fmt::Debug + fmt::Display + Ord + Hash + Send + Sync + Serialize + DeserializeOwned + 'static
+{}
 
 /// A trait defining application specific data.
 ///
@@ -51,7 +77,21 @@ pub type NodeId = u64;
 /// models as-is to Raft, Raft will present it to the application's `RaftStorage` impl when ready,
 /// and the application may then deal with the data directly in the storage engine without having
 /// to do a preliminary deserialization.
-pub trait AppData: Clone + Send + Sync + Serialize + DeserializeOwned + 'static {}
+pub trait AppData: Clone + Send + Sync + Serialize + DeserializeOwned + 'static {
+    /// The size of this data in bytes.
+    ///
+    /// This is used to size replication payloads, see `Config::max_payload_bytes`, and to count
+    /// the bytes of the log for `SnapshotPolicy::LogBytesSinceLast` and
+    /// `ReadyToJoinPolicy::MaxLagBytes`. It is called once for every entry replicated, and for
+    /// every entry appended while the bytes of the log are counted, so it should be cheap, e.g. add
+    /// up the lengths of the fields rather than serializing the data.
+    ///
+    /// Defaults to 0, so that the data is not counted at all. Applications which use any of the
+    /// above must implement it.
+    fn size(&self) -> u64 {
+        0
+    }
+}
 
 /// A trait defining application specific response data.
 ///
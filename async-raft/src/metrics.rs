@@ -25,9 +25,10 @@ use crate::ReplicationMetrics;
 
 /// A set of metrics describing the current state of a Raft node.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub struct RaftMetrics {
+#[serde(bound = "NID: NodeId")]
+pub struct RaftMetrics<NID: NodeId = u64> {
     /// The ID of the Raft node.
-    pub id: NodeId,
+    pub id: NID,
     /// The state of the Raft node.
     pub state: State,
     /// The current term of the Raft node.
@@ -37,28 +38,59 @@ pub struct RaftMetrics {
     /// The last log index to be applied to this Raft node's state machine.
     pub last_applied: u64,
     /// The current cluster leader.
-    pub current_leader: Option<NodeId>,
+    pub current_leader: Option<NID>,
     /// The current membership config of the cluster.
-    pub membership_config: MembershipConfig,
+    pub membership_config: MembershipConfig<NID>,
 
     /// The id of the last log included in snapshot.
     /// If there is no snapshot, it is (0,0).
     pub snapshot: LogId,
 
+    /// The number of requests waiting in the queue of requests to the Raft node, as of this update.
+    ///
+    /// This is sampled whenever the metrics are updated for another reason, so it may be out of
+    /// date; `Raft::api_queue_len` returns the current value. The capacity of the queue is
+    /// `Config::api_queue_capacity`.
+    pub api_queue_len: u64,
+
     /// The metrics about the leader. It is Some() only when this node is leader.
-    pub leader_metrics: Option<LeaderMetrics>,
+    pub leader_metrics: Option<LeaderMetrics<NID>>,
 }
 
 /// The metrics about the leader. It is Some() only when this node is leader.
-#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
-pub struct LeaderMetrics {
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "NID: NodeId")]
+pub struct LeaderMetrics<NID: NodeId = u64> {
     /// Replication metrics of all known replication target: voters and non-voters
-    pub replication: HashMap<NodeId, ReplicationMetrics>,
+    pub replication: HashMap<NID, ReplicationMetrics>,
+    /// The non-voters which the leader is replicating to, but which are not cluster members yet.
+    pub non_voters: BTreeSet<NID>,
+    /// The number of events waiting in the queue to each replication stream, as of this update.
+    ///
+    /// Like `RaftMetrics::api_queue_len`, this is sampled whenever the metrics are updated. The
+    /// capacity of each queue is `Config::replication_queue_capacity`.
+    pub replication_queue_lens: HashMap<NID, u64>,
+    /// The number of events from the replication streams waiting to be handled by the leader, as of this update.
+    ///
+    /// This is sampled whenever the metrics are updated. The capacity of the queue is
+    /// `Config::replication_queue_capacity`.
+    pub replica_event_queue_len: u64,
 }
 
-impl RaftMetrics {
-    pub(crate) fn new_initial(id: NodeId) -> Self {
-        let membership_config = MembershipConfig::new_initial(id);
+impl<NID: NodeId> Default for LeaderMetrics<NID> {
+    fn default() -> Self {
+        Self {
+            replication: HashMap::new(),
+            non_voters: BTreeSet::new(),
+            replication_queue_lens: HashMap::new(),
+            replica_event_queue_len: 0,
+        }
+    }
+}
+
+impl<NID: NodeId> RaftMetrics<NID> {
+    pub(crate) fn new_initial(id: NID) -> Self {
+        let membership_config = MembershipConfig::new_initial(id.clone());
         Self {
             id,
             state: State::Follower,
@@ -68,6 +100,7 @@ impl RaftMetrics {
             current_leader: None,
             membership_config,
             snapshot: LogId { term: 0, index: 0 },
+            api_queue_len: 0,
             leader_metrics: None,
         }
     }
@@ -84,16 +117,16 @@ pub enum WaitError {
 }
 
 /// Wait is a wrapper of RaftMetrics channel that impls several utils to wait for metrics to satisfy some condition.
-pub struct Wait {
+pub struct Wait<NID: NodeId = u64> {
     pub timeout: Duration,
-    pub rx: watch::Receiver<RaftMetrics>,
+    pub rx: watch::Receiver<RaftMetrics<NID>>,
 }
 
-impl Wait {
+impl<NID: NodeId> Wait<NID> {
     /// Wait for metrics to satisfy some condition or timeout.
     #[tracing::instrument(level = "debug", skip(self, func), fields(msg=msg.to_string().as_str()))]
-    pub async fn metrics<T>(&self, func: T, msg: impl ToString) -> Result<RaftMetrics, WaitError>
-    where T: Fn(&RaftMetrics) -> bool + Send {
+    pub async fn metrics<T>(&self, func: T, msg: impl ToString) -> Result<RaftMetrics<NID>, WaitError>
+    where T: Fn(&RaftMetrics<NID>) -> bool + Send {
         let mut rx = self.rx.clone();
         loop {
             let latest = rx.borrow().clone();
@@ -135,9 +168,9 @@ impl Wait {
 
     /// Wait for `current_leader` to become `Some(leader_id)` until timeout.
     #[tracing::instrument(level = "debug", skip(self), fields(msg=msg.to_string().as_str()))]
-    pub async fn current_leader(&self, leader_id: NodeId, msg: impl ToString) -> Result<RaftMetrics, WaitError> {
+    pub async fn current_leader(&self, leader_id: NID, msg: impl ToString) -> Result<RaftMetrics<NID>, WaitError> {
         self.metrics(
-            |x| x.current_leader == Some(leader_id),
+            |x| x.current_leader.as_ref() == Some(&leader_id),
             &format!("{} .current_leader -> {}", msg.to_string(), leader_id),
         )
         .await
@@ -145,7 +178,7 @@ impl Wait {
 
     /// Wait until applied upto `want_log`(inclusive) logs or timeout.
     #[tracing::instrument(level = "debug", skip(self), fields(msg=msg.to_string().as_str()))]
-    pub async fn log(&self, want_log: u64, msg: impl ToString) -> Result<RaftMetrics, WaitError> {
+    pub async fn log(&self, want_log: u64, msg: impl ToString) -> Result<RaftMetrics<NID>, WaitError> {
         self.metrics(
             |x| x.last_log_index == want_log,
             &format!("{} .last_log_index -> {}", msg.to_string(), want_log),
@@ -161,7 +194,7 @@ impl Wait {
 
     /// Wait for `state` to become `want_state` or timeout.
     #[tracing::instrument(level = "debug", skip(self), fields(msg=msg.to_string().as_str()))]
-    pub async fn state(&self, want_state: State, msg: impl ToString) -> Result<RaftMetrics, WaitError> {
+    pub async fn state(&self, want_state: State, msg: impl ToString) -> Result<RaftMetrics<NID>, WaitError> {
         self.metrics(
             |x| x.state == want_state,
             &format!("{} .state -> {:?}", msg.to_string(), want_state),
@@ -171,7 +204,11 @@ impl Wait {
 
     /// Wait for `membership_config.members` to become expected node set or timeout.
     #[tracing::instrument(level = "debug", skip(self), fields(msg=msg.to_string().as_str()))]
-    pub async fn members(&self, want_members: BTreeSet<NodeId>, msg: impl ToString) -> Result<RaftMetrics, WaitError> {
+    pub async fn members(
+        &self,
+        want_members: BTreeSet<NID>,
+        msg: impl ToString,
+    ) -> Result<RaftMetrics<NID>, WaitError> {
         self.metrics(
             |x| x.membership_config.members == want_members,
             &format!("{} .membership_config.members -> {:?}", msg.to_string(), want_members),
@@ -183,9 +220,9 @@ impl Wait {
     #[tracing::instrument(level = "debug", skip(self), fields(msg=msg.to_string().as_str()))]
     pub async fn next_members(
         &self,
-        want_members: Option<BTreeSet<NodeId>>,
+        want_members: Option<BTreeSet<NID>>,
         msg: impl ToString,
-    ) -> Result<RaftMetrics, WaitError> {
+    ) -> Result<RaftMetrics<NID>, WaitError> {
         self.metrics(
             |x| x.membership_config.members_after_consensus == want_members,
             &format!(
@@ -199,7 +236,7 @@ impl Wait {
 
     /// Wait for `snapshot` to become `want_snapshot` or timeout.
     #[tracing::instrument(level = "debug", skip(self), fields(msg=msg.to_string().as_str()))]
-    pub async fn snapshot(&self, want_snapshot: LogId, msg: impl ToString) -> Result<RaftMetrics, WaitError> {
+    pub async fn snapshot(&self, want_snapshot: LogId, msg: impl ToString) -> Result<RaftMetrics<NID>, WaitError> {
         self.metrics(
             |x| x.snapshot == want_snapshot,
             &format!("{} .snapshot -> {:?}", msg.to_string(), want_snapshot),
@@ -1,5 +1,6 @@
 use std::time::Duration;
 
+use maplit::btreemap;
 use maplit::btreeset;
 use tokio::sync::watch;
 use tokio::time::sleep;
@@ -178,8 +179,11 @@ fn init_wait_test() -> (RaftMetrics, Wait, watch::Sender<RaftMetrics>) {
         membership_config: MembershipConfig {
             members: Default::default(),
             members_after_consensus: None,
+            learners: btreeset! {},
+            nodes: btreemap! {},
         },
         snapshot: LogId { term: 0, index: 0 },
+        api_queue_len: 0,
         leader_metrics: None,
     };
     let (tx, rx) = watch::channel(init.clone());
@@ -1,15 +1,26 @@
 //! The Raft network interface.
 
+use anyhow::anyhow;
 use anyhow::Result;
 use async_trait::async_trait;
 
+use crate::error::ClientWriteError;
+use crate::error::ResponseError;
 use crate::raft::AppendEntriesRequest;
 use crate::raft::AppendEntriesResponse;
+use crate::raft::ClientWriteResponse;
+use crate::raft::ForwardChangeMembershipRequest;
+use crate::raft::ForwardClientWriteRequest;
 use crate::raft::InstallSnapshotRequest;
 use crate::raft::InstallSnapshotResponse;
+use crate::raft::ReadIndexRequest;
+use crate::raft::ReadIndexResponse;
+use crate::raft::TimeoutNowRequest;
+use crate::raft::TimeoutNowResponse;
 use crate::raft::VoteRequest;
 use crate::raft::VoteResponse;
 use crate::AppData;
+use crate::AppDataResponse;
 use crate::NodeId;
 
 /// A trait defining the interface for a Raft network between cluster members.
@@ -17,19 +28,62 @@ use crate::NodeId;
 /// See the [network chapter of the guide](https://async-raft.github.io/async-raft/network.html)
 /// for details and discussion on this trait and how to implement it.
 #[async_trait]
-pub trait RaftNetwork<D>: Send + Sync + 'static
-where D: AppData
+pub trait RaftNetwork<D, R, NID = u64>: Send + Sync + 'static
+where
+    D: AppData,
+    R: AppDataResponse,
+    NID: NodeId,
 {
     /// Send an AppendEntries RPC to the target Raft node (§5).
-    async fn send_append_entries(&self, target: NodeId, rpc: AppendEntriesRequest<D>) -> Result<AppendEntriesResponse>;
+    async fn send_append_entries(
+        &self,
+        target: NID,
+        rpc: AppendEntriesRequest<D, NID>,
+    ) -> Result<AppendEntriesResponse>;
 
     /// Send an InstallSnapshot RPC to the target Raft node (§7).
     async fn send_install_snapshot(
         &self,
-        target: NodeId,
-        rpc: InstallSnapshotRequest,
+        target: NID,
+        rpc: InstallSnapshotRequest<NID>,
     ) -> Result<InstallSnapshotResponse>;
 
     /// Send a RequestVote RPC to the target Raft node (§5).
-    async fn send_vote(&self, target: NodeId, rpc: VoteRequest) -> Result<VoteResponse>;
+    async fn send_vote(&self, target: NID, rpc: VoteRequest<NID>) -> Result<VoteResponse>;
+
+    /// Send a TimeoutNow RPC to the target Raft node, as part of a leadership transfer.
+    async fn send_timeout_now(&self, target: NID, rpc: TimeoutNowRequest<NID>) -> Result<TimeoutNowResponse>;
+
+    /// Send a ReadIndex RPC to the target Raft node, which is expected to be the cluster leader.
+    async fn send_read_index(&self, target: NID, rpc: ReadIndexRequest<NID>) -> Result<ReadIndexResponse>;
+
+    /// Forward a client write to the target Raft node, which is expected to be the cluster leader,
+    /// and return its response.
+    ///
+    /// This is only used when `Config::forward_to_leader` is enabled. The target node is expected
+    /// to submit the request with `Raft::handle_forward_client_write`, whose result, including a
+    /// `ClientWriteError`, is to be returned as is. The outer error is for failing to get it.
+    async fn forward_client_write(
+        &self,
+        target: NID,
+        rpc: ForwardClientWriteRequest<D, NID>,
+    ) -> Result<Result<ClientWriteResponse<R>, ClientWriteError<D, NID>>> {
+        let _ = rpc;
+        Err(anyhow!("forwarding client writes to {} is not supported", target))
+    }
+
+    /// Forward a membership change to the target Raft node, which is expected to be the cluster
+    /// leader.
+    ///
+    /// This is only used when `Config::forward_to_leader` is enabled. The target node is expected
+    /// to submit the request with `Raft::handle_forward_change_membership`, whose result, including
+    /// a `ResponseError`, is to be returned as is. The outer error is for failing to get it.
+    async fn forward_change_membership(
+        &self,
+        target: NID,
+        rpc: ForwardChangeMembershipRequest<NID>,
+    ) -> Result<Result<(), ResponseError<NID>>> {
+        let _ = rpc;
+        Err(anyhow!("forwarding membership changes to {} is not supported", target))
+    }
 }
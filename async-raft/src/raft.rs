@@ -1,29 +1,43 @@
 //! Public Raft interface and data types.
 
+use std::collections::BTreeMap;
 use std::collections::BTreeSet;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
 
 use serde::Deserialize;
 use serde::Serialize;
 use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::SendTimeoutError;
+use tokio::sync::mpsc::error::TrySendError;
 use tokio::sync::oneshot;
 use tokio::sync::watch;
 use tokio::sync::Mutex;
 use tokio::task::JoinHandle;
+use tokio::time::timeout_at;
+use tokio::time::Instant;
 use tracing::Span;
 
+use crate::compression::SnapshotCompression;
 use crate::config::Config;
 use crate::core::RaftCore;
 use crate::error::ChangeConfigError;
 use crate::error::ClientReadError;
 use crate::error::ClientWriteError;
 use crate::error::InitializeError;
+use crate::error::PurgeLogError;
 use crate::error::RaftError;
 use crate::error::RaftResult;
 use crate::error::ResponseError;
+use crate::error::SnapshotError;
+use crate::error::StaleReadError;
+use crate::error::TransferLeaderError;
 use crate::metrics::RaftMetrics;
 use crate::metrics::Wait;
+use crate::session::ClientSession;
 use crate::AppData;
 use crate::AppDataResponse;
 use crate::LogId;
@@ -33,12 +47,27 @@ use crate::RaftNetwork;
 use crate::RaftStorage;
 use crate::SnapshotMeta;
 
-struct RaftInner<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
-    tx_api: mpsc::UnboundedSender<(RaftMsg<D, R>, Span)>,
-    rx_metrics: watch::Receiver<RaftMetrics>,
+struct RaftInner<D, R, N, S, NID>
+where
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, R, NID>,
+    S: RaftStorage<D, R, NID>,
+    NID: NodeId,
+{
+    tx_api: mpsc::Sender<(RaftMsg<D, R, NID>, Span)>,
+    /// The capacity of `tx_api`, see `Config::api_queue_capacity`.
+    api_queue_capacity: u64,
+    /// How long a client write may wait for room in the API queue, see `Config::client_write_queue_timeout`.
+    client_write_queue_timeout: Option<Duration>,
+    /// The hop limit of requests forwarded to the leader, if forwarding is enabled, see `Config::forward_to_leader`.
+    max_forward_hops: Option<u64>,
+    rx_metrics: watch::Receiver<RaftMetrics<NID>>,
     raft_handle: Mutex<Option<JoinHandle<RaftResult<()>>>>,
     tx_shutdown: Mutex<Option<oneshot::Sender<()>>>,
-    marker_n: std::marker::PhantomData<N>,
+    network: Arc<N>,
+    /// The ID of the next membership change started with `Raft::start_change_membership`.
+    next_change_id: AtomicU64,
     marker_s: std::marker::PhantomData<S>,
 }
 
@@ -63,11 +92,28 @@ struct RaftInner<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStora
 /// is shutting down (potentially for data safety reasons due to a storage error), and the `shutdown`
 /// method should be called on this type to await the shutdown of the node. If the parent
 /// application needs to shutdown the Raft node for any reason, calling `shutdown` will do the trick.
-pub struct Raft<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
-    inner: Arc<RaftInner<D, R, N, S>>,
+///
+/// ### node IDs
+/// Nodes are identified by the `NID` type, which is `u64` unless another `NodeId` type is given.
+pub struct Raft<D, R, N, S, NID = u64>
+where
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, R, NID>,
+    S: RaftStorage<D, R, NID>,
+    NID: NodeId,
+{
+    inner: Arc<RaftInner<D, R, N, S, NID>>,
 }
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Raft<D, R, N, S> {
+impl<D, R, N, S, NID> Raft<D, R, N, S, NID>
+where
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, R, NID>,
+    S: RaftStorage<D, R, NID>,
+    NID: NodeId,
+{
     /// Create and spawn a new Raft task.
     ///
     /// ### `id`
@@ -87,17 +133,37 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// An implementation of the `RaftStorage` trait which will be used by Raft for data storage.
     /// See the docs on the `RaftStorage` trait for more details.
     #[tracing::instrument(level="trace", skip(config, network, storage), fields(cluster=%config.cluster_name))]
-    pub fn new(id: NodeId, config: Arc<Config>, network: Arc<N>, storage: Arc<S>) -> Self {
-        let (tx_api, rx_api) = mpsc::unbounded_channel();
-        let (tx_metrics, rx_metrics) = watch::channel(RaftMetrics::new_initial(id));
+    pub fn new(id: NID, config: Arc<Config>, network: Arc<N>, storage: Arc<S>) -> Self {
+        let (tx_api, rx_api) = mpsc::channel(config.api_queue_capacity as usize);
+        let (tx_metrics, rx_metrics) = watch::channel(RaftMetrics::new_initial(id.clone()));
         let (tx_shutdown, rx_shutdown) = oneshot::channel();
-        let raft_handle = RaftCore::spawn(id, config, network, storage, rx_api, tx_metrics, rx_shutdown);
+        let api_queue_capacity = config.api_queue_capacity;
+        let client_write_queue_timeout = config.client_write_queue_timeout.map(Duration::from_millis);
+        let max_forward_hops = if config.forward_to_leader {
+            Some(config.max_forward_hops)
+        } else {
+            None
+        };
+        let raft_handle = RaftCore::spawn(
+            id,
+            config,
+            network.clone(),
+            storage,
+            rx_api,
+            tx_api.clone(),
+            tx_metrics,
+            rx_shutdown,
+        );
         let inner = RaftInner {
             tx_api,
+            api_queue_capacity,
+            client_write_queue_timeout,
+            max_forward_hops,
             rx_metrics,
             raft_handle: Mutex::new(Some(raft_handle)),
             tx_shutdown: Mutex::new(Some(tx_shutdown)),
-            marker_n: std::marker::PhantomData,
+            network,
+            next_change_id: AtomicU64::new(0),
             marker_s: std::marker::PhantomData,
         };
         Self { inner: Arc::new(inner) }
@@ -108,7 +174,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// These RPCs are sent by the cluster leader to replicate log entries (§5.3), and are also
     /// used as heartbeats (§5.2).
     #[tracing::instrument(level = "debug", skip(self, rpc),fields(rpc=%rpc.summary()))]
-    pub async fn append_entries(&self, rpc: AppendEntriesRequest<D>) -> Result<AppendEntriesResponse, RaftError> {
+    pub async fn append_entries(&self, rpc: AppendEntriesRequest<D, NID>) -> Result<AppendEntriesResponse, RaftError> {
         let span = tracing::debug_span!("CH");
 
         let (tx, rx) = oneshot::channel();
@@ -116,6 +182,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         self.inner
             .tx_api
             .send((RaftMsg::AppendEntries { rpc, tx }, span))
+            .await
             .map_err(|_| RaftError::ShuttingDown)?;
 
         rx.await.map_err(|_| RaftError::ShuttingDown).and_then(|res| res)
@@ -125,13 +192,14 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     ///
     /// These RPCs are sent by cluster peers which are in candidate state attempting to gather votes (§5.2).
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn vote(&self, rpc: VoteRequest) -> Result<VoteResponse, RaftError> {
+    pub async fn vote(&self, rpc: VoteRequest<NID>) -> Result<VoteResponse, RaftError> {
         let span = tracing::debug_span!("CH");
 
         let (tx, rx) = oneshot::channel();
         self.inner
             .tx_api
             .send((RaftMsg::RequestVote { rpc, tx }, span))
+            .await
             .map_err(|_| RaftError::ShuttingDown)?;
 
         rx.await.map_err(|_| RaftError::ShuttingDown).and_then(|res| res)
@@ -142,7 +210,10 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// These RPCs are sent by the cluster leader in order to bring a new node or a slow node up-to-speed
     /// with the leader (§7).
     #[tracing::instrument(level = "debug", skip(self, rpc), fields(snapshot_id=%rpc.meta.last_log_id))]
-    pub async fn install_snapshot(&self, rpc: InstallSnapshotRequest) -> Result<InstallSnapshotResponse, RaftError> {
+    pub async fn install_snapshot(
+        &self,
+        rpc: InstallSnapshotRequest<NID>,
+    ) -> Result<InstallSnapshotResponse, RaftError> {
         let span = tracing::debug_span!("CH");
 
         let (tx, rx) = oneshot::channel();
@@ -150,27 +221,74 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         self.inner
             .tx_api
             .send((RaftMsg::InstallSnapshot { rpc, tx }, span))
+            .await
             .map_err(|_| RaftError::ShuttingDown)?;
 
         rx.await.map_err(|_| RaftError::ShuttingDown).and_then(|res| res)
     }
 
+    /// Submit a TimeoutNow RPC to this Raft node.
+    ///
+    /// These RPCs are sent by the cluster leader to the target of a leadership transfer, once the
+    /// target's log is up-to-date with the leader's. The target will start an election at once.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn timeout_now(&self, rpc: TimeoutNowRequest<NID>) -> Result<TimeoutNowResponse, RaftError> {
+        let span = tracing::debug_span!("CH");
+
+        let (tx, rx) = oneshot::channel();
+        self.inner
+            .tx_api
+            .send((RaftMsg::TimeoutNow { rpc, tx }, span))
+            .await
+            .map_err(|_| RaftError::ShuttingDown)?;
+
+        rx.await.map_err(|_| RaftError::ShuttingDown).and_then(|res| res)
+    }
+
+    /// Submit a ReadIndex RPC to this Raft node.
+    ///
+    /// These RPCs are sent by followers and non-voters to the cluster leader, in order to serve
+    /// `read_index` calls. Only the leader answers them; other nodes respond with
+    /// `ClientReadError::ForwardToLeader`, unless `Config::forward_to_leader` is set, in which case
+    /// they forward the RPC to the leader they know of.
+    ///
+    /// The leader also refuses an RPC sent to another node as the leader, or in a term later than
+    /// its own, as it may have been deposed.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn handle_read_index(
+        &self,
+        rpc: ReadIndexRequest<NID>,
+    ) -> Result<ReadIndexResponse, ClientReadError<NID>> {
+        let log_id = match self.local_read_index(Some(rpc.clone())).await {
+            Err(ClientReadError::ForwardToLeader(Some(leader))) => match self.forward_path(rpc.path, &leader) {
+                Some(path) => self.send_read_index(leader, rpc.node_id, path).await?,
+                None => return Err(ClientReadError::ForwardToLeader(Some(leader))),
+            },
+            res => res?,
+        };
+        Ok(ReadIndexResponse { log_id })
+    }
+
     /// Get the ID of the current leader from this Raft node.
     ///
     /// This method is based on the Raft metrics system which does a good job at staying
     /// up-to-date; however, the `client_read` method must still be used to guard against stale
     /// reads. This method is perfect for making decisions on where to route client requests.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn current_leader(&self) -> Option<NodeId> {
-        self.metrics().borrow().current_leader
+    pub async fn current_leader(&self) -> Option<NID> {
+        self.metrics().borrow().current_leader.clone()
     }
 
     /// Check to ensure this node is still the cluster leader, in order to guard against stale reads (§8).
     ///
     /// The actual read operation itself is up to the application, this method just ensures that
     /// the read will not be stale.
+    ///
+    /// If `Config::forward_to_leader` is set, this may be called on any node: a node which is not
+    /// the leader gets the read index from the leader, like `read_index`, and waits for its state
+    /// machine to catch up with it, after which the read may be served from it.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn client_read(&self) -> Result<(), ClientReadError> {
+    pub async fn client_read(&self) -> Result<(), ClientReadError<NID>> {
         let span = tracing::debug_span!("CH");
 
         let (tx, rx) = oneshot::channel();
@@ -178,6 +296,112 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         self.inner
             .tx_api
             .send((RaftMsg::ClientReadRequest { tx }, span))
+            .await
+            .map_err(|_| ClientReadError::RaftError(RaftError::ShuttingDown))?;
+
+        let res = rx.await.map_err(|_| ClientReadError::RaftError(RaftError::ShuttingDown)).and_then(|res| res);
+        match res {
+            Err(ClientReadError::ForwardToLeader(Some(leader))) => match self.forward_path(Vec::new(), &leader) {
+                Some(path) => {
+                    let id = path[0].clone();
+                    let log_id = self.send_read_index(leader, id, path).await?;
+                    self.wait_for_applied(log_id).await.map(|_| ())
+                }
+                None => Err(ClientReadError::ForwardToLeader(Some(leader))),
+            },
+            res => res,
+        }
+    }
+
+    /// Get the log position at which a linearizable read may be served from this node (§6.4 of the
+    /// Raft dissertation).
+    ///
+    /// On the leader, this confirms leadership in the same way as `client_read`, and returns the
+    /// leader's commit index. On followers and non-voters, the read index is requested from the
+    /// current leader via `RaftNetwork::send_read_index`. In both cases, this method then waits for
+    /// the local state machine to apply the log up to the returned index, after which the application
+    /// may serve the read from its local state machine.
+    ///
+    /// A newly elected leader only answers once it has committed an entry from its own term.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn read_index(&self) -> Result<LogId, ClientReadError<NID>> {
+        let log_id = match self.local_read_index(None).await {
+            Err(ClientReadError::ForwardToLeader(Some(leader))) => {
+                let id = self.inner.rx_metrics.borrow().id.clone();
+                self.send_read_index(leader, id.clone(), vec![id]).await?
+            }
+            res => res?,
+        };
+        self.wait_for_applied(log_id).await
+    }
+
+    /// Get the read index from the given leader with a ReadIndex RPC, on behalf of the given node.
+    async fn send_read_index(&self, leader: NID, node_id: NID, path: Vec<NID>) -> Result<LogId, ClientReadError<NID>> {
+        let term = self.inner.rx_metrics.borrow().current_term;
+        let rpc = ReadIndexRequest {
+            term,
+            leader_id: leader.clone(),
+            node_id,
+            path,
+        };
+        let res = self
+            .inner
+            .network
+            .send_read_index(leader, rpc)
+            .await
+            .map_err(|err| ClientReadError::RaftError(RaftError::RaftNetwork(err)))?;
+        Ok(res.log_id)
+    }
+
+    /// Wait for the local state machine to catch up with the given read index.
+    async fn wait_for_applied(&self, log_id: LogId) -> Result<LogId, ClientReadError<NID>> {
+        let mut rx = self.metrics();
+        loop {
+            if rx.borrow().last_applied >= log_id.index {
+                return Ok(log_id);
+            }
+            rx.changed().await.map_err(|_| ClientReadError::RaftError(RaftError::ShuttingDown))?;
+        }
+    }
+
+    /// Check that this node's state machine is within `max_lag` of the cluster leader, in order to
+    /// serve a read of possibly stale data from it without involving the leader.
+    ///
+    /// Unlike `client_read` and `read_index`, this may be called on any node, and sends no RPCs.
+    /// On followers and non-voters, the lag is measured against the last AppendEntries RPC received
+    /// from the leader: either the time since it arrived, or the number of entries the leader had
+    /// committed by then which are not applied locally yet. As the leader may have committed more
+    /// entries since, an entry-based lag also requires that RPC to have arrived within
+    /// `Config::election_timeout_max`. On the leader, the time is measured since a quorum of the
+    /// cluster last acknowledged it.
+    ///
+    /// Returns the ID of the last log applied to the state machine. The read will reflect at least
+    /// the log up to it.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn stale_read(&self, max_lag: MaxLag) -> Result<LogId, StaleReadError> {
+        let span = tracing::debug_span!("CH");
+
+        let (tx, rx) = oneshot::channel();
+
+        self.inner
+            .tx_api
+            .send((RaftMsg::StaleRead { max_lag, tx }, span))
+            .await
+            .map_err(|_| StaleReadError::RaftError(RaftError::ShuttingDown))?;
+
+        rx.await.map_err(|_| StaleReadError::RaftError(RaftError::ShuttingDown)).and_then(|res| res)
+    }
+
+    /// Get the read index from the local Raft node, which is only known if it is the leader.
+    async fn local_read_index(&self, rpc: Option<ReadIndexRequest<NID>>) -> Result<LogId, ClientReadError<NID>> {
+        let span = tracing::debug_span!("CH");
+
+        let (tx, rx) = oneshot::channel();
+
+        self.inner
+            .tx_api
+            .send((RaftMsg::ReadIndex { rpc, tx }, span))
+            .await
             .map_err(|_| ClientReadError::RaftError(RaftError::ShuttingDown))?;
 
         rx.await.map_err(|_| ClientReadError::RaftError(RaftError::ShuttingDown)).and_then(|res| res)
@@ -200,20 +424,101 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     ///
     /// These are application specific requirements, and must be implemented by the application which is
     /// being built on top of Raft.
+    ///
+    /// If the queue of requests to this node stays full for `Config::client_write_queue_timeout`,
+    /// this fails with `ClientWriteError::Overloaded`, and the request is not submitted.
+    ///
+    /// If this node is not the leader, this fails with `ClientWriteError::ForwardToLeader`, unless
+    /// `Config::forward_to_leader` is set, in which case the request is forwarded to the leader with
+    /// `RaftNetwork::forward_client_write`, and the leader's response is returned. If that RPC fails,
+    /// the request may or may not have been applied.
     #[tracing::instrument(level = "debug", skip(self, rpc))]
     pub async fn client_write(
         &self,
-        rpc: ClientWriteRequest<D>,
-    ) -> Result<ClientWriteResponse<R>, ClientWriteError<D>> {
+        rpc: ClientWriteRequest<D, NID>,
+    ) -> Result<ClientWriteResponse<R>, ClientWriteError<D, NID>> {
+        self.client_write_via(rpc, Vec::new()).await
+    }
+
+    /// Submit a client write forwarded by another Raft node with `RaftNetwork::forward_client_write`.
+    ///
+    /// This is handled like a `client_write` call on this node, except that it is only forwarded
+    /// further if the hop limit allows it, and not to a node it was forwarded by already.
+    #[tracing::instrument(level = "debug", skip(self, rpc), fields(path=?rpc.path))]
+    pub async fn handle_forward_client_write(
+        &self,
+        rpc: ForwardClientWriteRequest<D, NID>,
+    ) -> Result<ClientWriteResponse<R>, ClientWriteError<D, NID>> {
+        self.client_write_via(rpc.rpc, rpc.path).await
+    }
+
+    /// Submit a client write to this node, and forward it to the leader if this node is not the
+    /// leader and forwarding is enabled. `path` is the list of nodes which forwarded it so far.
+    async fn client_write_via(
+        &self,
+        rpc: ClientWriteRequest<D, NID>,
+        path: Vec<NID>,
+    ) -> Result<ClientWriteResponse<R>, ClientWriteError<D, NID>> {
+        // The session is not handed back along with the data, so keep it for forwarding.
+        let session = match &rpc.entry {
+            EntryPayload::Normal(entry) => entry.session.clone(),
+            _ => None,
+        };
+
+        match self.local_client_write(rpc).await {
+            Err(ClientWriteError::ForwardToLeader(data, Some(leader))) => {
+                self.forward_client_write(data, session, leader, path).await
+            }
+            res => res,
+        }
+    }
+
+    /// Forward a client write which this node turned away to the given leader, if forwarding is
+    /// enabled and the hop limit allows it, else fail with `ForwardToLeader`.
+    async fn forward_client_write(
+        &self,
+        data: D,
+        session: Option<ClientSession>,
+        leader: NID,
+        path: Vec<NID>,
+    ) -> Result<ClientWriteResponse<R>, ClientWriteError<D, NID>> {
+        let path = match self.forward_path(path, &leader) {
+            Some(path) => path,
+            None => return Err(ClientWriteError::ForwardToLeader(data, Some(leader))),
+        };
+
+        let rpc = ForwardClientWriteRequest {
+            path,
+            rpc: ClientWriteRequest::new_base(EntryPayload::Normal(EntryNormal { data, session })),
+        };
+        self.inner
+            .network
+            .forward_client_write(leader, rpc)
+            .await
+            .unwrap_or_else(|err| Err(ClientWriteError::RaftError(RaftError::RaftNetwork(err))))
+    }
+
+    /// Submit a client write to this node.
+    async fn local_client_write(
+        &self,
+        rpc: ClientWriteRequest<D, NID>,
+    ) -> Result<ClientWriteResponse<R>, ClientWriteError<D, NID>> {
         let span = tracing::debug_span!("CH");
 
         let (tx, rx) = oneshot::channel();
 
-        let res = self.inner.tx_api.send((RaftMsg::ClientWriteRequest { rpc, tx }, span));
+        let res = self.send_client_write((RaftMsg::ClientWriteRequest { rpc, tx }, span)).await;
 
-        if let Err(e) = res {
-            tracing::error!("error when Raft::client_write: send to tx_api: {}", e);
-            return Err(ClientWriteError::RaftError(RaftError::ShuttingDown));
+        match res {
+            Ok(()) => {}
+            Err(SendTimeoutError::Timeout((RaftMsg::ClientWriteRequest { rpc, .. }, _))) => {
+                tracing::warn!("Raft::client_write: the request queue is full");
+                return Err(rpc.into_error(ClientWriteError::Overloaded));
+            }
+            Err(_) => {
+                tracing::error!("error when Raft::client_write: send to tx_api: channel closed");
+                return Err(ClientWriteError::RaftError(RaftError::ShuttingDown));
+            }
         }
 
         let res = rx.await;
@@ -231,6 +536,152 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         }
     }
 
+    /// Submit a mutating client request to Raft like `client_write`, but give up at the given deadline.
+    ///
+    /// If the request has not been appended to the leader's log by the deadline, it never will be,
+    /// and this fails with `ClientWriteError::NotAppended`, handing back its data. It is then safe
+    /// to retry it. If it has been appended, but is not known to be committed by the deadline, e.g.
+    /// because the cluster lost its quorum, this fails with `ClientWriteError::OutcomeUnknown`: the
+    /// request may still be committed and applied later.
+    ///
+    /// Dropping the returned future abandons the request in the same way.
+    ///
+    /// The request is given by its data, and the client session it is submitted with, if any, as
+    /// with `ClientWriteRequest::new` and `ClientWriteRequest::new_with_session`.
+    ///
+    /// Unlike `client_write`, this is never forwarded to the leader, as the deadline only holds on
+    /// this node: if this node is not the leader, it fails with `ClientWriteError::ForwardToLeader`.
+    #[tracing::instrument(level = "debug", skip(self, data))]
+    pub async fn client_write_with_deadline(
+        &self,
+        data: D,
+        session: Option<ClientSession>,
+        deadline: Instant,
+    ) -> Result<ClientWriteResponse<R>, ClientWriteError<D, NID>> {
+        let span = tracing::debug_span!("CH");
+
+        // Keep a copy of the data, to hand it back if the request is abandoned before the leader gets to it.
+        let mut rpc = match session {
+            Some(session) => ClientWriteRequest::new_with_session(data.clone(), session),
+            None => ClientWriteRequest::new(data.clone()),
+        };
+        let append_deadline = Arc::new(AppendDeadline::new(deadline));
+        rpc.deadline = Some(append_deadline.clone());
+
+        let (tx, rx) = oneshot::channel();
+
+        let res = match timeout_at(
+            deadline,
+            self.send_client_write((RaftMsg::ClientWriteRequest { rpc, tx }, span)),
+        )
+        .await
+        {
+            Ok(res) => res,
+            Err(_) => return Err(ClientWriteError::NotAppended(data)),
+        };
+        match res {
+            Ok(()) => {}
+            Err(SendTimeoutError::Timeout((RaftMsg::ClientWriteRequest { rpc, .. }, _))) => {
+                tracing::warn!("Raft::client_write_with_deadline: the request queue is full");
+                return Err(rpc.into_error(ClientWriteError::Overloaded));
+            }
+            Err(_) => {
+                tracing::error!("error when Raft::client_write_with_deadline: send to tx_api: channel closed");
+                return Err(ClientWriteError::RaftError(RaftError::ShuttingDown));
+            }
+        }
+
+        match timeout_at(deadline, rx).await {
+            Ok(Ok(res)) => res,
+            // The leader stepped down or shut down.
+            Ok(Err(_)) if append_deadline.is_appended() => Err(ClientWriteError::OutcomeUnknown),
+            Ok(Err(_)) => Err(ClientWriteError::RaftError(RaftError::ShuttingDown)),
+            Err(_) if append_deadline.try_abandon() => Err(ClientWriteError::NotAppended(data)),
+            Err(_) => Err(ClientWriteError::OutcomeUnknown),
+        }
+    }
+
+    /// Submit a batch of mutating client requests to Raft to update the state of the system (§5.1).
+    ///
+    /// This is the same as calling `client_write` for each of the given requests, in order, except
+    /// that the leader appends all of them to its log at once and replicates them together. A
+    /// leader also batches requests submitted concurrently through `client_write` on its own.
+    ///
+    /// Every request gets its own response, in the order of the requests. The batch is submitted
+    /// as one request, see `client_write` on how it may fail with `ClientWriteError::Overloaded`.
+    /// An empty batch is answered at once, with no responses.
+    ///
+    /// If this node is not the leader and `Config::forward_to_leader` is set, the requests are
+    /// forwarded to the leader one by one, as with `client_write`.
+    #[tracing::instrument(level = "debug", skip(self, data), fields(len = data.len()))]
+    pub async fn client_write_batch(
+        &self,
+        data: Vec<D>,
+    ) -> Vec<Result<ClientWriteResponse<R>, ClientWriteError<D, NID>>> {
+        if data.is_empty() {
+            return Vec::new();
+        }
+
+        let span = tracing::debug_span!("CH");
+
+        let mut rpcs = Vec::with_capacity(data.len());
+        let mut rxs = Vec::with_capacity(data.len());
+        for d in data {
+            let (tx, rx) = oneshot::channel();
+            rpcs.push((ClientWriteRequest::new(d), tx));
+            rxs.push(rx);
+        }
+
+        match self.send_client_write((RaftMsg::ClientWriteBatch { rpcs }, span)).await {
+            Ok(()) => {}
+            Err(SendTimeoutError::Timeout((RaftMsg::ClientWriteBatch { rpcs }, _))) => {
+                tracing::warn!("Raft::client_write_batch: the request queue is full");
+                return rpcs.into_iter().map(|(rpc, _)| Err(rpc.into_error(ClientWriteError::Overloaded))).collect();
+            }
+            Err(_) => {
+                tracing::error!("error when Raft::client_write_batch: send to tx_api: channel closed");
+                return rxs.iter().map(|_| Err(ClientWriteError::RaftError(RaftError::ShuttingDown))).collect();
+            }
+        }
+
+        let mut res = Vec::with_capacity(rxs.len());
+        for rx in rxs {
+            let r = rx.await.unwrap_or_else(|e| {
+                tracing::error!("error when Raft::client_write_batch: recv from rx: {}", e);
+                Err(ClientWriteError::RaftError(RaftError::ShuttingDown))
+            });
+            // Requests are forwarded one at a time, so that the leader appends them in order.
+            let r = match r {
+                Err(ClientWriteError::ForwardToLeader(data, Some(leader))) => {
+                    self.forward_client_write(data, None, leader, Vec::new()).await
+                }
+                r => r,
+            };
+            res.push(r);
+        }
+        res
+    }
+
+    /// Submit a client write message to the API queue, waiting for room in it for at most
+    /// `Config::client_write_queue_timeout`.
+    ///
+    /// A message which could not be submitted is handed back with the error.
+    async fn send_client_write(
+        &self,
+        msg: (RaftMsg<D, R, NID>, Span),
+    ) -> Result<(), SendTimeoutError<(RaftMsg<D, R, NID>, Span)>> {
+        match self.inner.client_write_queue_timeout {
+            None => self.inner.tx_api.send(msg).await.map_err(|err| SendTimeoutError::Closed(err.0)),
+            Some(timeout) if timeout == Duration::from_millis(0) => {
+                self.inner.tx_api.try_send(msg).map_err(|err| match err {
+                    TrySendError::Full(msg) => SendTimeoutError::Timeout(msg),
+                    TrySendError::Closed(msg) => SendTimeoutError::Closed(msg),
+                })
+            }
+            Some(timeout) => self.inner.tx_api.send_timeout(msg, timeout).await,
+        }
+    }
+
     /// Initialize a pristine Raft node with the given config.
     ///
     /// This command should be called on pristine nodes — where the log index is 0 and the node is
@@ -260,7 +711,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// free, and Raft guarantees that the first node to become the cluster leader will propagate
     /// only its own config.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn initialize(&self, members: BTreeSet<NodeId>) -> Result<(), InitializeError> {
+    pub async fn initialize(&self, members: BTreeSet<NID>) -> Result<(), InitializeError> {
         let span = tracing::debug_span!("CH");
 
         let (tx, rx) = oneshot::channel();
@@ -268,6 +719,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         self.inner
             .tx_api
             .send((RaftMsg::Initialize { members, tx }, span))
+            .await
             .map_err(|_| RaftError::ShuttingDown)?;
 
         rx.await.map_err(|_| InitializeError::RaftError(RaftError::ShuttingDown)).and_then(|res| res)
@@ -284,30 +736,32 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// Once the node is up-to-speed, this function will return. It is the responsibility of the
     /// application to then call `change_membership` once all of the new nodes are synced.
     ///
+    /// The target is recorded in `MembershipConfig.learners` through a config change entry, so
+    /// that future leaders keep replicating to it. This can not be done while a membership change
+    /// is in progress, i.e. while new nodes are being synced or the cluster is in joint consensus,
+    /// in which case this call fails with `ChangeConfigError::ConfigChangeInProgress`.
+    ///
+    /// The given `node` metadata, e.g. the address of the target, is recorded in
+    /// `MembershipConfig.nodes` along with it.
+    ///
     /// If this Raft node is not the cluster leader, then this call will fail.
-    #[tracing::instrument(level = "debug", skip(self, id), fields(target=id))]
-    pub async fn add_non_voter(&self, id: NodeId) -> Result<(), ResponseError> {
-        let span = tracing::debug_span!("CH");
-
-        let (tx, rx) = oneshot::channel();
-
-        self.inner
-            .tx_api
-            .send((RaftMsg::AddNonVoter { id, tx }, span))
-            .map_err(|_| RaftError::ShuttingDown)?;
-
-        let recv_res = rx.await;
-        let res = match recv_res {
-            Ok(x) => x,
-            Err(e) => {
-                tracing::error!("recv rx error: {}", e);
-                return Err(ChangeConfigError::RaftError(RaftError::ShuttingDown).into());
-            }
-        };
-
-        res?;
+    #[tracing::instrument(level = "debug", skip(self, id), fields(target=%id))]
+    pub async fn add_non_voter(&self, id: NID, node: Option<Node>) -> Result<(), ResponseError<NID>> {
+        self.change_config(MembershipChange::AddNonVoter { id, node }, Vec::new()).await
+    }
 
-        Ok(())
+    /// Remove a non-voter from the cluster, stopping replication to it.
+    ///
+    /// This cancels an earlier `add_non_voter` call for the target, e.g. because its host has
+    /// died. A pending `add_non_voter` call for the target returns `ChangeConfigError::NonVoterRemoved`.
+    /// Non-voters which are being synced as part of a `change_membership` call can not be removed
+    /// until the membership change has finished. The target is removed from
+    /// `MembershipConfig.learners` through a config change entry.
+    ///
+    /// If this Raft node is not the cluster leader, then this call will fail.
+    #[tracing::instrument(level = "debug", skip(self, id), fields(target=%id))]
+    pub async fn remove_non_voter(&self, id: NID) -> Result<(), ResponseError<NID>> {
+        self.change_config(MembershipChange::RemoveNonVoter { id }, Vec::new()).await
     }
 
     /// Propose a cluster configuration change (§6).
@@ -319,18 +773,164 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// recommended that `add_non_voter` be called first for new nodes, and then once all new nodes
     /// have been synchronized, call this method to start reconfiguration.
     ///
+    /// `nodes` sets the metadata of members in the proposed config, e.g. of new nodes which were not
+    /// added with `add_non_voter`. Members which are not given keep their current metadata, and
+    /// metadata of nodes which are not in `members` is ignored.
+    ///
     /// If this Raft node is not the cluster leader, then the proposed configuration change will be
     /// rejected.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn change_membership(&self, members: BTreeSet<NodeId>) -> Result<(), ResponseError> {
+    pub async fn change_membership(
+        &self,
+        members: BTreeSet<NID>,
+        nodes: BTreeMap<NID, Node>,
+    ) -> Result<(), ResponseError<NID>> {
+        self.change_config(MembershipChange::ChangeMembership { members, nodes }, Vec::new()).await
+    }
+
+    /// Propose a cluster configuration change (§6), without waiting for it to finish.
+    ///
+    /// This is the same as `change_membership`, except that it returns as soon as the leader has
+    /// accepted the change. The returned handle reports the phase the change has reached, and can
+    /// cancel the change while new nodes are still being synced.
+    ///
+    /// Dropping the handle does not stop the change.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn start_change_membership(
+        &self,
+        members: BTreeSet<NID>,
+        nodes: BTreeMap<NID, Node>,
+    ) -> Result<ChangeMembershipHandle<D, R, NID>, ResponseError<NID>> {
         let span = tracing::debug_span!("CH");
 
+        let id = self.inner.next_change_id.fetch_add(1, Ordering::Relaxed);
+        let (phase_tx, mut phase_rx) = watch::channel(ChangeMembershipPhase::Syncing);
+        let progress = ChangeMembershipProgress { id, tx: phase_tx };
+
         let (tx, rx) = oneshot::channel();
         self.inner
             .tx_api
-            .send((RaftMsg::ChangeMembership { members, tx }, span))
+            .send((
+                RaftMsg::ChangeMembership {
+                    members,
+                    nodes,
+                    progress: Some(progress),
+                    tx,
+                },
+                span,
+            ))
+            .await
             .map_err(|_| RaftError::ShuttingDown)?;
 
+        // The leader reports the first phase once it has accepted the change. Otherwise the
+        // progress reporter is dropped, and the reason is sent on the response channel.
+        if phase_rx.changed().await.is_err() {
+            return match rx.await {
+                Ok(Err(err)) => Err(err),
+                _ => Err(ChangeConfigError::RaftError(RaftError::ShuttingDown).into()),
+            };
+        }
+
+        Ok(ChangeMembershipHandle {
+            id,
+            phase: phase_rx,
+            rx,
+            tx_api: self.inner.tx_api.clone(),
+        })
+    }
+
+    /// Add a voting member to the cluster with a single-server membership change (§4.1 of the Raft dissertation).
+    ///
+    /// Unlike `change_membership`, this writes only one config entry and does not go through joint
+    /// consensus, which is safe because the majorities of the old and the new config always overlap
+    /// when they differ by one node. The new config takes effect as soon as it is appended, and this
+    /// call returns once it has been committed.
+    ///
+    /// If the target is not already a non-voter which is up-to-speed, it is first synced as a
+    /// non-voter. The given `node` metadata is recorded in `MembershipConfig.nodes`.
+    ///
+    /// Only one membership change may be in progress at a time. This fails with
+    /// `ChangeConfigError::ConfigChangeInProgress` if another change has not yet been committed, and
+    /// with `ChangeConfigError::Noop` if the target is already a voting member.
+    ///
+    /// If this Raft node is not the cluster leader, then this call will fail.
+    #[tracing::instrument(level = "debug", skip(self, id), fields(target=%id))]
+    pub async fn add_voter(&self, id: NID, node: Option<Node>) -> Result<(), ResponseError<NID>> {
+        self.change_config(MembershipChange::AddVoter { id, node }, Vec::new()).await
+    }
+
+    /// Remove a voting member from the cluster with a single-server membership change (§4.1 of the
+    /// Raft dissertation).
+    ///
+    /// The removed node reverts to non-voter state once it has replicated the new config. If the
+    /// target is this node, it steps down once the new config has been committed.
+    ///
+    /// Only one membership change may be in progress at a time. This fails with
+    /// `ChangeConfigError::ConfigChangeInProgress` if another change has not yet been committed, and
+    /// with `ChangeConfigError::Noop` if the target is not a voting member.
+    ///
+    /// If this Raft node is not the cluster leader, then this call will fail.
+    #[tracing::instrument(level = "debug", skip(self, id), fields(target=%id))]
+    pub async fn remove_voter(&self, id: NID) -> Result<(), ResponseError<NID>> {
+        self.change_config(MembershipChange::RemoveVoter { id }, Vec::new()).await
+    }
+
+    /// Submit a membership change forwarded by another Raft node with
+    /// `RaftNetwork::forward_change_membership`.
+    ///
+    /// This is handled like a call of the corresponding method on this node, except that it is only
+    /// forwarded further if the hop limit allows it, and not to a node it was forwarded by already.
+    #[tracing::instrument(level = "debug", skip(self), fields(path=?rpc.path))]
+    pub async fn handle_forward_change_membership(
+        &self,
+        rpc: ForwardChangeMembershipRequest<NID>,
+    ) -> Result<(), ResponseError<NID>> {
+        self.change_config(rpc.change, rpc.path).await
+    }
+
+    /// Submit a membership change to this node, and forward it to the leader if this node is not the
+    /// leader and forwarding is enabled. `path` is the list of nodes which forwarded it so far.
+    async fn change_config(&self, change: MembershipChange<NID>, path: Vec<NID>) -> Result<(), ResponseError<NID>> {
+        if self.inner.max_forward_hops.is_none() {
+            return self.local_change_config(change).await;
+        }
+
+        let leader = match self.local_change_config(change.clone()).await {
+            Err(ResponseError::ChangeConfig(ChangeConfigError::NodeNotLeader(Some(leader)))) => leader,
+            res => return res,
+        };
+        let path = match self.forward_path(path, &leader) {
+            Some(path) => path,
+            None => return Err(ChangeConfigError::NodeNotLeader(Some(leader)).into()),
+        };
+
+        let rpc = ForwardChangeMembershipRequest { path, change };
+        self.inner
+            .network
+            .forward_change_membership(leader, rpc)
+            .await
+            .unwrap_or_else(|err| Err(RaftError::RaftNetwork(err).into()))
+    }
+
+    /// Submit a membership change to this node.
+    async fn local_change_config(&self, change: MembershipChange<NID>) -> Result<(), ResponseError<NID>> {
+        let span = tracing::debug_span!("CH");
+
+        let (tx, rx) = oneshot::channel();
+        let msg = match change {
+            MembershipChange::AddNonVoter { id, node } => RaftMsg::AddNonVoter { id, node, tx },
+            MembershipChange::RemoveNonVoter { id } => RaftMsg::RemoveNonVoter { id, tx },
+            MembershipChange::ChangeMembership { members, nodes } => RaftMsg::ChangeMembership {
+                members,
+                nodes,
+                progress: None,
+                tx,
+            },
+            MembershipChange::AddVoter { id, node } => RaftMsg::AddVoter { id, node, tx },
+            MembershipChange::RemoveVoter { id } => RaftMsg::RemoveVoter { id, tx },
+        };
+        self.inner.tx_api.send((msg, span)).await.map_err(|_| RaftError::ShuttingDown)?;
+
         let recv_res = rx.await;
         let res = match recv_res {
             Ok(x) => x,
@@ -345,8 +945,101 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         Ok(())
     }
 
+    /// The path along which to forward a request to the given leader, given the nodes which
+    /// forwarded it so far, or `None` if it must not be forwarded: because forwarding is disabled,
+    /// because of the hop limit, or because it would go around in a loop.
+    fn forward_path(&self, mut path: Vec<NID>, leader: &NID) -> Option<Vec<NID>> {
+        let max_hops = self.inner.max_forward_hops?;
+        let id = self.inner.rx_metrics.borrow().id.clone();
+        if path.len() as u64 >= max_hops || path.contains(leader) || path.contains(&id) {
+            tracing::debug!(?path, %leader, "not forwarding request to the leader");
+            return None;
+        }
+        path.push(id);
+        Some(path)
+    }
+
+    /// Transfer leadership of the cluster to the given voting member (§3.10 of the Raft dissertation).
+    ///
+    /// This is useful for rolling restarts, or for draining a host before taking it down. The leader
+    /// stops accepting client writes, brings the target's log up-to-date, and then asks the target
+    /// to start an election at once. Client writes received during the transfer are rejected with
+    /// `ClientWriteError::ForwardToLeader`, without a leader hint.
+    ///
+    /// This call returns once this node has stepped down in favor of the target. If that does not
+    /// happen within an election timeout, the transfer is aborted, this node resumes accepting
+    /// client writes, and `TransferLeaderError::Timeout` is returned.
+    ///
+    /// If this Raft node is not the cluster leader, then the request will be rejected.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn transfer_leadership(&self, target: NID) -> Result<(), TransferLeaderError<NID>> {
+        let span = tracing::debug_span!("CH");
+
+        let (tx, rx) = oneshot::channel();
+        self.inner
+            .tx_api
+            .send((RaftMsg::TransferLeadership { target, tx }, span))
+            .await
+            .map_err(|_| RaftError::ShuttingDown)?;
+
+        rx.await.map_err(|_| TransferLeaderError::RaftError(RaftError::ShuttingDown)).and_then(|res| res)
+    }
+
+    /// Build a snapshot of the state machine now, regardless of `Config::snapshot_policy`, e.g.
+    /// before taking a backup of this node.
+    ///
+    /// If a snapshot is being built already, this waits for it instead of building another one.
+    /// Returns the metadata of the resulting snapshot, which is then this node's current snapshot.
+    ///
+    /// This may be called on any node, and only affects the node it is called on.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn trigger_snapshot(&self) -> Result<SnapshotMeta<NID>, SnapshotError> {
+        let span = tracing::debug_span!("CH");
+
+        let (tx, rx) = oneshot::channel();
+        self.inner
+            .tx_api
+            .send((RaftMsg::TriggerSnapshot { tx }, span))
+            .await
+            .map_err(|_| RaftError::ShuttingDown)?;
+
+        rx.await.map_err(|_| SnapshotError::RaftError(RaftError::ShuttingDown)).and_then(|res| res)
+    }
+
+    /// Purge the entries of the log up to and including `upto` from storage, e.g. to reclaim disk
+    /// space before maintenance, without waiting for the next snapshot to do so.
+    ///
+    /// Only entries before the last one applied to the state machine may be purged; the last
+    /// applied entry is always kept, like log compaction does. On the leader, entries past its
+    /// current snapshot are only purged if every replication target has received them already,
+    /// else `PurgeLogError::NeededByReplication` is returned. Targets which lag behind the purged
+    /// entries are sent a snapshot instead, so `trigger_snapshot` first to purge entries they lack.
+    ///
+    /// This may be called on any node, and only affects the node it is called on.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn purge_log_upto(&self, upto: u64) -> Result<(), PurgeLogError<NID>> {
+        let span = tracing::debug_span!("CH");
+
+        let (tx, rx) = oneshot::channel();
+        self.inner
+            .tx_api
+            .send((RaftMsg::PurgeLog { upto, tx }, span))
+            .await
+            .map_err(|_| RaftError::ShuttingDown)?;
+
+        rx.await.map_err(|_| PurgeLogError::RaftError(RaftError::ShuttingDown)).and_then(|res| res)
+    }
+
+    /// The number of requests currently waiting in the queue of requests to this Raft node.
+    ///
+    /// Unlike `RaftMetrics::api_queue_len`, which is sampled when the metrics are updated, this
+    /// is always up to date. The capacity of the queue is `Config::api_queue_capacity`.
+    pub fn api_queue_len(&self) -> u64 {
+        self.inner.api_queue_capacity - self.inner.tx_api.capacity() as u64
+    }
+
     /// Get a handle to the metrics channel.
-    pub fn metrics(&self) -> watch::Receiver<RaftMetrics> {
+    pub fn metrics(&self) -> watch::Receiver<RaftMetrics<NID>> {
         self.inner.rx_metrics.clone()
     }
 
@@ -367,7 +1060,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// // wait for raft state to become a follower
     /// r.wait(None).state(State::Follower).await?;
     /// ```
-    pub fn wait(&self, timeout: Option<Duration>) -> Wait {
+    pub fn wait(&self, timeout: Option<Duration>) -> Wait<NID> {
         let timeout = match timeout {
             Some(t) => t,
             None => Duration::from_millis(500),
@@ -390,7 +1083,14 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     }
 }
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Clone for Raft<D, R, N, S> {
+impl<D, R, N, S, NID> Clone for Raft<D, R, N, S, NID>
+where
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, R, NID>,
+    S: RaftStorage<D, R, NID>,
+    NID: NodeId,
+{
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
@@ -398,42 +1098,189 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Cl
     }
 }
 
-pub(crate) type ClientWriteResponseTx<D, R> = oneshot::Sender<Result<ClientWriteResponse<R>, ClientWriteError<D>>>;
-pub(crate) type ClientReadResponseTx = oneshot::Sender<Result<(), ClientReadError>>;
-pub(crate) type ResponseTx = oneshot::Sender<Result<u64, ResponseError>>;
+pub(crate) type ClientWriteResponseTx<D, R, NID> =
+    oneshot::Sender<Result<ClientWriteResponse<R>, ClientWriteError<D, NID>>>;
+/// Client write requests along with their response channels, to be appended to the log together.
+pub(crate) type ClientWriteBatch<D, R, NID> = Vec<(ClientWriteRequest<D, NID>, ClientWriteResponseTx<D, R, NID>)>;
+pub(crate) type ClientReadResponseTx<NID> = oneshot::Sender<Result<(), ClientReadError<NID>>>;
+pub(crate) type ReadIndexResponseTx<NID> = oneshot::Sender<Result<LogId, ClientReadError<NID>>>;
+pub(crate) type StaleReadResponseTx = oneshot::Sender<Result<LogId, StaleReadError>>;
+pub(crate) type SnapshotResponseTx<NID> = oneshot::Sender<Result<SnapshotMeta<NID>, SnapshotError>>;
+pub(crate) type PurgeLogResponseTx<NID> = oneshot::Sender<Result<(), PurgeLogError<NID>>>;
+pub(crate) type ResponseTx<NID> = oneshot::Sender<Result<u64, ResponseError<NID>>>;
+
+/// The phases of a membership change started with `Raft::start_change_membership`, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChangeMembershipPhase {
+    /// New nodes are being synced as non-voters before the cluster enters joint consensus.
+    Syncing,
+    /// The joint config has been appended to the log.
+    Joint,
+    /// The joint config has been committed, and the final config appended to the log.
+    JointCommitted,
+    /// The final config has been committed. The change is complete.
+    FinalCommitted,
+}
+
+/// Reports the phase of a membership change to its `ChangeMembershipHandle`.
+pub(crate) struct ChangeMembershipProgress {
+    /// The ID of the change, used to cancel it.
+    pub id: u64,
+    pub tx: watch::Sender<ChangeMembershipPhase>,
+}
+
+/// A handle to a membership change started with `Raft::start_change_membership`.
+pub struct ChangeMembershipHandle<D: AppData, R: AppDataResponse, NID: NodeId = u64> {
+    id: u64,
+    phase: watch::Receiver<ChangeMembershipPhase>,
+    rx: oneshot::Receiver<Result<u64, ResponseError<NID>>>,
+    tx_api: mpsc::Sender<(RaftMsg<D, R, NID>, Span)>,
+}
+
+impl<D: AppData, R: AppDataResponse, NID: NodeId> ChangeMembershipHandle<D, R, NID> {
+    /// The latest phase the change has reached.
+    pub fn phase(&self) -> ChangeMembershipPhase {
+        *self.phase.borrow()
+    }
+
+    /// Wait until the change has reached the given phase.
+    ///
+    /// Returns `false` if the change stopped before that, e.g. because it was cancelled or the
+    /// leader stepped down. `finish` returns the reason.
+    pub async fn wait_for(&mut self, phase: ChangeMembershipPhase) -> bool {
+        loop {
+            if *self.phase.borrow() >= phase {
+                return true;
+            }
+            if self.phase.changed().await.is_err() {
+                return *self.phase.borrow() >= phase;
+            }
+        }
+    }
+
+    /// Wait for the change to finish, i.e. for the final config to be committed.
+    pub async fn finish(self) -> Result<(), ResponseError<NID>> {
+        let res = match self.rx.await {
+            Ok(x) => x,
+            Err(e) => {
+                tracing::error!("recv rx error: {}", e);
+                return Err(ChangeConfigError::RaftError(RaftError::ShuttingDown).into());
+            }
+        };
+
+        res?;
+
+        Ok(())
+    }
+
+    /// Cancel the change, which is only possible while new nodes are still being synced.
+    ///
+    /// Nodes which were being synced only for this change stop being replicated to, and `finish`
+    /// returns `ChangeConfigError::Cancelled`. If the change has moved on, this fails with
+    /// `ChangeConfigError::NotSyncing`.
+    pub async fn cancel(&self) -> Result<(), ResponseError<NID>> {
+        let span = tracing::debug_span!("CH");
+
+        let (tx, rx) = oneshot::channel();
+        self.tx_api
+            .send((RaftMsg::CancelChangeMembership { id: self.id, tx }, span))
+            .await
+            .map_err(|_| RaftError::ShuttingDown)?;
+
+        let recv_res = rx.await;
+        let res = match recv_res {
+            Ok(x) => x,
+            Err(e) => {
+                tracing::error!("recv rx error: {}", e);
+                return Err(ChangeConfigError::RaftError(RaftError::ShuttingDown).into());
+            }
+        };
+
+        res?;
+
+        Ok(())
+    }
+}
 
 /// A message coming from the Raft API.
-pub(crate) enum RaftMsg<D: AppData, R: AppDataResponse> {
+pub(crate) enum RaftMsg<D: AppData, R: AppDataResponse, NID: NodeId> {
     AppendEntries {
-        rpc: AppendEntriesRequest<D>,
+        rpc: AppendEntriesRequest<D, NID>,
         tx: oneshot::Sender<Result<AppendEntriesResponse, RaftError>>,
     },
     RequestVote {
-        rpc: VoteRequest,
+        rpc: VoteRequest<NID>,
         tx: oneshot::Sender<Result<VoteResponse, RaftError>>,
     },
     InstallSnapshot {
-        rpc: InstallSnapshotRequest,
+        rpc: InstallSnapshotRequest<NID>,
         tx: oneshot::Sender<Result<InstallSnapshotResponse, RaftError>>,
     },
     ClientWriteRequest {
-        rpc: ClientWriteRequest<D>,
-        tx: ClientWriteResponseTx<D, R>,
+        rpc: ClientWriteRequest<D, NID>,
+        tx: ClientWriteResponseTx<D, R, NID>,
+    },
+    ClientWriteBatch {
+        rpcs: ClientWriteBatch<D, R, NID>,
     },
     ClientReadRequest {
-        tx: ClientReadResponseTx,
+        tx: ClientReadResponseTx<NID>,
+    },
+    ReadIndex {
+        /// The RPC the read index is requested with, or `None` if it is requested by this node.
+        rpc: Option<ReadIndexRequest<NID>>,
+        tx: ReadIndexResponseTx<NID>,
+    },
+    StaleRead {
+        max_lag: MaxLag,
+        tx: StaleReadResponseTx,
     },
     Initialize {
-        members: BTreeSet<NodeId>,
+        members: BTreeSet<NID>,
         tx: oneshot::Sender<Result<(), InitializeError>>,
     },
     AddNonVoter {
-        id: NodeId,
-        tx: ResponseTx,
+        id: NID,
+        node: Option<Node>,
+        tx: ResponseTx<NID>,
+    },
+    RemoveNonVoter {
+        id: NID,
+        tx: ResponseTx<NID>,
     },
     ChangeMembership {
-        members: BTreeSet<NodeId>,
-        tx: ResponseTx,
+        members: BTreeSet<NID>,
+        nodes: BTreeMap<NID, Node>,
+        progress: Option<ChangeMembershipProgress>,
+        tx: ResponseTx<NID>,
+    },
+    CancelChangeMembership {
+        id: u64,
+        tx: ResponseTx<NID>,
+    },
+    AddVoter {
+        id: NID,
+        node: Option<Node>,
+        tx: ResponseTx<NID>,
+    },
+    RemoveVoter {
+        id: NID,
+        tx: ResponseTx<NID>,
+    },
+    TimeoutNow {
+        rpc: TimeoutNowRequest<NID>,
+        tx: oneshot::Sender<Result<TimeoutNowResponse, RaftError>>,
+    },
+    TransferLeadership {
+        target: NID,
+        tx: oneshot::Sender<Result<(), TransferLeaderError<NID>>>,
+    },
+    TriggerSnapshot {
+        tx: SnapshotResponseTx<NID>,
+    },
+    PurgeLog {
+        upto: u64,
+        tx: PurgeLogResponseTx<NID>,
     },
 }
 
@@ -441,11 +1288,12 @@ pub(crate) enum RaftMsg<D: AppData, R: AppDataResponse> {
 
 /// An RPC sent by a cluster leader to replicate log entries (§5.3), and as a heartbeat (§5.2).
 #[derive(Debug, Serialize, Deserialize)]
-pub struct AppendEntriesRequest<D: AppData> {
+pub struct AppendEntriesRequest<D: AppData, NID: NodeId = u64> {
     /// The leader's current term.
     pub term: u64,
     /// The leader's ID. Useful in redirecting clients.
-    pub leader_id: u64,
+    #[serde(bound = "NID: NodeId")]
+    pub leader_id: NID,
 
     /// The log entry immediately preceding the new entries.
     pub prev_log_id: LogId,
@@ -454,13 +1302,13 @@ pub struct AppendEntriesRequest<D: AppData> {
     ///
     /// This may be empty when the leader is sending heartbeats. Entries
     /// are batched for efficiency.
-    #[serde(bound = "D: AppData")]
-    pub entries: Vec<Entry<D>>,
+    #[serde(bound = "D: AppData, NID: NodeId")]
+    pub entries: Vec<Entry<D, NID>>,
     /// The leader's commit index.
     pub leader_commit: u64,
 }
 
-impl<D: AppData> MessageSummary for AppendEntriesRequest<D> {
+impl<D: AppData, NID: NodeId> MessageSummary for AppendEntriesRequest<D, NID> {
     fn summary(&self) -> String {
         format!(
             "term={}, leader_id={}, prev_log_id={}, leader_commit={}, n={}",
@@ -502,15 +1350,27 @@ pub struct ConflictOpt {
 
 /// A Raft log entry.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub struct Entry<D: AppData> {
+pub struct Entry<D: AppData, NID: NodeId = u64> {
     pub log_id: LogId,
 
     /// This entry's payload.
-    #[serde(bound = "D: AppData")]
-    pub payload: EntryPayload<D>,
+    #[serde(bound = "D: AppData, NID: NodeId")]
+    pub payload: EntryPayload<D, NID>,
 }
 
-impl<D: AppData> Entry<D> {
+impl<D: AppData, NID: NodeId> Entry<D, NID> {
+    /// The size of this entry in bytes, as counted against the byte limits on replication.
+    ///
+    /// This is the `AppData::size` of a normal entry's data, and the size of the membership config of
+    /// a config change entry. Other entries are counted by the size of the entry itself.
+    pub fn payload_size(&self) -> u64 {
+        match &self.payload {
+            EntryPayload::Normal(normal) => normal.data.size(),
+            EntryPayload::ConfigChange(change) => change.membership.size(),
+            _ => std::mem::size_of_val(self) as u64,
+        }
+    }
+
     /// Create a new snapshot pointer from the given snapshot meta.
     pub fn new_purged_marker(log_id: LogId) -> Self {
         Entry {
@@ -520,13 +1380,13 @@ impl<D: AppData> Entry<D> {
     }
 }
 
-impl<D: AppData> MessageSummary for Entry<D> {
+impl<D: AppData, NID: NodeId> MessageSummary for Entry<D, NID> {
     fn summary(&self) -> String {
         format!("{}:{}", self.log_id, self.payload.summary())
     }
 }
 
-impl<D: AppData> MessageSummary for &[Entry<D>] {
+impl<D: AppData, NID: NodeId> MessageSummary for &[Entry<D, NID>] {
     fn summary(&self) -> String {
         let mut res = Vec::with_capacity(self.len());
         for x in self.iter() {
@@ -540,19 +1400,20 @@ impl<D: AppData> MessageSummary for &[Entry<D>] {
 
 /// Log entry payload variants.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub enum EntryPayload<D: AppData> {
+pub enum EntryPayload<D: AppData, NID: NodeId = u64> {
     /// An empty payload committed by a new cluster leader.
     Blank,
     /// A normal log entry.
     #[serde(bound = "D: AppData")]
     Normal(EntryNormal<D>),
     /// A config change log entry.
-    ConfigChange(EntryConfigChange),
+    #[serde(bound = "NID: NodeId")]
+    ConfigChange(EntryConfigChange<NID>),
     /// An entry before which all logs are removed.
     PurgedMarker,
 }
 
-impl<D: AppData> MessageSummary for EntryPayload<D> {
+impl<D: AppData, NID: NodeId> MessageSummary for EntryPayload<D, NID> {
     fn summary(&self) -> String {
         match self {
             EntryPayload::Blank => "blank".to_string(),
@@ -571,13 +1432,17 @@ pub struct EntryNormal<D: AppData> {
     /// The contents of this entry.
     #[serde(bound = "D: AppData")]
     pub data: D,
+    /// The client session of this entry, if it was submitted with one.
+    #[serde(default)]
+    pub session: Option<ClientSession>,
 }
 
 /// A log entry holding a config change.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub struct EntryConfigChange {
+#[serde(bound = "NID: NodeId")]
+pub struct EntryConfigChange<NID: NodeId = u64> {
     /// Details on the cluster's membership configuration.
-    pub membership: MembershipConfig,
+    pub membership: MembershipConfig<NID>,
 }
 
 //////////////////////////////////////////////////////////////////////////////////////////////////
@@ -585,22 +1450,45 @@ pub struct EntryConfigChange {
 /// The membership configuration of the cluster.
 /// Unlike original raft, the membership always a joint.
 /// It could be a joint of one, two or more members, i.e., a quorum requires a majority of every members
-#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub struct MembershipConfig {
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "NID: NodeId")]
+pub struct MembershipConfig<NID: NodeId = u64> {
     /// All members of the Raft cluster.
-    pub members: BTreeSet<NodeId>,
+    pub members: BTreeSet<NID>,
     /// All members of the Raft cluster after joint consensus is finalized.
     ///
     /// The presence of a value here indicates that the config is in joint consensus.
-    pub members_after_consensus: Option<BTreeSet<NodeId>>,
+    pub members_after_consensus: Option<BTreeSet<NID>>,
+    /// Non-voters (learners) of the Raft cluster.
+    ///
+    /// Learners are replicated to by every leader, but take no part in elections or in committing
+    /// entries.
+    #[serde(default)]
+    pub learners: BTreeSet<NID>,
+    /// Metadata of the voters and learners in this config, such as their addresses.
+    ///
+    /// A node has no entry here if it was added without metadata.
+    #[serde(default)]
+    pub nodes: BTreeMap<NID, Node>,
 }
 
-impl MembershipConfig {
+impl<NID: NodeId> Default for MembershipConfig<NID> {
+    fn default() -> Self {
+        Self {
+            members: BTreeSet::new(),
+            members_after_consensus: None,
+            learners: BTreeSet::new(),
+            nodes: BTreeMap::new(),
+        }
+    }
+}
+
+impl<NID: NodeId> MembershipConfig<NID> {
     /// Get an iterator over all nodes in the current config.
-    pub fn all_nodes(&self) -> BTreeSet<u64> {
+    pub fn all_nodes(&self) -> BTreeSet<NID> {
         let mut all = self.members.clone();
         if let Some(members) = &self.members_after_consensus {
-            all.extend(members);
+            all.extend(members.iter().cloned());
         }
         all
     }
@@ -608,7 +1496,7 @@ impl MembershipConfig {
     /// Check if the given NodeId exists in this membership config.
     ///
     /// When in joint consensus, this will check both config groups.
-    pub fn contains(&self, x: &NodeId) -> bool {
+    pub fn contains(&self, x: &NID) -> bool {
         self.members.contains(x)
             || if let Some(members) = &self.members_after_consensus {
                 members.contains(x)
@@ -623,55 +1511,138 @@ impl MembershipConfig {
     }
 
     /// Create a new initial config containing only the given node ID.
-    pub fn new_initial(id: NodeId) -> Self {
+    pub fn new_initial(id: NID) -> Self {
         let mut members = BTreeSet::new();
         members.insert(id);
         Self {
             members,
             members_after_consensus: None,
+            learners: BTreeSet::new(),
+            nodes: BTreeMap::new(),
         }
     }
 
+    /// Get the metadata of the given node, if it is part of this config and has any.
+    pub fn get_node(&self, id: &NID) -> Option<&Node> {
+        self.nodes.get(id)
+    }
+
     pub fn to_final_config(&self) -> Self {
         match self.members_after_consensus {
             None => self.clone(),
-            Some(ref m) => MembershipConfig {
-                members: m.clone(),
-                members_after_consensus: None,
-            },
+            Some(ref m) => {
+                let mut final_config = MembershipConfig {
+                    members: m.clone(),
+                    members_after_consensus: None,
+                    learners: self.learners.clone(),
+                    nodes: self.nodes.clone(),
+                };
+                final_config.retain_known_nodes();
+                final_config
+            }
         }
     }
+
+    /// Drop the metadata of nodes which are neither voters nor learners in this config.
+    pub(crate) fn retain_known_nodes(&mut self) {
+        let all = self.all_nodes();
+        let learners = &self.learners;
+        self.nodes.retain(|id, _| all.contains(id) || learners.contains(id));
+    }
+
+    /// The size of this config in bytes, as counted against the byte limits on replication.
+    ///
+    /// This counts every node ID in it, by the size of the ID type, and the metadata of its nodes.
+    pub(crate) fn size(&self) -> u64 {
+        let joint = self.members_after_consensus.as_ref().map(|members| members.len()).unwrap_or(0);
+        let ids = self.members.len() + joint + self.learners.len() + self.nodes.len();
+        let nodes: usize = self.nodes.values().map(Node::size).sum();
+        (ids * std::mem::size_of::<NID>() + nodes) as u64
+    }
+}
+
+/// Metadata of a node in the cluster, recorded in `MembershipConfig.nodes`.
+///
+/// This is replicated along with the membership config, so that a `RaftNetwork` implementation
+/// can resolve its peers from the cluster state, e.g. through `RaftMetrics.membership_config`.
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Node {
+    /// The address at which the node may be reached.
+    pub addr: String,
+    /// Application specific metadata of the node.
+    #[serde(default)]
+    pub data: BTreeMap<String, String>,
+}
+
+impl Node {
+    /// Create metadata for a node reachable at the given address.
+    pub fn new(addr: impl ToString) -> Self {
+        Self {
+            addr: addr.to_string(),
+            data: BTreeMap::new(),
+        }
+    }
+
+    /// The size of this metadata in bytes.
+    fn size(&self) -> usize {
+        self.addr.len() + self.data.iter().map(|(key, value)| key.len() + value.len()).sum::<usize>()
+    }
 }
 
 //////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// An RPC sent by candidates to gather votes (§5.2).
 #[derive(Debug, Serialize, Deserialize)]
-pub struct VoteRequest {
+#[serde(bound = "NID: NodeId")]
+pub struct VoteRequest<NID: NodeId = u64> {
     /// The candidate's current term.
     pub term: u64,
     /// The candidate's ID.
-    pub candidate_id: u64,
+    pub candidate_id: NID,
     /// The index of the candidate’s last log entry (§5.4).
     pub last_log_index: u64,
     /// The term of the candidate’s last log entry (§5.4).
     pub last_log_term: u64,
+    /// Will be `true` if this is a PreVote request (§9.6 of the Raft dissertation).
+    ///
+    /// A PreVote asks the peer whether it would grant a vote for `term`, which is the term the
+    /// candidate would use if it started an election. Neither the candidate nor the peer update
+    /// their term or vote as part of a PreVote.
+    #[serde(default)]
+    pub pre_vote: bool,
+    /// Will be `true` if the candidate started this election because the leader asked it to take
+    /// over leadership (§3.10 of the Raft dissertation).
+    ///
+    /// Peers do not ignore such a request when they have recently heard from the leader, as the
+    /// leader itself initiated the election.
+    #[serde(default)]
+    pub leadership_transfer: bool,
 }
 
-impl MessageSummary for VoteRequest {
+impl<NID: NodeId> MessageSummary for VoteRequest<NID> {
     fn summary(&self) -> String {
         format!("{:?}", self)
     }
 }
 
-impl VoteRequest {
+impl<NID: NodeId> VoteRequest<NID> {
     /// Create a new instance.
-    pub fn new(term: u64, candidate_id: u64, last_log_index: u64, last_log_term: u64) -> Self {
+    pub fn new(term: u64, candidate_id: NID, last_log_index: u64, last_log_term: u64) -> Self {
         Self {
             term,
             candidate_id,
             last_log_index,
             last_log_term,
+            pre_vote: false,
+            leadership_transfer: false,
+        }
+    }
+
+    /// Create a new PreVote request.
+    pub fn new_pre_vote(term: u64, candidate_id: NID, last_log_index: u64, last_log_term: u64) -> Self {
+        Self {
+            pre_vote: true,
+            ..Self::new(term, candidate_id, last_log_index, last_log_term)
         }
     }
 }
@@ -687,35 +1658,101 @@ pub struct VoteResponse {
 
 //////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// An RPC sent by the Raft leader to the target of a leadership transfer, asking it to start an
+/// election at once (§3.10 of the Raft dissertation).
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "NID: NodeId")]
+pub struct TimeoutNowRequest<NID: NodeId = u64> {
+    /// The leader's current term.
+    pub term: u64,
+    /// The leader's ID.
+    pub leader_id: NID,
+}
+
+impl<NID: NodeId> MessageSummary for TimeoutNowRequest<NID> {
+    fn summary(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+/// The response to a `TimeoutNowRequest`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimeoutNowResponse {
+    /// The receiving node's current term, for leader to update itself.
+    pub term: u64,
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// An RPC sent by a follower or non-voter to the Raft leader, asking for the log position at which
+/// a linearizable read may be served (§6.4 of the Raft dissertation).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "NID: NodeId")]
+pub struct ReadIndexRequest<NID: NodeId = u64> {
+    /// The term of the node which sent this request, as of sending it.
+    pub term: u64,
+    /// The node this request was sent to, as the leader known to the sender.
+    pub leader_id: NID,
+    /// The ID of the node which is serving the read.
+    pub node_id: NID,
+    /// The nodes which forwarded this request, starting with `node_id`, when it is forwarded by
+    /// nodes which have stale information about the leader. See `Config::forward_to_leader`.
+    #[serde(default)]
+    pub path: Vec<NID>,
+}
+
+impl<NID: NodeId> MessageSummary for ReadIndexRequest<NID> {
+    fn summary(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+/// The response to a `ReadIndexRequest`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadIndexResponse {
+    /// The leader's commit index, which the requesting node must apply up to before serving the read.
+    pub log_id: LogId,
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////
+
 /// An RPC sent by the Raft leader to send chunks of a snapshot to a follower (§7).
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct InstallSnapshotRequest {
+#[serde(bound = "NID: NodeId")]
+pub struct InstallSnapshotRequest<NID: NodeId = u64> {
     /// The leader's current term.
     pub term: u64,
     /// The leader's ID. Useful in redirecting clients.
-    pub leader_id: u64,
+    pub leader_id: NID,
 
     /// Metadata of a snapshot: snapshot_id, last_log_ed membership etc.
-    pub meta: SnapshotMeta,
+    pub meta: SnapshotMeta<NID>,
 
     /// The byte offset where this chunk of data is positioned in the snapshot file.
     pub offset: u64,
-    /// The raw bytes of the snapshot chunk, starting at `offset`.
+    /// The raw bytes of the snapshot chunk, starting at `offset`, compressed with `compression` if set.
     pub data: Vec<u8>,
+    /// The CRC-32 checksum of the raw bytes of the chunk, as computed by `checksum::crc32`, if known.
+    #[serde(default)]
+    pub checksum: Option<u32>,
+    /// The codec `data` is compressed with, if any.
+    #[serde(default)]
+    pub compression: Option<SnapshotCompression>,
 
     /// Will be `true` if this is the last chunk in the snapshot.
     pub done: bool,
 }
 
-impl MessageSummary for InstallSnapshotRequest {
+impl<NID: NodeId> MessageSummary for InstallSnapshotRequest<NID> {
     fn summary(&self) -> String {
         format!(
-            "term={}, leader_id={}, meta={:?}, offset={}, len={}, done={}",
+            "term={}, leader_id={}, meta={:?}, offset={}, len={}, compression={:?}, done={}",
             self.term,
             self.leader_id,
             self.meta,
             self.offset,
             self.data.len(),
+            self.compression,
             self.done
         )
     }
@@ -726,6 +1763,28 @@ impl MessageSummary for InstallSnapshotRequest {
 pub struct InstallSnapshotResponse {
     /// The receiving node's current term, for leader to update itself.
     pub term: u64,
+    /// The number of bytes of the snapshot the receiving node has, i.e. the offset at which the
+    /// leader is to send the next chunk.
+    ///
+    /// This is ahead of the chunk sent when the node has already received more of the snapshot,
+    /// e.g. before the transfer was interrupted, so that the leader resumes from there. It is `None`
+    /// if the request was rejected for its term.
+    #[serde(default)]
+    pub offset: Option<u64>,
+    /// The codec the chunk was compressed with, if the receiving node decompressed it.
+    ///
+    /// This is `None` if the chunk was sent uncompressed, or compressed with a codec which the
+    /// receiving node does not support, or could not be decompressed. In the latter cases the node
+    /// did not take the chunk, and the leader sends the rest of the snapshot uncompressed.
+    #[serde(default)]
+    pub compression: Option<SnapshotCompression>,
+    /// The codecs the receiving node supports.
+    ///
+    /// The leader only compresses the chunks of a snapshot once the receiving node has named the
+    /// configured codec here. A node which does not know of this field, e.g. one built before
+    /// snapshot compression was added, is sent the snapshot uncompressed.
+    #[serde(default)]
+    pub supported_compression: Vec<SnapshotCompression>,
 }
 
 //////////////////////////////////////////////////////////////////////////////////////////////////
@@ -735,34 +1794,59 @@ pub struct InstallSnapshotResponse {
 /// The entry of this payload will be appended to the Raft log and then applied to the Raft state
 /// machine according to the Raft protocol.
 #[derive(Debug, Serialize, Deserialize)]
-pub struct ClientWriteRequest<D: AppData> {
+pub struct ClientWriteRequest<D: AppData, NID: NodeId = u64> {
     /// The application specific contents of this client request.
-    #[serde(bound = "D: AppData")]
-    pub(crate) entry: EntryPayload<D>,
+    #[serde(bound = "D: AppData, NID: NodeId")]
+    pub(crate) entry: EntryPayload<D, NID>,
+    /// The deadline of a request submitted with `Raft::client_write_with_deadline`.
+    #[serde(skip)]
+    pub(crate) deadline: Option<Arc<AppendDeadline>>,
 }
 
-impl<D: AppData> MessageSummary for ClientWriteRequest<D> {
+impl<D: AppData, NID: NodeId> MessageSummary for ClientWriteRequest<D, NID> {
     fn summary(&self) -> String {
         self.entry.summary()
     }
 }
 
-impl<D: AppData> ClientWriteRequest<D> {
+impl<D: AppData, NID: NodeId> ClientWriteRequest<D, NID> {
     /// Create a new client payload instance with a normal entry type.
     pub fn new(entry: D) -> Self {
-        Self::new_base(EntryPayload::Normal(EntryNormal { data: entry }))
+        Self::new_base(EntryPayload::Normal(EntryNormal {
+            data: entry,
+            session: None,
+        }))
+    }
+
+    /// Create a new client payload instance with a normal entry type, which is applied to the state
+    /// machine at most once per the given client session. See the `session` module.
+    pub fn new_with_session(entry: D, session: ClientSession) -> Self {
+        Self::new_base(EntryPayload::Normal(EntryNormal {
+            data: entry,
+            session: Some(session),
+        }))
     }
 
     /// Create a new instance.
-    pub(crate) fn new_base(entry: EntryPayload<D>) -> Self {
-        Self { entry }
+    pub(crate) fn new_base(entry: EntryPayload<D, NID>) -> Self {
+        Self { entry, deadline: None }
     }
 
     /// Generate a new payload holding a config change.
-    pub(crate) fn new_config(membership: MembershipConfig) -> Self {
+    pub(crate) fn new_config(membership: MembershipConfig<NID>) -> Self {
         Self::new_base(EntryPayload::ConfigChange(EntryConfigChange { membership }))
     }
 
+    /// Turn this request into the given error handing back its data.
+    pub(crate) fn into_error<F>(self, err: F) -> ClientWriteError<D, NID>
+    where F: FnOnce(D) -> ClientWriteError<D, NID> {
+        match self.entry {
+            EntryPayload::Normal(entry) => err(entry.data),
+            // Only normal entries are submitted through the Raft API.
+            _ => ClientWriteError::RaftError(RaftError::ShuttingDown),
+        }
+    }
+
     /// Generate a new blank payload.
     ///
     /// This is used by new leaders when first coming to power.
@@ -771,6 +1855,109 @@ impl<D: AppData> ClientWriteRequest<D> {
     }
 }
 
+/// An RPC sent by a node which is not the leader to forward a client write to the leader, when
+/// `Config::forward_to_leader` is enabled.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "D: AppData, NID: NodeId")]
+pub struct ForwardClientWriteRequest<D: AppData, NID: NodeId = u64> {
+    /// The nodes which forwarded this request, starting with the one it was submitted to.
+    pub path: Vec<NID>,
+    /// The client write.
+    pub rpc: ClientWriteRequest<D, NID>,
+}
+
+impl<D: AppData, NID: NodeId> MessageSummary for ForwardClientWriteRequest<D, NID> {
+    fn summary(&self) -> String {
+        format!("path={:?}, rpc={}", self.path, self.rpc.summary())
+    }
+}
+
+/// A membership change, as submitted with one of the membership methods of `Raft`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "NID: NodeId")]
+pub enum MembershipChange<NID: NodeId = u64> {
+    /// See `Raft::add_non_voter`.
+    AddNonVoter { id: NID, node: Option<Node> },
+    /// See `Raft::remove_non_voter`.
+    RemoveNonVoter { id: NID },
+    /// See `Raft::change_membership`.
+    ChangeMembership {
+        members: BTreeSet<NID>,
+        nodes: BTreeMap<NID, Node>,
+    },
+    /// See `Raft::add_voter`.
+    AddVoter { id: NID, node: Option<Node> },
+    /// See `Raft::remove_voter`.
+    RemoveVoter { id: NID },
+}
+
+/// An RPC sent by a node which is not the leader to forward a membership change to the leader,
+/// when `Config::forward_to_leader` is enabled.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "NID: NodeId")]
+pub struct ForwardChangeMembershipRequest<NID: NodeId = u64> {
+    /// The nodes which forwarded this request, starting with the one it was submitted to.
+    pub path: Vec<NID>,
+    /// The membership change.
+    pub change: MembershipChange<NID>,
+}
+
+impl<NID: NodeId> MessageSummary for ForwardChangeMembershipRequest<NID> {
+    fn summary(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+/// Tracks whether a client write submitted with `Raft::client_write_with_deadline` has been
+/// appended to the log, or abandoned because its deadline passed first.
+///
+/// Both the leader and the caller race to settle it once, so that the caller can tell whether the
+/// write was appended when giving up on it.
+#[derive(Debug)]
+pub(crate) struct AppendDeadline {
+    deadline: Instant,
+    state: AtomicU8,
+}
+
+impl AppendDeadline {
+    const PENDING: u8 = 0;
+    const APPENDED: u8 = 1;
+    const ABANDONED: u8 = 2;
+
+    pub(crate) fn new(deadline: Instant) -> Self {
+        Self {
+            deadline,
+            state: AtomicU8::new(Self::PENDING),
+        }
+    }
+
+    /// Mark the write as appended, which fails if it was abandoned, or if its deadline has passed,
+    /// in which case it is abandoned.
+    pub(crate) fn try_append(&self) -> bool {
+        if Instant::now() >= self.deadline {
+            return !self.try_abandon();
+        }
+        self.settle(Self::APPENDED) == Self::APPENDED
+    }
+
+    /// Mark the write as abandoned, which fails if it was appended.
+    pub(crate) fn try_abandon(&self) -> bool {
+        self.settle(Self::ABANDONED) == Self::ABANDONED
+    }
+
+    pub(crate) fn is_appended(&self) -> bool {
+        self.state.load(Ordering::SeqCst) == Self::APPENDED
+    }
+
+    /// Settle the state as `to`, unless it is settled already, and return the settled state.
+    fn settle(&self, to: u8) -> u8 {
+        match self.state.compare_exchange(Self::PENDING, to, Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => to,
+            Err(settled) => settled,
+        }
+    }
+}
+
 /// The response to a `ClientRequest`.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ClientWriteResponse<R: AppDataResponse> {
@@ -780,3 +1967,14 @@ pub struct ClientWriteResponse<R: AppDataResponse> {
     #[serde(bound = "R: AppDataResponse")]
     pub data: R,
 }
+
+/// The maximum lag behind the cluster leader allowed for a `Raft::stale_read`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MaxLag {
+    /// The leader must have been heard from within the given duration.
+    Time(Duration),
+    /// The state machine may be at most the given number of entries behind the leader's commit index.
+    ///
+    /// The leader must also have been heard from within `Config::election_timeout_max`.
+    Entries(u64),
+}
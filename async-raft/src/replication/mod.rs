@@ -3,7 +3,10 @@
 use std::io::SeekFrom;
 use std::sync::Arc;
 
+use futures::future::BoxFuture;
 use futures::future::FutureExt;
+use futures::stream::FuturesOrdered;
+use futures::stream::StreamExt;
 use serde::Deserialize;
 use serde::Serialize;
 use tokio::io::AsyncRead;
@@ -13,17 +16,23 @@ use tokio::io::AsyncSeekExt;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
 // use tokio::task::JoinHandle;
+use tokio::time::error::Elapsed;
 use tokio::time::interval;
 use tokio::time::timeout;
 use tokio::time::Duration;
+use tokio::time::Instant;
 use tokio::time::Interval;
 use tracing::Instrument;
 use tracing::Span;
 
+use crate::checksum::crc32;
+use crate::checksum::snapshot_checksum;
+use crate::compression::SnapshotCompression;
+use crate::compression::MAX_RAW_CHUNK_SIZE;
 use crate::config::Config;
-use crate::config::SnapshotPolicy;
 use crate::error::RaftResult;
 use crate::raft::AppendEntriesRequest;
+use crate::raft::AppendEntriesResponse;
 use crate::raft::Entry;
 use crate::raft::EntryPayload;
 use crate::raft::InstallSnapshotRequest;
@@ -42,25 +51,36 @@ pub struct ReplicationMetrics {
 }
 
 /// The public handle to a spawned replication stream.
-pub(crate) struct ReplicationStream<D: AppData> {
+pub(crate) struct ReplicationStream<D: AppData, NID: NodeId> {
     /// The spawn handle the `ReplicationCore` task.
     // pub handle: JoinHandle<()>,
     /// The channel used for communicating with the replication task.
-    pub repl_tx: mpsc::UnboundedSender<(RaftEvent<D>, Span)>,
+    repl_tx: mpsc::Sender<(RaftEvent<D, NID>, Span)>,
+    /// The capacity of `repl_tx`, see `Config::replication_queue_capacity`.
+    queue_capacity: u64,
+    /// The latest last log index sent to the replication task, or to be sent once it is resynced.
+    last_log_index: u64,
+    /// The latest commit index sent to the replication task, or to be sent once it is resynced.
+    commit_index: u64,
+    /// The latest index the log was purged up to, sent to the replication task, or to be sent once it is resynced.
+    log_purged_upto: u64,
+    /// Whether an event was dropped as the channel was full, so the replication task must be resynced.
+    is_out_of_sync: bool,
 }
 
-impl<D: AppData> ReplicationStream<D> {
+impl<D: AppData, NID: NodeId> ReplicationStream<D, NID> {
     /// Create a new replication stream for the target peer.
-    pub(crate) fn new<R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>>(
-        id: NodeId,
-        target: NodeId,
+    pub(crate) fn new<R: AppDataResponse, N: RaftNetwork<D, R, NID>, S: RaftStorage<D, R, NID>>(
+        id: NID,
+        target: NID,
         term: u64,
         config: Arc<Config>,
         last_log: LogId,
         commit_index: u64,
+        log_purged_upto: u64,
         network: Arc<N>,
         storage: Arc<S>,
-        replication_tx: mpsc::UnboundedSender<(ReplicaEvent<S::SnapshotData>, Span)>,
+        replication_tx: mpsc::Sender<(ReplicaEvent<S::SnapshotData, NID>, Span)>,
     ) -> Self {
         ReplicationCore::spawn(
             id,
@@ -69,33 +89,92 @@ impl<D: AppData> ReplicationStream<D> {
             config,
             last_log,
             commit_index,
+            log_purged_upto,
             network,
             storage,
             replication_tx,
         )
     }
+
+    /// Send an event to the replication task, without waiting for room in its channel.
+    ///
+    /// If the channel is full, the event is dropped, and the replication task is sent a
+    /// `RaftEvent::Resync` once there is room, from which it catches up from storage.
+    pub(crate) fn send(&mut self, event: RaftEvent<D, NID>) {
+        match &event {
+            RaftEvent::Replicate { entries, commit_index } => {
+                if let Some(last) = entries.last() {
+                    self.last_log_index = last.log_id.index;
+                }
+                self.commit_index = *commit_index;
+            }
+            RaftEvent::UpdateCommitIndex { commit_index } => self.commit_index = *commit_index,
+            RaftEvent::PurgeLog { upto } => self.log_purged_upto = std::cmp::max(self.log_purged_upto, *upto),
+            // Dropping the handle terminates the replication task as well, so it does not matter if this is dropped.
+            RaftEvent::Terminate => {
+                let _ = self.repl_tx.try_send((event, tracing::debug_span!("CH")));
+                return;
+            }
+            RaftEvent::Resync { .. } => {}
+        }
+
+        if self.is_out_of_sync {
+            self.resync();
+            return;
+        }
+        if let Err(mpsc::error::TrySendError::Full(_)) = self.repl_tx.try_send((event, tracing::debug_span!("CH"))) {
+            tracing::debug!("replication channel is full, resyncing once there is room");
+            self.is_out_of_sync = true;
+        }
+    }
+
+    /// Send the replication task the state of the leader it missed while its channel was full, if any.
+    pub(crate) fn resync(&mut self) {
+        if !self.is_out_of_sync {
+            return;
+        }
+        let event = RaftEvent::Resync {
+            last_log_index: self.last_log_index,
+            commit_index: self.commit_index,
+            log_purged_upto: self.log_purged_upto,
+        };
+        if self.repl_tx.try_send((event, tracing::debug_span!("CH"))).is_ok() {
+            self.is_out_of_sync = false;
+        }
+    }
+
+    /// The number of events waiting in the channel to the replication task.
+    pub(crate) fn queue_len(&self) -> u64 {
+        self.queue_capacity - self.repl_tx.capacity() as u64
+    }
 }
 
 /// A task responsible for sending replication events to a target follower in the Raft cluster.
 ///
-/// NOTE: we do not stack replication requests to targets because this could result in
-/// out-of-order delivery. We always buffer until we receive a success response, then send the
-/// next payload from the buffer.
-struct ReplicationCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
+/// NOTE: we only stack replication requests to targets at line rate, up to `Config::max_inflight`
+/// of them, as this could result in out-of-order delivery. Otherwise, we always buffer until we
+/// receive a success response, then send the next payload from the buffer.
+struct ReplicationCore<
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, R, NID>,
+    S: RaftStorage<D, R, NID>,
+    NID: NodeId,
+> {
     //////////////////////////////////////////////////////////////////////////
     // Static Fields /////////////////////////////////////////////////////////
     /// The ID of this Raft node.
-    id: NodeId,
+    id: NID,
     /// The ID of the target Raft node which replication events are to be sent to.
-    target: NodeId,
+    target: NID,
     /// The current term, which will never change during the lifetime of this task.
     term: u64,
 
     /// A channel for sending events to the Raft node.
-    raft_core_tx: mpsc::UnboundedSender<(ReplicaEvent<S::SnapshotData>, Span)>,
+    raft_core_tx: mpsc::Sender<(ReplicaEvent<S::SnapshotData, NID>, Span)>,
 
     /// A channel for receiving events from the Raft node.
-    repl_rx: mpsc::UnboundedReceiver<(RaftEvent<D>, Span)>,
+    repl_rx: mpsc::Receiver<(RaftEvent<D, NID>, Span)>,
 
     /// The `RaftNetwork` interface.
     network: Arc<N>,
@@ -107,6 +186,8 @@ struct ReplicationCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: Raf
     config: Arc<Config>,
     /// The configured max payload entries, simply as a usize.
     max_payload_entries: usize,
+    /// The configured max in-flight requests, simply as a usize.
+    max_inflight: usize,
     marker_r: std::marker::PhantomData<R>,
 
     //////////////////////////////////////////////////////////////////////////
@@ -118,6 +199,8 @@ struct ReplicationCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: Raf
     last_log_index: u64,
     /// The index of the highest log entry which is known to be committed in the cluster.
     commit_index: u64,
+    /// The index up to which the leader's log may have been purged, with `Raft::purge_log_upto` or by log compaction.
+    log_purged_upto: u64,
 
     /// The index of the next log to send.
     ///
@@ -148,13 +231,22 @@ struct ReplicationCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: Raf
     ///
     /// The buffered payload here will be expanded as more replication commands come in from the
     /// Raft node. Data from this buffer will flow into the `outbound_buffer` in chunks.
-    replication_buffer: Vec<Arc<Entry<D>>>,
+    replication_buffer: Vec<Arc<Entry<D, NID>>>,
     /// A buffer of data which is being sent to the follower.
     ///
     /// Data in this buffer comes directly from the `replication_buffer` in chunks, and will
     /// remain here until it is confirmed that the payload has been successfully received by the
     /// target node. This allows for retransmission of payloads in the face of transient errors.
-    outbound_buffer: Vec<OutboundEntry<D>>,
+    outbound_buffer: Vec<OutboundEntry<D, NID>>,
+    /// The AppendEntries RPCs sent at line rate whose responses are yet to be handled.
+    ///
+    /// Entries sent in these requests have been taken out of the buffers. Responses are handled in
+    /// the order the requests were sent.
+    inflight: FuturesOrdered<BoxFuture<'static, InflightAppendEntries>>,
+    /// The last log sent by the requests in `inflight`, which the next request follows.
+    last_sent: LogId,
+    /// Whether sending is paused until the next heartbeat, as a request in flight got no response.
+    is_paused: bool,
     /// The heartbeat interval for ensuring that heartbeats are always delivered in a timely fashion.
     heartbeat: Interval,
 
@@ -166,25 +258,30 @@ struct ReplicationCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: Raf
     install_snapshot_timeout: Duration,
 }
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> ReplicationCore<D, R, N, S> {
+impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D, R, NID>, S: RaftStorage<D, R, NID>, NID: NodeId>
+    ReplicationCore<D, R, N, S, NID>
+{
     /// Spawn a new replication task for the target node.
     pub(self) fn spawn(
-        id: NodeId,
-        target: NodeId,
+        id: NID,
+        target: NID,
         term: u64,
         config: Arc<Config>,
         last_log: LogId,
         commit_index: u64,
+        log_purged_upto: u64,
         network: Arc<N>,
         storage: Arc<S>,
-        raft_core_tx: mpsc::UnboundedSender<(ReplicaEvent<S::SnapshotData>, Span)>,
-    ) -> ReplicationStream<D> {
+        raft_core_tx: mpsc::Sender<(ReplicaEvent<S::SnapshotData, NID>, Span)>,
+    ) -> ReplicationStream<D, NID> {
         // other component to ReplicationStream
-        let (repl_tx, repl_rx) = mpsc::unbounded_channel();
+        let queue_capacity = config.replication_queue_capacity;
+        let (repl_tx, repl_rx) = mpsc::channel(queue_capacity as usize);
         let heartbeat_timeout = Duration::from_millis(config.heartbeat_interval);
         let install_snapshot_timeout = Duration::from_millis(config.install_snapshot_timeout);
 
         let max_payload_entries = config.max_payload_entries as usize;
+        let max_inflight = config.max_inflight as usize;
         let this = Self {
             id,
             target,
@@ -193,10 +290,12 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
             storage,
             config,
             max_payload_entries,
+            max_inflight,
             marker_r: std::marker::PhantomData,
             target_state: TargetReplState::Lagging,
             last_log_index: last_log.index,
             commit_index,
+            log_purged_upto,
             next_index: last_log.index + 1,
             matched: last_log,
             raft_core_tx,
@@ -206,6 +305,9 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
             install_snapshot_timeout,
             replication_buffer: Vec::new(),
             outbound_buffer: Vec::new(),
+            inflight: FuturesOrdered::new(),
+            last_sent: last_log,
+            is_paused: false,
         };
 
         let _handle = tokio::spawn(this.main().instrument(tracing::debug_span!("spawn")));
@@ -213,10 +315,15 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
         ReplicationStream {
             // handle,
             repl_tx,
+            queue_capacity,
+            last_log_index: last_log.index,
+            commit_index,
+            log_purged_upto,
+            is_out_of_sync: false,
         }
     }
 
-    #[tracing::instrument(level="trace", skip(self), fields(id=self.id, target=self.target, cluster=%self.config.cluster_name))]
+    #[tracing::instrument(level="trace", skip(self), fields(id=%self.id, target=%self.target, cluster=%self.config.cluster_name))]
     async fn main(mut self) {
         // Perform an initial heartbeat.
         self.send_append_entries().await;
@@ -224,7 +331,10 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
         // Proceed to the replication stream's inner loop.
         loop {
             match &self.target_state {
-                TargetReplState::LineRate => self.line_rate_loop().await,
+                TargetReplState::LineRate => {
+                    self.line_rate_loop().await;
+                    self.reset_inflight();
+                }
                 TargetReplState::Lagging => self.lagging_loop().await,
                 TargetReplState::Snapshotting => SnapshottingState::new(&mut self).run().await,
                 TargetReplState::Shutdown => return,
@@ -240,31 +350,34 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
     async fn send_append_entries(&mut self) {
         // Attempt to fill the send buffer from the replication buffer.
         if self.outbound_buffer.is_empty() {
-            let repl_len = self.replication_buffer.len();
-            if repl_len > 0 {
-                let chunk_size = if repl_len < self.max_payload_entries {
-                    repl_len
-                } else {
-                    self.max_payload_entries
-                };
-                self.outbound_buffer.extend(self.replication_buffer.drain(..chunk_size).map(OutboundEntry::Arc));
-            }
+            let chunk_size = self.payload_len(
+                self.replication_buffer.iter().map(|entry| entry.as_ref()),
+                self.max_payload_entries,
+            );
+            self.outbound_buffer.extend(self.replication_buffer.drain(..chunk_size).map(OutboundEntry::Arc));
         }
 
+        // The outbound buffer is already limited in count, but may exceed the byte limit.
+        let send_len = self.payload_len(
+            self.outbound_buffer.iter().map(|entry| entry.as_ref()),
+            self.outbound_buffer.len(),
+        );
+
         // Build the heartbeat frame to be sent to the follower.
         let payload = AppendEntriesRequest {
             term: self.term,
-            leader_id: self.id,
+            leader_id: self.id.clone(),
             prev_log_id: self.matched,
             leader_commit: self.commit_index,
-            entries: self.outbound_buffer.iter().map(|entry| entry.as_ref().clone()).collect(),
+            entries: self.outbound_buffer[..send_len].iter().map(|entry| entry.as_ref().clone()).collect(),
         };
 
         // Send the payload.
         tracing::debug!("start sending append_entries, timeout: {:?}", self.heartbeat_timeout);
+        let sent_at = Instant::now();
         let res = match timeout(
             self.heartbeat_timeout,
-            self.network.send_append_entries(self.target, payload),
+            self.network.send_append_entries(self.target.clone(), payload),
         )
         .await
         {
@@ -280,28 +393,166 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                 return;
             }
         };
-        let last_log_id = self.outbound_buffer.last().map(|last| last.as_ref().log_id);
+        let last_log_id = self.outbound_buffer[..send_len].last().map(|last| last.as_ref().log_id);
 
         // Once we've successfully sent a payload of entries, don't send them again.
+        self.outbound_buffer.drain(..send_len);
+
+        let matched = self.matched;
+        self.handle_append_entries_response(res, sent_at, matched, last_log_id).await;
+    }
+
+    /// Send an AppendEntries RPC to the target without waiting for its response, which is handled
+    /// by `handle_inflight_response` once it arrives.
+    ///
+    /// The request carries the next payload of buffered entries, if any, following those already
+    /// in flight.
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn send_append_entries_pipelined(&mut self) {
+        let entries: Vec<Entry<D, NID>> = if !self.outbound_buffer.is_empty() {
+            let chunk_size = self.payload_len(
+                self.outbound_buffer.iter().map(|entry| entry.as_ref()),
+                self.max_payload_entries,
+            );
+            self.outbound_buffer.drain(..chunk_size).map(|entry| entry.as_ref().clone()).collect()
+        } else {
+            let chunk_size = self.payload_len(
+                self.replication_buffer.iter().map(|entry| entry.as_ref()),
+                self.max_payload_entries,
+            );
+            self.replication_buffer.drain(..chunk_size).map(|entry| entry.as_ref().clone()).collect()
+        };
+
+        let prev_log_id = if self.inflight.is_empty() {
+            self.matched
+        } else {
+            self.last_sent
+        };
+        let last_log_id = entries.last().map(|entry| entry.log_id);
+        if let Some(log_id) = last_log_id {
+            self.next_index = log_id.index + 1;
+        }
+        self.last_sent = last_log_id.unwrap_or(prev_log_id);
+
+        let payload = AppendEntriesRequest {
+            term: self.term,
+            leader_id: self.id.clone(),
+            prev_log_id,
+            leader_commit: self.commit_index,
+            entries,
+        };
+
+        tracing::debug!(
+            "start sending append_entries, in flight: {}, timeout: {:?}",
+            self.inflight.len(),
+            self.heartbeat_timeout
+        );
+        let network = self.network.clone();
+        let target = self.target.clone();
+        let heartbeat_timeout = self.heartbeat_timeout;
+        let matched = self.matched;
+        let sent_at = Instant::now();
+        self.inflight.push_back(
+            async move {
+                let res = timeout(heartbeat_timeout, network.send_append_entries(target, payload)).await;
+                InflightAppendEntries {
+                    sent_at,
+                    matched,
+                    last_log_id,
+                    res,
+                }
+            }
+            .boxed(),
+        );
+    }
+
+    /// The number of leading `entries` to send in the next payload.
+    ///
+    /// This is at most `max_entries`, and the entries add up to at most `Config::max_payload_bytes`,
+    /// except that the first entry is always included, so that an entry larger than the limit
+    /// still makes progress.
+    fn payload_len<'a>(&self, entries: impl Iterator<Item = &'a Entry<D, NID>>, max_entries: usize) -> usize {
+        let max_bytes = match self.config.max_payload_bytes {
+            Some(max_bytes) => max_bytes,
+            None => return entries.take(max_entries).count(),
+        };
+
+        let mut len = 0;
+        let mut size = 0;
+        for entry in entries.take(max_entries) {
+            size += entry.payload_size();
+            if len > 0 && size > max_bytes {
+                break;
+            }
+            len += 1;
+        }
+        len
+    }
+
+    /// Handle the outcome of an AppendEntries RPC sent by `send_append_entries_pipelined`.
+    #[tracing::instrument(level = "trace", skip(self, inflight))]
+    async fn handle_inflight_response(&mut self, inflight: InflightAppendEntries) {
+        let res = match inflight.res {
+            Ok(Ok(res)) => res,
+            Ok(Err(err)) => {
+                tracing::warn!(error=%err, "error sending AppendEntries RPC to target");
+                self.reset_inflight();
+                self.is_paused = true;
+                return;
+            }
+            Err(err) => {
+                tracing::warn!(error=%err, "timeout while sending AppendEntries RPC to target");
+                self.reset_inflight();
+                self.is_paused = true;
+                return;
+            }
+        };
+
+        self.handle_append_entries_response(res, inflight.sent_at, inflight.matched, inflight.last_log_id)
+            .await;
+    }
+
+    /// Drop the requests in flight, so that replication resumes from the last log the target is
+    /// known to have.
+    fn reset_inflight(&mut self) {
+        self.inflight = FuturesOrdered::new();
+        self.replication_buffer.clear();
         self.outbound_buffer.clear();
+        self.next_index = self.matched.index + 1;
+        self.last_sent = self.matched;
+    }
 
+    /// Handle the response to an AppendEntries RPC sent at `sent_at`, when the target was known to
+    /// have replicated up to `matched`, and carrying entries up to `last_log_id`, if any.
+    #[tracing::instrument(level = "trace", skip(self, res))]
+    async fn handle_append_entries_response(
+        &mut self,
+        res: AppendEntriesResponse,
+        sent_at: Instant,
+        matched: LogId,
+        last_log_id: Option<LogId>,
+    ) {
         tracing::debug!("append_entries last: {:?}", last_log_id);
 
+        // Any response in this term shows that the target still accepts this node as its leader.
+        if res.term == self.term {
+            self.report_acked(sent_at).await;
+        }
+
         // Handle success conditions.
         if res.success {
             tracing::debug!("append entries succeeded to {:?}", last_log_id);
 
             // If this was a proper replication event (last index & term were provided), then update state.
             if let Some(log_id) = last_log_id {
-                self.next_index = log_id.index + 1; // This should always be the next expected index.
+                // Requests sent after this one may already have moved `next_index` further.
+                self.next_index = std::cmp::max(self.next_index, log_id.index + 1);
                 self.matched = log_id;
-                let _ = self.raft_core_tx.send((
-                    ReplicaEvent::UpdateMatchIndex {
-                        target: self.target,
-                        matched: log_id,
-                    },
-                    tracing::debug_span!("CH"),
-                ));
+                self.send_to_core(ReplicaEvent::UpdateMatchIndex {
+                    target: self.target.clone(),
+                    matched: log_id,
+                })
+                .await;
 
                 // If running at line rate, and our buffered outbound requests have accumulated too
                 // much, we need to purge and transition to a lagging state. The target is not able to
@@ -321,17 +572,23 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
         // Replication was not successful, if a newer term has been returned, revert to follower.
         if res.term > self.term {
             tracing::debug!({ res.term }, "append entries failed, reverting to follower");
-            let _ = self.raft_core_tx.send((
-                ReplicaEvent::RevertToFollower {
-                    target: self.target,
-                    term: res.term,
-                },
-                tracing::debug_span!("CH"),
-            ));
+            self.send_to_core(ReplicaEvent::RevertToFollower {
+                target: self.target.clone(),
+                term: res.term,
+            })
+            .await;
             self.target_state = TargetReplState::Shutdown;
             return;
         }
 
+        // Requests in flight after the rejected one can not succeed either. If the target has
+        // acknowledged more entries since this request was sent, the request overtook an earlier one
+        // on its way to the target, so there is no conflict to handle: just send again from there.
+        self.reset_inflight();
+        if self.matched.index > matched.index {
+            return;
+        }
+
         // Replication was not successful, handle conflict optimization record, else decrement `next_index`.
         if let Some(conflict) = res.conflict_opt {
             tracing::debug!(?conflict, res.term, "append entries failed, handling conflict opt");
@@ -339,6 +596,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
             // If the returned conflict opt index is greater than last_log_index, then this is a
             // logical error, and no action should be taken. This represents a replication failure.
             if conflict.log_id.index > self.last_log_index {
+                self.is_paused = true;
                 return;
             }
             self.next_index = conflict.log_id.index + 1;
@@ -348,13 +606,11 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
             // it will never exist. So instead, we just return, and accept the conflict data.
             if conflict.log_id.index == 0 {
                 self.target_state = TargetReplState::Lagging;
-                let _ = self.raft_core_tx.send((
-                    ReplicaEvent::UpdateMatchIndex {
-                        target: self.target,
-                        matched: self.matched,
-                    },
-                    tracing::debug_span!("CH"),
-                ));
+                self.send_to_core(ReplicaEvent::UpdateMatchIndex {
+                    target: self.target.clone(),
+                    matched: self.matched,
+                })
+                .await;
                 return;
             }
 
@@ -364,7 +620,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                 Ok(x) => x,
                 Err(err) => {
                     tracing::error!(error=?err, "error fetching log entry due to returned AppendEntries RPC conflict_opt");
-                    let _ = self.raft_core_tx.send((ReplicaEvent::Shutdown, tracing::debug_span!("CH")));
+                    self.send_to_core(ReplicaEvent::Shutdown).await;
                     self.target_state = TargetReplState::Shutdown;
                     return;
                 }
@@ -379,70 +635,85 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                     // This condition would only ever be reached if the log has been removed due to
                     // log compaction (barring critical storage failure), so transition to snapshotting.
                     self.target_state = TargetReplState::Snapshotting;
-                    let _ = self.raft_core_tx.send((
-                        ReplicaEvent::UpdateMatchIndex {
-                            target: self.target,
-                            matched: self.matched,
-                        },
-                        tracing::debug_span!("CH"),
-                    ));
+                    self.send_to_core(ReplicaEvent::UpdateMatchIndex {
+                        target: self.target.clone(),
+                        matched: self.matched,
+                    })
+                    .await;
                     return;
                 }
             };
 
             // Check snapshot policy and handle conflict as needed.
-            let _ = self.raft_core_tx.send((
-                ReplicaEvent::UpdateMatchIndex {
-                    target: self.target,
-                    matched: self.matched,
-                },
-                tracing::debug_span!("CH"),
-            ));
-            match &self.config.snapshot_policy {
-                SnapshotPolicy::LogsSinceLast(threshold) => {
-                    let diff = self.last_log_index - conflict.log_id.index; // NOTE WELL: underflow is guarded against above.
-                    if &diff >= threshold {
-                        // Follower is far behind and needs to receive an InstallSnapshot RPC.
-                        self.target_state = TargetReplState::Snapshotting;
-                        return;
-                    }
-                    // Follower is behind, but not too far behind to receive an InstallSnapshot RPC.
-                    self.target_state = TargetReplState::Lagging;
+            self.send_to_core(ReplicaEvent::UpdateMatchIndex {
+                target: self.target.clone(),
+                matched: self.matched,
+            })
+            .await;
+            // Follower lacks entries which may have been purged, or is far behind, and needs to receive an
+            // InstallSnapshot RPC, whatever the snapshot policy.
+            if self.next_index <= self.log_purged_upto {
+                self.target_state = TargetReplState::Snapshotting;
+                return;
+            }
+            if let Some(threshold) = self.config.snapshot_policy.max_lag_entries() {
+                let diff = self.last_log_index - conflict.log_id.index; // NOTE WELL: underflow is guarded against above.
+                if diff >= threshold {
+                    self.target_state = TargetReplState::Snapshotting;
                     return;
                 }
             }
+            // Follower is behind, but not too far behind to receive an InstallSnapshot RPC.
+            self.target_state = TargetReplState::Lagging;
         }
     }
 
+    /// Send an event to the Raft node, waiting for room in the channel if it is full.
+    async fn send_to_core(&mut self, event: ReplicaEvent<S::SnapshotData, NID>) {
+        let _ = self.raft_core_tx.send((event, tracing::debug_span!("CH"))).await;
+    }
+
+    /// Report to the Raft node that the target responded to a request sent at `sent_at`.
+    async fn report_acked(&mut self, sent_at: Instant) {
+        self.send_to_core(ReplicaEvent::UpdateAcked {
+            target: self.target.clone(),
+            acked_at: sent_at,
+        })
+        .await;
+    }
+
     /// Perform a check to see if this replication stream is lagging behind far enough that a
     /// snapshot is warranted.
     #[tracing::instrument(level = "trace", skip(self))]
     pub(self) fn needs_snapshot(&self) -> bool {
-        match &self.config.snapshot_policy {
-            SnapshotPolicy::LogsSinceLast(threshold) => {
-                let needs_snap =
-                    self.commit_index.checked_sub(self.matched.index).map(|diff| diff >= *threshold).unwrap_or(false);
-                if needs_snap {
-                    tracing::trace!("snapshot needed");
-                    true
-                } else {
-                    tracing::trace!("snapshot not needed");
-                    false
-                }
-            }
+        // The entries the target lacks may have been purged, e.g. as they are covered by the leader's snapshot. This
+        // holds whatever the snapshot policy.
+        if self.next_index <= self.log_purged_upto {
+            tracing::trace!("snapshot needed, log purged upto: {}", self.log_purged_upto);
+            return true;
+        }
+
+        // Without a threshold, a snapshot is only sent once the entries the target lacks have been purged.
+        let threshold = match self.config.snapshot_policy.max_lag_entries() {
+            Some(threshold) => threshold,
+            None => return false,
+        };
+        let needs_snap =
+            self.commit_index.checked_sub(self.matched.index).map(|diff| diff >= threshold).unwrap_or(false);
+        if needs_snap {
+            tracing::trace!("snapshot needed");
+            true
+        } else {
+            tracing::trace!("snapshot not needed");
+            false
         }
     }
 
     /// Fully drain the channel coming in from the Raft node.
-    pub(self) fn drain_raft_rx(&mut self, first: RaftEvent<D>, span: Span) {
+    pub(self) fn drain_raft_rx(&mut self, first: RaftEvent<D, NID>, span: Span) {
         let mut event_opt = Some((first, span));
         let mut iters = 0;
         loop {
-            // Just ensure we don't get stuck draining a REALLY hot replication feed.
-            if iters > self.config.max_payload_entries {
-                return;
-            }
-
             // Unpack the event opt, else return if we don't have one to process.
             let (event, span) = match event_opt.take() {
                 Some(event) => event,
@@ -457,39 +728,63 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                     self.commit_index = commit_index;
                 }
 
-                RaftEvent::Replicate { entry, commit_index } => {
+                RaftEvent::PurgeLog { upto } => {
+                    self.log_purged_upto = std::cmp::max(self.log_purged_upto, upto);
+                }
+
+                RaftEvent::Replicate { entries, commit_index } => {
                     self.commit_index = commit_index;
-                    self.last_log_index = entry.log_id.index;
+                    if let Some(last) = entries.last() {
+                        self.last_log_index = last.log_id.index;
+                    }
                     if self.target_state == TargetReplState::LineRate {
-                        self.replication_buffer.push(entry);
+                        self.replication_buffer.extend(entries);
                     }
                 }
 
+                RaftEvent::Resync {
+                    last_log_index,
+                    commit_index,
+                    log_purged_upto,
+                } => {
+                    self.commit_index = commit_index;
+                    self.log_purged_upto = std::cmp::max(self.log_purged_upto, log_purged_upto);
+                    self.last_log_index = std::cmp::max(self.last_log_index, last_log_index);
+                    // Entries were missed, so the buffer has a gap; they are fetched from storage instead.
+                    self.replication_buffer.clear();
+                }
+
                 RaftEvent::Terminate => {
                     self.target_state = TargetReplState::Shutdown;
                     return;
                 }
             }
 
+            // Just ensure we don't get stuck draining a REALLY hot replication feed. This is checked
+            // before receiving the next event, which would otherwise be lost.
+            iters += 1;
+            if iters > self.config.max_payload_entries {
+                return;
+            }
+
             // Attempt to unpack the next event for the next loop iteration.
             if let Some(event_span) = self.repl_rx.recv().now_or_never() {
                 event_opt = event_span;
             }
-            iters += 1;
         }
     }
 }
 
 /// A type which wraps two possible forms of an outbound entry for replication.
-enum OutboundEntry<D: AppData> {
+enum OutboundEntry<D: AppData, NID: NodeId> {
     /// An entry owned by an Arc, hot off the replication stream from the Raft leader.
-    Arc(Arc<Entry<D>>),
+    Arc(Arc<Entry<D, NID>>),
     /// An entry which was fetched directly from storage.
-    Raw(Entry<D>),
+    Raw(Entry<D, NID>),
 }
 
-impl<D: AppData> AsRef<Entry<D>> for OutboundEntry<D> {
-    fn as_ref(&self) -> &Entry<D> {
+impl<D: AppData, NID: NodeId> AsRef<Entry<D, NID>> for OutboundEntry<D, NID> {
+    fn as_ref(&self) -> &Entry<D, NID> {
         match self {
             Self::Arc(inner) => inner.as_ref(),
             Self::Raw(inner) => inner,
@@ -497,6 +792,18 @@ impl<D: AppData> AsRef<Entry<D>> for OutboundEntry<D> {
     }
 }
 
+/// The outcome of an AppendEntries RPC sent at line rate, along with what is needed to handle it.
+struct InflightAppendEntries {
+    /// The time at which the request was sent.
+    sent_at: Instant,
+    /// The last log the target was known to have when the request was sent.
+    matched: LogId,
+    /// The last log sent in the request, if it carried any entries.
+    last_log_id: Option<LogId>,
+    /// The response, or why none was received.
+    res: Result<anyhow::Result<AppendEntriesResponse>, Elapsed>,
+}
+
 //////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// The state of the replication stream.
@@ -513,13 +820,13 @@ enum TargetReplState {
 }
 
 /// An event from the Raft node.
-pub(crate) enum RaftEvent<D: AppData> {
+pub(crate) enum RaftEvent<D: AppData, NID: NodeId> {
     Replicate {
-        /// The new entry which needs to be replicated.
+        /// The new entries which need to be replicated, with consecutive indexes.
         ///
-        /// This entry will always be the most recent entry to have been appended to the log, so its
-        /// index is the new last_log_index value.
-        entry: Arc<Entry<D>>,
+        /// These entries will always be the most recent entries to have been appended to the log, so
+        /// the index of the last one is the new last_log_index value.
+        entries: Vec<Arc<Entry<D, NID>>>,
         /// The index of the highest log entry which is known to be committed in the cluster.
         commit_index: u64,
     },
@@ -528,17 +835,34 @@ pub(crate) enum RaftEvent<D: AppData> {
         /// The index of the highest log entry which is known to be committed in the cluster.
         commit_index: u64,
     },
+    /// A message from Raft indicating that the log was purged up to the given index.
+    PurgeLog {
+        upto: u64,
+    },
+    /// A message from Raft carrying the state of the leader which was not sent while the channel was full.
+    ///
+    /// Entries sent since then are fetched from storage.
+    Resync {
+        /// The index of the last entry appended to the leader's log.
+        last_log_index: u64,
+        /// The index of the highest log entry which is known to be committed in the cluster.
+        commit_index: u64,
+        /// The index up to which the leader's log may have been purged.
+        log_purged_upto: u64,
+    },
     Terminate,
 }
 
 /// An event coming from a replication stream.
-pub(crate) enum ReplicaEvent<S>
-where S: AsyncRead + AsyncSeek + Send + Unpin + 'static
+pub(crate) enum ReplicaEvent<S, NID>
+where
+    S: AsyncRead + AsyncSeek + Send + Unpin + 'static,
+    NID: NodeId,
 {
     /// An event representing an update to the replication rate of a replication stream.
     RateUpdate {
         /// The ID of the Raft node to which this event relates.
-        target: NodeId,
+        target: NID,
         /// A flag indicating if the corresponding target node is replicating at line rate.
         ///
         /// When replicating at line rate, the replication stream will receive log entries to
@@ -549,29 +873,39 @@ where S: AsyncRead + AsyncSeek + Send + Unpin + 'static
     /// An event from a replication stream which updates the target node's match index.
     UpdateMatchIndex {
         /// The ID of the target node for which the match index is to be updated.
-        target: NodeId,
+        target: NID,
         /// The log of the most recent log known to have been successfully replicated on the target.
         matched: LogId,
     },
+    /// An event from a replication stream reporting that the target has responded in the leader's term.
+    UpdateAcked {
+        /// The ID of the target node which responded.
+        target: NID,
+        /// The time at which the acknowledged request was sent.
+        ///
+        /// The target is only known to have accepted this node as leader as of this time, not as of
+        /// the time the response arrived.
+        acked_at: Instant,
+    },
     /// An event indicating that the Raft node needs to revert to follower state.
     RevertToFollower {
         /// The ID of the target node from which the new term was observed.
-        target: NodeId,
+        target: NID,
         /// The new term observed.
         term: u64,
     },
     /// An event from a replication stream requesting snapshot info.
     NeedsSnapshot {
         /// The ID of the target node from which the event was sent.
-        target: NodeId,
+        target: NID,
         /// The response channel for delivering the snapshot data.
-        tx: oneshot::Sender<Snapshot<S>>,
+        tx: oneshot::Sender<Snapshot<S, NID>>,
     },
     /// Some critical error has taken place, and Raft needs to shutdown.
     Shutdown,
 }
 
-impl<S: AsyncRead + AsyncSeek + Send + Unpin + 'static> MessageSummary for ReplicaEvent<S> {
+impl<S: AsyncRead + AsyncSeek + Send + Unpin + 'static, NID: NodeId> MessageSummary for ReplicaEvent<S, NID> {
     fn summary(&self) -> String {
         match self {
             ReplicaEvent::RateUpdate {
@@ -586,6 +920,12 @@ impl<S: AsyncRead + AsyncSeek + Send + Unpin + 'static> MessageSummary for Repli
             } => {
                 format!("UpdateMatchIndex: target: {}, matched: {}", target, matched)
             }
+            ReplicaEvent::UpdateAcked {
+                ref target,
+                ref acked_at,
+            } => {
+                format!("UpdateAcked: target: {}, acked_at: {:?}", target, acked_at)
+            }
             ReplicaEvent::RevertToFollower { ref target, ref term } => {
                 format!("RevertToFollower: target: {}, term: {}", target, term)
             }
@@ -597,14 +937,16 @@ impl<S: AsyncRead + AsyncSeek + Send + Unpin + 'static> MessageSummary for Repli
     }
 }
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> ReplicationCore<D, R, N, S> {
+impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D, R, NID>, S: RaftStorage<D, R, NID>, NID: NodeId>
+    ReplicationCore<D, R, N, S, NID>
+{
     #[tracing::instrument(level = "trace", skip(self), fields(state = "line-rate"))]
     pub async fn line_rate_loop(&mut self) {
         let event = ReplicaEvent::RateUpdate {
-            target: self.target,
+            target: self.target.clone(),
             is_line_rate: true,
         };
-        let _ = self.raft_core_tx.send((event, tracing::debug_span!("CH")));
+        self.send_to_core(event).await;
         loop {
             if self.target_state != TargetReplState::LineRate {
                 return;
@@ -636,16 +978,32 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                 if self.target_state != TargetReplState::LineRate {
                     return;
                 }
+            }
 
-                self.send_append_entries().await;
-                continue;
+            // Keep as many payloads in flight as allowed, unless waiting for the next heartbeat
+            // after a request got no response.
+            while !self.is_paused
+                && self.inflight.len() < self.max_inflight
+                && (!self.outbound_buffer.is_empty() || !self.replication_buffer.is_empty())
+            {
+                self.send_append_entries_pipelined();
             }
 
             let span = tracing::debug_span!("CHrx:LineRate");
             let _en = span.enter();
 
             tokio::select! {
-                _ = self.heartbeat.tick() => self.send_append_entries().await,
+                _ = self.heartbeat.tick() => {
+                    // Requests in flight serve as heartbeats as well.
+                    self.is_paused = false;
+                    if self.inflight.len() < self.max_inflight {
+                        self.send_append_entries_pipelined();
+                    }
+                }
+
+                Some(inflight) = self.inflight.next(), if !self.inflight.is_empty() => {
+                    self.handle_inflight_response(inflight).await;
+                }
 
                 event_span = self.repl_rx.recv() => {
                     match event_span {
@@ -660,11 +1018,16 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
     /// Ensure there are no gaps in the outbound buffer due to transition from lagging.
     #[tracing::instrument(level = "trace", skip(self))]
     async fn frontload_outbound_buffer(&mut self, start: u64, stop: u64) {
+        if start <= self.log_purged_upto {
+            self.target_state = TargetReplState::Snapshotting;
+            return;
+        }
+
         let entries = match self.storage.get_log_entries(start..stop).await {
             Ok(entries) => entries,
             Err(err) => {
                 tracing::error!(error=%err, "error while frontloading outbound buffer");
-                let _ = self.raft_core_tx.send((ReplicaEvent::Shutdown, tracing::debug_span!("CH")));
+                self.send_to_core(ReplicaEvent::Shutdown).await;
                 return;
             }
         };
@@ -685,10 +1048,10 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
     #[tracing::instrument(level = "trace", skip(self), fields(state = "lagging"))]
     pub async fn lagging_loop(&mut self) {
         let event = ReplicaEvent::RateUpdate {
-            target: self.target,
+            target: self.target.clone(),
             is_line_rate: false,
         };
-        let _ = self.raft_core_tx.send((event, tracing::debug_span!("CH")));
+        self.send_to_core(event).await;
         self.replication_buffer.clear();
         self.outbound_buffer.clear();
         loop {
@@ -757,7 +1120,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                 Ok(entries) => entries,
                 Err(err) => {
                     tracing::error!(error=%err, "error fetching logs from storage");
-                    let _ = self.raft_core_tx.send((ReplicaEvent::Shutdown, tracing::debug_span!("CH")));
+                    self.send_to_core(ReplicaEvent::Shutdown).await;
                     self.target_state = TargetReplState::Shutdown;
                     return;
                 }
@@ -778,16 +1141,25 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
 //////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// Snapshotting specific state.
-struct SnapshottingState<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
+struct SnapshottingState<
+    'a,
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, R, NID>,
+    S: RaftStorage<D, R, NID>,
+    NID: NodeId,
+> {
     /// An exclusive handle to the replication core.
-    replication_core: &'a mut ReplicationCore<D, R, N, S>,
-    snapshot: Option<Snapshot<S::SnapshotData>>,
-    snapshot_fetch_rx: Option<oneshot::Receiver<Snapshot<S::SnapshotData>>>,
+    replication_core: &'a mut ReplicationCore<D, R, N, S, NID>,
+    snapshot: Option<Snapshot<S::SnapshotData, NID>>,
+    snapshot_fetch_rx: Option<oneshot::Receiver<Snapshot<S::SnapshotData, NID>>>,
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> SnapshottingState<'a, D, R, N, S> {
+impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D, R, NID>, S: RaftStorage<D, R, NID>, NID: NodeId>
+    SnapshottingState<'a, D, R, N, S, NID>
+{
     /// Create a new instance.
-    pub fn new(replication_core: &'a mut ReplicationCore<D, R, N, S>) -> Self {
+    pub fn new(replication_core: &'a mut ReplicationCore<D, R, N, S, NID>) -> Self {
         Self {
             replication_core,
             snapshot: None,
@@ -798,10 +1170,10 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     #[tracing::instrument(level = "trace", skip(self), fields(state = "snapshotting"))]
     pub async fn run(mut self) {
         let event = ReplicaEvent::RateUpdate {
-            target: self.replication_core.target,
+            target: self.replication_core.target.clone(),
             is_line_rate: false,
         };
-        let _ = self.replication_core.raft_core_tx.send((event, tracing::debug_span!("CH")));
+        self.replication_core.send_to_core(event).await;
         self.replication_core.replication_buffer.clear();
         self.replication_core.outbound_buffer.clear();
 
@@ -813,13 +1185,12 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             // If we don't have any of the components we need, fetch the current snapshot.
             if self.snapshot.is_none() && self.snapshot_fetch_rx.is_none() {
                 let (tx, rx) = oneshot::channel();
-                let _ = self.replication_core.raft_core_tx.send((
-                    ReplicaEvent::NeedsSnapshot {
-                        target: self.replication_core.target,
+                self.replication_core
+                    .send_to_core(ReplicaEvent::NeedsSnapshot {
+                        target: self.replication_core.target.clone(),
                         tx,
-                    },
-                    tracing::debug_span!("CH"),
-                ));
+                    })
+                    .await;
                 self.snapshot_fetch_rx = Some(rx);
             }
 
@@ -845,7 +1216,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     /// If an error comes up during processing, this routine should simple be called again after
     /// issuing a new request to the storage layer.
     #[tracing::instrument(level = "trace", skip(self, rx))]
-    async fn wait_for_snapshot(&mut self, mut rx: oneshot::Receiver<Snapshot<S::SnapshotData>>) {
+    async fn wait_for_snapshot(&mut self, mut rx: oneshot::Receiver<Snapshot<S::SnapshotData, NID>>) {
         loop {
             let span = tracing::debug_span!("FFF:wait_for_snapshot");
             let _ent = span.enter();
@@ -878,27 +1249,45 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     }
 
     #[tracing::instrument(level = "trace", skip(self, snapshot))]
-    async fn stream_snapshot(&mut self, mut snapshot: Snapshot<S::SnapshotData>) -> RaftResult<()> {
+    async fn stream_snapshot(&mut self, mut snapshot: Snapshot<S::SnapshotData, NID>) -> RaftResult<()> {
+        if snapshot.meta.checksum.is_none() {
+            snapshot.meta.checksum = Some(snapshot_checksum(snapshot.snapshot.as_mut()).await?);
+        }
         let end = snapshot.snapshot.seek(SeekFrom::End(0)).await?;
 
         let mut offset = 0;
 
         self.replication_core.next_index = snapshot.meta.last_log_id.index + 1;
         self.replication_core.matched = snapshot.meta.last_log_id;
-        let mut buf = Vec::with_capacity(self.replication_core.config.snapshot_max_chunk_size as usize);
+        let chunk_size = self.replication_core.config.snapshot_chunk_size();
+        let mut buf = Vec::with_capacity(chunk_size as usize);
+
+        // Chunks are compressed with the configured codec once the target has named it as supported, so the first chunk
+        // is always sent uncompressed. Chunks too large for the target to decompress are never compressed.
+        let mut compression: Option<SnapshotCompression> = None;
+        let mut wanted_compression = self.replication_core.config.snapshot_compression;
+        if chunk_size > MAX_RAW_CHUNK_SIZE {
+            wanted_compression = None;
+        }
 
         loop {
             // Build the RPC.
             snapshot.snapshot.seek(SeekFrom::Start(offset)).await?;
-            let n_read = snapshot.snapshot.read_buf(&mut buf).await?;
+            let n_read = (&mut snapshot.snapshot).take(chunk_size).read_buf(&mut buf).await?;
 
             let done = (offset + n_read as u64) == end; // If bytes read == 0, then we're done.
+            let data = match compression {
+                Some(codec) => codec.compress(&buf[..n_read])?,
+                None => Vec::from(&buf[..n_read]),
+            };
             let req = InstallSnapshotRequest {
                 term: self.replication_core.term,
-                leader_id: self.replication_core.id,
+                leader_id: self.replication_core.id.clone(),
                 meta: snapshot.meta.clone(),
                 offset,
-                data: Vec::from(&buf[..n_read]),
+                data,
+                checksum: Some(crc32(&buf[..n_read])),
+                compression,
                 done,
             };
             buf.clear();
@@ -912,9 +1301,10 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                 "sending snapshot chunk"
             );
 
+            let sent_at = Instant::now();
             let res = timeout(
                 self.replication_core.install_snapshot_timeout,
-                self.replication_core.network.send_install_snapshot(self.replication_core.target, req),
+                self.replication_core.network.send_install_snapshot(self.replication_core.target.clone(), req),
             )
             .await;
 
@@ -934,25 +1324,52 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
             // Handle response conditions.
             if res.term > self.replication_core.term {
-                let _ = self.replication_core.raft_core_tx.send((
-                    ReplicaEvent::RevertToFollower {
-                        target: self.replication_core.target,
+                self.replication_core
+                    .send_to_core(ReplicaEvent::RevertToFollower {
+                        target: self.replication_core.target.clone(),
                         term: res.term,
-                    },
-                    tracing::debug_span!("CH"),
-                ));
+                    })
+                    .await;
                 self.replication_core.target_state = TargetReplState::Shutdown;
                 return Ok(());
             }
+            self.replication_core.report_acked(sent_at).await;
+
+            // The target did not take the chunk, as it does not support its codec or failed to decompress it, so send
+            // it again uncompressed.
+            if compression.is_some() && res.compression != compression {
+                tracing::info!(
+                    ?compression,
+                    "target did not decompress snapshot chunk, sending it uncompressed"
+                );
+                compression = None;
+                wanted_compression = None;
+                continue;
+            }
+
+            // Compress the following chunks once the target names the configured codec as supported.
+            if let Some(codec) = wanted_compression.filter(|codec| res.supported_compression.contains(codec)) {
+                compression = Some(codec);
+            }
+
+            // The target tells how much of the snapshot it has, which is ahead of this chunk when it resumes an
+            // earlier transfer of the same snapshot.
+            let next_offset = match res.offset {
+                Some(next_offset) => std::cmp::min(next_offset, end),
+                None => offset + n_read as u64,
+            };
 
             // If we just sent the final chunk of the snapshot, then transition to lagging state.
-            if done {
+            if done && next_offset == end {
                 self.replication_core.target_state = TargetReplState::Lagging;
                 return Ok(());
             }
 
             // Everything is good, so update offset for sending the next chunk.
-            offset += n_read as u64;
+            if next_offset != offset + n_read as u64 {
+                tracing::debug!(offset, next_offset, "target resumes snapshot at offset");
+            }
+            offset = next_offset;
 
             // Check raft channel to ensure we are staying up-to-date, then loop.
             if let Some(Some((event, span))) = self.replication_core.repl_rx.recv().now_or_never() {
@@ -0,0 +1,335 @@
+//! Client sessions, for applying each client write to the state machine exactly once.
+//!
+//! A client which retries a write, e.g. after a timeout or a `ClientWriteError::ForwardToLeader`,
+//! may get it appended to the log more than once. Writes submitted with a `ClientSession` carry
+//! the ID of their client and a sequence number, and the leader stamps them with the time at which
+//! it appended them.
+//!
+//! Raft keeps a `ClientSessions` table of the last write applied in each session, and consults it
+//! before handing entries to `RaftStorage::apply_to_state_machine`. A duplicate of a write which
+//! was applied already is handed to storage as a blank entry instead, and its client gets the
+//! response cached when the write was first applied. The table is carried in `SnapshotMeta`, so a
+//! node restored from a snapshot still knows which writes were applied; `RaftStorage` implementations
+//! fill it in from the `SessionHistory` given to `do_log_compaction`.
+
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::config::DEFAULT_CLIENT_SESSION_TTL;
+
+/// The session of a client write.
+///
+/// A client numbers its writes with increasing sequence numbers, and waits for the response to
+/// one write, retrying it as often as needed, before submitting the next.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientSession {
+    /// The ID of the client.
+    pub client_id: String,
+    /// The sequence number of the write within the client's session.
+    pub seq: u64,
+    /// The log time of the write: the time at which the leader appended it to the log, in
+    /// milliseconds since the UNIX epoch. This is set by the leader.
+    pub time: u64,
+}
+
+impl ClientSession {
+    /// Create a new instance, for the write with the given sequence number of the given client.
+    pub fn new(client_id: impl Into<String>, seq: u64) -> Self {
+        Self {
+            client_id: client_id.into(),
+            seq,
+            time: 0,
+        }
+    }
+
+    /// Stamp this session with the current time, as its log time.
+    pub(crate) fn stamp(&mut self) {
+        self.time = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or_default();
+    }
+}
+
+/// The last write applied in a client session.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionEntry {
+    /// The sequence number of the write.
+    pub seq: u64,
+    /// The log time of the last write of the session.
+    pub last_active: u64,
+}
+
+/// The table of client sessions of a state machine.
+///
+/// Sessions expire after being idle for `Config::client_session_ttl`, measured in log time, so
+/// that all nodes expire them alike regardless of their clocks. A write of an expired session is
+/// applied as if it were the first of a new session, so clients must not retry a write for longer
+/// than the TTL.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientSessions {
+    /// The time after which an idle session expires, in milliseconds.
+    ttl: u64,
+    /// The latest log time seen.
+    now: u64,
+    /// The log time at which to next drop the expired sessions.
+    next_sweep: u64,
+    sessions: BTreeMap<String, SessionEntry>,
+}
+
+impl Default for ClientSessions {
+    fn default() -> Self {
+        Self::new(DEFAULT_CLIENT_SESSION_TTL)
+    }
+}
+
+impl ClientSessions {
+    /// Create a new table, whose sessions expire after being idle for the given time in milliseconds.
+    pub fn new(ttl: u64) -> Self {
+        Self {
+            ttl,
+            now: 0,
+            next_sweep: 0,
+            sessions: BTreeMap::new(),
+        }
+    }
+
+    /// Record the write of the given session as applied.
+    ///
+    /// Returns whether the write is new, i.e. is to be applied. A write which is older than the
+    /// last one applied in its session is not new either; its client has moved on.
+    pub(crate) fn record(&mut self, session: &ClientSession) -> bool {
+        // Log time is taken from the clocks of successive leaders, so it may go backwards.
+        self.now = std::cmp::max(self.now, session.time);
+        self.sweep();
+
+        let now = self.now;
+        if let Some(entry) = self.get_mut(&session.client_id) {
+            entry.last_active = now;
+            if session.seq <= entry.seq {
+                return false;
+            }
+            entry.seq = session.seq;
+            return true;
+        }
+
+        self.sessions.insert(session.client_id.clone(), SessionEntry {
+            seq: session.seq,
+            last_active: now,
+        });
+        true
+    }
+
+    /// Get the last write applied in the session of the given client, unless it expired.
+    pub fn get(&self, client_id: &str) -> Option<&SessionEntry> {
+        self.sessions.get(client_id).filter(|entry| !self.is_expired(entry))
+    }
+
+    /// The number of sessions in the table, including any which expired but were not dropped yet.
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Whether the table has no sessions.
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    fn get_mut(&mut self, client_id: &str) -> Option<&mut SessionEntry> {
+        let now = self.now;
+        let ttl = self.ttl;
+        self.sessions.get_mut(client_id).filter(|entry| entry.last_active.saturating_add(ttl) > now)
+    }
+
+    fn is_expired(&self, entry: &SessionEntry) -> bool {
+        entry.last_active.saturating_add(self.ttl) <= self.now
+    }
+
+    /// Drop the expired sessions, at most twice per TTL.
+    ///
+    /// Whether a session is expired is decided when looking it up, so when this runs does not
+    /// change the outcome of any write.
+    fn sweep(&mut self) {
+        if self.now < self.next_sweep {
+            return;
+        }
+        let (now, ttl) = (self.now, self.ttl);
+        self.sessions.retain(|_, entry| entry.last_active.saturating_add(ttl) > now);
+        self.next_sweep = now.saturating_add(ttl / 2);
+    }
+}
+
+/// The table of client sessions of a Raft node, as of each entry applied since the last snapshot.
+///
+/// Entries may still be being applied to the state machine while a snapshot is built, so this
+/// gives the table as of the last entry the snapshot includes, for `SnapshotMeta::sessions`.
+#[derive(Clone, Debug, Default)]
+pub struct SessionHistory {
+    inner: Arc<Mutex<History>>,
+}
+
+#[derive(Debug, Default)]
+struct History {
+    /// The table as of the last entry no snapshot will be built before.
+    base: ClientSessions,
+    /// The index and session of each client write applied after `base`, in log order.
+    applied: VecDeque<(u64, ClientSession)>,
+    /// The table as of the last entry applied.
+    current: ClientSessions,
+}
+
+impl SessionHistory {
+    /// Create a new instance, whose sessions expire after being idle for the given time in milliseconds.
+    pub(crate) fn new(ttl: u64) -> Self {
+        let sessions = ClientSessions::new(ttl);
+        Self {
+            inner: Arc::new(Mutex::new(History {
+                base: sessions.clone(),
+                applied: VecDeque::new(),
+                current: sessions,
+            })),
+        }
+    }
+
+    /// Get the table of client sessions as of the entry with the given index being applied.
+    ///
+    /// The table is kept as of any entry past the last snapshot, which is the earliest one a new
+    /// snapshot may be built at.
+    pub fn at(&self, index: u64) -> ClientSessions {
+        let history = self.inner.lock().unwrap();
+        let mut sessions = history.base.clone();
+        for (_, session) in history.applied.iter().take_while(|(i, _)| *i <= index) {
+            sessions.record(session);
+        }
+        sessions
+    }
+
+    /// Record the write of the given session, at the given index, as applied.
+    ///
+    /// Returns whether the write is new, i.e. is to be applied.
+    pub(crate) fn record(&self, index: u64, session: &ClientSession) -> bool {
+        let mut history = self.inner.lock().unwrap();
+        history.applied.push_back((index, session.clone()));
+        history.current.record(session)
+    }
+
+    /// Whether the given client has a session which has not expired.
+    pub(crate) fn contains(&self, client_id: &str) -> bool {
+        self.inner.lock().unwrap().current.get(client_id).is_some()
+    }
+
+    /// Forget the tables as of entries before the given index, as no snapshot will be built before it.
+    pub(crate) fn settle(&self, index: u64) {
+        let mut history = self.inner.lock().unwrap();
+        let history = &mut *history;
+        while let Some((i, session)) = history.applied.front() {
+            if *i > index {
+                break;
+            }
+            history.base.record(session);
+            history.applied.pop_front();
+        }
+    }
+
+    /// Reset the table to the given one, e.g. that of a snapshot being installed.
+    ///
+    /// The TTL configured for this node is kept.
+    pub(crate) fn reset(&self, mut sessions: ClientSessions) {
+        let mut history = self.inner.lock().unwrap();
+        sessions.ttl = history.current.ttl;
+        history.base = sessions.clone();
+        history.applied.clear();
+        history.current = sessions;
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////
+// Unit Tests ////////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(client_id: &str, seq: u64, time: u64) -> ClientSession {
+        ClientSession {
+            client_id: client_id.into(),
+            seq,
+            time,
+        }
+    }
+
+    #[test]
+    fn test_record_once() {
+        let mut sessions = ClientSessions::new(1000);
+
+        assert!(sessions.record(&session("a", 1, 0)));
+        // A retried write is not new.
+        assert!(!sessions.record(&session("a", 1, 10)));
+        assert!(sessions.record(&session("a", 2, 20)));
+        // Nor is a write older than the last one.
+        assert!(!sessions.record(&session("a", 1, 30)));
+        // Sessions are independent.
+        assert!(sessions.record(&session("b", 1, 40)));
+
+        assert_eq!(2, sessions.len());
+        assert_eq!(Some(2), sessions.get("a").map(|entry| entry.seq));
+        assert_eq!(Some(30), sessions.get("a").map(|entry| entry.last_active));
+    }
+
+    #[test]
+    fn test_expire_by_log_time() {
+        let mut sessions = ClientSessions::new(1000);
+
+        sessions.record(&session("a", 1, 0));
+        sessions.record(&session("b", 1, 500));
+        // Being active keeps a session alive.
+        sessions.record(&session("a", 1, 900));
+
+        // Log time going backwards does not expire sessions.
+        sessions.record(&session("c", 1, 100));
+        assert_eq!(900, sessions.now);
+
+        sessions.record(&session("c", 2, 1500));
+        assert!(sessions.get("a").is_some());
+        assert!(sessions.get("b").is_none());
+        assert_eq!(2, sessions.len(), "expired session is dropped");
+
+        // A write of an expired session is applied again.
+        assert!(sessions.record(&session("a", 1, 3000)));
+        assert!(!sessions.record(&session("a", 1, 3000)));
+        assert!(sessions.get("c").is_none());
+    }
+
+    #[test]
+    fn test_history_at() {
+        let history = SessionHistory::new(1000);
+
+        assert!(history.record(2, &session("a", 1, 0)));
+        assert!(!history.record(3, &session("a", 1, 10)));
+        assert!(history.record(5, &session("a", 2, 20)));
+
+        assert_eq!(None, history.at(1).get("a"));
+        assert_eq!(
+            Some(&SessionEntry {
+                seq: 1,
+                last_active: 10
+            }),
+            history.at(4).get("a")
+        );
+        assert_eq!(Some(2), history.at(5).get("a").map(|entry| entry.seq));
+
+        // Settling keeps the tables as of later entries.
+        history.settle(3);
+        assert_eq!(Some(1), history.at(4).get("a").map(|entry| entry.seq));
+        assert_eq!(Some(2), history.at(5).get("a").map(|entry| entry.seq));
+
+        history.reset(ClientSessions::new(5));
+        assert!(history.at(10).is_empty());
+        assert_eq!(1000, history.at(10).ttl, "the configured TTL is kept");
+        assert!(history.record(11, &session("a", 1, 30)));
+    }
+}
@@ -15,30 +15,49 @@ use tokio::io::AsyncWrite;
 use crate::raft::Entry;
 use crate::raft::MembershipConfig;
 use crate::raft_types::SnapshotId;
+use crate::session::ClientSessions;
+use crate::session::SessionHistory;
 use crate::AppData;
 use crate::AppDataResponse;
 use crate::LogId;
 use crate::NodeId;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct SnapshotMeta {
+#[serde(bound = "NID: NodeId")]
+pub struct SnapshotMeta<NID: NodeId = u64> {
     // Log entries upto which this snapshot includes, inclusive.
     pub last_log_id: LogId,
 
     /// The latest membership configuration covered by the snapshot.
-    pub membership: MembershipConfig,
+    pub membership: MembershipConfig<NID>,
 
     /// To identify a snapshot when transferring.
     /// Caveat: even when two snapshot is built with the same `last_log_id`, they still could be different in bytes.
     pub snapshot_id: SnapshotId,
+
+    /// The CRC-32 checksum of the snapshot data, as computed by `checksum::crc32`, if known.
+    ///
+    /// A node installing a snapshot checks it against the data it received. The leader computes it
+    /// before sending a snapshot without one, so a storage implementation which can tell it when
+    /// building the snapshot saves the leader reading the snapshot once more.
+    #[serde(default)]
+    pub checksum: Option<u32>,
+
+    /// The client sessions as of the last log entry the snapshot includes, as given by
+    /// `SessionHistory::at`, so that a node restored from the snapshot does not apply a write
+    /// again when its client retries it.
+    #[serde(default)]
+    pub sessions: ClientSessions,
 }
 
 /// The data associated with the current snapshot.
-pub struct Snapshot<S>
-where S: AsyncRead + AsyncSeek + Send + Unpin + 'static
+pub struct Snapshot<S, NID = u64>
+where
+    S: AsyncRead + AsyncSeek + Send + Unpin + 'static,
+    NID: NodeId,
 {
     /// metadata of a snapshot
-    pub meta: SnapshotMeta,
+    pub meta: SnapshotMeta<NID>,
 
     /// A read handle to the associated snapshot.
     pub snapshot: Box<S>,
@@ -48,35 +67,45 @@ where S: AsyncRead + AsyncSeek + Send + Unpin + 'static
 ///
 /// This model derives serde's traits for easily (de)serializing this
 /// model for storage & retrieval.
-#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Default)]
-pub struct HardState {
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(bound = "NID: NodeId")]
+pub struct HardState<NID: NodeId = u64> {
     /// The last recorded term observed by this system.
     pub current_term: u64,
     /// The ID of the node voted for in the `current_term`.
-    pub voted_for: Option<NodeId>,
+    pub voted_for: Option<NID>,
+}
+
+impl<NID: NodeId> Default for HardState<NID> {
+    fn default() -> Self {
+        Self {
+            current_term: 0,
+            voted_for: None,
+        }
+    }
 }
 
 /// A struct used to represent the initial state which a Raft node needs when first starting.
 #[derive(Clone, Debug)]
-pub struct InitialState {
+pub struct InitialState<NID: NodeId = u64> {
     /// The last entry.
     pub last_log_id: LogId,
 
     /// The LogId of the last log applied to the state machine.
     pub last_applied_log: LogId,
     /// The saved hard state of the node.
-    pub hard_state: HardState,
+    pub hard_state: HardState<NID>,
     /// The latest cluster membership configuration found in the log, else a new initial
     /// membership config consisting only of this node's ID.
-    pub membership: MembershipConfig,
+    pub membership: MembershipConfig<NID>,
 }
 
-impl InitialState {
+impl<NID: NodeId> InitialState<NID> {
     /// Create a new instance for a pristine Raft node.
     ///
     /// ### `id`
     /// The ID of the Raft node.
-    pub fn new_initial(id: NodeId) -> Self {
+    pub fn new_initial(id: NID) -> Self {
         Self {
             last_log_id: LogId { term: 0, index: 0 },
             last_applied_log: LogId { term: 0, index: 0 },
@@ -94,10 +123,11 @@ impl InitialState {
 /// See the [storage chapter of the guide](https://async-raft.github.io/async-raft/storage.html)
 /// for details and discussion on this trait and how to implement it.
 #[async_trait]
-pub trait RaftStorage<D, R>: Send + Sync + 'static
+pub trait RaftStorage<D, R, NID = u64>: Send + Sync + 'static
 where
     D: AppData,
     R: AppDataResponse,
+    NID: NodeId,
 {
     /// The storage engine's associated type used for exposing a snapshot for reading & writing.
     ///
@@ -134,7 +164,7 @@ where
     /// the node's ID so that it is consistent across restarts.
     ///
     /// Errors returned from this method will cause Raft to go into shutdown.
-    async fn get_membership_config(&self) -> Result<MembershipConfig>;
+    async fn get_membership_config(&self) -> Result<MembershipConfig<NID>>;
 
     /// Get Raft's state information from storage.
     ///
@@ -147,12 +177,12 @@ where
     /// the node's hard state record; and the index of the last log applied to the state machine.
     ///
     /// Errors returned from this method will cause Raft to go into shutdown.
-    async fn get_initial_state(&self) -> Result<InitialState>;
+    async fn get_initial_state(&self) -> Result<InitialState<NID>>;
 
     /// Save Raft's hard-state.
     ///
     /// Errors returned from this method will cause Raft to go into shutdown.
-    async fn save_hard_state(&self, hs: &HardState) -> Result<()>;
+    async fn save_hard_state(&self, hs: &HardState<NID>) -> Result<()>;
 
     /// Get a series of log entries from storage.
     ///
@@ -162,11 +192,11 @@ where
     async fn get_log_entries<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
         &self,
         range: RNG,
-    ) -> Result<Vec<Entry<D>>>;
+    ) -> Result<Vec<Entry<D, NID>>>;
 
     /// Try to get an log entry.
     /// It does not return an error if in defensive mode and the log entry at `log_index` is not found.
-    async fn try_get_log_entry(&self, log_index: u64) -> Result<Option<Entry<D>>>;
+    async fn try_get_log_entry(&self, log_index: u64) -> Result<Option<Entry<D, NID>>>;
 
     /// Returns the last known log id.
     /// It could be the id of the last entry in log, or the last applied id that is saved in state machine.
@@ -198,7 +228,7 @@ where
     /// determine its location to be written in the log.
     ///
     /// Errors returned from this method will cause Raft to go into shutdown.
-    async fn append_to_log(&self, entries: &[&Entry<D>]) -> Result<()>;
+    async fn append_to_log(&self, entries: &[&Entry<D, NID>]) -> Result<()>;
 
     /// Apply the given payload of entries to the state machine.
     ///
@@ -223,7 +253,7 @@ where
     /// `Raft.client_write` call point.
     ///
     /// Errors returned from this method will cause Raft to go into shutdown.
-    async fn apply_to_state_machine(&self, entries: &[&Entry<D>]) -> Result<Vec<R>>;
+    async fn apply_to_state_machine(&self, entries: &[&Entry<D, NID>]) -> Result<Vec<R>>;
 
     /// Perform log compaction, returning a handle to the generated snapshot.
     ///
@@ -234,8 +264,11 @@ where
     /// the value of that export's last applied log as the metadata indicating the breadth of the
     /// log covered by the snapshot.
     ///
+    /// The snapshot's `SnapshotMeta::sessions` must be taken from the given `sessions` as of that
+    /// same last applied log, with `SessionHistory::at`.
+    ///
     /// Errors returned from this method will be logged and retried.
-    async fn do_log_compaction(&self) -> Result<Snapshot<Self::SnapshotData>>;
+    async fn do_log_compaction(&self, sessions: &SessionHistory) -> Result<Snapshot<Self::SnapshotData, NID>>;
 
     /// Create a new blank snapshot, returning a writable handle to the snapshot object.
     ///
@@ -248,6 +281,39 @@ where
     /// Errors returned from this method will cause Raft to go into shutdown.
     async fn begin_receiving_snapshot(&self) -> Result<Box<Self::SnapshotData>>;
 
+    /// Save the part of a snapshot received so far from the cluster leader, so that receiving it can
+    /// be resumed with `get_partial_snapshot`, e.g. after a restart.
+    ///
+    /// This is called after each chunk of the snapshot is written, with the handle created by
+    /// `begin_receiving_snapshot`, of which the first `offset` bytes have been written and flushed.
+    /// Only one partial snapshot needs to be kept: saving one replaces any other. An `offset` of 0
+    /// discards what was received, e.g. after the snapshot did not match its checksum.
+    ///
+    /// The default implementation saves nothing, so that an interrupted snapshot is received anew
+    /// after a restart. Errors returned from this method will be logged.
+    async fn save_partial_snapshot(
+        &self,
+        meta: &SnapshotMeta<NID>,
+        offset: u64,
+        snapshot: &mut Self::SnapshotData,
+    ) -> Result<()> {
+        let _ = (meta, offset, snapshot);
+        Ok(())
+    }
+
+    /// Get the partial snapshot saved with `save_partial_snapshot` for the snapshot of the given
+    /// metadata, along with the number of bytes of it which were saved.
+    ///
+    /// Returns `None` if no part of that snapshot was saved, e.g. if the partial snapshot is of
+    /// another one, which may then be deleted. Raft resumes receiving the snapshot by writing to
+    /// the returned handle at the returned offset.
+    ///
+    /// Errors returned from this method will be logged, and the snapshot received anew.
+    async fn get_partial_snapshot(&self, meta: &SnapshotMeta<NID>) -> Result<Option<(u64, Box<Self::SnapshotData>)>> {
+        let _ = meta;
+        Ok(None)
+    }
+
     /// Finalize the installation of a snapshot which has finished streaming from the cluster leader.
     ///
     /// Delete all entries in the log through `meta.last_log_id.index`.
@@ -256,7 +322,8 @@ where
     /// constructed via the `Entry::new_snapshot_pointer` constructor and the other parameters
     /// provided to this method.
     ///
-    /// All other snapshots should be deleted at this point.
+    /// All other snapshots should be deleted at this point, along with any partial snapshot saved
+    /// with `save_partial_snapshot`.
     ///
     /// ### snapshot
     /// A snapshot created from an earlier call to `created_snapshot` which provided the snapshot.
@@ -267,7 +334,7 @@ where
     /// Errors returned from this method will cause Raft to go into shutdown.
     async fn finalize_snapshot_installation(
         &self,
-        meta: &SnapshotMeta,
+        meta: &SnapshotMeta<NID>,
         snapshot: Box<Self::SnapshotData>,
     ) -> Result<()>;
 
@@ -284,15 +351,15 @@ where
     /// of the snapshot, which should be decoded for creating this method's response data.
     ///
     /// Errors returned from this method will cause Raft to go into shutdown.
-    async fn get_current_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData>>>;
+    async fn get_current_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData, NID>>>;
 }
 
 /// APIs for debugging a store.
 #[async_trait]
-pub trait RaftStorageDebug<SM> {
+pub trait RaftStorageDebug<SM, NID: NodeId = u64> {
     /// Get a handle to the state machine for testing purposes.
     async fn get_state_machine(&self) -> SM;
 
     /// Get the current hard state for testing purposes.
-    async fn read_hard_state(&self) -> Option<HardState>;
+    async fn read_hard_state(&self) -> Option<HardState<NID>>;
 }
@@ -89,6 +89,7 @@ async fn add_remove_voter() -> Result<()> {
     while let Some(inner) = new_nodes.next().await {
         inner?;
     }
+    want += 4; // one learner log per non-voter
 
     wait_log(router.clone(), &all_members, want).await?;
 
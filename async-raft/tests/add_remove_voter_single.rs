@@ -0,0 +1,179 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::error::ResponseError;
+use async_raft::ChangeConfigError;
+use async_raft::Config;
+use async_raft::State;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Single-server membership change test.
+///
+/// What does this test do?
+///
+/// - brings 4 nodes online: one leader and 3 non-voters.
+/// - add node 1 as a voter without syncing it first, then add node 2 after adding it as a non-voter.
+/// - asserts that each change writes a single config entry, and that every node sees the new members.
+/// - add node 3 as a voter while the new config can not be committed, and asserts that a concurrent change is rejected
+///   until it is.
+/// - remove node 3, and asserts that it becomes a non-voter.
+/// - asserts that changes with no effect are rejected.
+/// - remove the leader, and asserts that it steps down and the remaining voters elect a new leader.
+///
+/// RUST_LOG=async_raft,memstore,add_remove_voter_single=trace cargo test -p async-raft --test add_remove_voter_single
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn add_remove_voter_single() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(2000));
+
+    // Setup test dependencies.
+    let config = Arc::new(Config::build("test".into()).validate().expect("failed to build Raft config"));
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+
+    let mut want = 0;
+
+    // Assert all nodes are in non-voter state & have no entries.
+    router.wait_for_log(&btreeset![0], want, timeout, "empty node").await?;
+    router.wait_for_state(&btreeset![0], State::NonVoter, timeout, "empty node").await?;
+    router.assert_pristine_cluster().await;
+
+    tracing::info!("--- initializing cluster");
+
+    router.initialize_from_single_node(0).await?;
+    want += 1;
+
+    router.wait_for_log(&btreeset![0], want, timeout, "init cluster").await?;
+
+    router.new_raft_node(1).await;
+    router.new_raft_node(2).await;
+    router.new_raft_node(3).await;
+
+    tracing::info!("--- add voter 1 without syncing it first");
+
+    router.add_voter(0, 1).await?;
+    want += 1; // single config log
+
+    router.wait_for_log(&btreeset![0, 1], want, timeout, "add voter 1").await?;
+    router.wait_for_state(&btreeset![1], State::Follower, timeout, "add voter 1").await?;
+
+    tracing::info!("--- add voter 2 after syncing it as a non-voter");
+
+    router.add_non_voter(0, 2).await?;
+    want += 1; // learner log
+    router.add_voter(0, 2).await?;
+    want += 1; // single config log
+
+    router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "add voter 2").await?;
+    router.wait_for_state(&btreeset![2], State::Follower, timeout, "add voter 2").await?;
+
+    for m in router.latest_metrics().await.iter().filter(|m| m.id != 3) {
+        assert_eq!(btreeset![0, 1, 2], m.membership_config.members, "node {} members", m.id);
+        assert_eq!(None, m.membership_config.members_after_consensus, "node {} joint", m.id);
+        assert!(m.membership_config.learners.is_empty(), "node {} learners", m.id);
+    }
+
+    router.client_request_many(0, "0", 10).await;
+    want += 10;
+
+    router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "write logs").await?;
+
+    tracing::info!("--- add voter 3 and reject an overlapping change");
+    {
+        // With nodes 2 and 3 isolated, the new config can not be committed, which keeps the change in progress.
+        router.isolate_node(2).await;
+        router.isolate_node(3).await;
+
+        let r = router.clone();
+        let adding = tokio::spawn(async move { r.add_voter(0, 3).await });
+
+        router
+            .wait_for_metrics(
+                &0,
+                |x| x.membership_config.members.contains(&3),
+                timeout,
+                "voter 3 appended",
+            )
+            .await?;
+        let rst = router.remove_voter(0, 2).await;
+        assert!(
+            matches!(
+                rst,
+                Err(ResponseError::ChangeConfig(ChangeConfigError::ConfigChangeInProgress))
+            ),
+            "unexpected result: {:?}",
+            rst
+        );
+
+        router.restore_node(2).await;
+        router.restore_node(3).await;
+        adding.await??;
+        want += 1; // single config log
+    }
+
+    router.wait_for_log(&btreeset![0, 1, 2, 3], want, timeout, "add voter 3").await?;
+
+    tracing::info!("--- remove voter 3");
+
+    router.remove_voter(0, 3).await?;
+    want += 1; // single config log
+
+    router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "remove voter 3").await?;
+    router.wait_for_state(&btreeset![3], State::NonVoter, timeout, "remove voter 3").await?;
+
+    router.client_request_many(0, "0", 10).await;
+    want += 10;
+
+    router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "write logs").await?;
+
+    // log will not be sync to removed node
+    let x = router.latest_metrics().await;
+    assert!(x[3].last_log_index < want);
+
+    tracing::info!("--- changes with no effect");
+
+    let rst = router.add_voter(0, 1).await;
+    assert!(
+        matches!(rst, Err(ResponseError::ChangeConfig(ChangeConfigError::Noop))),
+        "unexpected result: {:?}",
+        rst
+    );
+    let rst = router.remove_voter(0, 3).await;
+    assert!(
+        matches!(rst, Err(ResponseError::ChangeConfig(ChangeConfigError::Noop))),
+        "unexpected result: {:?}",
+        rst
+    );
+
+    tracing::info!("--- remove the leader");
+
+    router.remove_voter(0, 0).await?;
+    want += 1; // single config log
+
+    router.wait_for_state(&btreeset![0], State::NonVoter, timeout, "leader stepped down").await?;
+
+    let leader = loop {
+        if let Some(leader) = router.leader().await {
+            if leader != 0 {
+                break leader;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    };
+    assert!(leader == 1 || leader == 2, "unexpected leader {}", leader);
+    want += 1; // blank log of the new leader
+
+    router.wait_for_log(&btreeset![1, 2], want, timeout, "new leader").await?;
+
+    let m = router.latest_metrics().await;
+    assert_eq!(btreeset![1, 2], m[leader as usize].membership_config.members);
+
+    Ok(())
+}
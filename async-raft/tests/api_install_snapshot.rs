@@ -17,7 +17,8 @@ mod fixtures;
 /// What does this test do?
 ///
 /// - build a stable single node cluster.
-/// - send install_snapshot request with matched/mismatched id and offset
+/// - send install_snapshot request with matched/mismatched id and offset, and assert the offset each response asks for
+///   next.
 ///
 /// export RUST_LOG=async_raft,memstore,snapshot_ge_half_threshold=trace
 /// cargo test -p async-raft --test snapshot_ge_half_threshold
@@ -53,9 +54,13 @@ async fn snapshot_ge_half_threshold() -> Result<()> {
             snapshot_id: "ss1".into(),
             last_log_id: LogId { term: 1, index: 0 },
             membership: Default::default(),
+            checksum: None,
+            sessions: Default::default(),
         },
         offset: 0,
         data: vec![1, 2, 3],
+        checksum: None,
+        compression: None,
         done: false,
     };
 
@@ -63,14 +68,22 @@ async fn snapshot_ge_half_threshold() -> Result<()> {
     {
         let mut req = req0.clone();
         req.offset = 2;
-        let res = n.0.install_snapshot(req).await;
-        assert_eq!("expect: ss1+0, got: ss1+2", res.unwrap_err().to_string());
+        let res = n.0.install_snapshot(req).await?;
+        assert_eq!(Some(0), res.offset);
     }
 
     tracing::info!("--- install and write ss1:[0,3)");
     {
         let req = req0.clone();
-        n.0.install_snapshot(req).await?;
+        let res = n.0.install_snapshot(req).await?;
+        assert_eq!(Some(3), res.offset);
+    }
+
+    tracing::info!("--- a resent chunk is not written again");
+    {
+        let req = req0.clone();
+        let res = n.0.install_snapshot(req).await?;
+        assert_eq!(Some(3), res.offset);
     }
 
     tracing::info!("-- continue write with different id");
@@ -95,12 +108,13 @@ async fn snapshot_ge_half_threshold() -> Result<()> {
         n.0.install_snapshot(req).await?;
     }
 
-    tracing::info!("-- continue write with mismatched offset is allowed");
+    tracing::info!("-- continue write with mismatched offset is answered with the received offset");
     {
         let mut req = req0.clone();
         req.offset = 8;
         req.meta.snapshot_id = "ss2".into();
-        n.0.install_snapshot(req).await?;
+        let res = n.0.install_snapshot(req).await?;
+        assert_eq!(Some(6), res.offset);
     }
     Ok(())
 }
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_raft::raft::AppendEntriesRequest;
+use async_raft::raft::Entry;
+use async_raft::raft::EntryPayload;
+use async_raft::Config;
+use async_raft::LogId;
+use async_raft::RaftNetwork;
+use async_raft::RaftStorage;
+use fixtures::RaftRouter;
+
+#[macro_use]
+mod fixtures;
+
+/// Append duplicate entries test.
+///
+/// A leader may send a follower entries it already has, e.g. when a replication stream is restarted, or when it resends
+/// a payload whose response was lost. The follower must skip them, rather than appending them again.
+///
+/// What does this test do?
+///
+/// - brings a 1 NonVoter node online, and feeds it 3 entries.
+/// - sends the same entries again, from the start of the log and from the middle of it, along with a new one, and
+///   asserts that each entry is in the log once.
+/// - sends an entry of a later term, which conflicts with the last 2 entries, and asserts that they are replaced.
+///
+/// RUST_LOG=async_raft,memstore,append_duplicate_entries=trace cargo test -p async-raft --test append_duplicate_entries
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn append_duplicate_entries() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // Setup test dependencies.
+    let config = Arc::new(Config::build("test".into()).validate().expect("failed to build Raft config"));
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_raft_node(0).await;
+
+    let rpc = AppendEntriesRequest::<memstore::ClientRequest> {
+        term: 1,
+        leader_id: 1,
+        prev_log_id: LogId { term: 0, index: 0 },
+        entries: vec![ent(1, 1), ent(1, 2), ent(1, 3)],
+        leader_commit: 0,
+    };
+    let resp = router.send_append_entries(0, rpc).await?;
+    assert!(resp.success);
+
+    tracing::info!("--- entries sent again are skipped");
+    {
+        let rpc = AppendEntriesRequest::<memstore::ClientRequest> {
+            term: 1,
+            leader_id: 1,
+            prev_log_id: LogId { term: 0, index: 0 },
+            entries: vec![ent(1, 1), ent(1, 2), ent(1, 3)],
+            leader_commit: 0,
+        };
+        let resp = router.send_append_entries(0, rpc).await?;
+        assert!(resp.success);
+
+        let rpc = AppendEntriesRequest::<memstore::ClientRequest> {
+            term: 1,
+            leader_id: 1,
+            prev_log_id: LogId { term: 1, index: 1 },
+            entries: vec![ent(1, 2), ent(1, 3), ent(1, 4)],
+            leader_commit: 0,
+        };
+        let resp = router.send_append_entries(0, rpc).await?;
+        assert!(resp.success);
+
+        let sto = router.get_storage_handle(&0).await?;
+        let logs = sto.get_log_entries(1..).await?;
+        assert_eq!(vec![(1, 1), (1, 2), (1, 3), (1, 4)], log_ids(&logs));
+    }
+
+    tracing::info!("--- conflicting entries are replaced");
+    {
+        let rpc = AppendEntriesRequest::<memstore::ClientRequest> {
+            term: 2,
+            leader_id: 2,
+            prev_log_id: LogId { term: 1, index: 1 },
+            entries: vec![ent(1, 2), ent(2, 3)],
+            leader_commit: 0,
+        };
+        let resp = router.send_append_entries(0, rpc).await?;
+        assert!(resp.success);
+
+        let sto = router.get_storage_handle(&0).await?;
+        let logs = sto.get_log_entries(1..).await?;
+        assert_eq!(vec![(1, 1), (1, 2), (2, 3)], log_ids(&logs));
+    }
+
+    Ok(())
+}
+
+fn ent(term: u64, index: u64) -> Entry<memstore::ClientRequest> {
+    Entry {
+        log_id: LogId { term, index },
+        payload: EntryPayload::Blank,
+    }
+}
+
+fn log_ids(logs: &[Entry<memstore::ClientRequest>]) -> Vec<(u64, u64)> {
+    logs.iter().map(|x| (x.log_id.term, x.log_id.index)).collect()
+}
@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::error::ResponseError;
+use async_raft::raft::ChangeMembershipPhase;
+use async_raft::ChangeConfigError;
+use async_raft::Config;
+use async_raft::State;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Non-blocking membership change test.
+///
+/// What does this test do?
+///
+/// - brings 5 nodes online: one leader and 4 non-voters.
+/// - starts a change to 3 members, and asserts that the handle reports every phase in order.
+/// - isolates nodes 2, 3 and 4, so that a change to 5 members can not be committed, and asserts that the handle reports
+///   it as joint, that it can no longer be cancelled, and that another change is rejected.
+/// - restores the network, and asserts that the change finishes.
+/// - asserts that a change can not be started on a non-leader.
+///
+/// RUST_LOG=async_raft,memstore,change_membership_handle=trace cargo test -p async-raft --test change_membership_handle
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn change_membership_handle() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(2000));
+
+    // Setup test dependencies.
+    let config = Arc::new(Config::build("test".into()).validate().expect("failed to build Raft config"));
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+
+    let mut want = 0;
+
+    // Assert all nodes are in non-voter state & have no entries.
+    router.wait_for_log(&btreeset![0], want, timeout, "empty node").await?;
+    router.wait_for_state(&btreeset![0], State::NonVoter, timeout, "empty node").await?;
+    router.assert_pristine_cluster().await;
+
+    tracing::info!("--- initializing cluster");
+
+    router.initialize_from_single_node(0).await?;
+    want += 1;
+
+    router.wait_for_log(&btreeset![0], want, timeout, "init cluster").await?;
+
+    router.new_raft_node(1).await;
+    router.new_raft_node(2).await;
+    router.new_raft_node(3).await;
+    router.new_raft_node(4).await;
+
+    let leader = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- change to 3 members");
+    {
+        let mut handle = leader.start_change_membership(btreeset![0, 1, 2], BTreeMap::new()).await?;
+
+        assert!(handle.wait_for(ChangeMembershipPhase::Joint).await);
+        assert!(handle.wait_for(ChangeMembershipPhase::JointCommitted).await);
+        assert!(handle.wait_for(ChangeMembershipPhase::FinalCommitted).await);
+        assert_eq!(ChangeMembershipPhase::FinalCommitted, handle.phase());
+
+        let rst = handle.cancel().await;
+        assert!(
+            matches!(rst, Err(ResponseError::ChangeConfig(ChangeConfigError::NotSyncing))),
+            "unexpected result: {:?}",
+            rst
+        );
+
+        handle.finish().await?;
+        want += 2; // 2 member-change logs
+    }
+
+    router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "change to 3 members").await?;
+
+    tracing::info!("--- change to 5 members while most of them are isolated");
+    {
+        // Without nodes 2, 3 and 4, the new config has no majority to commit the joint config.
+        router.isolate_node(2).await;
+        router.isolate_node(3).await;
+        router.isolate_node(4).await;
+
+        let mut handle = leader.start_change_membership(btreeset![0, 1, 2, 3, 4], BTreeMap::new()).await?;
+        assert!(handle.wait_for(ChangeMembershipPhase::Joint).await);
+        assert_eq!(ChangeMembershipPhase::Joint, handle.phase());
+
+        let rst = handle.cancel().await;
+        assert!(
+            matches!(rst, Err(ResponseError::ChangeConfig(ChangeConfigError::NotSyncing))),
+            "unexpected result: {:?}",
+            rst
+        );
+
+        let rst = leader.start_change_membership(btreeset![0, 1], BTreeMap::new()).await;
+        assert!(
+            matches!(
+                rst,
+                Err(ResponseError::ChangeConfig(ChangeConfigError::ConfigChangeInProgress))
+            ),
+            "unexpected result: {:?}",
+            rst.map(|_| ())
+        );
+
+        router.restore_node(2).await;
+        router.restore_node(3).await;
+        router.restore_node(4).await;
+
+        assert!(handle.wait_for(ChangeMembershipPhase::FinalCommitted).await);
+        handle.finish().await?;
+        want += 2; // 2 member-change logs
+    }
+
+    router.wait_for_log(&btreeset![0, 1, 2, 3, 4], want, timeout, "change to 5 members").await?;
+
+    tracing::info!("--- start a change on a non-leader");
+    {
+        let follower = router.get_raft_handle(&1).await?;
+        let rst = follower.start_change_membership(btreeset![0, 1, 2], BTreeMap::new()).await;
+        assert!(
+            matches!(
+                rst,
+                Err(ResponseError::ChangeConfig(ChangeConfigError::NodeNotLeader(Some(0))))
+            ),
+            "unexpected result: {:?}",
+            rst.map(|_| ())
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::Config;
+use async_raft::State;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// CheckQuorum test.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster with CheckQuorum and PreVote enabled.
+/// - isolate the leader, and assert that it steps down on its own while the rest of the cluster elects a new leader.
+/// - restore the old leader, and assert that it rejoins the cluster as a follower.
+///
+/// RUST_LOG=async_raft,memstore,check_quorum=trace cargo test -p async-raft --test check_quorum
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn check_quorum() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // Setup test dependencies.
+    let config = Arc::new(
+        Config::build("test".into())
+            .check_quorum(true)
+            .pre_vote(true)
+            .validate()
+            .expect("failed to build Raft config"),
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+    router.new_raft_node(1).await;
+    router.new_raft_node(2).await;
+
+    let mut want = 0;
+
+    // Assert all nodes are in non-voter state & have no entries.
+    router.wait_for_log(&btreeset![0, 1, 2], want, None, "empty").await?;
+    router.wait_for_state(&btreeset![0, 1, 2], State::NonVoter, None, "empty").await?;
+    router.assert_pristine_cluster().await;
+
+    // Initialize the cluster, then assert that a stable cluster was formed & held.
+    tracing::info!("--- initializing cluster");
+    router.initialize_from_single_node(0).await?;
+    want += 1;
+
+    router.wait_for_log(&btreeset![0, 1, 2], want, None, "init").await?;
+    router.assert_stable_cluster(Some(1), Some(want)).await;
+
+    // A leader in contact with the cluster must not step down.
+    tokio::time::sleep(Duration::from_millis(config.election_timeout_max * 2)).await;
+    router.assert_stable_cluster(Some(1), Some(want)).await;
+
+    // Isolate the leader, it must step down without observing a higher term. With PreVote, it does not start a new
+    // term of its own while it can not reach a quorum either.
+    tracing::info!("--- isolating leader node 0");
+    router.isolate_node(0).await;
+
+    let timeout = Some(Duration::from_millis(config.election_timeout_max * 3));
+    router
+        .wait_for_metrics(
+            &0,
+            |m| m.state != State::Leader && m.current_term == 1,
+            timeout,
+            "isolated leader steps down",
+        )
+        .await?;
+
+    // The remaining nodes elect a new leader, which commits a blank entry.
+    want += 1;
+    router.wait_for_log(&btreeset![1, 2], want, timeout, "new leader elected").await?;
+
+    // Restore the old leader, it rejoins as a follower.
+    tracing::info!("--- restoring node 0");
+    router.restore_node(0).await;
+    router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "node 0 catches up").await?;
+
+    router.wait_for_state(&btreeset![0], State::Follower, timeout, "node 0 rejoins as follower").await?;
+    router.assert_stable_cluster(None, Some(want)).await;
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::Config;
+use async_raft::ReadPolicy;
+use async_raft::State;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Client read with lease test.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster with the lease read policy.
+/// - call the client_read interface on the leader and the followers, and assert the leader succeeds.
+/// - isolate both followers, and assert that the leader keeps serving reads from its lease, without being able to
+///   exchange heartbeats.
+/// - wait for the lease to expire, and assert that reads fail.
+///
+/// RUST_LOG=async_raft,memstore,client_reads_lease=trace cargo test -p async-raft --test client_reads_lease
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn client_reads_lease() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // Setup test dependencies. Long election timeouts leave plenty of time to read within the lease.
+    let config = Arc::new(
+        Config::build("test".into())
+            .election_timeout_min(1000)
+            .election_timeout_max(2000)
+            .read_policy(ReadPolicy::Lease { max_clock_drift: 100 })
+            .validate()
+            .expect("failed to build Raft config"),
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+    router.new_raft_node(1).await;
+    router.new_raft_node(2).await;
+
+    let mut want = 0;
+
+    // Assert all nodes are in non-voter state & have no entries.
+    router.wait_for_log(&btreeset![0, 1, 2], want, None, "empty node").await?;
+    router.wait_for_state(&btreeset![0, 1, 2], State::NonVoter, None, "empty node").await?;
+    router.assert_pristine_cluster().await;
+
+    // Initialize the cluster, then assert that a stable cluster was formed & held.
+    tracing::info!("--- initializing cluster");
+    router.initialize_from_single_node(0).await?;
+    want += 1;
+
+    let timeout = Some(Duration::from_millis(config.election_timeout_max * 2));
+    router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "init leader").await?;
+    router.assert_stable_cluster(Some(1), Some(want)).await;
+
+    let leader = router.leader().await.expect("leader not found");
+    assert_eq!(leader, 0, "expected leader to be node 0, got {}", leader);
+    router.client_read(leader).await?;
+    router.client_read(1).await.expect_err("expected client_read on follower node 1 to fail");
+    router.client_read(2).await.expect_err("expected client_read on follower node 2 to fail");
+
+    tracing::info!("--- isolate both followers, the leader reads from its lease");
+
+    router.isolate_node(1).await;
+    router.isolate_node(2).await;
+    router.client_read(leader).await?;
+
+    tracing::info!("--- the lease expires, reads fail");
+
+    tokio::time::sleep(Duration::from_millis(config.election_timeout_min)).await;
+    let rst = router.client_read(leader).await;
+    tracing::debug!(?rst, "client_read with an expired lease");
+
+    assert!(rst.is_err());
+
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::error::ClientWriteError;
+use async_raft::raft::ClientWriteRequest;
+use async_raft::ClientSession;
+use async_raft::Config;
+use async_raft::LogId;
+use async_raft::RaftStorage;
+use async_raft::RaftStorageDebug;
+use async_raft::SnapshotPolicy;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+
+#[macro_use]
+mod fixtures;
+
+/// Client session test.
+///
+/// What does this test do?
+///
+/// - brings a 3 node cluster online, with a snapshot policy which builds a snapshot at log index 10.
+/// - retries a write of a client session, and asserts that it is applied once, with every attempt getting the same
+///   response.
+/// - writes to a follower, and retries the write on the leader after `ForwardToLeader`, and asserts that it is applied
+///   once.
+/// - asserts that an older write of a session, which its client moved on from, is not applied either.
+/// - adds a non-voter, which is brought up to speed with a snapshot, and asserts that the snapshot carried the client
+///   sessions, so that the non-voter does not apply a retried write either.
+///
+/// RUST_LOG=async_raft,memstore,client_sessions=trace cargo test -p async-raft --test client_sessions
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn client_sessions() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(2000));
+    let snapshot_threshold = 10;
+
+    // Setup test dependencies.
+    let config = Arc::new(
+        Config::build("test".into())
+            .snapshot_policy(SnapshotPolicy::LogsSinceLast(snapshot_threshold))
+            .validate()
+            .expect("failed to build Raft config"),
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0, 1, 2}, btreeset! {}).await?;
+
+    let leader = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- a retried write is applied once");
+    {
+        let first = leader.client_write(request("c", 1, "a")).await?;
+        // A retry which differs from the write shows whether it was applied again.
+        let retry = leader.client_write(request("c", 1, "x")).await?;
+        want += 2;
+
+        assert_eq!(want - 1, first.index);
+        assert_eq!(want, retry.index);
+        assert_eq!(format!("{:?}", first.data), format!("{:?}", retry.data));
+
+        router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "retried write").await?;
+        for id in 0..3 {
+            let sm = router.get_storage_handle(&id).await?.get_state_machine().await;
+            assert_eq!(Some(&"a".to_string()), sm.client_status.get("c"));
+        }
+    }
+
+    tracing::info!("--- a write retried on the leader after being forwarded is applied once");
+    {
+        let follower = router.get_raft_handle(&1).await?;
+        let res = follower.client_write(request("c", 2, "b")).await;
+        let data = match res {
+            Err(ClientWriteError::ForwardToLeader(data, Some(0))) => data,
+            _ => panic!("unexpected result: {:?}", res),
+        };
+
+        let session = ClientSession::new("c", 2);
+        leader.client_write(ClientWriteRequest::new_with_session(data.clone(), session.clone())).await?;
+        leader.client_write(ClientWriteRequest::new_with_session(data, session)).await?;
+        want += 2;
+
+        router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "forwarded write").await?;
+        for id in 0..3 {
+            let sm = router.get_storage_handle(&id).await?.get_state_machine().await;
+            assert_eq!(Some(&"b".to_string()), sm.client_status.get("c"));
+        }
+    }
+
+    tracing::info!("--- an older write which was appended late is not applied");
+    {
+        let res = leader.client_write(request("c", 1, "stale")).await;
+        assert!(
+            matches!(res, Err(ClientWriteError::AlreadyApplied)),
+            "unexpected result: {:?}",
+            res
+        );
+        want += 1;
+
+        router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "stale write").await?;
+        for id in 0..3 {
+            let sm = router.get_storage_handle(&id).await?.get_state_machine().await;
+            assert_eq!(Some(&"b".to_string()), sm.client_status.get("c"));
+        }
+    }
+
+    tracing::info!("--- client sessions are part of snapshots");
+    {
+        router.client_request_many(0, "0", (snapshot_threshold - want) as usize).await;
+        want = snapshot_threshold;
+
+        let snapshot = LogId {
+            term: 1,
+            index: snapshot_threshold,
+        };
+        router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "fill up to snapshot").await?;
+        router.wait_for_snapshot(&btreeset![0], snapshot, timeout, "build snapshot").await?;
+
+        router.new_raft_node(3).await;
+        router.add_non_voter(0, 3).await?;
+        want += 1;
+
+        router.wait_for_log(&btreeset![0, 1, 2, 3], want, timeout, "add non-voter").await?;
+        router.wait_for_snapshot(&btreeset![3], snapshot, timeout, "install snapshot").await?;
+
+        let sto = router.get_storage_handle(&3).await?;
+        let current = sto.get_current_snapshot().await?.expect("expected a snapshot to be installed");
+        assert_eq!(Some(2), current.meta.sessions.get("c").map(|s| s.seq));
+
+        // A retry of a write the snapshot includes is not applied on the non-voter either.
+        leader.client_write(request("c", 2, "y")).await?;
+        want += 1;
+
+        router.wait_for_log(&btreeset![0, 1, 2, 3], want, timeout, "retry after snapshot").await?;
+        let sm = sto.get_state_machine().await;
+        assert_eq!(Some(&"b".to_string()), sm.client_status.get("c"));
+    }
+
+    Ok(())
+}
+
+fn request(client: &str, seq: u64, status: &str) -> ClientWriteRequest<ClientRequest> {
+    let req = ClientRequest {
+        client: client.into(),
+        serial: seq,
+        status: status.into(),
+    };
+    ClientWriteRequest::new_with_session(req, ClientSession::new(client, seq))
+}
@@ -0,0 +1,119 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::error::ClientWriteError;
+use async_raft::Config;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+
+#[macro_use]
+mod fixtures;
+
+/// Batched client write test.
+///
+/// What does this test do?
+///
+/// - brings a 3 node cluster online.
+/// - writes a batch of entries to the leader, and asserts that every entry gets its own response, at consecutive log
+///   indexes in the order of the batch.
+/// - writes an empty batch, and asserts that it gets no responses, and appends nothing.
+/// - writes concurrently with single writes and batches, and asserts that every node replicates every entry.
+/// - writes a batch to a follower, and asserts that every entry is handed back to be forwarded to the leader.
+///
+/// RUST_LOG=async_raft,memstore,client_write_batch=trace cargo test -p async-raft --test client_write_batch
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn client_write_batch() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(2000));
+
+    // Setup test dependencies.
+    let config = Arc::new(Config::build("test".into()).validate().expect("failed to build Raft config"));
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0, 1, 2}, btreeset! {}).await?;
+
+    let leader = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- write a batch to the leader");
+    {
+        let batch = requests("batch", 0..100);
+        let res = leader.client_write_batch(batch).await;
+        assert_eq!(100, res.len());
+        for (i, r) in res.into_iter().enumerate() {
+            let r = r.expect("batched write failed");
+            assert_eq!(want + 1 + i as u64, r.index);
+        }
+        want += 100;
+
+        router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "write batch").await?;
+    }
+
+    tracing::info!("--- an empty batch appends nothing");
+    {
+        let res = leader.client_write_batch(vec![]).await;
+        assert!(res.is_empty());
+
+        router.wait_for_log(&btreeset![0], want, timeout, "empty batch").await?;
+    }
+
+    tracing::info!("--- write single entries and batches concurrently");
+    {
+        let mut clients = Vec::new();
+        for client in 0..10 {
+            let router = router.clone();
+            clients.push(tokio::spawn(async move {
+                router.client_request_many(0, &client.to_string(), 20).await;
+            }));
+        }
+        for client in 10..20 {
+            let leader = leader.clone();
+            clients.push(tokio::spawn(async move {
+                for serial in 0..4 {
+                    let batch = requests(&client.to_string(), serial * 5..(serial + 1) * 5);
+                    for r in leader.client_write_batch(batch).await {
+                        r.expect("batched write failed");
+                    }
+                }
+            }));
+        }
+        for client in clients {
+            client.await?;
+        }
+        want += 10 * 20 + 10 * 20;
+
+        router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "concurrent writes").await?;
+        router.assert_stable_cluster(Some(1), Some(want)).await;
+    }
+
+    tracing::info!("--- a follower hands back every entry of a batch");
+    {
+        let follower = router.get_raft_handle(&1).await?;
+        let res = follower.client_write_batch(requests("follower", 0..3)).await;
+        assert_eq!(3, res.len());
+        for (i, r) in res.into_iter().enumerate() {
+            match r {
+                Err(ClientWriteError::ForwardToLeader(req, leader)) => {
+                    assert_eq!(i as u64, req.serial);
+                    assert_eq!(Some(0), leader);
+                }
+                _ => panic!("unexpected result: {:?}", r),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn requests(client: &str, serials: std::ops::Range<u64>) -> Vec<ClientRequest> {
+    serials
+        .map(|serial| ClientRequest {
+            client: client.into(),
+            serial,
+            status: format!("request-{}", serial),
+        })
+        .collect()
+}
@@ -0,0 +1,119 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::error::ClientWriteError;
+use async_raft::Config;
+use async_raft::RaftStorageDebug;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use tokio::time::Instant;
+
+#[macro_use]
+mod fixtures;
+
+/// Client write deadline test.
+///
+/// What does this test do?
+///
+/// - brings a 3 node cluster online, with election timeouts long enough that isolated followers do not unseat the
+///   leader.
+/// - writes with a deadline which has already passed, and asserts that the write is not appended.
+/// - isolates both followers, writes with deadlines from many concurrent clients twice, so that the requests abandoned
+///   the first time are pruned by the leader, and asserts that the outcome of every write is unknown.
+/// - waits for the leader to prune the abandoned requests on its heartbeat, restores the followers, and asserts that
+///   every write is committed and applied, without a further write.
+///
+/// RUST_LOG=async_raft,memstore,client_write_deadline=trace cargo test -p async-raft --test client_write_deadline
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn client_write_deadline() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(5000));
+
+    // Setup test dependencies. Long election timeouts keep the leader in place while its followers are isolated.
+    let config = Arc::new(
+        Config::build("test".into())
+            .election_timeout_min(2000)
+            .election_timeout_max(3000)
+            .validate()
+            .expect("failed to build Raft config"),
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0, 1, 2}, btreeset! {}).await?;
+
+    let leader = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- a write whose deadline has passed is not appended");
+    {
+        let res = leader.client_write_with_deadline(request("0", 0), None, Instant::now()).await;
+        match res {
+            Err(ClientWriteError::NotAppended(req)) => assert_eq!(0, req.serial),
+            _ => panic!("unexpected result: {:?}", res),
+        }
+
+        router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "nothing appended").await?;
+    }
+
+    tracing::info!("--- the outcome of writes is unknown without a quorum");
+    {
+        router.isolate_node(1).await;
+        router.isolate_node(2).await;
+
+        for round in 0..2 {
+            let mut clients = Vec::new();
+            for client in 0..100 {
+                let leader = leader.clone();
+                clients.push(tokio::spawn(async move {
+                    let deadline = Instant::now() + Duration::from_millis(200);
+                    leader.client_write_with_deadline(request(&client.to_string(), round), None, deadline).await
+                }));
+            }
+            for client in clients {
+                let res = client.await?;
+                assert!(
+                    matches!(res, Err(ClientWriteError::OutcomeUnknown)),
+                    "unexpected result: {:?}",
+                    res
+                );
+            }
+            want += 100;
+        }
+
+        router.wait_for_metrics(&0, |x| x.last_log_index == want, timeout, "appended on the leader").await?;
+
+        // The leader prunes the abandoned requests every heartbeat interval.
+        tokio::time::sleep(Duration::from_millis(config.heartbeat_interval * 3)).await;
+    }
+
+    tracing::info!("--- every write is applied once the quorum is back");
+    {
+        router.restore_node(1).await;
+        router.restore_node(2).await;
+
+        router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "quorum restored").await?;
+        router.assert_stable_cluster(Some(1), Some(want)).await;
+
+        let sto = router.get_storage_handle(&0).await?;
+        let sm = sto.get_state_machine().await;
+        for client in 0..100 {
+            assert_eq!(
+                Some(&"request-1".to_string()),
+                sm.client_status.get(&client.to_string())
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn request(client: &str, serial: u64) -> ClientRequest {
+    ClientRequest {
+        client: client.into(),
+        serial,
+        status: format!("request-{}", serial),
+    }
+}
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::error::ClientWriteError;
+use async_raft::raft::ClientWriteRequest;
+use async_raft::Config;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+
+#[macro_use]
+mod fixtures;
+
+/// Client write backpressure test.
+///
+/// What does this test do?
+///
+/// - brings a single node cluster online, with room for 1 request in its request queue, and writes to it from many
+///   concurrent clients which must not wait for room in the queue.
+/// - asserts that some writes fail with `Overloaded`, handing back their data, and that exactly the others are appended
+///   to the log.
+/// - brings another single node cluster online, with the same queue but clients waiting for room in it, and asserts
+///   that every write succeeds.
+///
+/// RUST_LOG=async_raft,memstore,client_write_overloaded=trace cargo test -p async-raft --test client_write_overloaded
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn client_write_overloaded() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(2000));
+    let clients = 200;
+
+    tracing::info!("--- writes fail fast once the queue is full");
+    {
+        let config = Arc::new(
+            Config::build("test".into())
+                .api_queue_capacity(1)
+                .client_write_queue_timeout(0)
+                .validate()
+                .expect("failed to build Raft config"),
+        );
+        let router = Arc::new(RaftRouter::new(config.clone()));
+        let mut want = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+        let results = write_concurrently(&router, clients).await?;
+
+        let mut overloaded = 0;
+        for (serial, res) in results.into_iter().enumerate() {
+            match res {
+                Ok(()) => want += 1,
+                Err(ClientWriteError::Overloaded(req)) => {
+                    assert_eq!(serial as u64, req.serial);
+                    overloaded += 1;
+                }
+                Err(err) => panic!("unexpected error: {:?}", err),
+            }
+        }
+        assert!(overloaded > 0, "no write was turned away");
+
+        router.wait_for_log(&btreeset![0], want, timeout, "writes which were not turned away").await?;
+
+        let metrics = router.wait_for_metrics(&0, |_| true, timeout, "metrics").await?;
+        assert!(metrics.api_queue_len <= 1);
+    }
+
+    tracing::info!("--- writes wait for room in the queue");
+    {
+        let config = Arc::new(
+            Config::build("test".into()).api_queue_capacity(1).validate().expect("failed to build Raft config"),
+        );
+        let router = Arc::new(RaftRouter::new(config.clone()));
+        let mut want = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+        let results = write_concurrently(&router, clients).await?;
+        for res in results {
+            res.expect("write failed");
+        }
+        want += clients as u64;
+
+        router.wait_for_log(&btreeset![0], want, timeout, "every write").await?;
+    }
+
+    Ok(())
+}
+
+/// Write to node 0 from the given number of concurrent clients, and return the result of each write.
+async fn write_concurrently(
+    router: &Arc<RaftRouter>,
+    clients: usize,
+) -> Result<Vec<Result<(), ClientWriteError<ClientRequest>>>> {
+    let leader = router.get_raft_handle(&0).await?;
+
+    let mut handles = Vec::new();
+    for serial in 0..clients as u64 {
+        let leader = leader.clone();
+        handles.push(tokio::spawn(async move {
+            let req = ClientRequest {
+                client: "0".into(),
+                serial,
+                status: format!("request-{}", serial),
+            };
+            leader.client_write(ClientWriteRequest::new(req)).await.map(|_| ())
+        }));
+    }
+
+    let mut results = Vec::new();
+    for handle in handles {
+        results.push(handle.await?);
+    }
+    Ok(results)
+}
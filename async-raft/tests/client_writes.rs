@@ -7,6 +7,7 @@ use async_raft::LogId;
 use async_raft::State;
 use fixtures::RaftRouter;
 use futures::prelude::*;
+use maplit::btreemap;
 use maplit::btreeset;
 
 #[macro_use]
@@ -74,6 +75,8 @@ async fn client_writes() -> Result<()> {
             Some(((5000..5100).into(), 1, MembershipConfig {
                 members: btreeset![0, 1, 2],
                 members_after_consensus: None,
+                learners: btreeset! {},
+                nodes: btreemap! {},
             })),
         )
         .await;
@@ -7,6 +7,7 @@ use async_raft::LogId;
 use async_raft::SnapshotPolicy;
 use async_raft::State;
 use fixtures::RaftRouter;
+use maplit::btreemap;
 use maplit::btreeset;
 
 #[macro_use]
@@ -71,6 +72,8 @@ async fn compaction() -> Result<()> {
             Some((want.into(), 1, MembershipConfig {
                 members: btreeset![0],
                 members_after_consensus: None,
+                learners: btreeset! {},
+                nodes: btreemap! {},
             })),
         )
         .await;
@@ -78,6 +81,7 @@ async fn compaction() -> Result<()> {
     // Add a new node and assert that it received the same snapshot.
     router.new_raft_node(1).await;
     router.add_non_voter(0, 1).await.expect("failed to add new node as non-voter");
+    want += 1; // learner log
 
     tracing::info!("--- add 1 log after snapshot");
 
@@ -88,6 +92,8 @@ async fn compaction() -> Result<()> {
     let expected_snap = Some((snapshot_threshold.into(), 1, MembershipConfig {
         members: btreeset![0u64],
         members_after_consensus: None,
+        learners: btreeset! {},
+        nodes: btreemap! {},
     }));
     router
         .assert_storage_state(
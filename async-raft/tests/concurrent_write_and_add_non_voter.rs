@@ -69,6 +69,7 @@ async fn concurrent_write_and_add_non_voter() -> Result<()> {
         router.new_raft_node(2).await;
         router.add_non_voter(0, 1).await?;
         router.add_non_voter(0, 2).await?;
+        want += 2; // one learner log per non-voter
 
         tracing::info!("--- changing cluster config");
 
@@ -76,7 +77,7 @@ async fn concurrent_write_and_add_non_voter() -> Result<()> {
         want += 2; // Tow member change logs
 
         wait_log(router.clone(), &candidates, want).await?;
-        router.assert_stable_cluster(Some(1), Some(3)).await; // Still in term 1, so leader is still node 0.
+        router.assert_stable_cluster(Some(1), Some(want)).await; // Still in term 1, so leader is still node 0.
     }
 
     let leader = router.leader().await.unwrap();
@@ -109,6 +110,7 @@ async fn concurrent_write_and_add_non_voter() -> Result<()> {
         want += 1;
 
         let _ = handle.await?;
+        want += 1; // learner log
     };
 
     wait_log(router.clone(), &candidates, want).await?;
@@ -87,6 +87,7 @@ async fn conflict_with_empty_entries() -> Result<()> {
                         serial: 1,
                         status: "bar".to_string(),
                     },
+                    session: None,
                 }),
             },
         ],
@@ -66,6 +66,7 @@ async fn dynamic_membership() -> Result<()> {
     while let Some(inner) = new_nodes.next().await {
         inner?;
     }
+    want += 4; // one learner log per non-voter
 
     tracing::info!("--- changing cluster config");
     router.change_membership(0, btreeset![0, 1, 2, 3, 4]).await?;
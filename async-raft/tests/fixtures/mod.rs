@@ -15,19 +15,29 @@ use async_raft::async_trait::async_trait;
 use async_raft::error::ClientReadError;
 use async_raft::error::ClientWriteError;
 use async_raft::error::ResponseError;
+use async_raft::error::StaleReadError;
+use async_raft::error::TransferLeaderError;
 use async_raft::metrics::Wait;
 use async_raft::raft::AppendEntriesRequest;
 use async_raft::raft::AppendEntriesResponse;
 use async_raft::raft::ClientWriteRequest;
+use async_raft::raft::ClientWriteResponse;
+use async_raft::raft::ForwardChangeMembershipRequest;
+use async_raft::raft::ForwardClientWriteRequest;
 use async_raft::raft::InstallSnapshotRequest;
 use async_raft::raft::InstallSnapshotResponse;
+use async_raft::raft::MaxLag;
 use async_raft::raft::MembershipConfig;
+use async_raft::raft::Node;
+use async_raft::raft::ReadIndexRequest;
+use async_raft::raft::ReadIndexResponse;
+use async_raft::raft::TimeoutNowRequest;
+use async_raft::raft::TimeoutNowResponse;
 use async_raft::raft::VoteRequest;
 use async_raft::raft::VoteResponse;
 use async_raft::storage::RaftStorage;
 use async_raft::Config;
 use async_raft::LogId;
-use async_raft::NodeId;
 use async_raft::Raft;
 use async_raft::RaftMetrics;
 use async_raft::RaftNetwork;
@@ -88,13 +98,26 @@ pub struct RaftRouter {
     /// The Raft runtime config which all nodes are using.
     config: Arc<Config>,
     /// The table of all nodes currently known to this router instance.
-    routing_table: RwLock<BTreeMap<NodeId, (MemRaft, Arc<MemStore>)>>,
+    routing_table: RwLock<BTreeMap<u64, (MemRaft, Arc<MemStore>)>>,
     /// Nodes which are isolated can neither send nor receive frames.
-    isolated_nodes: RwLock<HashSet<NodeId>>,
+    isolated_nodes: RwLock<HashSet<u64>>,
 
     /// To enumlate network delay for sending, in milli second.
     /// 0 means no delay.
     send_delay: u64,
+
+    /// The sizes of the entries of every non-empty AppendEntries payload sent, as given by `Entry::payload_size`.
+    sent_payloads: std::sync::Mutex<Vec<Vec<u64>>>,
+
+    /// The offsets of every InstallSnapshot chunk sent.
+    sent_snapshot_chunks: std::sync::Mutex<Vec<u64>>,
+
+    /// The number of responses to non-empty AppendEntries payloads still to be lost after the target has handled them.
+    lost_append_responses: std::sync::Mutex<u64>,
+
+    /// Whether the responses to RequestVote RPCs are lost after the target has handled them, so that no candidate
+    /// wins.
+    lose_vote_responses: std::sync::atomic::AtomicBool,
 }
 
 pub struct Builder {
@@ -114,6 +137,10 @@ impl Builder {
             routing_table: Default::default(),
             isolated_nodes: Default::default(),
             send_delay: self.send_delay,
+            sent_payloads: Default::default(),
+            sent_snapshot_chunks: Default::default(),
+            lost_append_responses: Default::default(),
+            lose_vote_responses: Default::default(),
         }
     }
 }
@@ -132,6 +159,31 @@ impl RaftRouter {
         self.send_delay = ms;
     }
 
+    /// The sizes of the entries of every non-empty AppendEntries payload sent so far.
+    pub fn sent_payloads(&self) -> Vec<Vec<u64>> {
+        self.sent_payloads.lock().unwrap().clone()
+    }
+
+    /// The offsets of every InstallSnapshot chunk sent so far.
+    pub fn sent_snapshot_chunks(&self) -> Vec<u64> {
+        self.sent_snapshot_chunks.lock().unwrap().clone()
+    }
+
+    /// Lose the responses to the next `n` non-empty AppendEntries payloads, after the target has handled them.
+    pub fn lose_append_responses(&self, n: u64) {
+        *self.lost_append_responses.lock().unwrap() = n;
+    }
+
+    /// The number of AppendEntries responses still to be lost.
+    pub fn append_responses_to_lose(&self) -> u64 {
+        *self.lost_append_responses.lock().unwrap()
+    }
+
+    /// Lose the responses to RequestVote RPCs after the target has handled them, or stop losing them.
+    pub fn lose_vote_responses(&self, lose: bool) {
+        self.lose_vote_responses.store(lose, std::sync::atomic::Ordering::SeqCst);
+    }
+
     async fn rand_send_delay(&self) {
         if self.send_delay == 0 {
             return;
@@ -146,8 +198,8 @@ impl RaftRouter {
     /// NOTE: it create a single node cluster first, then change it to a multi-voter cluster.
     pub async fn new_nodes_from_single(
         self: &Arc<Self>,
-        node_ids: BTreeSet<NodeId>,
-        non_voters: BTreeSet<NodeId>,
+        node_ids: BTreeSet<u64>,
+        non_voters: BTreeSet<u64>,
     ) -> anyhow::Result<u64> {
         assert!(node_ids.contains(&0));
 
@@ -178,6 +230,7 @@ impl RaftRouter {
 
             self.new_raft_node(*id).await;
             self.add_non_voter(0, *id).await?;
+            want += 1; // learner log
         }
 
         if node_ids.len() > 1 {
@@ -193,25 +246,26 @@ impl RaftRouter {
             tracing::info!("--- add non-voter: {}", id);
             self.new_raft_node(id).await;
             self.add_non_voter(0, id).await?;
+            want += 1; // learner log
         }
 
         Ok(want)
     }
 
     /// Create and register a new Raft node bearing the given ID.
-    pub async fn new_raft_node(self: &Arc<Self>, id: NodeId) {
+    pub async fn new_raft_node(self: &Arc<Self>, id: u64) {
         let memstore = Arc::new(MemStore::new(id));
         self.new_raft_node_with_sto(id, memstore).await
     }
 
-    pub async fn new_raft_node_with_sto(self: &Arc<Self>, id: NodeId, sto: Arc<MemStore>) {
+    pub async fn new_raft_node_with_sto(self: &Arc<Self>, id: u64, sto: Arc<MemStore>) {
         let node = Raft::new(id, self.config.clone(), self.clone(), sto.clone());
         let mut rt = self.routing_table.write().await;
         rt.insert(id, (node, sto));
     }
 
     /// Remove the target node from the routing table & isolation.
-    pub async fn remove_node(&self, id: NodeId) -> Option<(MemRaft, Arc<MemStore>)> {
+    pub async fn remove_node(&self, id: u64) -> Option<(MemRaft, Arc<MemStore>)> {
         let mut rt = self.routing_table.write().await;
         let opt_handles = rt.remove(&id);
         let mut isolated = self.isolated_nodes.write().await;
@@ -221,10 +275,10 @@ impl RaftRouter {
     }
 
     /// Initialize all nodes based on the config in the routing table.
-    pub async fn initialize_from_single_node(&self, node: NodeId) -> Result<()> {
+    pub async fn initialize_from_single_node(&self, node: u64) -> Result<()> {
         tracing::info!({ node }, "initializing cluster from single node");
         let rt = self.routing_table.read().await;
-        let members: BTreeSet<NodeId> = rt.keys().cloned().collect();
+        let members: BTreeSet<u64> = rt.keys().cloned().collect();
         rt.get(&node)
             .ok_or_else(|| anyhow!("node {} not found in routing table", node))?
             .0
@@ -234,7 +288,7 @@ impl RaftRouter {
     }
 
     /// Initialize cluster with specified node ids.
-    pub async fn initialize_with(&self, node: NodeId, members: BTreeSet<NodeId>) -> Result<()> {
+    pub async fn initialize_with(&self, node: u64, members: BTreeSet<u64>) -> Result<()> {
         tracing::info!({ node }, "initializing cluster from single node");
         let rt = self.routing_table.read().await;
         rt.get(&node)
@@ -247,7 +301,7 @@ impl RaftRouter {
 
     /// Isolate the network of the specified node.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn isolate_node(&self, id: NodeId) {
+    pub async fn isolate_node(&self, id: u64) {
         self.isolated_nodes.write().await.insert(id);
     }
 
@@ -262,7 +316,13 @@ impl RaftRouter {
     }
 
     /// Get a handle to the storage backend for the target node.
-    pub async fn get_storage_handle(&self, node_id: &NodeId) -> Result<Arc<MemStore>> {
+    pub async fn get_raft_handle(&self, node_id: &u64) -> Result<MemRaft> {
+        let rt = self.routing_table.read().await;
+        let addr = rt.get(node_id).with_context(|| format!("could not find node {} in routing table", node_id))?;
+        Ok(addr.0.clone())
+    }
+
+    pub async fn get_storage_handle(&self, node_id: &u64) -> Result<Arc<MemStore>> {
         let rt = self.routing_table.read().await;
         let addr = rt.get(node_id).with_context(|| format!("could not find node {} in routing table", node_id))?;
         let sto = addr.clone().1;
@@ -273,7 +333,7 @@ impl RaftRouter {
     #[tracing::instrument(level = "info", skip(self, func))]
     pub async fn wait_for_metrics<T>(
         &self,
-        node_id: &NodeId,
+        node_id: &u64,
         func: T,
         timeout: Option<Duration>,
         msg: &str,
@@ -286,7 +346,7 @@ impl RaftRouter {
         Ok(rst)
     }
 
-    pub async fn wait(&self, node_id: &NodeId, timeout: Option<Duration>) -> Result<Wait> {
+    pub async fn wait(&self, node_id: &u64, timeout: Option<Duration>) -> Result<Wait> {
         let rt = self.routing_table.read().await;
         let node = rt.get(node_id).with_context(|| format!("node {} not found", node_id))?;
 
@@ -339,7 +399,7 @@ impl RaftRouter {
     }
 
     /// Get the ID of the current leader.
-    pub async fn leader(&self) -> Option<NodeId> {
+    pub async fn leader(&self) -> Option<u64> {
         let isolated = self.isolated_nodes.read().await;
         self.latest_metrics().await.into_iter().find_map(|node| {
             if node.current_leader == Some(node.id) {
@@ -356,32 +416,94 @@ impl RaftRouter {
 
     /// Restore the network of the specified node.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn restore_node(&self, id: NodeId) {
+    pub async fn restore_node(&self, id: u64) {
         let mut nodes = self.isolated_nodes.write().await;
         nodes.remove(&id);
     }
 
-    pub async fn add_non_voter(&self, leader: NodeId, target: NodeId) -> Result<(), ResponseError> {
+    pub async fn add_non_voter(&self, leader: u64, target: u64) -> Result<(), ResponseError> {
+        let rt = self.routing_table.read().await;
+        let node = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
+        node.0.add_non_voter(target, None).await
+    }
+
+    pub async fn add_non_voter_with_node(
+        &self,
+        leader: u64,
+        target: u64,
+        target_node: Node,
+    ) -> Result<(), ResponseError> {
         let rt = self.routing_table.read().await;
         let node = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
-        node.0.add_non_voter(target).await
+        node.0.add_non_voter(target, Some(target_node)).await
     }
 
-    pub async fn change_membership(&self, leader: NodeId, members: BTreeSet<NodeId>) -> Result<(), ResponseError> {
+    pub async fn remove_non_voter(&self, leader: u64, target: u64) -> Result<(), ResponseError> {
         let rt = self.routing_table.read().await;
         let node = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
-        node.0.change_membership(members).await
+        node.0.remove_non_voter(target).await
+    }
+
+    pub async fn change_membership(&self, leader: u64, members: BTreeSet<u64>) -> Result<(), ResponseError> {
+        let rt = self.routing_table.read().await;
+        let node = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
+        node.0.change_membership(members, BTreeMap::new()).await
+    }
+
+    pub async fn change_membership_with_nodes(
+        &self,
+        leader: u64,
+        members: BTreeSet<u64>,
+        nodes: BTreeMap<u64, Node>,
+    ) -> Result<(), ResponseError> {
+        let rt = self.routing_table.read().await;
+        let node = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
+        node.0.change_membership(members, nodes).await
+    }
+
+    pub async fn add_voter(&self, leader: u64, target: u64) -> Result<(), ResponseError> {
+        let rt = self.routing_table.read().await;
+        let node = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
+        node.0.add_voter(target, None).await
+    }
+
+    pub async fn remove_voter(&self, leader: u64, target: u64) -> Result<(), ResponseError> {
+        let rt = self.routing_table.read().await;
+        let node = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
+        node.0.remove_voter(target).await
+    }
+
+    pub async fn transfer_leadership(&self, leader: u64, target: u64) -> Result<(), TransferLeaderError> {
+        let rt = self.routing_table.read().await;
+        let node = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
+        node.0.transfer_leadership(target).await
     }
 
     /// Send a client read request to the target node.
-    pub async fn client_read(&self, target: NodeId) -> Result<(), ClientReadError> {
+    pub async fn client_read(&self, target: u64) -> Result<(), ClientReadError> {
         let rt = self.routing_table.read().await;
         let node = rt.get(&target).unwrap_or_else(|| panic!("node with ID {} does not exist", target));
         node.0.client_read().await
     }
 
+    /// Request a read index from the target node.
+    pub async fn read_index(&self, target: u64) -> Result<LogId, ClientReadError> {
+        let node = {
+            let rt = self.routing_table.read().await;
+            rt.get(&target).unwrap_or_else(|| panic!("node with ID {} does not exist", target)).0.clone()
+        };
+        node.read_index().await
+    }
+
+    /// Request a stale read from the target node.
+    pub async fn stale_read(&self, target: u64, max_lag: MaxLag) -> Result<LogId, StaleReadError> {
+        let rt = self.routing_table.read().await;
+        let node = rt.get(&target).unwrap_or_else(|| panic!("node with ID {} does not exist", target));
+        node.0.stale_read(max_lag).await
+    }
+
     /// Send a client request to the target node, causing test failure on error.
-    pub async fn client_request(&self, target: NodeId, client_id: &str, serial: u64) {
+    pub async fn client_request(&self, target: u64, client_id: &str, serial: u64) {
         let req = MemClientRequest {
             client: client_id.into(),
             serial,
@@ -394,14 +516,14 @@ impl RaftRouter {
     }
 
     /// Request the current leader from the target node.
-    pub async fn current_leader(&self, target: NodeId) -> Option<NodeId> {
+    pub async fn current_leader(&self, target: u64) -> Option<u64> {
         let rt = self.routing_table.read().await;
         let node = rt.get(&target).unwrap_or_else(|| panic!("node with ID {} does not exist", target));
         node.0.current_leader().await
     }
 
     /// Send multiple client requests to the target node, causing test failure on error.
-    pub async fn client_request_many(&self, target: NodeId, client_id: &str, count: usize) {
+    pub async fn client_request_many(&self, target: u64, client_id: &str, count: usize) {
         for idx in 0..count {
             self.client_request(target, client_id, idx as u64).await
         }
@@ -409,7 +531,7 @@ impl RaftRouter {
 
     async fn send_client_request(
         &self,
-        target: NodeId,
+        target: u64,
         req: MemClientRequest,
     ) -> std::result::Result<MemClientResponse, ClientWriteError<MemClientRequest>> {
         let rt = self.routing_table.read().await;
@@ -622,7 +744,7 @@ impl RaftRouter {
 }
 
 #[async_trait]
-impl RaftNetwork<MemClientRequest> for RaftRouter {
+impl RaftNetwork<MemClientRequest, MemClientResponse> for RaftRouter {
     /// Send an AppendEntries RPC to the target Raft node (§5).
     async fn send_append_entries(
         &self,
@@ -630,6 +752,10 @@ impl RaftNetwork<MemClientRequest> for RaftRouter {
         rpc: AppendEntriesRequest<MemClientRequest>,
     ) -> Result<AppendEntriesResponse> {
         tracing::debug!("append_entries to id={} {:?}", target, rpc);
+        if !rpc.entries.is_empty() {
+            let sizes = rpc.entries.iter().map(|entry| entry.payload_size()).collect();
+            self.sent_payloads.lock().unwrap().push(sizes);
+        }
         self.rand_send_delay().await;
 
         let rt = self.routing_table.read().await;
@@ -638,14 +764,23 @@ impl RaftNetwork<MemClientRequest> for RaftRouter {
         if isolated.contains(&target) || isolated.contains(&rpc.leader_id) {
             return Err(anyhow!("target node is isolated"));
         }
+        let has_entries = !rpc.entries.is_empty();
         let resp = addr.0.append_entries(rpc).await;
 
         tracing::debug!("append_entries: recv resp from id={} {:?}", target, resp);
+        if has_entries {
+            let mut lost = self.lost_append_responses.lock().unwrap();
+            if *lost > 0 {
+                *lost -= 1;
+                return Err(anyhow!("response lost"));
+            }
+        }
         Ok(resp?)
     }
 
     /// Send an InstallSnapshot RPC to the target Raft node (§7).
     async fn send_install_snapshot(&self, target: u64, rpc: InstallSnapshotRequest) -> Result<InstallSnapshotResponse> {
+        self.sent_snapshot_chunks.lock().unwrap().push(rpc.offset);
         self.rand_send_delay().await;
 
         let rt = self.routing_table.read().await;
@@ -667,7 +802,71 @@ impl RaftNetwork<MemClientRequest> for RaftRouter {
         if isolated.contains(&target) || isolated.contains(&rpc.candidate_id) {
             return Err(anyhow!("target node is isolated"));
         }
-        Ok(addr.0.vote(rpc).await?)
+        let resp = addr.0.vote(rpc).await?;
+        if self.lose_vote_responses.load(std::sync::atomic::Ordering::SeqCst) {
+            return Err(anyhow!("vote response lost"));
+        }
+        Ok(resp)
+    }
+
+    /// Send a TimeoutNow RPC to the target Raft node.
+    async fn send_timeout_now(&self, target: u64, rpc: TimeoutNowRequest) -> Result<TimeoutNowResponse> {
+        self.rand_send_delay().await;
+
+        let rt = self.routing_table.read().await;
+        let isolated = self.isolated_nodes.read().await;
+        let addr = rt.get(&target).expect("target node not found in routing table");
+        if isolated.contains(&target) || isolated.contains(&rpc.leader_id) {
+            return Err(anyhow!("target node is isolated"));
+        }
+        Ok(addr.0.timeout_now(rpc).await?)
+    }
+
+    /// Send a ReadIndex RPC to the target Raft node.
+    async fn send_read_index(&self, target: u64, rpc: ReadIndexRequest) -> Result<ReadIndexResponse> {
+        self.rand_send_delay().await;
+
+        let rt = self.routing_table.read().await;
+        let isolated = self.isolated_nodes.read().await;
+        let addr = rt.get(&target).expect("target node not found in routing table");
+        if isolated.contains(&target) || isolated.contains(&rpc.node_id) {
+            return Err(anyhow!("target node is isolated"));
+        }
+        Ok(addr.0.handle_read_index(rpc).await?)
+    }
+
+    /// Forward a client write to the target Raft node.
+    async fn forward_client_write(
+        &self,
+        target: u64,
+        rpc: ForwardClientWriteRequest<MemClientRequest>,
+    ) -> Result<Result<ClientWriteResponse<MemClientResponse>, ClientWriteError<MemClientRequest>>> {
+        self.rand_send_delay().await;
+
+        let rt = self.routing_table.read().await;
+        let isolated = self.isolated_nodes.read().await;
+        let addr = rt.get(&target).expect("target node not found in routing table");
+        if isolated.contains(&target) || rpc.path.last().map(|id| isolated.contains(id)).unwrap_or_default() {
+            return Err(anyhow!("target node is isolated"));
+        }
+        Ok(addr.0.handle_forward_client_write(rpc).await)
+    }
+
+    /// Forward a membership change to the target Raft node.
+    async fn forward_change_membership(
+        &self,
+        target: u64,
+        rpc: ForwardChangeMembershipRequest,
+    ) -> Result<Result<(), ResponseError>> {
+        self.rand_send_delay().await;
+
+        let rt = self.routing_table.read().await;
+        let isolated = self.isolated_nodes.read().await;
+        let addr = rt.get(&target).expect("target node not found in routing table");
+        if isolated.contains(&target) || rpc.path.last().map(|id| isolated.contains(id)).unwrap_or_default() {
+            return Err(anyhow!("target node is isolated"));
+        }
+        Ok(addr.0.handle_forward_change_membership(rpc).await)
     }
 }
 
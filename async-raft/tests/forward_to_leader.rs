@@ -0,0 +1,152 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::error::ChangeConfigError;
+use async_raft::error::ClientWriteError;
+use async_raft::error::ResponseError;
+use async_raft::raft::ClientWriteRequest;
+use async_raft::raft::ForwardClientWriteRequest;
+use async_raft::Config;
+use async_raft::RaftStorageDebug;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+
+#[macro_use]
+mod fixtures;
+
+/// Request forwarding test.
+///
+/// What does this test do?
+///
+/// - brings a 3 node cluster online, with forwarding to the leader enabled.
+/// - writes to a follower, and asserts that the write is forwarded to the leader and applied, and that the follower
+///   returns the leader's response.
+/// - writes a batch to a follower, and asserts that every write is forwarded to the leader, in order.
+/// - reads on a follower, and asserts that it succeeds.
+/// - adds a non-voter through a follower, and asserts that the membership change is forwarded to the leader.
+/// - adds the same non-voter again through a follower, and asserts that the follower returns the leader's `Noop` error.
+/// - submits forwarded writes which would go around in a loop, or which were forwarded as many times as allowed, and
+///   asserts that they fail with `ForwardToLeader` instead of being forwarded again.
+/// - writes to a follower while the leader turns writes away during a leadership transfer, and asserts that the
+///   follower returns the leader's `ForwardToLeader` error.
+///
+/// RUST_LOG=async_raft,memstore,forward_to_leader=trace cargo test -p async-raft --test forward_to_leader
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn forward_to_leader() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(2000));
+
+    // Setup test dependencies.
+    let config = Arc::new(
+        Config::build("test".into())
+            .forward_to_leader(true)
+            .max_forward_hops(2)
+            .validate()
+            .expect("failed to build Raft config"),
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0, 1, 2}, btreeset! {}).await?;
+
+    let follower = router.get_raft_handle(&1).await?;
+
+    tracing::info!("--- a write to a follower is forwarded to the leader");
+    {
+        let res = follower.client_write(ClientWriteRequest::new(request(1))).await?;
+        want += 1;
+        assert_eq!(want, res.index);
+
+        router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "forwarded write").await?;
+        let sm = router.get_storage_handle(&0).await?.get_state_machine().await;
+        assert_eq!(Some(&"request-1".to_string()), sm.client_status.get("0"));
+    }
+
+    tracing::info!("--- a batch written to a follower is forwarded to the leader");
+    {
+        let res = follower.client_write_batch(vec![request(2), request(3), request(4)]).await;
+        assert_eq!(3, res.len());
+        for r in res {
+            let r = r.expect("forwarded batched write failed");
+            want += 1;
+            assert_eq!(want, r.index);
+        }
+
+        router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "forwarded batch").await?;
+        let sm = router.get_storage_handle(&0).await?.get_state_machine().await;
+        assert_eq!(Some(&"request-4".to_string()), sm.client_status.get("0"));
+    }
+
+    tracing::info!("--- a read on a follower gets its read index from the leader");
+    {
+        router.client_read(1).await?;
+        router.client_read(2).await?;
+    }
+
+    tracing::info!("--- a membership change submitted to a follower is forwarded to the leader");
+    {
+        router.new_raft_node(3).await;
+        router.get_raft_handle(&2).await?.add_non_voter(3, None).await?;
+        want += 1;
+
+        router.wait_for_log(&btreeset![0, 1, 2, 3], want, timeout, "forwarded add non-voter").await?;
+
+        let res = router.get_raft_handle(&2).await?.add_non_voter(3, None).await;
+        assert!(
+            matches!(res, Err(ResponseError::ChangeConfig(ChangeConfigError::Noop))),
+            "unexpected result: {:?}",
+            res
+        );
+    }
+
+    tracing::info!("--- a request is not forwarded in a loop, nor beyond the hop limit");
+    {
+        for path in [vec![0], vec![1], vec![2, 3]].iter() {
+            let res = follower
+                .handle_forward_client_write(ForwardClientWriteRequest {
+                    path: path.clone(),
+                    rpc: ClientWriteRequest::new(request(2)),
+                })
+                .await;
+            match res {
+                Err(ClientWriteError::ForwardToLeader(req, Some(0))) => assert_eq!(2, req.serial),
+                _ => panic!("unexpected result for path {:?}: {:?}", path, res),
+            }
+        }
+
+        router.wait_for_log(&btreeset![0, 1, 2, 3], want, timeout, "nothing appended").await?;
+    }
+
+    tracing::info!("--- the leader's error for a forwarded write is returned as is");
+    {
+        // The leader turns writes away while transferring leadership to a target which can not catch up.
+        router.isolate_node(2).await;
+        let transfer = {
+            let router = router.clone();
+            tokio::spawn(async move { router.transfer_leadership(0, 2).await })
+        };
+        router.wait_for_metrics(&0, |x| x.current_leader == Some(0), timeout, "transfer started").await?;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let res = follower.client_write(ClientWriteRequest::new(request(5))).await;
+        match res {
+            Err(ClientWriteError::ForwardToLeader(req, None)) => assert_eq!(5, req.serial),
+            _ => panic!("unexpected result: {:?}", res),
+        }
+
+        let _ = transfer.await?;
+    }
+
+    Ok(())
+}
+
+fn request(serial: u64) -> ClientRequest {
+    ClientRequest {
+        client: "0".into(),
+        serial,
+        status: format!("request-{}", serial),
+    }
+}
@@ -8,6 +8,7 @@ use async_raft::RaftStorage;
 use async_raft::RaftStorageDebug;
 use async_raft::State;
 use fixtures::RaftRouter;
+use maplit::btreemap;
 use maplit::btreeset;
 
 #[macro_use]
@@ -70,6 +71,8 @@ async fn initialization() -> Result<()> {
             Some(MembershipConfig {
                 members: btreeset![0, 1, 2],
                 members_after_consensus: None,
+                learners: btreeset! {},
+                nodes: btreemap! {},
             }),
             sm_mem
         );
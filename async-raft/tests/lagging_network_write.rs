@@ -60,6 +60,7 @@ async fn lagging_network_write() -> Result<()> {
 
     router.new_raft_node(2).await;
     router.add_non_voter(0, 2).await?;
+    want += 2; // one learner log per non-voter
 
     router.wait_for_log(&btreeset![1, 2], want, timeout, "non-voter init").await?;
 
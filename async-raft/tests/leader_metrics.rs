@@ -95,6 +95,7 @@ async fn leader_metrics() -> Result<()> {
     while let Some(inner) = new_nodes.next().await {
         inner?;
     }
+    want += 4; // one learner log per non-voter
 
     router.wait_for_log(&all_members, want, timeout, "add non-voter 1,2,3,4").await?;
 
@@ -167,14 +168,7 @@ async fn leader_metrics() -> Result<()> {
 
     tracing::info!("--- take leadership of node 0");
 
-    router
-        .send_vote(0, VoteRequest {
-            term: 100,
-            candidate_id: 100,
-            last_log_index: 100,
-            last_log_term: 10,
-        })
-        .await?;
+    router.send_vote(0, VoteRequest::new(100, 100, 100, 10)).await?;
 
     router.wait_for_state(&btreeset![0], State::Candidate, timeout, "node 0 to candidate").await?;
 
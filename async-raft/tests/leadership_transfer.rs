@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_raft::error::TransferLeaderError;
+use async_raft::Config;
+use async_raft::State;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Leadership transfer test.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster with PreVote enabled.
+/// - assert that transfer requests sent to a follower, or naming a non-member, are rejected.
+/// - isolate a follower and assert that a transfer to it times out, leaving the leader in place.
+/// - restore the follower, transfer leadership to it, and assert that it becomes leader in the next term and accepts
+///   client writes.
+///
+/// RUST_LOG=async_raft,memstore,leadership_transfer=trace cargo test -p async-raft --test leadership_transfer
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn leadership_transfer() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // Setup test dependencies. PreVote keeps the isolated node from driving up the term.
+    let config = Arc::new(Config::build("test".into()).pre_vote(true).validate().expect("failed to build Raft config"));
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+    router.new_raft_node(1).await;
+    router.new_raft_node(2).await;
+
+    let mut want = 0;
+
+    // Assert all nodes are in non-voter state & have no entries.
+    router.wait_for_log(&btreeset![0, 1, 2], want, None, "empty").await?;
+    router.wait_for_state(&btreeset![0, 1, 2], State::NonVoter, None, "empty").await?;
+    router.assert_pristine_cluster().await;
+
+    // Initialize the cluster, then assert that a stable cluster was formed & held.
+    tracing::info!("--- initializing cluster");
+    router.initialize_from_single_node(0).await?;
+    want += 1;
+
+    router.wait_for_log(&btreeset![0, 1, 2], want, None, "init").await?;
+    router.assert_stable_cluster(Some(1), Some(want)).await;
+
+    router.client_request_many(0, "0", 10).await;
+    want += 10;
+    router.wait_for_log(&btreeset![0, 1, 2], want, None, "write").await?;
+
+    tracing::info!("--- invalid transfer requests");
+    {
+        let res = router.transfer_leadership(1, 2).await;
+        assert!(
+            matches!(res, Err(TransferLeaderError::NodeNotLeader(Some(0)))),
+            "expected NodeNotLeader, got {:?}",
+            res
+        );
+
+        let res = router.transfer_leadership(0, 5).await;
+        assert!(
+            matches!(res, Err(TransferLeaderError::InvalidTarget(5))),
+            "expected InvalidTarget, got {:?}",
+            res
+        );
+    }
+
+    tracing::info!("--- transfer to an isolated node times out");
+    {
+        router.isolate_node(2).await;
+
+        let res = router.transfer_leadership(0, 2).await;
+        assert!(
+            matches!(res, Err(TransferLeaderError::Timeout(2))),
+            "expected Timeout, got {:?}",
+            res
+        );
+
+        // The leader accepts writes again once the transfer is aborted.
+        router.client_request_many(0, "0", 10).await;
+        want += 10;
+        router.wait_for_log(&btreeset![0, 1], want, None, "write after aborted transfer").await?;
+
+        router.restore_node(2).await;
+        router.wait_for_log(&btreeset![0, 1, 2], want, None, "node 2 catches up").await?;
+        router.assert_stable_cluster(Some(1), Some(want)).await;
+    }
+
+    tracing::info!("--- transfer leadership to node 2");
+    {
+        router.transfer_leadership(0, 2).await?;
+
+        // The new leader commits a blank entry in its term.
+        want += 1;
+        router.wait_for_state(&btreeset![2], State::Leader, None, "node 2 becomes leader").await?;
+        router.wait_for_log(&btreeset![0, 1, 2], want, None, "new leader's initial entry").await?;
+        router.assert_stable_cluster(Some(2), Some(want)).await;
+
+        router.client_request_many(2, "0", 10).await;
+        want += 10;
+        router.wait_for_log(&btreeset![0, 1, 2], want, None, "write to the new leader").await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::error::TransferLeaderError;
+use async_raft::Config;
+use async_raft::State;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Leadership transfer to a target which is never elected test.
+///
+/// Once the leader has asked the target to take over, it steps down as soon as it hears of the target's election. If
+/// no leader is elected after that, the transfer must still be answered once its deadline has passed.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster.
+/// - lose every vote response, so that no candidate can win an election, and transfer leadership to node 2.
+/// - assert that the transfer times out, although the old leader stepped down and never learns of a new leader.
+/// - stop losing vote responses, and assert that a leader is elected again.
+///
+/// RUST_LOG=async_raft,memstore,leadership_transfer_not_elected=trace cargo test -p async-raft --test
+/// leadership_transfer_not_elected
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn leadership_transfer_not_elected() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(5000));
+
+    // Setup test dependencies.
+    let config = Arc::new(Config::build("test".into()).validate().expect("failed to build Raft config"));
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0, 1, 2}, btreeset! {}).await?;
+
+    router.client_request_many(0, "0", 10).await;
+    want += 10;
+    router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "write").await?;
+
+    tracing::info!("--- a transfer to a target which is never elected times out");
+    {
+        router.lose_vote_responses(true);
+
+        let res = tokio::time::timeout(Duration::from_millis(5000), router.transfer_leadership(0, 2)).await?;
+        assert!(
+            matches!(res, Err(TransferLeaderError::Timeout(2))),
+            "expected Timeout, got {:?}",
+            res
+        );
+
+        let metrics = router.latest_metrics().await;
+        assert_ne!(State::Leader, metrics[0].state, "expected node 0 to have stepped down");
+        assert_ne!(Some(2), metrics[0].current_leader, "expected node 2 not to be elected");
+    }
+
+    tracing::info!("--- a leader is elected once votes get through");
+    {
+        router.lose_vote_responses(false);
+
+        router.wait_for_metrics(&0, |m| m.current_leader.is_some(), timeout, "a leader is elected").await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_raft::Config;
+use async_raft::State;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Learner leader change test.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster, with an additional non-voter.
+/// - assert that the non-voter is recorded as a learner in the membership config of every node.
+/// - transfer leadership to another node.
+/// - assert that the new leader replicates to the non-voter, without it being added again.
+///
+/// RUST_LOG=async_raft,memstore,learner_leader_change=trace cargo test -p async-raft --test learner_leader_change
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn learner_leader_change() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // Setup test dependencies.
+    let config = Arc::new(Config::build("test".into()).validate().expect("failed to build Raft config"));
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0, 1, 2}, btreeset! {3}).await?;
+    router.wait_for_log(&btreeset![0, 1, 2, 3], want, None, "cluster with a non-voter").await?;
+
+    for m in router.latest_metrics().await {
+        assert_eq!(btreeset![0, 1, 2], m.membership_config.members, "node {} voters", m.id);
+        assert_eq!(btreeset![3], m.membership_config.learners, "node {} learners", m.id);
+    }
+
+    tracing::info!("--- transfer leadership to node 1");
+    {
+        router.transfer_leadership(0, 1).await?;
+
+        // The new leader commits a blank entry in its term.
+        want += 1;
+        router.wait_for_state(&btreeset![1], State::Leader, None, "node 1 becomes leader").await?;
+        router.wait_for_log(&btreeset![0, 1, 2, 3], want, None, "new leader's initial entry").await?;
+        router.wait_for_state(&btreeset![3], State::NonVoter, None, "node 3 stays non-voter").await?;
+    }
+
+    tracing::info!("--- the new leader replicates to the learner");
+    {
+        router.client_request_many(1, "0", 10).await;
+        want += 10;
+        router.wait_for_log(&btreeset![0, 1, 2, 3], want, None, "write to the new leader").await?;
+
+        router
+            .wait_for_metrics(
+                &1,
+                |x| x.leader_metrics.as_ref().map(|m| m.non_voters == btreeset![3]).unwrap_or(false),
+                None,
+                "non-voter 3 in new leader metrics",
+            )
+            .await?;
+    }
+
+    Ok(())
+}
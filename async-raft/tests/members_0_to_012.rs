@@ -30,7 +30,7 @@ async fn members_0_to_012() -> Result<()> {
     router.new_raft_node(0).await;
 
     // Assert all nodes are in non-voter state & have no entries.
-    let want;
+    let mut want;
 
     // router.assert_pristine_cluster().await;
 
@@ -52,6 +52,7 @@ async fn members_0_to_012() -> Result<()> {
     while let Some(inner) = new_nodes.next().await {
         inner?;
     }
+    want += 2; // one learner log per non-voter
 
     router.wait_for_log(&btreeset![0], want, None, "init node 0").await?;
 
@@ -11,6 +11,7 @@ use async_raft::LogId;
 use async_raft::Raft;
 use async_raft::RaftStorage;
 use fixtures::RaftRouter;
+use maplit::btreemap;
 use maplit::btreeset;
 
 #[macro_use]
@@ -49,6 +50,8 @@ async fn members_leader_fix_partial() -> Result<()> {
                 membership: MembershipConfig {
                     members: btreeset! {0},
                     members_after_consensus: Some(btreeset! {0,1,2}),
+                    learners: btreeset! {},
+                    nodes: btreemap! {},
                 },
             }),
         }])
@@ -84,6 +87,8 @@ async fn members_leader_fix_partial() -> Result<()> {
         MembershipConfig {
             members: btreeset! {0,1,2},
             members_after_consensus: None,
+            learners: btreeset! {},
+            nodes: btreemap! {},
         },
         m
     );
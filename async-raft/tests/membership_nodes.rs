@@ -0,0 +1,119 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_raft::raft::Node;
+use async_raft::Config;
+use async_raft::State;
+use fixtures::RaftRouter;
+use maplit::btreemap;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Membership node metadata test.
+///
+/// What does this test do?
+///
+/// - brings a single-node cluster online.
+/// - add a non-voter with metadata, and assert the metadata is replicated to it.
+/// - change membership to 3 voters, giving metadata of the new nodes, and assert that every node has the metadata of
+///   every member.
+/// - remove a voter, and assert that its metadata is removed as well.
+///
+/// RUST_LOG=async_raft,memstore,membership_nodes=trace cargo test -p async-raft --test membership_nodes
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn membership_nodes() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // Setup test dependencies.
+    let config = Arc::new(Config::build("test".into()).validate().expect("failed to build Raft config"));
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+
+    let mut want = 0;
+
+    // Assert all nodes are in non-voter state & have no entries.
+    router.wait_for_log(&btreeset![0], want, None, "empty").await?;
+    router.wait_for_state(&btreeset![0], State::NonVoter, None, "empty").await?;
+    router.assert_pristine_cluster().await;
+
+    tracing::info!("--- initializing cluster");
+
+    router.initialize_from_single_node(0).await?;
+    want += 1;
+
+    router.wait_for_log(&btreeset![0], want, None, "init").await?;
+
+    router.new_raft_node(1).await;
+    router.new_raft_node(2).await;
+
+    tracing::info!("--- add non-voter 1 with metadata");
+    {
+        router.add_non_voter_with_node(0, 1, Node::new("127.0.0.1:21001")).await?;
+        want += 1; // learner log
+
+        router.wait_for_log(&btreeset![0, 1], want, None, "add non-voter 1").await?;
+        for m in router.latest_metrics().await.into_iter().filter(|m| m.id != 2) {
+            assert_eq!(
+                btreemap! {1 => Node::new("127.0.0.1:21001")},
+                m.membership_config.nodes,
+                "node {} metadata",
+                m.id
+            );
+        }
+    }
+
+    tracing::info!("--- change membership to 3 voters, with metadata of the other nodes");
+    {
+        router
+            .change_membership_with_nodes(0, btreeset![0, 1, 2], btreemap! {
+                0 => Node::new("127.0.0.1:21000"),
+                2 => Node::new("127.0.0.1:21002"),
+                // Not a member, ignored.
+                3 => Node::new("127.0.0.1:21003"),
+            })
+            .await?;
+        want += 2;
+
+        router.wait_for_log(&btreeset![0, 1, 2], want, None, "cluster of 3").await?;
+        for m in router.latest_metrics().await {
+            assert_eq!(
+                btreemap! {
+                    0 => Node::new("127.0.0.1:21000"),
+                    1 => Node::new("127.0.0.1:21001"),
+                    2 => Node::new("127.0.0.1:21002"),
+                },
+                m.membership_config.nodes,
+                "node {} metadata",
+                m.id
+            );
+            assert_eq!(
+                Some("127.0.0.1:21002"),
+                m.membership_config.get_node(&2).map(|n| n.addr.as_str())
+            );
+        }
+    }
+
+    tracing::info!("--- remove voter 2, its metadata is removed");
+    {
+        router.change_membership(0, btreeset![0, 1]).await?;
+        want += 2;
+
+        router.wait_for_log(&btreeset![0, 1], want, None, "cluster of 2").await?;
+        for m in router.latest_metrics().await.into_iter().filter(|m| m.id != 2) {
+            assert_eq!(
+                btreemap! {
+                    0 => Node::new("127.0.0.1:21000"),
+                    1 => Node::new("127.0.0.1:21001"),
+                },
+                m.membership_config.nodes,
+                "node {} metadata",
+                m.id
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -47,7 +47,7 @@ async fn metrics_state_machine_consistency() -> Result<()> {
     // Wait for metrics to be up to date.
     // Once last_applied updated, the key should be visible in state machine.
     tracing::info!("--- wait for log to sync");
-    let want = 2u64;
+    let want = 3u64; // initial log, learner log and the written log
     for node_id in 0..2 {
         router.wait_for_log(&btreeset![node_id], want, None, "write one log").await?;
         let sto = router.get_storage_handle(&node_id).await?;
@@ -3,7 +3,6 @@ use std::time::Duration;
 
 use anyhow::Result;
 use async_raft::Config;
-use async_raft::NodeId;
 use async_raft::Raft;
 use async_raft::State;
 use fixtures::RaftRouter;
@@ -24,7 +23,7 @@ mod fixtures;
 /// - asserts that the leader was able to successfully commit its initial payload and that the non-voter has
 ///   successfully replicated the payload.
 /// - shutdown all and retstart the non-voter node.
-/// - asserts the non-voter stays in non-vtoer state.
+/// - asserts the non-voter stays in non-vtoer state, and finds itself as a learner in its membership config.
 ///
 /// RUST_LOG=async_raft,memstore,non_voter_restart=trace cargo test -p async-raft --test non_voter_restart
 #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
@@ -52,28 +51,33 @@ async fn non_voter_restart() -> Result<()> {
     want += 1;
 
     router.add_non_voter(0, 1).await?;
+    want += 1; // learner log
     router.client_request(0, "foo", 1).await;
     want += 1;
 
     router.wait_for_log(&btreeset![0, 1], want, None, "write one log").await?;
 
     let (node0, _sto0) = router.remove_node(0).await.unwrap();
-    assert_node_state(0, &node0, 1, 2, State::Leader);
+    assert_node_state(0, &node0, 1, want, State::Leader);
     node0.shutdown().await?;
 
     let (node1, sto1) = router.remove_node(1).await.unwrap();
-    assert_node_state(0, &node1, 1, 2, State::NonVoter);
+    assert_node_state(0, &node1, 1, want, State::NonVoter);
     node1.shutdown().await?;
 
     // restart node-1, assert the state as expected.
     let restarted = Raft::new(1, config.clone(), router.clone(), sto1);
     sleep(Duration::from_secs(2)).await;
-    assert_node_state(1, &restarted, 1, 2, State::NonVoter);
+    assert_node_state(1, &restarted, 1, want, State::NonVoter);
+
+    let membership = restarted.metrics().borrow().membership_config.clone();
+    assert_eq!(btreeset![0], membership.members, "node 1 voters");
+    assert_eq!(btreeset![1], membership.learners, "node 1 learners");
 
     Ok(())
 }
 
-fn assert_node_state(id: NodeId, node: &MemRaft, expected_term: u64, expected_log: u64, state: State) {
+fn assert_node_state(id: u64, node: &MemRaft, expected_term: u64, expected_log: u64, state: State) {
     let m = node.metrics().borrow().clone();
     tracing::info!("node {} metrics: {:?}", id, m);
 
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::Config;
+use async_raft::State;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// PreVote test.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster with PreVote enabled.
+/// - isolate a follower for several election timeouts, and assert that it campaigns without incrementing its term.
+/// - restore the follower, and assert that it rejoins the cluster as a follower without disturbing the leader or the
+///   term.
+///
+/// RUST_LOG=async_raft,memstore,pre_vote=trace cargo test -p async-raft --test pre_vote
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn pre_vote() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // Setup test dependencies.
+    let config = Arc::new(Config::build("test".into()).pre_vote(true).validate().expect("failed to build Raft config"));
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+    router.new_raft_node(1).await;
+    router.new_raft_node(2).await;
+
+    let mut want = 0;
+
+    // Assert all nodes are in non-voter state & have no entries.
+    router.wait_for_log(&btreeset![0, 1, 2], want, None, "empty").await?;
+    router.wait_for_state(&btreeset![0, 1, 2], State::NonVoter, None, "empty").await?;
+    router.assert_pristine_cluster().await;
+
+    // Initialize the cluster, then assert that a stable cluster was formed & held.
+    tracing::info!("--- initializing cluster");
+    router.initialize_from_single_node(0).await?;
+    want += 1;
+
+    router.wait_for_log(&btreeset![0, 1, 2], want, None, "init").await?;
+    router.assert_stable_cluster(Some(1), Some(want)).await;
+
+    // Isolate a follower & give it enough time to campaign a few times.
+    tracing::info!("--- isolating node 2");
+    router.isolate_node(2).await;
+    router.wait_for_state(&btreeset![2], State::Candidate, None, "isolated node campaigns").await?;
+    tokio::time::sleep(Duration::from_millis(config.election_timeout_max * 4)).await;
+
+    let metrics = router.latest_metrics().await;
+    for m in metrics.iter() {
+        assert_eq!(
+            m.current_term, 1,
+            "expected node {} to remain in term 1, got {}",
+            m.id, m.current_term
+        );
+    }
+
+    // Write some data while node 2 is isolated.
+    router.client_request_many(0, "0", 10).await;
+    want += 10;
+    router.wait_for_log(&btreeset![0, 1], want, None, "write to the majority").await?;
+
+    // Restore the follower, it must rejoin without forcing a new election.
+    tracing::info!("--- restoring node 2");
+    router.restore_node(2).await;
+    router.wait_for_log(&btreeset![0, 1, 2], want, None, "node 2 catches up").await?;
+    router.wait_for_state(&btreeset![2], State::Follower, None, "node 2 rejoins as follower").await?;
+    router.assert_stable_cluster(Some(1), Some(want)).await;
+
+    Ok(())
+}
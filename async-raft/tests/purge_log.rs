@@ -0,0 +1,124 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::Config;
+use async_raft::PurgeLogError;
+use async_raft::RaftStorage;
+use async_raft::RaftStorageDebug;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Log purge test.
+///
+/// What does this test do?
+///
+/// - build a stable 3 node cluster, and send a few requests to it.
+/// - assert that the last applied entry can not be purged.
+/// - isolate a follower and send more requests, then assert that the leader refuses to purge entries the follower
+///   lacks.
+/// - restore the follower, and assert that the entries are purged once every node has them.
+/// - add a non-voter, and assert that it is sent a snapshot covering the purged entries.
+///
+/// RUST_LOG=async_raft,memstore,purge_log=trace cargo test -p async-raft --test purge_log
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn purge_log() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(5000));
+
+    let config = Arc::new(Config::build("test".into()).validate().expect("failed to build Raft config"));
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0, 1, 2}, btreeset! {}).await?;
+
+    router.client_request_many(0, "0", 10).await;
+    want += 10;
+    router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "write").await?;
+
+    let leader = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- the last applied entry is kept");
+    {
+        let res = leader.purge_log_upto(want).await;
+        match res {
+            Err(PurgeLogError::NotApplied { upto, last_applied }) => {
+                assert_eq!(want, upto);
+                assert_eq!(want, last_applied);
+            }
+            _ => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    tracing::info!("--- entries a lagging follower lacks are kept");
+    let lagging = want;
+    {
+        router.isolate_node(2).await;
+
+        router.client_request_many(0, "1", 10).await;
+        want += 10;
+        router.wait_for_log(&btreeset![0, 1], want, timeout, "write while follower is isolated").await?;
+
+        let res = leader.purge_log_upto(want - 1).await;
+        match res {
+            Err(PurgeLogError::NeededByReplication { target, matched }) => {
+                assert_eq!(2, target);
+                assert_eq!(lagging, matched.index);
+            }
+            _ => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    tracing::info!("--- entries every node has are purged");
+    {
+        router.restore_node(2).await;
+        router.wait_for_log(&btreeset![2], want, timeout, "follower catches up").await?;
+        router
+            .wait_for_metrics(
+                &0,
+                |x| x.leader_metrics.as_ref().map(|m| m.replication[&2].matched.index) == Some(want),
+                timeout,
+                "leader sees the follower caught up",
+            )
+            .await?;
+
+        leader.purge_log_upto(want - 1).await?;
+
+        let sto = router.get_storage_handle(&0).await?;
+        assert!(sto.try_get_log_entry(want - 1).await?.is_none());
+        assert!(sto.try_get_log_entry(want).await?.is_some());
+    }
+
+    tracing::info!("--- a new non-voter is sent a snapshot covering the purged entries");
+    {
+        router.new_raft_node(3).await;
+        router.add_non_voter(0, 3).await?;
+        want += 1;
+
+        router.wait_for_log(&btreeset![0, 3], want, timeout, "add non-voter").await?;
+        let purged = want - 2;
+        router
+            .wait_for_metrics(
+                &3,
+                |x| x.snapshot.index >= purged,
+                timeout,
+                "snapshot covering the purged entries",
+            )
+            .await?;
+
+        let sm0 = router.get_storage_handle(&0).await?.get_state_machine().await;
+        let sm3 = router.get_storage_handle(&3).await?.get_state_machine().await;
+        assert_eq!(sm0.client_status, sm3.client_status);
+    }
+
+    tracing::info!("--- a follower may purge the entries it applied");
+    {
+        router.get_raft_handle(&1).await?.purge_log_upto(lagging).await?;
+    }
+
+    Ok(())
+}
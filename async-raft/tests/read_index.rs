@@ -0,0 +1,112 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::error::ClientReadError;
+use async_raft::raft::ReadIndexRequest;
+use async_raft::Config;
+use async_raft::LogId;
+use async_raft::State;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Read index test.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster, with an additional non-voter.
+/// - write a number of entries to the cluster.
+/// - call the read_index interface on every node, and assert that they all return the leader's commit index, and that
+///   each node has applied its log up to it.
+/// - assert that the leader refuses a ReadIndex RPC sent to another node as the leader, or in a later term.
+/// - isolate a follower, and assert that its read_index fails.
+///
+/// RUST_LOG=async_raft,memstore,read_index=trace cargo test -p async-raft --test read_index
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn read_index() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // Setup test dependencies.
+    let config = Arc::new(Config::build("test".into()).validate().expect("failed to build Raft config"));
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+    router.new_raft_node(1).await;
+    router.new_raft_node(2).await;
+
+    let mut want = 0;
+
+    // Assert all nodes are in non-voter state & have no entries.
+    router.wait_for_log(&btreeset![0, 1, 2], want, None, "empty node").await?;
+    router.wait_for_state(&btreeset![0, 1, 2], State::NonVoter, None, "empty node").await?;
+    router.assert_pristine_cluster().await;
+
+    // Initialize the cluster, then assert that a stable cluster was formed & held.
+    tracing::info!("--- initializing cluster");
+    router.initialize_from_single_node(0).await?;
+    want += 1;
+
+    let timeout = Some(Duration::from_millis(config.election_timeout_max * 2));
+    router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "init leader").await?;
+    router.assert_stable_cluster(Some(1), Some(want)).await;
+
+    let leader = router.leader().await.expect("leader not found");
+    assert_eq!(leader, 0, "expected leader to be node 0, got {}", leader);
+
+    tracing::info!("--- add a non-voter");
+
+    router.new_raft_node(3).await;
+    router.add_non_voter(leader, 3).await?;
+    want += 1; // learner log
+
+    tracing::info!("--- write entries, then get the read index from every node");
+
+    router.client_request_many(leader, "0", 10).await;
+    want += 10;
+
+    for id in 0..4 {
+        let log_id = router.read_index(id).await?;
+        assert_eq!(LogId { term: 1, index: want }, log_id, "read index of node {}", id);
+
+        let metrics = router.latest_metrics().await.into_iter().find(|m| m.id == id).expect("metrics not found");
+        assert!(
+            metrics.last_applied >= log_id.index,
+            "node {} has applied up to {}, expected at least {}",
+            id,
+            metrics.last_applied,
+            log_id.index
+        );
+    }
+
+    tracing::info!("--- the leader refuses RPCs which were not sent to it in its term");
+    {
+        let raft = router.get_raft_handle(&leader).await?;
+        for (term, leader_id) in [(1, 1), (2, leader)] {
+            let rpc = ReadIndexRequest {
+                term,
+                leader_id,
+                node_id: 2,
+                path: vec![2],
+            };
+            let res = raft.handle_read_index(rpc).await;
+            assert!(
+                matches!(res, Err(ClientReadError::ForwardToLeader(None))),
+                "expected ForwardToLeader, got {:?}",
+                res
+            );
+        }
+    }
+
+    tracing::info!("--- isolate a follower, its read index fails");
+
+    router.isolate_node(2).await;
+    let rst = router.read_index(2).await;
+    tracing::debug!(?rst, "read_index on an isolated follower");
+
+    assert!(rst.is_err());
+
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::error::ResponseError;
+use async_raft::raft::ChangeMembershipPhase;
+use async_raft::ChangeConfigError;
+use async_raft::Config;
+use async_raft::ReadyToJoinPolicy;
+use async_raft::State;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Ready-to-join policy test.
+///
+/// What does this test do?
+///
+/// - brings 3 nodes online: one leader and 2 non-voters, with a policy requiring new nodes to have replicated every
+///   entry, and a timeout on syncing them.
+/// - isolates node 1 and changes membership to include it, and asserts that the change is aborted once syncing it times
+///   out.
+/// - isolates node 2 and starts a change to include it, and asserts that the change can be cancelled while syncing.
+/// - restores both nodes, and asserts that a change including them both succeeds.
+///
+/// RUST_LOG=async_raft,memstore,ready_to_join=trace cargo test -p async-raft --test ready_to_join
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn ready_to_join() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(2000));
+
+    // Setup test dependencies.
+    let config = Arc::new(
+        Config::build("test".into())
+            .ready_to_join(ReadyToJoinPolicy::MaxLagEntries(0))
+            .non_voter_sync_timeout(500)
+            .validate()
+            .expect("failed to build Raft config"),
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+
+    let mut want = 0;
+
+    // Assert all nodes are in non-voter state & have no entries.
+    router.wait_for_log(&btreeset![0], want, timeout, "empty node").await?;
+    router.wait_for_state(&btreeset![0], State::NonVoter, timeout, "empty node").await?;
+    router.assert_pristine_cluster().await;
+
+    tracing::info!("--- initializing cluster");
+
+    router.initialize_from_single_node(0).await?;
+    want += 1;
+
+    router.wait_for_log(&btreeset![0], want, timeout, "init cluster").await?;
+
+    router.new_raft_node(1).await;
+    router.new_raft_node(2).await;
+
+    let leader = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- syncing an isolated node times out");
+    {
+        router.isolate_node(1).await;
+
+        let rst = router.change_membership(0, btreeset![0, 1]).await;
+        match rst {
+            Err(ResponseError::ChangeConfig(ChangeConfigError::SyncTimeout(not_ready))) => {
+                assert_eq!(vec![1], not_ready);
+            }
+            _ => panic!("unexpected result: {:?}", rst),
+        }
+
+        router
+            .wait_for_metrics(
+                &0,
+                |m| {
+                    m.membership_config.members == btreeset![0]
+                        && m.leader_metrics
+                            .as_ref()
+                            .map(|l| l.non_voters.is_empty() && l.replication.is_empty())
+                            .unwrap_or(false)
+                },
+                timeout,
+                "node 1 is no longer replicated to",
+            )
+            .await?;
+    }
+
+    tracing::info!("--- cancel a change while syncing");
+    {
+        router.isolate_node(2).await;
+
+        let handle = leader.start_change_membership(btreeset![0, 2], BTreeMap::new()).await?;
+        assert_eq!(ChangeMembershipPhase::Syncing, handle.phase());
+
+        handle.cancel().await?;
+        let rst = handle.finish().await;
+        assert!(
+            matches!(rst, Err(ResponseError::ChangeConfig(ChangeConfigError::Cancelled))),
+            "unexpected result: {:?}",
+            rst
+        );
+
+        router
+            .wait_for_metrics(
+                &0,
+                |m| {
+                    m.membership_config.members == btreeset![0]
+                        && m.leader_metrics.as_ref().map(|l| l.non_voters.is_empty()).unwrap_or(false)
+                },
+                timeout,
+                "node 2 is no longer replicated to",
+            )
+            .await?;
+    }
+
+    tracing::info!("--- change membership once the nodes are reachable");
+    {
+        router.restore_node(1).await;
+        router.restore_node(2).await;
+
+        router.change_membership(0, btreeset![0, 1, 2]).await?;
+        want += 2; // 2 member-change logs
+
+        router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "change membership").await?;
+    }
+
+    Ok(())
+}
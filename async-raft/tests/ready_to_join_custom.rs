@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::config::ReadyToJoinFn;
+use async_raft::raft::ChangeMembershipPhase;
+use async_raft::Config;
+use async_raft::ReadyToJoinPolicy;
+use async_raft::State;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Custom ready-to-join policy test.
+///
+/// What does this test do?
+///
+/// - brings 2 nodes online: one leader and 1 non-voter, with a policy deciding readiness by a flag and the progress
+///   reported to it.
+/// - starts a change including the non-voter, and asserts that it keeps syncing while the policy rejects it.
+/// - flips the flag, and asserts that the change finishes.
+///
+/// RUST_LOG=async_raft,memstore,ready_to_join_custom=trace cargo test -p async-raft --test ready_to_join_custom
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn ready_to_join_custom() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(2000));
+
+    let allow = Arc::new(AtomicBool::new(false));
+    let policy = {
+        let allow = allow.clone();
+        ReadyToJoinFn::new(move |progress| {
+            allow.load(Ordering::SeqCst) && progress.matched.index == progress.last_log_index
+        })
+    };
+
+    // Setup test dependencies.
+    let config = Arc::new(
+        Config::build("test".into())
+            .ready_to_join(ReadyToJoinPolicy::Custom(policy))
+            .validate()
+            .expect("failed to build Raft config"),
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+
+    let mut want = 0;
+
+    // Assert all nodes are in non-voter state & have no entries.
+    router.wait_for_log(&btreeset![0], want, timeout, "empty node").await?;
+    router.wait_for_state(&btreeset![0], State::NonVoter, timeout, "empty node").await?;
+    router.assert_pristine_cluster().await;
+
+    tracing::info!("--- initializing cluster");
+
+    router.initialize_from_single_node(0).await?;
+    want += 1;
+
+    router.wait_for_log(&btreeset![0], want, timeout, "init cluster").await?;
+
+    router.new_raft_node(1).await;
+
+    let leader = router.get_raft_handle(&0).await?;
+
+    tracing::info!("--- the change keeps syncing while the policy rejects the non-voter");
+    let mut handle = leader.start_change_membership(btreeset![0, 1], BTreeMap::new()).await?;
+    {
+        assert_eq!(ChangeMembershipPhase::Syncing, handle.phase());
+
+        // The non-voter receives the logs, but is not let in.
+        router.wait_for_log(&btreeset![1], want, timeout, "non-voter synced").await?;
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(ChangeMembershipPhase::Syncing, handle.phase());
+    }
+
+    tracing::info!("--- the change finishes once the policy accepts the non-voter");
+    {
+        allow.store(true, Ordering::SeqCst);
+
+        assert!(handle.wait_for(ChangeMembershipPhase::FinalCommitted).await);
+        handle.finish().await?;
+        want += 2; // 2 member-change logs
+
+        router.wait_for_log(&btreeset![0, 1], want, timeout, "change membership").await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::error::ResponseError;
+use async_raft::ChangeConfigError;
+use async_raft::Config;
+use async_raft::State;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Remove non-voter test.
+///
+/// What does this test do?
+///
+/// - brings 3 nodes online: one leader and 2 non-voters.
+/// - add node 1 as a non-voter, and assert it shows up in the leader metrics.
+/// - remove node 1, and assert that the leader stops replicating to it.
+/// - isolate node 2 and add it as a non-voter, then remove it, and assert that it is gone from the leader metrics.
+/// - assert that removing an unknown node, or removing from a non-leader, fails.
+///
+/// RUST_LOG=async_raft,memstore,remove_non_voter=trace cargo test -p async-raft --test remove_non_voter
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn remove_non_voter() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(1000));
+
+    // Setup test dependencies.
+    let config = Arc::new(Config::build("test".into()).validate().expect("failed to build Raft config"));
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+
+    let mut want = 0;
+
+    // Assert all nodes are in non-voter state & have no entries.
+    router.wait_for_log(&btreeset![0], want, timeout, "empty node").await?;
+    router.wait_for_state(&btreeset![0], State::NonVoter, timeout, "empty node").await?;
+    router.assert_pristine_cluster().await;
+
+    tracing::info!("--- initializing cluster");
+
+    router.initialize_from_single_node(0).await?;
+    want += 1;
+
+    router.wait_for_log(&btreeset![0], want, timeout, "init cluster").await?;
+
+    router.new_raft_node(1).await;
+    router.new_raft_node(2).await;
+
+    tracing::info!("--- add non-voter 1");
+
+    router.add_non_voter(0, 1).await?;
+    want += 1; // learner log
+    router.client_request_many(0, "0", 5).await;
+    want += 5;
+
+    router.wait_for_log(&btreeset![0, 1], want, timeout, "non-voter 1 synced").await?;
+    router
+        .wait_for_metrics(
+            &0,
+            |x| x.leader_metrics.as_ref().map(|m| m.non_voters == btreeset![1]).unwrap_or(false),
+            timeout,
+            "non-voter 1 in leader metrics",
+        )
+        .await?;
+
+    tracing::info!("--- remove non-voter 1, it receives no more logs");
+
+    router.remove_non_voter(0, 1).await?;
+    let removed_at = want;
+    want += 1; // learner log
+    router
+        .wait_for_metrics(
+            &0,
+            |x| {
+                x.leader_metrics
+                    .as_ref()
+                    .map(|m| m.non_voters.is_empty() && !m.replication.contains_key(&1))
+                    .unwrap_or(false)
+            },
+            timeout,
+            "non-voter 1 removed from leader metrics",
+        )
+        .await?;
+
+    router.client_request_many(0, "0", 5).await;
+    want += 5;
+    router.wait_for_log(&btreeset![0], want, timeout, "write after removal").await?;
+
+    // Give the removed node a chance to receive logs, if it wrongly still would.
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    router
+        .wait_for_log(&btreeset![1], removed_at, timeout, "removed non-voter receives no logs")
+        .await?;
+
+    tracing::info!("--- remove non-voter 2, whose host is down");
+
+    router.isolate_node(2).await;
+    router.add_non_voter(0, 2).await?;
+    want += 1; // learner log
+    router
+        .wait_for_metrics(
+            &0,
+            |x| x.leader_metrics.as_ref().map(|m| m.non_voters == btreeset![2]).unwrap_or(false),
+            timeout,
+            "non-voter 2 in leader metrics",
+        )
+        .await?;
+
+    router.remove_non_voter(0, 2).await?;
+    want += 1; // learner log
+    router
+        .wait_for_metrics(
+            &0,
+            |x| {
+                x.leader_metrics
+                    .as_ref()
+                    .map(|m| m.non_voters.is_empty() && m.replication.is_empty())
+                    .unwrap_or(false)
+            },
+            timeout,
+            "non-voter 2 removed from leader metrics",
+        )
+        .await?;
+
+    tracing::info!("--- invalid removals fail");
+
+    let rst = router.remove_non_voter(0, 1).await;
+    assert!(
+        matches!(rst, Err(ResponseError::ChangeConfig(ChangeConfigError::NotNonVoter(1)))),
+        "got {:?}",
+        rst
+    );
+
+    let rst = router.remove_non_voter(1, 2).await;
+    assert!(
+        matches!(
+            rst,
+            Err(ResponseError::ChangeConfig(ChangeConfigError::NodeNotLeader(_)))
+        ),
+        "got {:?}",
+        rst
+    );
+
+    router.wait_for_log(&btreeset![0], want, timeout, "leader log unchanged").await?;
+
+    Ok(())
+}
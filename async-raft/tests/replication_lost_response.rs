@@ -0,0 +1,68 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::Config;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Pipelined replication with lost responses test.
+///
+/// What does this test do?
+///
+/// - brings a 3 node cluster online, with up to 8 AppendEntries RPCs in flight to each target.
+/// - loses the responses to a few payloads after the targets have appended their entries, so that the leader sends
+///   those entries again.
+/// - writes entries through many concurrent clients, and asserts that every node replicates every entry, and that the
+///   cluster stays stable.
+///
+/// RUST_LOG=async_raft,memstore,replication_lost_response=trace cargo test -p async-raft --test
+/// replication_lost_response
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn replication_lost_response() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(5000));
+
+    // Setup test dependencies.
+    let config = Arc::new(
+        Config::build("test".into())
+            .heartbeat_interval(100)
+            .election_timeout_min(500)
+            .election_timeout_max(1000)
+            .max_payload_entries(4)
+            .max_inflight(8)
+            .validate()
+            .expect("failed to build Raft config"),
+    );
+    let router = Arc::new(RaftRouter::builder(config).send_delay(5).build());
+
+    let mut want = router.new_nodes_from_single(btreeset! {0, 1, 2}, btreeset! {}).await?;
+
+    tracing::info!("--- write from concurrent clients, losing some responses");
+    {
+        router.lose_append_responses(3);
+
+        let mut clients = Vec::new();
+        for client in 0..10 {
+            let router = router.clone();
+            clients.push(tokio::spawn(async move {
+                router.client_request_many(0, &client.to_string(), 10).await;
+            }));
+        }
+        for client in clients {
+            client.await?;
+        }
+        want += 10 * 10;
+    }
+
+    router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "concurrent writes").await?;
+    router.assert_stable_cluster(Some(1), Some(want)).await;
+    assert_eq!(0, router.append_responses_to_lose(), "no response was lost");
+
+    Ok(())
+}
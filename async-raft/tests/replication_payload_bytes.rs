@@ -0,0 +1,103 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::raft::ClientWriteRequest;
+use async_raft::Config;
+use async_raft::State;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use memstore::ClientRequest;
+
+#[macro_use]
+mod fixtures;
+
+/// Replication payload byte limit test.
+///
+/// What does this test do?
+///
+/// - brings 1 node online as the leader, with a byte limit on replication payloads, and writes many small entries and
+///   one entry larger than the limit.
+/// - adds a non-voter, which is brought up to speed from the log.
+/// - asserts that the non-voter replicates every entry, that every payload of more than one entry is within the limit,
+///   and that the large entry was sent on its own.
+///
+/// RUST_LOG=async_raft,memstore,replication_payload_bytes=trace cargo test -p async-raft --test
+/// replication_payload_bytes
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn replication_payload_bytes() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(2000));
+    let max_bytes = 400;
+
+    // Setup test dependencies.
+    let config = Arc::new(
+        Config::build("test".into())
+            .max_payload_bytes(max_bytes)
+            .validate()
+            .expect("failed to build Raft config"),
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+
+    let mut want = 0;
+
+    // Assert all nodes are in non-voter state & have no entries.
+    router.wait_for_log(&btreeset![0], want, timeout, "empty node").await?;
+    router.wait_for_state(&btreeset![0], State::NonVoter, timeout, "empty node").await?;
+    router.assert_pristine_cluster().await;
+
+    tracing::info!("--- initializing cluster");
+
+    router.initialize_from_single_node(0).await?;
+    want += 1;
+
+    router.wait_for_log(&btreeset![0], want, timeout, "init cluster").await?;
+
+    tracing::info!("--- write small entries and one entry larger than the limit");
+    {
+        router.client_request_many(0, "0", 50).await;
+        want += 50;
+
+        let leader = router.get_raft_handle(&0).await?;
+        let req = ClientRequest {
+            client: "0".into(),
+            serial: 50,
+            status: "x".repeat(max_bytes as usize),
+        };
+        leader.client_write(ClientWriteRequest::new(req)).await?;
+        want += 1;
+
+        router.wait_for_log(&btreeset![0], want, timeout, "write entries").await?;
+    }
+
+    tracing::info!("--- add a non-voter and sync it");
+    {
+        router.new_raft_node(1).await;
+        router.add_non_voter(0, 1).await?;
+        want += 1;
+
+        router.wait_for_log(&btreeset![0, 1], want, timeout, "sync non-voter").await?;
+    }
+
+    let payloads = router.sent_payloads();
+    for sizes in payloads.iter().filter(|sizes| sizes.len() > 1) {
+        assert!(
+            sizes.iter().sum::<u64>() <= max_bytes,
+            "payload over the limit: {:?}",
+            sizes
+        );
+    }
+    assert!(
+        payloads.iter().any(|sizes| sizes.len() > 1),
+        "entries were never batched"
+    );
+    assert!(
+        payloads.iter().any(|sizes| sizes.len() == 1 && sizes[0] > max_bytes),
+        "the large entry was not sent"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::Config;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Full replication queue test.
+///
+/// The queues between the leader and its replication streams are bounded. Once the queue to a replication stream is
+/// full, the leader drops further events for it, and the stream catches up from storage instead.
+///
+/// What does this test do?
+///
+/// - brings a 3 node cluster online, with room for a single event in each replication queue, and a lagging network.
+/// - writes concurrently from many clients, which overflows the replication queues, and asserts that every node
+///   replicates every entry.
+/// - asserts that the leader reports the depth of the queue to each replication stream.
+///
+/// RUST_LOG=async_raft,memstore,replication_queue_full=trace cargo test -p async-raft --test replication_queue_full
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn replication_queue_full() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(5000));
+
+    // Setup test dependencies.
+    let config = Arc::new(
+        Config::build("test".into())
+            .replication_queue_capacity(1)
+            .validate()
+            .expect("failed to build Raft config"),
+    );
+    let router = Arc::new(RaftRouter::builder(config.clone()).send_delay(10).build());
+
+    let mut want = router.new_nodes_from_single(btreeset! {0, 1, 2}, btreeset! {}).await?;
+
+    tracing::info!("--- write concurrently, overflowing the replication queues");
+    {
+        let mut clients = Vec::new();
+        for client in 0..20 {
+            let router = router.clone();
+            clients.push(tokio::spawn(async move {
+                router.client_request_many(0, &client.to_string(), 20).await;
+            }));
+        }
+        for client in clients {
+            client.await?;
+        }
+        want += 20 * 20;
+
+        router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "concurrent writes").await?;
+        router.assert_stable_cluster(Some(1), Some(want)).await;
+    }
+
+    tracing::info!("--- the leader reports the replication queue depths");
+    {
+        router
+            .wait_for_metrics(
+                &0,
+                |x| {
+                    x.leader_metrics
+                        .as_ref()
+                        .map(|m| m.replication_queue_lens.keys().cloned().collect::<BTreeSet<_>>() == btreeset![1, 2])
+                        .unwrap_or(false)
+                },
+                timeout,
+                "replication queue lens",
+            )
+            .await?;
+    }
+
+    Ok(())
+}
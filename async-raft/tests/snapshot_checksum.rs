@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_raft::checksum::crc32;
+use async_raft::raft::InstallSnapshotRequest;
+use async_raft::Config;
+use async_raft::LogId;
+use async_raft::RaftError;
+use async_raft::RaftStorage;
+use async_raft::RaftStorageDebug;
+use async_raft::SnapshotMeta;
+use async_raft::SnapshotPolicy;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Snapshot checksum test.
+///
+/// What does this test do?
+///
+/// - build a stable single node cluster, and send enough requests to it that a snapshot is built.
+/// - send a corrupted chunk of the snapshot to a new node, and assert that it is rejected.
+/// - send the whole snapshot to the new node with a wrong checksum, and assert that it is rejected, and that the node
+///   asks for the snapshot from its start again.
+/// - add the new node as a non-voter, and assert that it installs the snapshot sent by the leader.
+///
+/// RUST_LOG=async_raft,memstore,snapshot_checksum=trace cargo test -p async-raft --test snapshot_checksum
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn snapshot_checksum() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let snapshot_threshold: u64 = 10;
+    let chunk_size: usize = 10;
+
+    let config = Arc::new(
+        Config::build("test".into())
+            .snapshot_policy(SnapshotPolicy::LogsSinceLast(snapshot_threshold))
+            .snapshot_max_chunk_size(chunk_size as u64)
+            .validate()
+            .expect("failed to build Raft config"),
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- send just enough logs to trigger snapshot");
+    {
+        router.client_request_many(0, "0", (snapshot_threshold - want) as usize).await;
+        want = snapshot_threshold;
+
+        router.wait_for_log(&btreeset![0], want, None, "send log to trigger snapshot").await?;
+        router.wait_for_snapshot(&btreeset![0], LogId { term: 1, index: want }, None, "snapshot").await?;
+    }
+
+    let snapshot = router.get_storage_handle(&0).await?.get_current_snapshot().await?.expect("no snapshot");
+    let meta = snapshot.meta;
+    let data = snapshot.snapshot.into_inner();
+    assert_eq!(Some(crc32(&data)), meta.checksum);
+
+    router.new_raft_node(1).await;
+    let node = router.get_raft_handle(&1).await?;
+
+    tracing::info!("--- a corrupted chunk is rejected");
+    {
+        let mut req = chunk(&meta, &data, 0, chunk_size);
+        req.data[0] ^= 0xff;
+
+        let res = node.install_snapshot(req).await;
+        match res {
+            Err(RaftError::SnapshotChecksumMismatch { offset: Some(0), .. }) => {}
+            _ => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    tracing::info!("--- a snapshot which does not match its checksum is rejected, and received anew");
+    {
+        let mut bad_meta = meta.clone();
+        bad_meta.checksum = meta.checksum.map(|checksum| !checksum);
+
+        let mut offset = 0;
+        loop {
+            let req = chunk(&bad_meta, &data, offset, chunk_size);
+            let done = req.done;
+            let res = node.install_snapshot(req).await;
+            if done {
+                match res {
+                    Err(RaftError::SnapshotChecksumMismatch { offset: None, .. }) => break,
+                    _ => panic!("unexpected result: {:?}", res),
+                }
+            }
+            offset = res?.offset.expect("no offset in response") as usize;
+        }
+
+        let res = node.install_snapshot(chunk(&meta, &data, chunk_size, chunk_size)).await?;
+        assert_eq!(Some(0), res.offset);
+    }
+
+    tracing::info!("--- the snapshot sent by the leader is installed");
+    {
+        router.add_non_voter(0, 1).await?;
+        want += 1;
+
+        router.wait_for_log(&btreeset![0, 1], want, None, "add non-voter").await?;
+        router.wait_for_snapshot(&btreeset![1], meta.last_log_id, None, "install snapshot").await?;
+
+        let sm0 = router.get_storage_handle(&0).await?.get_state_machine().await;
+        let sm1 = router.get_storage_handle(&1).await?.get_state_machine().await;
+        assert_eq!(sm0.client_status, sm1.client_status);
+    }
+
+    Ok(())
+}
+
+/// Build the InstallSnapshot RPC of the leader, node 0, for the chunk of the snapshot at the given offset.
+fn chunk(meta: &SnapshotMeta, data: &[u8], offset: usize, chunk_size: usize) -> InstallSnapshotRequest {
+    let end = std::cmp::min(offset + chunk_size, data.len());
+    InstallSnapshotRequest {
+        term: 1,
+        leader_id: 0,
+        meta: meta.clone(),
+        offset: offset as u64,
+        data: data[offset..end].to_vec(),
+        checksum: Some(crc32(&data[offset..end])),
+        compression: None,
+        done: end == data.len(),
+    }
+}
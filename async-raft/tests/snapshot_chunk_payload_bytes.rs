@@ -0,0 +1,95 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::Config;
+use async_raft::LogId;
+use async_raft::SnapshotPolicy;
+use async_raft::State;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Snapshot chunks limited by payload bytes test.
+///
+/// What does this test do?
+///
+/// - build a stable single node cluster, with a limit on the bytes of replication payloads far below the snapshot chunk
+///   size.
+/// - send enough requests to the node that log compaction will be triggered.
+/// - add a non-voter, and assert that it receives the snapshot in chunks no larger than the payload limit.
+///
+/// RUST_LOG=async_raft,memstore,snapshot_chunk_payload_bytes=trace cargo test -p async-raft --test
+/// snapshot_chunk_payload_bytes
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_chunk_payload_bytes() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(2000));
+    let snapshot_threshold: u64 = 10;
+    let max_bytes: u64 = 64;
+
+    let config = Arc::new(
+        Config::build("test".into())
+            .snapshot_policy(SnapshotPolicy::LogsSinceLast(snapshot_threshold))
+            .max_payload_bytes(max_bytes)
+            .validate()
+            .expect("failed to build Raft config"),
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = 0;
+
+    tracing::info!("--- initializing cluster");
+    {
+        router.new_raft_node(0).await;
+
+        router.wait_for_log(&btreeset![0], want, timeout, "empty").await?;
+        router.wait_for_state(&btreeset![0], State::NonVoter, timeout, "empty").await?;
+
+        router.initialize_from_single_node(0).await?;
+        want += 1;
+
+        router.wait_for_log(&btreeset![0], want, timeout, "init leader").await?;
+    }
+
+    tracing::info!("--- send just enough logs to trigger snapshot");
+    {
+        router.client_request_many(0, "0", (snapshot_threshold - want) as usize).await;
+        want = snapshot_threshold;
+
+        router.wait_for_log(&btreeset![0], want, timeout, "send log to trigger snapshot").await?;
+        router.wait_for_snapshot(&btreeset![0], LogId { term: 1, index: want }, timeout, "snapshot").await?;
+    }
+
+    tracing::info!("--- add non-voter to receive the snapshot in small chunks");
+    {
+        router.new_raft_node(1).await;
+        router.add_non_voter(0, 1).await?;
+        want += 1;
+
+        router.wait_for_log(&btreeset![0, 1], want, timeout, "add non-voter").await?;
+        router
+            .wait_for_snapshot(
+                &btreeset![1],
+                LogId {
+                    term: 1,
+                    index: snapshot_threshold,
+                },
+                timeout,
+                "snapshot received",
+            )
+            .await?;
+
+        let offsets = router.sent_snapshot_chunks();
+        assert!(offsets.len() > 1, "snapshot sent in one chunk");
+        for pair in offsets.windows(2) {
+            assert!(pair[1] - pair[0] <= max_bytes, "chunk over the limit: {:?}", offsets);
+        }
+    }
+
+    Ok(())
+}
@@ -7,6 +7,7 @@ use async_raft::LogId;
 use async_raft::SnapshotPolicy;
 use async_raft::State;
 use fixtures::RaftRouter;
+use maplit::btreemap;
 use maplit::btreeset;
 
 #[macro_use]
@@ -61,6 +62,8 @@ async fn snapshot_chunk_size() -> Result<()> {
         let want_snap = Some((want.into(), 1, MembershipConfig {
             members: btreeset![0u64],
             members_after_consensus: None,
+            learners: btreeset! {},
+            nodes: btreemap! {},
         }));
 
         router.wait_for_log(&btreeset![0], want, None, "send log to trigger snapshot").await?;
@@ -72,14 +75,27 @@ async fn snapshot_chunk_size() -> Result<()> {
     {
         router.new_raft_node(1).await;
         router.add_non_voter(0, 1).await.expect("failed to add new node as non-voter");
+        want += 1; // learner log
 
-        let want_snap = Some((want.into(), 1, MembershipConfig {
+        let want_snap = Some((snapshot_threshold.into(), 1, MembershipConfig {
             members: btreeset![0u64],
             members_after_consensus: None,
+            learners: btreeset! {},
+            nodes: btreemap! {},
         }));
 
         router.wait_for_log(&btreeset![0, 1], want, None, "add non-voter").await?;
-        router.wait_for_snapshot(&btreeset![1], LogId { term: 1, index: want }, None, "").await?;
+        router
+            .wait_for_snapshot(
+                &btreeset![1],
+                LogId {
+                    term: 1,
+                    index: snapshot_threshold,
+                },
+                None,
+                "",
+            )
+            .await?;
         router
             .assert_storage_state(
                 1,
@@ -0,0 +1,139 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_raft::checksum::crc32;
+use async_raft::compression::MAX_RAW_CHUNK_SIZE;
+use async_raft::raft::InstallSnapshotRequest;
+use async_raft::Config;
+use async_raft::LogId;
+use async_raft::RaftStorage;
+use async_raft::RaftStorageDebug;
+use async_raft::SnapshotCompression;
+use async_raft::SnapshotPolicy;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Snapshot compression test.
+///
+/// What does this test do?
+///
+/// - build a stable single node cluster, compressing snapshots if the `lz4` feature is enabled, and send enough
+///   requests to it that a snapshot is built.
+/// - send a compressed chunk of the snapshot to a new node, and assert that the node names the codecs it supports, and
+///   decompresses the chunk, or does not take it if it does not support the codec.
+/// - send a corrupted compressed chunk, and one claiming to be larger than `MAX_RAW_CHUNK_SIZE` when decompressed, to
+///   the node, and assert that it does not take them, and asks for them uncompressed.
+/// - add the new node as a non-voter, and assert that it installs the snapshot sent by the leader.
+///
+/// RUST_LOG=async_raft,memstore,snapshot_compression=trace cargo test -p async-raft --test snapshot_compression
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn snapshot_compression() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let snapshot_threshold: u64 = 10;
+    let chunk_size: usize = 64;
+    let codec = SnapshotCompression::Lz4;
+
+    let mut config = Config::build("test".into())
+        .snapshot_policy(SnapshotPolicy::LogsSinceLast(snapshot_threshold))
+        .snapshot_max_chunk_size(chunk_size as u64);
+    if codec.is_supported() {
+        config = config.snapshot_compression(codec);
+    }
+    let config = Arc::new(config.validate().expect("failed to build Raft config"));
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- send just enough logs to trigger snapshot");
+    {
+        router.client_request_many(0, "0", (snapshot_threshold - want) as usize).await;
+        want = snapshot_threshold;
+
+        router.wait_for_log(&btreeset![0], want, None, "send log to trigger snapshot").await?;
+        router.wait_for_snapshot(&btreeset![0], LogId { term: 1, index: want }, None, "snapshot").await?;
+    }
+
+    let snapshot = router.get_storage_handle(&0).await?.get_current_snapshot().await?.expect("no snapshot");
+    let meta = snapshot.meta;
+    let data = snapshot.snapshot.into_inner();
+    assert!(
+        data.len() > 2 * chunk_size,
+        "snapshot too small to be sent in chunks: {}",
+        data.len()
+    );
+
+    router.new_raft_node(1).await;
+    let node = router.get_raft_handle(&1).await?;
+
+    let chunk = |offset: usize, payload: Vec<u8>| InstallSnapshotRequest {
+        term: 1,
+        leader_id: 0,
+        meta: meta.clone(),
+        offset: offset as u64,
+        data: payload,
+        checksum: Some(crc32(&data[offset..offset + chunk_size])),
+        compression: Some(codec),
+        done: false,
+    };
+    let raw = |offset: usize| data[offset..offset + chunk_size].to_vec();
+    let compress = |offset: usize| codec.compress(&raw(offset)).unwrap_or_else(|_| raw(offset));
+
+    tracing::info!("--- a compressed chunk is decompressed by a node which supports its codec");
+    {
+        let res = node.install_snapshot(chunk(0, compress(0))).await?;
+        assert_eq!(SnapshotCompression::supported(), res.supported_compression);
+        if codec.is_supported() {
+            assert_eq!(Some(chunk_size as u64), res.offset);
+            assert_eq!(Some(codec), res.compression);
+        } else {
+            assert_eq!(
+                Some(0),
+                res.offset,
+                "expected the node to ask for the chunk uncompressed"
+            );
+            assert_eq!(None, res.compression);
+        }
+    }
+
+    if codec.is_supported() {
+        tracing::info!("--- a chunk which can not be decompressed is asked for uncompressed");
+        {
+            let mut corrupted = compress(chunk_size);
+            corrupted.truncate(corrupted.len() / 2);
+
+            let res = node.install_snapshot(chunk(chunk_size, corrupted)).await?;
+            assert_eq!(Some(chunk_size as u64), res.offset);
+            assert_eq!(None, res.compression);
+        }
+
+        tracing::info!("--- a chunk larger than MAX_RAW_CHUNK_SIZE when decompressed is asked for uncompressed");
+        {
+            let mut oversized = compress(chunk_size);
+            oversized[..4].copy_from_slice(&(MAX_RAW_CHUNK_SIZE as u32 + 1).to_le_bytes());
+
+            let res = node.install_snapshot(chunk(chunk_size, oversized)).await?;
+            assert_eq!(Some(chunk_size as u64), res.offset);
+            assert_eq!(None, res.compression);
+        }
+    }
+
+    tracing::info!("--- the snapshot sent by the leader is installed");
+    {
+        router.add_non_voter(0, 1).await?;
+        want += 1;
+
+        router.wait_for_log(&btreeset![0, 1], want, None, "add non-voter").await?;
+        router.wait_for_snapshot(&btreeset![1], meta.last_log_id, None, "install snapshot").await?;
+
+        let sm0 = router.get_storage_handle(&0).await?.get_state_machine().await;
+        let sm1 = router.get_storage_handle(&1).await?.get_state_machine().await;
+        assert_eq!(sm0.client_status, sm1.client_status);
+    }
+
+    Ok(())
+}
@@ -7,6 +7,7 @@ use async_raft::LogId;
 use async_raft::SnapshotPolicy;
 use async_raft::State;
 use fixtures::RaftRouter;
+use maplit::btreemap;
 use maplit::btreeset;
 
 #[macro_use]
@@ -74,6 +75,8 @@ async fn snapshot_ge_half_threshold() -> Result<()> {
                 Some((want.into(), 1, MembershipConfig {
                     members: btreeset![0],
                     members_after_consensus: None,
+                    learners: btreeset! {},
+                    nodes: btreemap! {},
                 })),
             )
             .await;
@@ -89,11 +92,14 @@ async fn snapshot_ge_half_threshold() -> Result<()> {
     {
         router.new_raft_node(1).await;
         router.add_non_voter(0, 1).await.expect("failed to add new node as non-voter");
+        want += 1; // learner log
 
         router.wait_for_log(&btreeset![0, 1], want, None, "add non-voter").await?;
         let expected_snap = Some((want.into(), 1, MembershipConfig {
             members: btreeset![0u64],
             members_after_consensus: None,
+            learners: btreeset! {1},
+            nodes: btreemap! {},
         }));
         router.wait_for_snapshot(&btreeset![1], LogId { term: 1, index: want }, None, "").await?;
         router
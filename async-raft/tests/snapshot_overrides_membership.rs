@@ -14,6 +14,7 @@ use async_raft::RaftStorage;
 use async_raft::SnapshotPolicy;
 use async_raft::State;
 use fixtures::RaftRouter;
+use maplit::btreemap;
 use maplit::btreeset;
 
 #[macro_use]
@@ -79,6 +80,8 @@ async fn snapshot_overrides_membership() -> Result<()> {
                 Some((want.into(), 1, MembershipConfig {
                     members: btreeset![0],
                     members_after_consensus: None,
+                    learners: btreeset! {},
+                    nodes: btreemap! {},
                 })),
             )
             .await;
@@ -102,6 +105,8 @@ async fn snapshot_overrides_membership() -> Result<()> {
                         membership: MembershipConfig {
                             members: btreeset![2, 3],
                             members_after_consensus: None,
+                            learners: btreeset! {},
+                            nodes: btreemap! {},
                         },
                     }),
                 }],
@@ -116,6 +121,8 @@ async fn snapshot_overrides_membership() -> Result<()> {
                     MembershipConfig {
                         members: btreeset![2, 3],
                         members_after_consensus: None,
+                        learners: btreeset! {},
+                        nodes: btreemap! {},
                     },
                     m
                 );
@@ -125,13 +132,26 @@ async fn snapshot_overrides_membership() -> Result<()> {
         tracing::info!("--- add non-voter to the cluster to receive snapshot, which overrides the non-voter storage");
         {
             router.add_non_voter(0, 1).await.expect("failed to add new node as non-voter");
+            want += 1; // learner log
 
             router.wait_for_log(&btreeset![0, 1], want, timeout(), "add non-voter").await?;
-            let expected_snap = Some((want.into(), 1, MembershipConfig {
+            let expected_snap = Some((snapshot_threshold.into(), 1, MembershipConfig {
                 members: btreeset![0u64],
                 members_after_consensus: None,
+                learners: btreeset! {},
+                nodes: btreemap! {},
             }));
-            router.wait_for_snapshot(&btreeset![1], LogId { term: 1, index: want }, timeout(), "").await?;
+            router
+                .wait_for_snapshot(
+                    &btreeset![1],
+                    LogId {
+                        term: 1,
+                        index: snapshot_threshold,
+                    },
+                    timeout(),
+                    "",
+                )
+                .await?;
             router
                 .assert_storage_state(
                     1,
@@ -147,9 +167,11 @@ async fn snapshot_overrides_membership() -> Result<()> {
                 MembershipConfig {
                     members: btreeset![0],
                     members_after_consensus: None,
+                    learners: btreeset! {1},
+                    nodes: btreemap! {},
                 },
                 m,
-                "membership should be overridden by the snapshot"
+                "membership should be overridden by the snapshot and the learner log"
             );
         }
     }
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::Config;
+use async_raft::SnapshotPolicy;
+use async_raft::State;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Log bytes snapshot policy test.
+///
+/// What does this test do?
+///
+/// - build a stable single node cluster, with a policy which takes a snapshot once the log has grown by a number of
+///   bytes, or else by far more entries than are written.
+/// - send requests whose entries add up to more than the bytes, and assert that a snapshot is taken.
+///
+/// RUST_LOG=async_raft,memstore,snapshot_policy_bytes=trace cargo test -p async-raft --test snapshot_policy_bytes
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn snapshot_policy_bytes() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(2000));
+
+    // Every entry of a client request takes about 20 bytes, so that the policy calls for a snapshot after about 10
+    // of them.
+    let max_bytes: u64 = 200;
+    let n_requests: u64 = 30;
+
+    // Setup test dependencies.
+    let config = Arc::new(
+        Config::build("test".into())
+            .snapshot_policy(SnapshotPolicy::Any(vec![
+                SnapshotPolicy::LogsSinceLast(1000),
+                SnapshotPolicy::LogBytesSinceLast(max_bytes),
+            ]))
+            .validate()
+            .expect("failed to build Raft config"),
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+
+    let mut want = 0;
+
+    router.wait_for_log(&btreeset![0], want, timeout, "empty").await?;
+    router.wait_for_state(&btreeset![0], State::NonVoter, timeout, "empty").await?;
+
+    tracing::info!("--- initializing cluster");
+    {
+        router.initialize_from_single_node(0).await?;
+        want += 1;
+
+        router.wait_for_log(&btreeset![0], want, timeout, "init leader").await?;
+        let metrics = router.latest_metrics().await;
+        assert_eq!(
+            0, metrics[0].snapshot.index,
+            "expected no snapshot of the initial entry"
+        );
+    }
+
+    tracing::info!("--- a snapshot is taken once the bytes of the log add up");
+    {
+        router.client_request_many(0, "0", n_requests as usize).await;
+        want += n_requests;
+
+        router.wait_for_log(&btreeset![0], want, timeout, "write").await?;
+        let metrics = router
+            .wait(&0, timeout)
+            .await?
+            .metrics(|metrics| metrics.snapshot.index > 0, "snapshot by log bytes")
+            .await?;
+        assert!(metrics.snapshot.index <= want);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::Config;
+use async_raft::RaftStorageDebug;
+use async_raft::SnapshotPolicy;
+use async_raft::State;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Log bytes snapshot policy with a new node test.
+///
+/// A policy which does not count entries gives no threshold of lag beyond which a target is sent a snapshot. A target
+/// which lacks entries covered by the leader's snapshot must be sent the snapshot nonetheless, as they may have been
+/// purged from the log.
+///
+/// What does this test do?
+///
+/// - build a stable single node cluster, with a policy which takes a snapshot once the log has grown by a number of
+///   bytes, and send requests whose entries add up to more than the bytes.
+/// - wait for a snapshot to be taken.
+/// - add a new node as a non-voter, and assert that it is sent the snapshot and catches up with the leader.
+///
+/// RUST_LOG=async_raft,memstore,snapshot_policy_bytes_new_node=trace cargo test -p async-raft --test
+/// snapshot_policy_bytes_new_node
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn snapshot_policy_bytes_new_node() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(2000));
+
+    // Every entry of a client request takes about 20 bytes, so that the policy calls for a snapshot after about 10
+    // of them.
+    let max_bytes: u64 = 200;
+
+    // Setup test dependencies.
+    let config = Arc::new(
+        Config::build("test".into())
+            .snapshot_policy(SnapshotPolicy::LogBytesSinceLast(max_bytes))
+            .max_payload_entries(5)
+            .validate()
+            .expect("failed to build Raft config"),
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- a snapshot is taken once the bytes of the log add up");
+    let snapshot = {
+        router.client_request_many(0, "0", 30).await;
+        want += 30;
+        router.wait_for_log(&btreeset![0], want, timeout, "write").await?;
+
+        let metrics = router
+            .wait(&0, timeout)
+            .await?
+            .metrics(|metrics| metrics.snapshot.index > 0, "snapshot by log bytes")
+            .await?;
+        metrics.snapshot
+    };
+
+    tracing::info!("--- a new node is sent the snapshot");
+    {
+        router.new_raft_node(1).await;
+        router.add_non_voter(0, 1).await?;
+        want += 1;
+
+        router.wait_for_log(&btreeset![0, 1], want, timeout, "add non-voter").await?;
+        router
+            .wait(&1, timeout)
+            .await?
+            .metrics(|metrics| metrics.snapshot.index >= snapshot.index, "install snapshot")
+            .await?;
+        router.wait_for_state(&btreeset![0], State::Leader, timeout, "leader stays").await?;
+
+        router.client_request_many(0, "0", 5).await;
+        want += 5;
+        router.wait_for_log(&btreeset![0, 1], want, timeout, "write after snapshot").await?;
+
+        let sm0 = router.get_storage_handle(&0).await?.get_state_machine().await;
+        let sm1 = router.get_storage_handle(&1).await?.get_state_machine().await;
+        assert_eq!(sm0.client_status, sm1.client_status);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::config::SnapshotMetrics;
+use async_raft::config::SnapshotPolicyFn;
+use async_raft::config::SnapshotProgress;
+use async_raft::Config;
+use async_raft::LogId;
+use async_raft::SnapshotPolicy;
+use async_raft::State;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Custom snapshot policy test.
+///
+/// What does this test do?
+///
+/// - build a stable single node cluster, with a policy deciding on snapshots by a flag, the node's metrics and the
+///   progress reported to it, and send a few requests to it.
+/// - assert that no snapshot is taken while the policy rejects it.
+/// - flip the flag, and assert that a snapshot is taken, although no more requests are sent.
+///
+/// RUST_LOG=async_raft,memstore,snapshot_policy_custom=trace cargo test -p async-raft --test snapshot_policy_custom
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn snapshot_policy_custom() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(2000));
+
+    let allow = Arc::new(AtomicBool::new(false));
+    let policy = {
+        let allow = allow.clone();
+        SnapshotPolicyFn::new(move |metrics: &SnapshotMetrics, progress: &SnapshotProgress| {
+            allow.load(Ordering::SeqCst) && metrics.state == State::Leader && progress.logs_since_last > 0
+        })
+    };
+
+    // Setup test dependencies.
+    let config = Arc::new(
+        Config::build("test".into())
+            .snapshot_policy(SnapshotPolicy::Custom(policy))
+            .validate()
+            .expect("failed to build Raft config"),
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    router.client_request_many(0, "0", 5).await;
+    want += 5;
+    router.wait_for_log(&btreeset![0], want, timeout, "write").await?;
+
+    tracing::info!("--- no snapshot is taken while the policy rejects it");
+    {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let metrics = router.latest_metrics().await;
+        assert_eq!(0, metrics[0].snapshot.index);
+    }
+
+    tracing::info!("--- a snapshot is taken once the policy accepts it");
+    {
+        allow.store(true, Ordering::SeqCst);
+
+        router
+            .wait_for_snapshot(
+                &btreeset![0],
+                LogId { term: 1, index: want },
+                timeout,
+                "snapshot by policy",
+            )
+            .await?;
+    }
+
+    Ok(())
+}
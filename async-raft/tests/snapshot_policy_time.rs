@@ -0,0 +1,65 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::Config;
+use async_raft::LogId;
+use async_raft::SnapshotPolicy;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Time snapshot policy test.
+///
+/// What does this test do?
+///
+/// - build a stable single node cluster, with a policy which takes a snapshot once some time has passed since the last
+///   one, and send a few requests to it.
+/// - assert that no snapshot is taken before then, and that one is taken after, although no more requests are sent.
+///
+/// RUST_LOG=async_raft,memstore,snapshot_policy_time=trace cargo test -p async-raft --test snapshot_policy_time
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn snapshot_policy_time() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(2000));
+    let since_last: u64 = 1000;
+
+    // Setup test dependencies.
+    let config = Arc::new(
+        Config::build("test".into())
+            .snapshot_policy(SnapshotPolicy::TimeSinceLast(since_last))
+            .validate()
+            .expect("failed to build Raft config"),
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    router.client_request_many(0, "0", 5).await;
+    want += 5;
+    router.wait_for_log(&btreeset![0], want, timeout, "write").await?;
+
+    tracing::info!("--- no snapshot is taken before the time has passed");
+    {
+        let metrics = router.latest_metrics().await;
+        assert_eq!(0, metrics[0].snapshot.index);
+    }
+
+    tracing::info!("--- a snapshot is taken once the time has passed");
+    {
+        router
+            .wait_for_snapshot(
+                &btreeset![0],
+                LogId { term: 1, index: want },
+                Some(Duration::from_millis(since_last * 3)),
+                "snapshot by time",
+            )
+            .await?;
+    }
+
+    Ok(())
+}
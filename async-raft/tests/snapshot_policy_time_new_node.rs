@@ -0,0 +1,94 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::Config;
+use async_raft::LogId;
+use async_raft::RaftStorageDebug;
+use async_raft::SnapshotPolicy;
+use async_raft::State;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Time snapshot policy with a new node test.
+///
+/// A policy which does not count entries gives no threshold of lag beyond which a target is sent a snapshot. A target
+/// which lacks entries covered by the leader's snapshot must be sent the snapshot nonetheless, as they may have been
+/// purged from the log.
+///
+/// What does this test do?
+///
+/// - build a stable single node cluster, with a policy which takes a snapshot once some time has passed since the last
+///   one, and send enough requests to it to take more than a few payloads to replicate.
+/// - wait for the snapshot to be taken.
+/// - add a new node as a non-voter, and assert that it is sent the snapshot and catches up with the leader.
+///
+/// RUST_LOG=async_raft,memstore,snapshot_policy_time_new_node=trace cargo test -p async-raft --test
+/// snapshot_policy_time_new_node
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn snapshot_policy_time_new_node() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(2000));
+    let since_last: u64 = 300;
+
+    // Setup test dependencies.
+    let config = Arc::new(
+        Config::build("test".into())
+            .snapshot_policy(SnapshotPolicy::TimeSinceLast(since_last))
+            .max_payload_entries(5)
+            .validate()
+            .expect("failed to build Raft config"),
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- a snapshot is taken once the time has passed");
+    {
+        router.client_request_many(0, "0", 30).await;
+        want += 30;
+        router.wait_for_log(&btreeset![0], want, timeout, "write").await?;
+
+        router
+            .wait_for_snapshot(
+                &btreeset![0],
+                LogId { term: 1, index: want },
+                Some(Duration::from_millis(since_last * 5)),
+                "snapshot by time",
+            )
+            .await?;
+    }
+
+    tracing::info!("--- a new node is sent the snapshot");
+    {
+        router.new_raft_node(1).await;
+        router.add_non_voter(0, 1).await?;
+        want += 1;
+
+        router.wait_for_log(&btreeset![0, 1], want, timeout, "add non-voter").await?;
+        router
+            .wait_for_snapshot(
+                &btreeset![1],
+                LogId { term: 1, index: want },
+                timeout,
+                "install snapshot",
+            )
+            .await?;
+        router.wait_for_state(&btreeset![0], State::Leader, timeout, "leader stays").await?;
+
+        router.client_request_many(0, "0", 5).await;
+        want += 5;
+        router.wait_for_log(&btreeset![0, 1], want, timeout, "write after snapshot").await?;
+
+        let sm0 = router.get_storage_handle(&0).await?.get_state_machine().await;
+        let sm1 = router.get_storage_handle(&1).await?.get_state_machine().await;
+        assert_eq!(sm0.client_status, sm1.client_status);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_raft::checksum::crc32;
+use async_raft::raft::InstallSnapshotRequest;
+use async_raft::Config;
+use async_raft::LogId;
+use async_raft::RaftStorage;
+use async_raft::RaftStorageDebug;
+use async_raft::SnapshotPolicy;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Snapshot transfer resume test.
+///
+/// What does this test do?
+///
+/// - build a stable single node cluster, and send enough requests to it that a snapshot is built.
+/// - send the first chunk of the snapshot to a new node, as the leader would before the transfer is interrupted.
+/// - restart the new node, and assert that it still has the chunk, by sending it a chunk past it.
+/// - add the new node as a non-voter, and assert that the leader resumes sending the snapshot where it was interrupted,
+///   and that the node installs it.
+///
+/// RUST_LOG=async_raft,memstore,snapshot_resume=trace cargo test -p async-raft --test snapshot_resume
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn snapshot_resume() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let snapshot_threshold: u64 = 10;
+    let chunk_size: u64 = 10;
+
+    let config = Arc::new(
+        Config::build("test".into())
+            .snapshot_policy(SnapshotPolicy::LogsSinceLast(snapshot_threshold))
+            .snapshot_max_chunk_size(chunk_size)
+            .validate()
+            .expect("failed to build Raft config"),
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- send just enough logs to trigger snapshot");
+    {
+        router.client_request_many(0, "0", (snapshot_threshold - want) as usize).await;
+        want = snapshot_threshold;
+
+        router.wait_for_log(&btreeset![0], want, None, "send log to trigger snapshot").await?;
+        router.wait_for_snapshot(&btreeset![0], LogId { term: 1, index: want }, None, "snapshot").await?;
+    }
+
+    let snapshot = router.get_storage_handle(&0).await?.get_current_snapshot().await?.expect("no snapshot");
+    let meta = snapshot.meta;
+    let data = snapshot.snapshot.into_inner();
+    let end = data.len() as u64;
+    assert!(end > 2 * chunk_size, "snapshot too small to be sent in chunks: {}", end);
+
+    tracing::info!("--- send the first chunk of the snapshot to a new node");
+    {
+        router.new_raft_node(1).await;
+
+        let res = router
+            .get_raft_handle(&1)
+            .await?
+            .install_snapshot(InstallSnapshotRequest {
+                term: 1,
+                leader_id: 0,
+                meta: meta.clone(),
+                offset: 0,
+                data: data[..chunk_size as usize].to_vec(),
+                checksum: Some(crc32(&data[..chunk_size as usize])),
+                compression: None,
+                done: false,
+            })
+            .await?;
+        assert_eq!(Some(chunk_size), res.offset);
+    }
+
+    tracing::info!("--- the partial snapshot survives a restart");
+    {
+        let (node1, sto1) = router.remove_node(1).await.expect("node 1 not found");
+        node1.shutdown().await?;
+        router.new_raft_node_with_sto(1, sto1).await;
+
+        let offset = 2 * chunk_size;
+        let res = router
+            .get_raft_handle(&1)
+            .await?
+            .install_snapshot(InstallSnapshotRequest {
+                term: 1,
+                leader_id: 0,
+                meta: meta.clone(),
+                offset,
+                data: data[offset as usize..(offset + chunk_size) as usize].to_vec(),
+                checksum: Some(crc32(&data[offset as usize..(offset + chunk_size) as usize])),
+                compression: None,
+                done: false,
+            })
+            .await?;
+        assert_eq!(
+            Some(chunk_size),
+            res.offset,
+            "expected the node to ask for the chunk after the one it has"
+        );
+    }
+
+    tracing::info!("--- the leader resumes the transfer");
+    {
+        router.add_non_voter(0, 1).await?;
+        want += 1;
+
+        router.wait_for_log(&btreeset![0, 1], want, None, "add non-voter").await?;
+        router.wait_for_snapshot(&btreeset![1], meta.last_log_id, None, "install snapshot").await?;
+
+        // The first chunk is sent again to find out where to resume, and the rest once each.
+        let mut want_chunks = vec![0];
+        want_chunks.extend((chunk_size..end).step_by(chunk_size as usize));
+        assert_eq!(want_chunks, router.sent_snapshot_chunks());
+
+        let sm0 = router.get_storage_handle(&0).await?.get_state_machine().await;
+        let sm1 = router.get_storage_handle(&1).await?.get_state_machine().await;
+        assert_eq!(sm0.client_status, sm1.client_status);
+    }
+
+    Ok(())
+}
@@ -9,6 +9,7 @@ use async_raft::RaftStorage;
 use async_raft::SnapshotPolicy;
 use async_raft::State;
 use fixtures::RaftRouter;
+use maplit::btreemap;
 use maplit::btreeset;
 
 #[macro_use]
@@ -56,6 +57,7 @@ async fn snapshot_uses_prev_snap_membership() -> Result<()> {
 
         router.new_raft_node(1).await;
         router.add_non_voter(0, 1).await?;
+        want += 1; // learner log
 
         router.change_membership(0, btreeset![0, 1]).await?;
         want += 2;
@@ -82,6 +84,8 @@ async fn snapshot_uses_prev_snap_membership() -> Result<()> {
             MembershipConfig {
                 members: btreeset![0, 1],
                 members_after_consensus: None,
+                learners: btreeset! {},
+                nodes: btreemap! {},
             },
             m,
             "membership "
@@ -125,6 +129,8 @@ async fn snapshot_uses_prev_snap_membership() -> Result<()> {
             MembershipConfig {
                 members: btreeset![0, 1],
                 members_after_consensus: None,
+                learners: btreeset! {},
+                nodes: btreemap! {},
             },
             m,
             "membership "
@@ -0,0 +1,127 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_raft::raft::MaxLag;
+use async_raft::Config;
+use async_raft::LogId;
+use async_raft::StaleReadError;
+use async_raft::State;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// Stale read test.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster, with an additional non-voter.
+/// - write a number of entries to the cluster.
+/// - call the stale_read interface on every node, and assert that they all serve reads up to the last entry, with both
+///   a time-based and an entry-based lag.
+/// - isolate a follower, and assert that its stale reads fail once the leader has not been heard from for longer than
+///   the allowed lag, or than `election_timeout_max` for an entry-based lag, while the other follower keeps serving
+///   them.
+///
+/// RUST_LOG=async_raft,memstore,stale_read=trace cargo test -p async-raft --test stale_read
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn stale_read() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // Setup test dependencies.
+    let config = Arc::new(Config::build("test".into()).pre_vote(true).validate().expect("failed to build Raft config"));
+    let router = Arc::new(RaftRouter::new(config.clone()));
+    router.new_raft_node(0).await;
+    router.new_raft_node(1).await;
+    router.new_raft_node(2).await;
+
+    let mut want = 0;
+
+    // Assert all nodes are in non-voter state & have no entries.
+    router.wait_for_log(&btreeset![0, 1, 2], want, None, "empty node").await?;
+    router.wait_for_state(&btreeset![0, 1, 2], State::NonVoter, None, "empty node").await?;
+    router.assert_pristine_cluster().await;
+
+    // A pristine node has never heard from a leader.
+    let rst = router.stale_read(0, MaxLag::Entries(100)).await;
+    assert!(
+        matches!(rst, Err(StaleReadError::LeaderUnreachable(None))),
+        "got {:?}",
+        rst
+    );
+
+    // Initialize the cluster, then assert that a stable cluster was formed & held.
+    tracing::info!("--- initializing cluster");
+    router.initialize_from_single_node(0).await?;
+    want += 1;
+
+    let timeout = Some(Duration::from_millis(config.election_timeout_max * 2));
+    router.wait_for_log(&btreeset![0, 1, 2], want, timeout, "init leader").await?;
+    router.assert_stable_cluster(Some(1), Some(want)).await;
+
+    let leader = router.leader().await.expect("leader not found");
+    assert_eq!(leader, 0, "expected leader to be node 0, got {}", leader);
+
+    tracing::info!("--- add a non-voter");
+
+    router.new_raft_node(3).await;
+    router.add_non_voter(leader, 3).await?;
+    want += 1; // learner log
+
+    tracing::info!("--- write entries, then read from every node");
+
+    router.client_request_many(leader, "0", 10).await;
+    want += 10;
+    router.wait_for_log(&btreeset![0, 1, 2, 3], want, None, "write entries").await?;
+
+    // Wait for a heartbeat to carry the latest commit index to every node.
+    tokio::time::sleep(Duration::from_millis(config.heartbeat_interval * 2)).await;
+
+    let max_time = Duration::from_millis(config.election_timeout_min);
+    for id in 0..4 {
+        let log_id = router.stale_read(id, MaxLag::Time(max_time)).await?;
+        assert_eq!(
+            LogId { term: 1, index: want },
+            log_id,
+            "time-based stale read on node {}",
+            id
+        );
+
+        let log_id = router.stale_read(id, MaxLag::Entries(0)).await?;
+        assert_eq!(
+            LogId { term: 1, index: want },
+            log_id,
+            "entry-based stale read on node {}",
+            id
+        );
+    }
+
+    tracing::info!("--- isolate a follower, its stale reads fail");
+
+    router.isolate_node(2).await;
+    tokio::time::sleep(Duration::from_millis(config.election_timeout_max * 2)).await;
+
+    let rst = router.stale_read(2, MaxLag::Time(max_time)).await;
+    assert!(
+        matches!(rst, Err(StaleReadError::LeaderUnreachable(Some(_)))),
+        "got {:?}",
+        rst
+    );
+
+    // The follower has applied everything the leader had committed when last heard from, but that is no longer known
+    // to be recent.
+    let rst = router.stale_read(2, MaxLag::Entries(100)).await;
+    assert!(
+        matches!(rst, Err(StaleReadError::LeaderUnreachable(Some(_)))),
+        "got {:?}",
+        rst
+    );
+
+    router.stale_read(1, MaxLag::Time(max_time)).await?;
+    router.stale_read(1, MaxLag::Entries(0)).await?;
+
+    Ok(())
+}
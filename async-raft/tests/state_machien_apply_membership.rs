@@ -7,6 +7,7 @@ use async_raft::RaftStorageDebug;
 use async_raft::State;
 use fixtures::RaftRouter;
 use futures::stream::StreamExt;
+use maplit::btreemap;
 use maplit::btreeset;
 
 #[macro_use]
@@ -53,6 +54,8 @@ async fn state_machine_apply_membership() -> Result<()> {
             Some(MembershipConfig {
                 members: btreeset![0],
                 members_after_consensus: None,
+                learners: btreeset! {},
+                nodes: btreemap! {},
             }),
             sm.last_membership
         );
@@ -73,6 +76,7 @@ async fn state_machine_apply_membership() -> Result<()> {
     while let Some(inner) = new_nodes.next().await {
         inner?;
     }
+    want += 4; // one learner log per non-voter
 
     tracing::info!("--- changing cluster config");
     router.change_membership(0, btreeset![0, 1, 2]).await?;
@@ -88,6 +92,8 @@ async fn state_machine_apply_membership() -> Result<()> {
             Some(MembershipConfig {
                 members: btreeset![0, 1, 2],
                 members_after_consensus: None,
+                learners: btreeset! {3, 4},
+                nodes: btreemap! {},
             }),
             sm.last_membership
         );
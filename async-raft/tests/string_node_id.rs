@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use async_raft::async_trait::async_trait;
+use async_raft::raft::AppendEntriesRequest;
+use async_raft::raft::AppendEntriesResponse;
+use async_raft::raft::ClientWriteRequest;
+use async_raft::raft::InstallSnapshotRequest;
+use async_raft::raft::InstallSnapshotResponse;
+use async_raft::raft::Node;
+use async_raft::raft::ReadIndexRequest;
+use async_raft::raft::ReadIndexResponse;
+use async_raft::raft::TimeoutNowRequest;
+use async_raft::raft::TimeoutNowResponse;
+use async_raft::raft::VoteRequest;
+use async_raft::raft::VoteResponse;
+use async_raft::Config;
+use async_raft::Raft;
+use async_raft::RaftNetwork;
+use async_raft::State;
+use maplit::btreemap;
+use maplit::btreeset;
+use memstore::ClientRequest as MemClientRequest;
+use memstore::ClientResponse as MemClientResponse;
+use memstore::MemStore;
+use tokio::sync::RwLock;
+
+#[macro_use]
+mod fixtures;
+
+type StringRaft = Raft<MemClientRequest, MemClientResponse, StringRouter, MemStore<String>, String>;
+
+/// A network of nodes identified by strings.
+#[derive(Default)]
+struct StringRouter {
+    routing_table: RwLock<BTreeMap<String, StringRaft>>,
+}
+
+impl StringRouter {
+    async fn get(&self, target: &str) -> Result<StringRaft> {
+        let rt = self.routing_table.read().await;
+        rt.get(target).cloned().ok_or_else(|| anyhow!("node {} not found in routing table", target))
+    }
+}
+
+#[async_trait]
+impl RaftNetwork<MemClientRequest, MemClientResponse, String> for StringRouter {
+    async fn send_append_entries(
+        &self,
+        target: String,
+        rpc: AppendEntriesRequest<MemClientRequest, String>,
+    ) -> Result<AppendEntriesResponse> {
+        Ok(self.get(&target).await?.append_entries(rpc).await?)
+    }
+
+    async fn send_install_snapshot(
+        &self,
+        target: String,
+        rpc: InstallSnapshotRequest<String>,
+    ) -> Result<InstallSnapshotResponse> {
+        Ok(self.get(&target).await?.install_snapshot(rpc).await?)
+    }
+
+    async fn send_vote(&self, target: String, rpc: VoteRequest<String>) -> Result<VoteResponse> {
+        Ok(self.get(&target).await?.vote(rpc).await?)
+    }
+
+    async fn send_timeout_now(&self, target: String, rpc: TimeoutNowRequest<String>) -> Result<TimeoutNowResponse> {
+        Ok(self.get(&target).await?.timeout_now(rpc).await?)
+    }
+
+    async fn send_read_index(&self, target: String, rpc: ReadIndexRequest<String>) -> Result<ReadIndexResponse> {
+        Ok(self.get(&target).await?.handle_read_index(rpc).await?)
+    }
+}
+
+/// String node ID test.
+///
+/// What does this test do?
+///
+/// - brings 3 nodes online, identified by strings instead of integers.
+/// - initializes the cluster from one of them, and waits for a leader to be elected.
+/// - writes a number of entries to the leader, and asserts that they are replicated to every node.
+/// - adds a non-voter with metadata, and asserts that every node records it under its string ID.
+///
+/// RUST_LOG=async_raft,memstore,string_node_id=trace cargo test -p async-raft --test string_node_id
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn string_node_id() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let timeout = Some(Duration::from_millis(5000));
+
+    // Setup test dependencies.
+    let config = Arc::new(Config::build("test".into()).validate().expect("failed to build Raft config"));
+    let router = Arc::new(StringRouter::default());
+
+    let ids = ["node-a", "node-b", "node-c", "node-d"].iter().map(|x| x.to_string()).collect::<Vec<_>>();
+    for id in ids.iter() {
+        let sto = Arc::new(MemStore::new(id.clone()));
+        let raft = Raft::new(id.clone(), config.clone(), router.clone(), sto);
+        router.routing_table.write().await.insert(id.clone(), raft);
+    }
+
+    tracing::info!("--- initializing cluster");
+
+    let members = btreeset! {ids[0].clone(), ids[1].clone(), ids[2].clone()};
+    router.get(&ids[0]).await?.initialize(members.clone()).await?;
+
+    let mut want = 1;
+    for id in members.iter() {
+        router.get(id).await?.wait(timeout).log(want, "init").await?;
+    }
+
+    let leader = router.get(&ids[0]).await?.current_leader().await.expect("leader not found");
+    assert!(members.contains(&leader), "unexpected leader {}", leader);
+    router.get(&leader).await?.wait(timeout).state(State::Leader, "leader elected").await?;
+
+    tracing::info!("--- write to the leader");
+    {
+        let raft = router.get(&leader).await?;
+        for serial in 0..10 {
+            let req = MemClientRequest {
+                client: "0".into(),
+                serial,
+                status: format!("request-{}", serial),
+            };
+            raft.client_write(ClientWriteRequest::new(req)).await?;
+        }
+        want += 10;
+
+        for id in members.iter() {
+            let m = router.get(id).await?.wait(timeout).log(want, "write").await?;
+            assert_eq!(Some(leader.clone()), m.current_leader, "node {} leader", id);
+            assert_eq!(members, m.membership_config.members, "node {} members", id);
+        }
+    }
+
+    tracing::info!("--- add a non-voter");
+    {
+        let raft = router.get(&leader).await?;
+        raft.add_non_voter(ids[3].clone(), Some(Node::new("127.0.0.1:21003"))).await?;
+        want += 1; // learner log
+
+        for id in ids.iter() {
+            let m = router.get(id).await?.wait(timeout).log(want, "add non-voter").await?;
+            assert_eq!(
+                btreeset! {ids[3].clone()},
+                m.membership_config.learners,
+                "node {} learners",
+                id
+            );
+            assert_eq!(
+                btreemap! {ids[3].clone() => Node::new("127.0.0.1:21003")},
+                m.membership_config.nodes,
+                "node {} metadata",
+                id
+            );
+        }
+        router.get(&ids[3]).await?.wait(timeout).state(State::NonVoter, "non-voter").await?;
+
+        let m = raft.metrics().borrow().clone();
+        let leader_metrics = m.leader_metrics.expect("leader metrics");
+        assert_eq!(btreeset! {ids[3].clone()}, leader_metrics.non_voters);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_raft::Config;
+use async_raft::LogId;
+use async_raft::RaftStorage;
+use async_raft::SnapshotError;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+
+#[macro_use]
+mod fixtures;
+
+/// On-demand snapshot test.
+///
+/// What does this test do?
+///
+/// - build a stable single node cluster, and send a few requests to it, too few for the snapshot policy to take a
+///   snapshot.
+/// - trigger a snapshot, and assert that it covers every applied entry and becomes the current snapshot.
+/// - trigger a snapshot on a pristine node, and assert that it is rejected.
+///
+/// RUST_LOG=async_raft,memstore,trigger_snapshot=trace cargo test -p async-raft --test trigger_snapshot
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn trigger_snapshot() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::build("test".into()).validate().expect("failed to build Raft config"));
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut want = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    router.client_request_many(0, "0", 10).await;
+    want += 10;
+    router.wait_for_log(&btreeset![0], want, None, "write").await?;
+
+    tracing::info!("--- a triggered snapshot covers every applied entry");
+    {
+        let metrics = router.latest_metrics().await;
+        assert_eq!(0, metrics[0].snapshot.index);
+
+        let meta = router.get_raft_handle(&0).await?.trigger_snapshot().await?;
+        assert_eq!(LogId { term: 1, index: want }, meta.last_log_id);
+
+        router.wait_for_snapshot(&btreeset![0], meta.last_log_id, None, "triggered snapshot").await?;
+
+        let snapshot = router.get_storage_handle(&0).await?.get_current_snapshot().await?.expect("no snapshot");
+        assert_eq!(meta.snapshot_id, snapshot.meta.snapshot_id);
+    }
+
+    tracing::info!("--- a pristine node has nothing to snapshot");
+    {
+        router.new_raft_node(1).await;
+
+        let res = router.get_raft_handle(&1).await?.trigger_snapshot().await;
+        match res {
+            Err(SnapshotError::NothingToSnapshot) => {}
+            _ => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    Ok(())
+}
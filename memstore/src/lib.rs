@@ -15,6 +15,7 @@ use std::sync::Mutex;
 
 use anyhow::Result;
 use async_raft::async_trait::async_trait;
+use async_raft::checksum::crc32;
 use async_raft::raft::Entry;
 use async_raft::raft::EntryPayload;
 use async_raft::raft::MembershipConfig;
@@ -27,6 +28,7 @@ use async_raft::LogId;
 use async_raft::NodeId;
 use async_raft::RaftStorage;
 use async_raft::RaftStorageDebug;
+use async_raft::SessionHistory;
 use async_raft::SnapshotMeta;
 use serde::Deserialize;
 use serde::Serialize;
@@ -49,10 +51,14 @@ pub struct ClientRequest {
     pub status: String,
 }
 
-impl AppData for ClientRequest {}
+impl AppData for ClientRequest {
+    fn size(&self) -> u64 {
+        (self.client.len() + std::mem::size_of::<u64>() + self.status.len()) as u64
+    }
+}
 
 /// The application data response type which the `MemStore` works with.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct ClientResponse(Option<String>);
 
 impl AppDataResponse for ClientResponse {}
@@ -66,48 +72,60 @@ pub enum ShutdownError {
 
 /// The application snapshot type which the `MemStore` works with.
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct MemStoreSnapshot {
-    pub meta: SnapshotMeta,
+#[serde(bound = "NID: NodeId")]
+pub struct MemStoreSnapshot<NID: NodeId = u64> {
+    pub meta: SnapshotMeta<NID>,
 
     /// The data of the state machine at the time of this snapshot.
     pub data: Vec<u8>,
 }
 
 /// The state machine of the `MemStore`.
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
-pub struct MemStoreStateMachine {
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound = "NID: NodeId")]
+pub struct MemStoreStateMachine<NID: NodeId = u64> {
     pub last_applied_log: LogId,
 
-    pub last_membership: Option<MembershipConfig>,
+    pub last_membership: Option<MembershipConfig<NID>>,
 
-    /// A mapping of client IDs to their state info.
-    pub client_serial_responses: HashMap<String, (u64, Option<String>)>,
     /// The current status of a client by ID.
     pub client_status: HashMap<String, String>,
 }
 
+impl<NID: NodeId> Default for MemStoreStateMachine<NID> {
+    fn default() -> Self {
+        Self {
+            last_applied_log: LogId::default(),
+            last_membership: None,
+            client_status: HashMap::new(),
+        }
+    }
+}
+
 /// An in-memory storage system implementing the `async_raft::RaftStorage` trait.
-pub struct MemStore {
+pub struct MemStore<NID: NodeId = u64> {
     /// Turn on defensive check for inputs.
     defensive: RwLock<bool>,
 
     /// The ID of the Raft node for which this memory storage instances is configured.
-    id: NodeId,
+    id: NID,
     /// The Raft log.
-    log: RwLock<BTreeMap<u64, Entry<ClientRequest>>>,
+    log: RwLock<BTreeMap<u64, Entry<ClientRequest, NID>>>,
     /// The Raft state machine.
-    sm: RwLock<MemStoreStateMachine>,
+    sm: RwLock<MemStoreStateMachine<NID>>,
     /// The current hard state.
-    hs: RwLock<Option<HardState>>,
+    hs: RwLock<Option<HardState<NID>>>,
 
     snapshot_idx: Arc<Mutex<u64>>,
     /// The current snapshot.
-    current_snapshot: RwLock<Option<MemStoreSnapshot>>,
+    current_snapshot: RwLock<Option<MemStoreSnapshot<NID>>>,
+    /// The part received so far of a snapshot being installed.
+    partial_snapshot: RwLock<Option<MemStoreSnapshot<NID>>>,
 }
 
-impl MemStore {
+impl<NID: NodeId> MemStore<NID> {
     /// Create a new `MemStore` instance.
-    pub fn new(id: NodeId) -> Self {
+    pub fn new(id: NID) -> Self {
         let log = RwLock::new(BTreeMap::new());
         let sm = RwLock::new(MemStoreStateMachine::default());
         let hs = RwLock::new(None);
@@ -120,17 +138,18 @@ impl MemStore {
             hs,
             snapshot_idx: Arc::new(Mutex::new(0)),
             current_snapshot,
+            partial_snapshot: RwLock::new(None),
         }
     }
 
     /// Create a new `MemStore` instance with some existing state (for testing).
     #[cfg(test)]
     pub fn new_with_state(
-        id: NodeId,
-        log: BTreeMap<u64, Entry<ClientRequest>>,
-        sm: MemStoreStateMachine,
-        hs: Option<HardState>,
-        current_snapshot: Option<MemStoreSnapshot>,
+        id: NID,
+        log: BTreeMap<u64, Entry<ClientRequest, NID>>,
+        sm: MemStoreStateMachine<NID>,
+        hs: Option<HardState<NID>>,
+        current_snapshot: Option<MemStoreSnapshot<NID>>,
     ) -> Self {
         let log = RwLock::new(log);
         let sm = RwLock::new(sm);
@@ -144,12 +163,13 @@ impl MemStore {
             hs,
             snapshot_idx: Arc::new(Mutex::new(0)),
             current_snapshot,
+            partial_snapshot: RwLock::new(None),
         }
     }
 }
 
 // TODO(xp): elaborate errors
-impl MemStore {
+impl<NID: NodeId> MemStore<NID> {
     /// Ensure that logs that have greater index than last_applied should have greater log_id.
     /// Invariant must hold: `log.log_id.index > last_applied.index` implies `log.log_id > last_applied`.
     pub async fn defensive_no_dirty_log(&self) -> anyhow::Result<()> {
@@ -171,7 +191,7 @@ impl MemStore {
 
     /// Ensure that current_term must increment for every update, and for every term there could be only one value for
     /// voted_for.
-    pub async fn defensive_incremental_hard_state(&self, hs: &HardState) -> anyhow::Result<()> {
+    pub async fn defensive_incremental_hard_state(&self, hs: &HardState<NID>) -> anyhow::Result<()> {
         if !*self.defensive.read().await {
             return Ok(());
         }
@@ -194,7 +214,7 @@ impl MemStore {
         Ok(())
     }
 
-    pub async fn defensive_consecutive_input<D: AppData>(&self, entries: &[&Entry<D>]) -> anyhow::Result<()> {
+    pub async fn defensive_consecutive_input<D: AppData>(&self, entries: &[&Entry<D, NID>]) -> anyhow::Result<()> {
         if !*self.defensive.read().await {
             return Ok(());
         }
@@ -220,7 +240,7 @@ impl MemStore {
         Ok(())
     }
 
-    pub async fn defensive_nonempty_input<D: AppData>(&self, entries: &[&Entry<D>]) -> anyhow::Result<()> {
+    pub async fn defensive_nonempty_input<D: AppData>(&self, entries: &[&Entry<D, NID>]) -> anyhow::Result<()> {
         if !*self.defensive.read().await {
             return Ok(());
         }
@@ -234,7 +254,7 @@ impl MemStore {
 
     pub async fn defensive_append_log_index_is_last_plus_one<D: AppData>(
         &self,
-        entries: &[&Entry<D>],
+        entries: &[&Entry<D, NID>],
     ) -> anyhow::Result<()> {
         if !*self.defensive.read().await {
             return Ok(());
@@ -254,7 +274,7 @@ impl MemStore {
         Ok(())
     }
 
-    pub async fn defensive_append_log_id_gt_last<D: AppData>(&self, entries: &[&Entry<D>]) -> anyhow::Result<()> {
+    pub async fn defensive_append_log_id_gt_last<D: AppData>(&self, entries: &[&Entry<D, NID>]) -> anyhow::Result<()> {
         if !*self.defensive.read().await {
             return Ok(());
         }
@@ -309,7 +329,7 @@ impl MemStore {
 
     pub async fn defensive_apply_index_is_last_applied_plus_one<D: AppData>(
         &self,
-        entries: &[&Entry<D>],
+        entries: &[&Entry<D, NID>],
     ) -> anyhow::Result<()> {
         if !*self.defensive.read().await {
             return Ok(());
@@ -382,7 +402,7 @@ impl MemStore {
     pub async fn defensive_range_hits_logs<T: AppData, RNG: RangeBounds<u64> + Debug + Send>(
         &self,
         range: RNG,
-        logs: &[Entry<T>],
+        logs: &[Entry<T, NID>],
     ) -> anyhow::Result<()> {
         if !*self.defensive.read().await {
             return Ok(());
@@ -429,7 +449,7 @@ impl MemStore {
         Ok(())
     }
 
-    pub async fn defensive_apply_log_id_gt_last<D: AppData>(&self, entries: &[&Entry<D>]) -> anyhow::Result<()> {
+    pub async fn defensive_apply_log_id_gt_last<D: AppData>(&self, entries: &[&Entry<D, NID>]) -> anyhow::Result<()> {
         if !*self.defensive.read().await {
             return Ok(());
         }
@@ -450,22 +470,22 @@ impl MemStore {
 }
 
 #[async_trait]
-impl RaftStorageDebug<MemStoreStateMachine> for MemStore {
+impl<NID: NodeId> RaftStorageDebug<MemStoreStateMachine<NID>, NID> for MemStore<NID> {
     /// Get a handle to the state machine for testing purposes.
-    async fn get_state_machine(&self) -> MemStoreStateMachine {
+    async fn get_state_machine(&self) -> MemStoreStateMachine<NID> {
         self.sm.write().await.clone()
     }
 
     /// Get a handle to the current hard state for testing purposes.
-    async fn read_hard_state(&self) -> Option<HardState> {
+    async fn read_hard_state(&self) -> Option<HardState<NID>> {
         self.hs.read().await.clone()
     }
 }
 
-impl MemStore {
-    fn find_first_membership_log<'a, T, D>(mut it: T) -> Option<(LogId, MembershipConfig)>
+impl<NID: NodeId> MemStore<NID> {
+    fn find_first_membership_log<'a, T, D>(mut it: T) -> Option<(LogId, MembershipConfig<NID>)>
     where
-        T: 'a + Iterator<Item = &'a Entry<D>>,
+        T: 'a + Iterator<Item = &'a Entry<D, NID>>,
         D: AppData,
     {
         it.find_map(|entry| match &entry.payload {
@@ -476,7 +496,7 @@ impl MemStore {
 
     /// Go backwards through the log to find the most recent membership config <= `upto_index`.
     #[tracing::instrument(level = "trace", skip(self))]
-    pub async fn get_membership_from_log(&self, upto_index: Option<u64>) -> Result<MembershipConfig> {
+    pub async fn get_membership_from_log(&self, upto_index: Option<u64>) -> Result<MembershipConfig<NID>> {
         self.defensive_no_dirty_log().await?;
 
         let membership = {
@@ -514,13 +534,13 @@ impl MemStore {
 
         Ok(match membership {
             Some(cfg) => cfg,
-            None => MembershipConfig::new_initial(self.id),
+            None => MembershipConfig::new_initial(self.id.clone()),
         })
     }
 }
 
 #[async_trait]
-impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
+impl<NID: NodeId> RaftStorage<ClientRequest, ClientResponse, NID> for MemStore<NID> {
     type SnapshotData = Cursor<Vec<u8>>;
     type ShutdownError = ShutdownError;
 
@@ -531,12 +551,12 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn get_membership_config(&self) -> Result<MembershipConfig> {
+    async fn get_membership_config(&self) -> Result<MembershipConfig<NID>> {
         self.get_membership_from_log(None).await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn get_initial_state(&self) -> Result<InitialState> {
+    async fn get_initial_state(&self) -> Result<InitialState<NID>> {
         self.defensive_no_dirty_log().await?;
 
         let membership = self.get_membership_config().await?;
@@ -567,7 +587,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
                 })
             }
             None => {
-                let new = InitialState::new_initial(self.id);
+                let new = InitialState::new_initial(self.id.clone());
                 *hs = Some(new.hard_state.clone());
                 Ok(new)
             }
@@ -575,7 +595,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
-    async fn save_hard_state(&self, hs: &HardState) -> Result<()> {
+    async fn save_hard_state(&self, hs: &HardState<NID>) -> Result<()> {
         self.defensive_incremental_hard_state(hs).await?;
 
         let mut h = self.hs.write().await;
@@ -588,7 +608,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
     async fn get_log_entries<RNG: RangeBounds<u64> + Clone + Debug + Send + Sync>(
         &self,
         range: RNG,
-    ) -> Result<Vec<Entry<ClientRequest>>> {
+    ) -> Result<Vec<Entry<ClientRequest, NID>>> {
         self.defensive_nonempty_range(range.clone()).await?;
 
         let res = {
@@ -602,7 +622,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn try_get_log_entry(&self, log_index: u64) -> Result<Option<Entry<ClientRequest>>> {
+    async fn try_get_log_entry(&self, log_index: u64) -> Result<Option<Entry<ClientRequest, NID>>> {
         let log = self.log.read().await;
         Ok(log.get(&log_index).cloned())
     }
@@ -633,7 +653,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
     }
 
     #[tracing::instrument(level = "trace", skip(self, entries))]
-    async fn append_to_log(&self, entries: &[&Entry<ClientRequest>]) -> Result<()> {
+    async fn append_to_log(&self, entries: &[&Entry<ClientRequest, NID>]) -> Result<()> {
         self.defensive_nonempty_input(entries).await?;
         self.defensive_consecutive_input(entries).await?;
         self.defensive_append_log_index_is_last_plus_one(entries).await?;
@@ -647,7 +667,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
     }
 
     #[tracing::instrument(level = "trace", skip(self, entries))]
-    async fn apply_to_state_machine(&self, entries: &[&Entry<ClientRequest>]) -> Result<Vec<ClientResponse>> {
+    async fn apply_to_state_machine(&self, entries: &[&Entry<ClientRequest, NID>]) -> Result<Vec<ClientResponse>> {
         self.defensive_nonempty_input(entries).await?;
         self.defensive_apply_index_is_last_applied_plus_one(entries).await?;
         self.defensive_apply_log_id_gt_last(entries).await?;
@@ -667,14 +687,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
                 }
                 EntryPayload::Normal(ref norm) => {
                     let data = &norm.data;
-                    if let Some((serial, r)) = sm.client_serial_responses.get(&data.client) {
-                        if serial == &data.serial {
-                            res.push(ClientResponse(r.clone()));
-                            continue;
-                        }
-                    }
                     let previous = sm.client_status.insert(data.client.clone(), data.status.clone());
-                    sm.client_serial_responses.insert(data.client.clone(), (data.serial, previous.clone()));
                     res.push(ClientResponse(previous));
                 }
                 EntryPayload::ConfigChange(ref mem) => {
@@ -686,8 +699,8 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
         Ok(res)
     }
 
-    #[tracing::instrument(level = "trace", skip(self))]
-    async fn do_log_compaction(&self) -> Result<Snapshot<Self::SnapshotData>> {
+    #[tracing::instrument(level = "trace", skip(self, sessions))]
+    async fn do_log_compaction(&self, sessions: &SessionHistory) -> Result<Snapshot<Self::SnapshotData, NID>> {
         let (data, last_applied_log);
         let membership_config;
         {
@@ -695,7 +708,8 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
             let sm = self.sm.read().await;
             data = serde_json::to_vec(&*sm)?;
             last_applied_log = sm.last_applied_log;
-            membership_config = sm.last_membership.clone().unwrap_or_else(|| MembershipConfig::new_initial(self.id));
+            membership_config =
+                sm.last_membership.clone().unwrap_or_else(|| MembershipConfig::new_initial(self.id.clone()));
         } // Release state machine read lock.
 
         let snapshot_size = data.len();
@@ -720,6 +734,8 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
                 last_log_id: last_applied_log,
                 snapshot_id,
                 membership: membership_config.clone(),
+                checksum: Some(crc32(&data)),
+                sessions: sessions.at(last_applied_log.index),
             };
 
             let snapshot = MemStoreSnapshot {
@@ -742,10 +758,54 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
         Ok(Box::new(Cursor::new(Vec::new())))
     }
 
+    #[tracing::instrument(level = "trace", skip(self, snapshot))]
+    async fn save_partial_snapshot(
+        &self,
+        meta: &SnapshotMeta<NID>,
+        offset: u64,
+        snapshot: &mut Self::SnapshotData,
+    ) -> Result<()> {
+        let received = &snapshot.get_ref()[..offset as usize];
+        let mut partial_snapshot = self.partial_snapshot.write().await;
+        match &mut *partial_snapshot {
+            // Only append what was received since the last call, rather than copying it all again.
+            Some(partial) if partial.meta.snapshot_id == meta.snapshot_id && partial.data.len() <= received.len() => {
+                let saved = partial.data.len();
+                partial.data.extend_from_slice(&received[saved..]);
+            }
+            Some(partial) if partial.meta.snapshot_id == meta.snapshot_id => {
+                partial.data.truncate(received.len());
+            }
+            _ => {
+                *partial_snapshot = Some(MemStoreSnapshot {
+                    meta: meta.clone(),
+                    data: received.to_vec(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn get_partial_snapshot(&self, meta: &SnapshotMeta<NID>) -> Result<Option<(u64, Box<Self::SnapshotData>)>> {
+        let mut partial_snapshot = self.partial_snapshot.write().await;
+        match &*partial_snapshot {
+            Some(partial) if partial.meta.snapshot_id == meta.snapshot_id => {
+                let data = partial.data.clone();
+                Ok(Some((data.len() as u64, Box::new(Cursor::new(data)))))
+            }
+            _ => {
+                // A partial snapshot of another snapshot will not be resumed.
+                *partial_snapshot = None;
+                Ok(None)
+            }
+        }
+    }
+
     #[tracing::instrument(level = "trace", skip(self, snapshot))]
     async fn finalize_snapshot_installation(
         &self,
-        meta: &SnapshotMeta,
+        meta: &SnapshotMeta<NID>,
         snapshot: Box<Self::SnapshotData>,
     ) -> Result<()> {
         tracing::info!(
@@ -779,7 +839,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
 
         // Update the state machine.
         {
-            let new_sm: MemStoreStateMachine = serde_json::from_slice(&new_snapshot.data)?;
+            let new_sm: MemStoreStateMachine<NID> = serde_json::from_slice(&new_snapshot.data)?;
             let mut sm = self.sm.write().await;
             *sm = new_sm;
         }
@@ -787,11 +847,12 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
         // Update current snapshot.
         let mut current_snapshot = self.current_snapshot.write().await;
         *current_snapshot = Some(new_snapshot);
+        *self.partial_snapshot.write().await = None;
         Ok(())
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn get_current_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData>>> {
+    async fn get_current_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData, NID>>> {
         match &*self.current_snapshot.read().await {
             Some(snapshot) => {
                 // TODO(xp): try not to clone the entire data.
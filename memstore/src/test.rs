@@ -1,10 +1,15 @@
 use std::future::Future;
+use std::io::SeekFrom;
 use std::marker::PhantomData;
 
 use async_raft::raft::EntryConfigChange;
 use async_raft::raft::EntryNormal;
 use async_trait::async_trait;
+use maplit::btreemap;
 use maplit::btreeset;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncSeekExt;
+use tokio::io::AsyncWriteExt;
 
 use super::*;
 
@@ -17,14 +22,14 @@ where
     R: AppDataResponse,
     S: RaftStorage<D, R>,
 {
-    async fn new_store(&self, id: NodeId) -> S;
+    async fn new_store(&self, id: u64) -> S;
 }
 
 struct MemStoreBuilder {}
 
 #[async_trait]
 impl StoreBuilder<ClientRequest, ClientResponse, MemStore> for MemStoreBuilder {
-    async fn new_store(&self, id: NodeId) -> MemStore {
+    async fn new_store(&self, id: u64) -> MemStore {
         let sto = MemStore::new(id);
         sto.defensive(false).await;
         sto
@@ -52,7 +57,7 @@ where
     S: RaftStorage<D, R>,
     B: StoreBuilder<D, R, S>,
 {
-    async fn new_store(&self, id: NodeId) -> S {
+    async fn new_store(&self, id: u64) -> S {
         let dsto = self.inner.new_store(id).await;
         let d = dsto.defensive(true).await;
         assert!(d, "inner must impl defensive check");
@@ -119,6 +124,7 @@ where
         run_fut(Suite::append_to_log(builder))?;
         run_fut(Suite::apply_single(builder))?;
         run_fut(Suite::apply_multi(builder))?;
+        run_fut(Suite::partial_snapshot(builder))?;
 
         Ok(())
     }
@@ -132,6 +138,8 @@ where
             MembershipConfig {
                 members: btreeset! {NODE_ID},
                 members_after_consensus: None,
+                learners: btreeset! {},
+                nodes: btreemap! {},
             },
             membership,
         );
@@ -156,6 +164,8 @@ where
                             membership: MembershipConfig {
                                 members: btreeset! {3,4,5},
                                 members_after_consensus: None,
+                                learners: btreeset! {},
+                                nodes: btreemap! {},
                             },
                         }),
                     },
@@ -168,6 +178,8 @@ where
                 MembershipConfig {
                     members: btreeset! {3,4,5},
                     members_after_consensus: None,
+                    learners: btreeset! {},
+                    nodes: btreemap! {},
                 },
                 mem,
             );
@@ -182,6 +194,8 @@ where
                         membership: MembershipConfig {
                             members: btreeset! {1,2,3},
                             members_after_consensus: None,
+                            learners: btreeset! {},
+                            nodes: btreemap! {},
                         },
                     }),
                 }])
@@ -193,6 +207,8 @@ where
                 MembershipConfig {
                     members: btreeset! {3, 4, 5},
                     members_after_consensus: None,
+                    learners: btreeset! {},
+                    nodes: btreemap! {},
                 },
                 mem,
             );
@@ -207,6 +223,8 @@ where
                         membership: MembershipConfig {
                             members: btreeset! {1,2,3},
                             members_after_consensus: None,
+                            learners: btreeset! {},
+                            nodes: btreemap! {},
                         },
                     }),
                 }])
@@ -218,6 +236,8 @@ where
                 MembershipConfig {
                     members: btreeset! {1,2,3},
                     members_after_consensus: None,
+                    learners: btreeset! {},
+                    nodes: btreemap! {},
                 },
                 mem,
             );
@@ -251,6 +271,8 @@ where
             MembershipConfig {
                 members: btreeset! {NODE_ID},
                 members_after_consensus: None,
+                learners: btreeset! {},
+                nodes: btreemap! {},
             },
             initial.membership,
         );
@@ -325,6 +347,8 @@ where
                             membership: MembershipConfig {
                                 members: btreeset! {3,4,5},
                                 members_after_consensus: None,
+                                learners: btreeset! {},
+                                nodes: btreemap! {},
                             },
                         }),
                     },
@@ -337,6 +361,8 @@ where
                 MembershipConfig {
                     members: btreeset! {3,4,5},
                     members_after_consensus: None,
+                    learners: btreeset! {},
+                    nodes: btreemap! {},
                 },
                 initial.membership,
             );
@@ -351,6 +377,8 @@ where
                         membership: MembershipConfig {
                             members: btreeset! {1,2,3},
                             members_after_consensus: None,
+                            learners: btreeset! {},
+                            nodes: btreemap! {},
                         },
                     }),
                 }])
@@ -362,6 +390,8 @@ where
                 MembershipConfig {
                     members: btreeset! {3, 4, 5},
                     members_after_consensus: None,
+                    learners: btreeset! {},
+                    nodes: btreemap! {},
                 },
                 initial.membership,
             );
@@ -376,6 +406,8 @@ where
                         membership: MembershipConfig {
                             members: btreeset! {1,2,3},
                             members_after_consensus: None,
+                            learners: btreeset! {},
+                            nodes: btreemap! {},
                         },
                     }),
                 }])
@@ -387,6 +419,8 @@ where
                 MembershipConfig {
                     members: btreeset! {1,2,3},
                     members_after_consensus: None,
+                    learners: btreeset! {},
+                    nodes: btreemap! {},
                 },
                 initial.membership,
             );
@@ -670,10 +704,12 @@ where
                     serial: 0,
                     status: "lit".into(),
                 },
+                session: None,
             }),
         };
 
-        store.apply_to_state_machine(&[&entry]).await?;
+        let res = store.apply_to_state_machine(&[&entry]).await?;
+        assert_eq!(vec![ClientResponse(None)], res, "unexpected responses");
         let sm = store.get_state_machine().await;
 
         assert_eq!(
@@ -683,10 +719,6 @@ where
             sm.last_applied_log
         );
 
-        let client_serial =
-            sm.client_serial_responses.get("0").expect("expected entry to exist in client_serial_responses");
-        assert_eq!(client_serial, &(0, None), "unexpected client serial response");
-
         let client_status = sm.client_status.get("0").expect("expected entry to exist in client_status");
         assert_eq!(
             client_status, "lit",
@@ -723,11 +755,23 @@ where
         .into_iter()
         .map(|(id, req)| Entry {
             log_id: *id,
-            payload: EntryPayload::Normal(EntryNormal { data: req.clone() }),
+            payload: EntryPayload::Normal(EntryNormal {
+                data: req.clone(),
+                session: None,
+            }),
         })
         .collect::<Vec<_>>();
 
-        store.apply_to_state_machine(&entries.iter().collect::<Vec<_>>()).await?;
+        let res = store.apply_to_state_machine(&entries.iter().collect::<Vec<_>>()).await?;
+        assert_eq!(
+            vec![
+                ClientResponse(None),
+                ClientResponse(Some("old".into())),
+                ClientResponse(None)
+            ],
+            res,
+            "unexpected responses"
+        );
         let sm = store.get_state_machine().await;
 
         assert_eq!(
@@ -737,24 +781,6 @@ where
             sm.last_applied_log
         );
 
-        let client_serial1 = sm
-            .client_serial_responses
-            .get("1")
-            .expect("expected entry to exist in client_serial_responses for client 1");
-        assert_eq!(client_serial1.0, 1, "unexpected client serial response");
-        assert_eq!(
-            client_serial1.1,
-            Some(String::from("old")),
-            "unexpected client serial response"
-        );
-
-        let client_serial2 = sm
-            .client_serial_responses
-            .get("2")
-            .expect("expected entry to exist in client_serial_responses for client 2");
-        assert_eq!(client_serial2.0, 0, "unexpected client serial response");
-        assert_eq!(client_serial2.1, None, "unexpected client serial response");
-
         let client_status1 = sm.client_status.get("1").expect("expected entry to exist in client_status for client 1");
         let client_status2 = sm.client_status.get("2").expect("expected entry to exist in client_status for client 2");
         assert_eq!(
@@ -770,6 +796,52 @@ where
         Ok(())
     }
 
+    pub async fn partial_snapshot(builder: &B) -> Result<()> {
+        let store = builder.new_store(NODE_ID).await;
+
+        let meta = |snapshot_id: &str| SnapshotMeta {
+            last_log_id: LogId { term: 1, index: 10 },
+            membership: MembershipConfig::new_initial(NODE_ID),
+            snapshot_id: snapshot_id.into(),
+            checksum: None,
+            sessions: Default::default(),
+        };
+
+        let mut snapshot = store.begin_receiving_snapshot().await?;
+        snapshot.write_all(b"partial").await?;
+        snapshot.flush().await?;
+        store.save_partial_snapshot(&meta("1"), 7, snapshot.as_mut()).await?;
+
+        let (offset, mut snapshot) =
+            store.get_partial_snapshot(&meta("1")).await?.expect("expected the partial snapshot to be saved");
+        assert_eq!(7, offset);
+
+        snapshot.seek(SeekFrom::Start(offset)).await?;
+        snapshot.write_all(b" snapshot").await?;
+        snapshot.seek(SeekFrom::Start(0)).await?;
+        let mut data = Vec::new();
+        snapshot.read_to_end(&mut data).await?;
+        assert_eq!(b"partial snapshot".to_vec(), data);
+
+        store.save_partial_snapshot(&meta("1"), 16, snapshot.as_mut()).await?;
+        let (offset, mut snapshot) =
+            store.get_partial_snapshot(&meta("1")).await?.expect("expected the partial snapshot to be saved");
+        assert_eq!(16, offset);
+        let mut data = Vec::new();
+        snapshot.read_to_end(&mut data).await?;
+        assert_eq!(b"partial snapshot".to_vec(), data);
+
+        store.save_partial_snapshot(&meta("1"), 0, snapshot.as_mut()).await?;
+        let saved = store.get_partial_snapshot(&meta("1")).await?;
+        assert_eq!(Some(0), saved.map(|(offset, _)| offset));
+
+        assert!(
+            store.get_partial_snapshot(&meta("2")).await?.is_none(),
+            "expected no partial snapshot of another snapshot"
+        );
+        Ok(())
+    }
+
     pub async fn feed_10_logs_vote_self(sto: &S) -> anyhow::Result<()> {
         for i in 1..=10 {
             sto.append_to_log(&[&Entry {
@@ -844,6 +916,8 @@ where
                             membership: MembershipConfig {
                                 members: btreeset! {1,2,3},
                                 members_after_consensus: None,
+                                learners: btreeset! {},
+                                nodes: btreemap! {},
                             },
                         }),
                     },
@@ -861,6 +935,8 @@ where
                             membership: MembershipConfig {
                                 members: btreeset! {3,4,5},
                                 members_after_consensus: None,
+                                learners: btreeset! {},
+                                nodes: btreemap! {},
                             },
                         }),
                     },
@@ -895,6 +971,8 @@ where
                             membership: MembershipConfig {
                                 members: btreeset! {1,2,3},
                                 members_after_consensus: None,
+                                learners: btreeset! {},
+                                nodes: btreemap! {},
                             },
                         }),
                     },
@@ -913,6 +991,8 @@ where
                             membership: MembershipConfig {
                                 members: btreeset! {3,4,5},
                                 members_after_consensus: None,
+                                learners: btreeset! {},
+                                nodes: btreemap! {},
                             },
                         }),
                     },
@@ -1297,6 +1377,7 @@ where
                     serial: 0,
                     status: "lit".into(),
                 },
+                session: None,
             }),
         };
 
@@ -1319,6 +1400,7 @@ where
                         serial: 0,
                         status: "lit".into(),
                     },
+                    session: None,
                 }),
             };
             let res = store.apply_to_state_machine(&[&entry]).await;